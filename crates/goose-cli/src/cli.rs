@@ -2,18 +2,27 @@ use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
 
 use goose::config::{Config, ExtensionConfig};
+use goose::recipe::{Response, Settings, SubRecipe};
 
 use crate::commands::bench::agent_generator;
 use crate::commands::configure::handle_configure;
 use crate::commands::info::handle_info;
 use crate::commands::mcp::run_server;
 use crate::commands::project::{handle_project_default, handle_projects_interactive};
-use crate::commands::recipe::{handle_deeplink, handle_validate};
-use crate::commands::session::{handle_session_list, handle_session_remove};
+use crate::commands::recipe::{
+    handle_deeplink, handle_install, handle_list, handle_test, handle_update, handle_validate,
+};
+use crate::commands::schedule::{
+    handle_schedule_add, handle_schedule_daemon, handle_schedule_list, handle_schedule_remove,
+    handle_schedule_run, handle_schedule_runs,
+};
+use crate::commands::session::{handle_session_list, handle_session_remove, handle_session_rewind};
 use crate::logging::setup_logging;
-use crate::recipes::recipe::{explain_recipe_with_parameters, load_recipe_as_template};
+use crate::recipes::recipe::{
+    explain_recipe_with_parameters, load_recipe_as_template, render_recipe,
+};
 use crate::session;
-use crate::session::{build_session, SessionBuilderConfig};
+use crate::session::{build_session, OutputFormat, SessionBuilderConfig};
 use goose_bench::bench_config::BenchRunConfig;
 use goose_bench::runners::bench_runner::BenchRunner;
 use goose_bench::runners::eval_runner::EvalRunner;
@@ -101,6 +110,20 @@ enum SessionCommand {
         )]
         regex: String,
     },
+    #[command(about = "Rewind a session and the files goose changed to before a turn")]
+    Rewind {
+        /// Identifier of the session to rewind, defaults to the most recent one
+        #[command(flatten)]
+        identifier: Option<Identifier>,
+
+        #[arg(
+            long = "to",
+            value_name = "TURN",
+            help = "Turn to rewind to, lists the turns when omitted",
+            long_help = "Restore the files goose changed and remove the conversation from this turn onwards. Turns are numbered from 1 in the order you sent them."
+        )]
+        to: Option<usize>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -163,6 +186,130 @@ enum RecipeCommand {
         )]
         recipe_name: String,
     },
+
+    /// Run a recipe's test cases against recorded model responses
+    #[command(about = "Test a recipe against recorded model responses")]
+    Test {
+        /// Recipe name to get recipe file to test
+        #[arg(help = "recipe name to get recipe file or full path to the recipe file to test")]
+        recipe_name: String,
+
+        /// Path to the test cases file
+        #[arg(
+            long = "cases",
+            value_name = "FILE",
+            help = "Path to the YAML file with the test cases"
+        )]
+        cases: PathBuf,
+
+        /// Call the configured provider and record its responses
+        #[arg(
+            long,
+            help = "Call the configured provider and record its responses as fixtures",
+            long_help = "Call the configured provider instead of replaying recorded responses, and save its responses to the fixtures file. Cases with scripted responses are not recorded."
+        )]
+        record: bool,
+    },
+
+    /// List installed recipes
+    #[command(about = "List installed recipes, or the recipes in the registries")]
+    List {
+        /// List the recipes available in the registries
+        #[arg(long, help = "List the recipes available in the configured registries")]
+        available: bool,
+    },
+
+    /// Install a recipe from the registries
+    #[command(about = "Install a recipe from the configured registries")]
+    Install {
+        /// Recipe name with an optional version requirement
+        #[arg(
+            help = "Recipe name, optionally with a version requirement (e.g., code-review@^1.2)"
+        )]
+        spec: String,
+    },
+
+    /// Update installed recipes
+    #[command(about = "Update installed recipes within their version requirements")]
+    Update {
+        /// Recipe to update
+        #[arg(help = "Recipe to update, all installed recipes by default")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommand {
+    /// Schedule a recipe
+    #[command(about = "Schedule a recipe to run on a cron schedule")]
+    Add {
+        /// Recipe name or path to the recipe file
+        #[arg(
+            long,
+            value_name = "RECIPE_NAME or FULL_PATH_TO_RECIPE_FILE",
+            help = "Recipe name or full path to the recipe file"
+        )]
+        recipe: String,
+
+        /// Cron expression of when to run the recipe
+        #[arg(
+            long,
+            value_name = "EXPRESSION",
+            help = "When to run, as a cron expression (e.g., \"0 9 * * 1-5\" for 9am on weekdays)",
+            long_help = "When to run, as a five-field cron expression in local time: minute, hour, day of month, month and day of week."
+        )]
+        cron: String,
+
+        /// Identifier of the scheduled job
+        #[arg(
+            long,
+            value_name = "ID",
+            help = "Identifier of the scheduled job, defaults to the recipe name"
+        )]
+        id: Option<String>,
+
+        /// Parameters for the recipe
+        #[arg(
+            long,
+            value_name = "KEY=VALUE",
+            help = "Parameters for the recipe (e.g., --params username=alice --params channel_name=goose-channel)",
+            action = clap::ArgAction::Append,
+            value_parser = parse_key_val,
+        )]
+        params: Vec<(String, String)>,
+    },
+
+    /// List scheduled jobs
+    #[command(about = "List scheduled jobs and when they run next")]
+    List,
+
+    /// Remove a scheduled job
+    #[command(about = "Remove a scheduled job")]
+    Remove {
+        #[arg(help = "Identifier of the scheduled job")]
+        id: String,
+    },
+
+    /// Run a scheduled job now
+    #[command(about = "Run a scheduled job now and wait for it to finish")]
+    Run {
+        #[arg(help = "Identifier of the scheduled job")]
+        id: String,
+    },
+
+    /// Show past runs
+    #[command(about = "Show past runs of scheduled jobs and their sessions")]
+    Runs {
+        #[arg(help = "Only show runs of this job")]
+        id: Option<String>,
+    },
+
+    /// Run scheduled jobs when they are due
+    #[command(
+        about = "Run scheduled jobs when they are due, until stopped",
+        long_about = "Run scheduled jobs when they are due, until stopped. Jobs are also run by the goose desktop app while it is open, only one of them runs jobs at a time."
+    )]
+    Daemon,
 }
 
 #[derive(Subcommand)]
@@ -324,6 +471,17 @@ enum Command {
         )]
         interactive: bool,
 
+        /// Format of the output
+        #[arg(
+            long = "output-format",
+            value_enum,
+            default_value = "text",
+            help = "Format of the output (text, json, stream-json)",
+            long_help = "Format of the output. 'json' prints a single JSON object with the result when the run is done, 'stream-json' prints newline-delimited JSON events as the run progresses.",
+            conflicts_with = "interactive"
+        )]
+        output_format: OutputFormat,
+
         /// Run without storing a session file
         #[arg(
             long = "no-session",
@@ -402,13 +560,20 @@ enum Command {
         builtins: Vec<String>,
     },
 
-    /// Recipe utilities for validation and deeplinking
-    #[command(about = "Recipe utilities for validation and deeplinking")]
+    /// Recipe utilities for validation, deeplinking and installing
+    #[command(about = "Recipe utilities for validation, deeplinking and installing")]
     Recipe {
         #[command(subcommand)]
         command: RecipeCommand,
     },
 
+    /// Run recipes on a schedule
+    #[command(about = "Run recipes on a schedule")]
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+
     /// Update the Goose CLI version
     #[command(about = "Update the goose CLI version")]
     Update {
@@ -443,6 +608,9 @@ struct InputConfig {
     contents: Option<String>,
     extensions_override: Option<Vec<ExtensionConfig>>,
     additional_system_prompt: Option<String>,
+    sub_recipes: Option<Vec<SubRecipe>>,
+    response: Option<Response>,
+    settings: Option<Settings>,
 }
 
 pub async fn cli() -> Result<()> {
@@ -489,6 +657,10 @@ pub async fn cli() -> Result<()> {
                     handle_session_remove(id, regex)?;
                     return Ok(());
                 }
+                Some(SessionCommand::Rewind { identifier, to }) => {
+                    handle_session_rewind(identifier.map(extract_identifier), to)?;
+                    Ok(())
+                }
                 None => {
                     // Run session command by default
                     let mut session: crate::Session = build_session(SessionBuilderConfig {
@@ -500,6 +672,10 @@ pub async fn cli() -> Result<()> {
                         builtins,
                         extensions_override: None,
                         additional_system_prompt: None,
+                        sub_recipes: None,
+                        response: None,
+                        settings: None,
+                        output_format: OutputFormat::Text,
                         debug,
                         max_tool_repetitions,
                     })
@@ -534,6 +710,7 @@ pub async fn cli() -> Result<()> {
             input_text,
             recipe,
             interactive,
+            output_format,
             identifier,
             resume,
             no_session,
@@ -556,6 +733,9 @@ pub async fn cli() -> Result<()> {
                         contents: Some(input),
                         extensions_override: None,
                         additional_system_prompt: None,
+                        sub_recipes: None,
+                        response: None,
+                        settings: None,
                     }
                 }
                 (Some(file), _, _, _) => {
@@ -570,27 +750,40 @@ pub async fn cli() -> Result<()> {
                         contents: Some(contents),
                         extensions_override: None,
                         additional_system_prompt: None,
+                        sub_recipes: None,
+                        response: None,
+                        settings: None,
                     }
                 }
                 (_, Some(text), _, _) => InputConfig {
                     contents: Some(text),
                     extensions_override: None,
                     additional_system_prompt: None,
+                    sub_recipes: None,
+                    response: None,
+                    settings: None,
                 },
                 (_, _, Some(recipe_name), explain) => {
                     if explain {
                         explain_recipe_with_parameters(&recipe_name, params)?;
                         return Ok(());
                     }
-                    let recipe =
-                        load_recipe_as_template(&recipe_name, params).unwrap_or_else(|err| {
-                            eprintln!("{}: {}", console::style("Error").red().bold(), err);
-                            std::process::exit(1);
-                        });
+                    let recipe = if output_format == OutputFormat::Text {
+                        load_recipe_as_template(&recipe_name, params)
+                    } else {
+                        render_recipe(&recipe_name, params)
+                    }
+                    .unwrap_or_else(|err| {
+                        eprintln!("{}: {}", console::style("Error").red().bold(), err);
+                        std::process::exit(1);
+                    });
                     InputConfig {
                         contents: recipe.prompt,
                         extensions_override: recipe.extensions,
                         additional_system_prompt: recipe.instructions,
+                        sub_recipes: recipe.sub_recipes,
+                        response: recipe.response,
+                        settings: recipe.settings,
                     }
                 }
                 (None, None, None, _) => {
@@ -608,6 +801,10 @@ pub async fn cli() -> Result<()> {
                 builtins,
                 extensions_override: input_config.extensions_override,
                 additional_system_prompt: input_config.additional_system_prompt,
+                sub_recipes: input_config.sub_recipes,
+                response: input_config.response,
+                settings: input_config.settings,
+                output_format,
                 debug,
                 max_tool_repetitions,
             })
@@ -621,7 +818,12 @@ pub async fn cli() -> Result<()> {
             if interactive {
                 let _ = session.interactive(input_config.contents).await;
             } else if let Some(contents) = input_config.contents {
-                let _ = session.headless(contents).await;
+                if let Err(e) = session.headless(contents).await {
+                    if output_format == OutputFormat::Text {
+                        eprintln!("Error: {}", e);
+                    }
+                    std::process::exit(1);
+                }
             } else {
                 eprintln!("Error: no text provided for prompt in headless mode");
                 std::process::exit(1);
@@ -662,6 +864,32 @@ pub async fn cli() -> Result<()> {
                 RecipeCommand::Deeplink { recipe_name } => {
                     handle_deeplink(&recipe_name)?;
                 }
+                RecipeCommand::Test {
+                    recipe_name,
+                    cases,
+                    record,
+                } => {
+                    handle_test(&recipe_name, &cases, record).await?;
+                }
+                RecipeCommand::List { available } => handle_list(available).await?,
+                RecipeCommand::Install { spec } => handle_install(&spec).await?,
+                RecipeCommand::Update { name } => handle_update(name.as_deref()).await?,
+            }
+            return Ok(());
+        }
+        Some(Command::Schedule { command }) => {
+            match command {
+                ScheduleCommand::Add {
+                    recipe,
+                    cron,
+                    id,
+                    params,
+                } => handle_schedule_add(&recipe, cron, id, params)?,
+                ScheduleCommand::List => handle_schedule_list()?,
+                ScheduleCommand::Remove { id } => handle_schedule_remove(&id)?,
+                ScheduleCommand::Run { id } => handle_schedule_run(&id).await?,
+                ScheduleCommand::Runs { id } => handle_schedule_runs(id.as_deref())?,
+                ScheduleCommand::Daemon => handle_schedule_daemon().await?,
             }
             return Ok(());
         }
@@ -680,6 +908,10 @@ pub async fn cli() -> Result<()> {
                     builtins: Vec::new(),
                     extensions_override: None,
                     additional_system_prompt: None,
+                    sub_recipes: None,
+                    response: None,
+                    settings: None,
+                    output_format: OutputFormat::Text,
                     debug: false,
                     max_tool_repetitions: None,
                 })
@@ -1,5 +1,5 @@
 use crate::session::build_session;
-use crate::session::SessionBuilderConfig;
+use crate::session::{OutputFormat, SessionBuilderConfig};
 use crate::{logging, session, Session};
 use async_trait::async_trait;
 use goose::message::Message;
@@ -40,6 +40,10 @@ pub async fn agent_generator(
         builtins: requirements.builtin,
         extensions_override: None,
         additional_system_prompt: None,
+        sub_recipes: None,
+        response: None,
+        settings: None,
+        output_format: OutputFormat::Text,
         debug: false,
         max_tool_repetitions: None,
     })
@@ -14,7 +14,9 @@ use goose::config::{
     PermissionManager,
 };
 use goose::message::Message;
-use goose::providers::{create, providers};
+use goose::model::MIN_THINKING_BUDGET;
+use goose::providers::base::ModelInfo;
+use goose::providers::{catalog, create, providers};
 use mcp_core::tool::ToolAnnotations;
 use mcp_core::Tool;
 use serde_json::{json, Value};
@@ -35,6 +37,7 @@ fn get_display_name(extension_id: &str) -> String {
         "memory" => "Memory".to_string(),
         "tutorial" => "Tutorial".to_string(),
         "jetbrains" => "JetBrains".to_string(),
+        "lsp" => "Language Servers".to_string(),
         // Add other extensions as needed
         _ => {
             extension_id
@@ -210,6 +213,26 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// A short summary of a model's capabilities for the model selection
+fn describe_model(model: &ModelInfo) -> String {
+    let mut parts = vec![format!("{}k context", model.context_limit / 1000)];
+    for (supported, capability) in [
+        (model.supports_tools, "tools"),
+        (model.supports_vision, "vision"),
+        (model.supports_thinking, "thinking"),
+    ] {
+        if supported == Some(true) {
+            parts.push(capability.to_string());
+        }
+    }
+    if let (Some(input), Some(output)) =
+        (model.input_cost_per_million, model.output_cost_per_million)
+    {
+        parts.push(format!("${:.2}/${:.2} per 1M tokens", input, output));
+    }
+    parts.join(", ")
+}
+
 /// Dialog for configuring the AI provider and model
 pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     // Get global config instance
@@ -333,7 +356,7 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     let models_res = {
         let temp_model_config = goose::model::ModelConfig::new(provider_meta.default_model.clone());
         let temp_provider = create(provider_name, temp_model_config)?;
-        temp_provider.fetch_supported_models_async().await
+        catalog::refresh_models(provider_name, temp_provider.as_ref()).await
     };
     spin.stop(style("Model fetch complete").green());
 
@@ -344,15 +367,16 @@ pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
             cliclack::outro(style(e.to_string()).on_red().white())?;
             return Ok(false);
         }
-        Ok(Some(models)) => cliclack::select("Select a model:")
-            .items(
-                &models
-                    .iter()
-                    .map(|m| (m, m.as_str(), ""))
-                    .collect::<Vec<_>>(),
-            )
-            .interact()?
-            .to_string(),
+        Ok(Some(models)) => {
+            let items: Vec<(&str, &str, String)> = models
+                .iter()
+                .map(|m| (m.name.as_str(), m.name.as_str(), describe_model(m)))
+                .collect();
+            cliclack::select("Select a model:")
+                .items(&items)
+                .interact()?
+                .to_string()
+        }
         Ok(None) => {
             let default_model =
                 std::env::var("GOOSE_MODEL").unwrap_or(provider_meta.default_model.clone());
@@ -529,6 +553,11 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     "Access interactive tutorials and guides",
                 )
                 .item("jetbrains", "JetBrains", "Connect to jetbrains IDEs")
+                .item(
+                    "lsp",
+                    "Language Servers",
+                    "Diagnostics, definitions, hover and renames from language servers",
+                )
                 .interact()?
                 .to_string();
 
@@ -824,6 +853,11 @@ pub fn remove_extension_dialog() -> Result<(), Box<dyn Error>> {
 pub async fn configure_settings_dialog() -> Result<(), Box<dyn Error>> {
     let setting_type = cliclack::select("What setting would you like to configure?")
         .item("goose_mode", "Goose Mode", "Configure Goose mode")
+        .item(
+            "model_parameters",
+            "Model Parameters",
+            "Set reasoning effort, thinking budget, top_p and stop sequences",
+        )
         .item(
             "tool_permission",
             "Tool Permission",
@@ -850,6 +884,9 @@ pub async fn configure_settings_dialog() -> Result<(), Box<dyn Error>> {
         "goose_mode" => {
             configure_goose_mode_dialog()?;
         }
+        "model_parameters" => {
+            configure_model_parameters_dialog()?;
+        }
         "tool_permission" => {
             configure_tool_permissions_dialog().await.and(Ok(()))?;
         }
@@ -921,6 +958,78 @@ pub fn configure_goose_mode_dialog() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Dialog for the reasoning and sampling parameters sent with every request
+///
+/// Empty answers remove the parameter, so the model's own default applies.
+pub fn configure_model_parameters_dialog() -> Result<(), Box<dyn Error>> {
+    let config = Config::global();
+
+    let effort = cliclack::select("How much should reasoning models think before they answer?")
+        .item("default", "Model Default", "Use the model's own default")
+        .item("low", "Low", "Faster answers, fewer thinking tokens")
+        .item("medium", "Medium", "")
+        .item(
+            "high",
+            "High",
+            "More thorough answers, more thinking tokens",
+        )
+        .interact()?;
+    if effort == "default" {
+        config.delete("GOOSE_REASONING_EFFORT")?;
+    } else {
+        config.set_param("GOOSE_REASONING_EFFORT", Value::String(effort.to_string()))?;
+    }
+
+    let budget: String = cliclack::input(
+        "Thinking budget in tokens for models with extended thinking (empty to follow the reasoning effort):",
+    )
+    .required(false)
+    .placeholder("16000")
+    .validate(|input: &String| match input.parse::<i32>() {
+        _ if input.is_empty() => Ok(()),
+        Ok(budget) if budget >= MIN_THINKING_BUDGET => Ok(()),
+        _ => Err("Please enter a number of at least 1024"),
+    })
+    .interact()?;
+    match budget.parse::<i32>() {
+        Ok(budget) => config.set_param("GOOSE_THINKING_BUDGET", Value::from(budget))?,
+        Err(_) => config.delete("GOOSE_THINKING_BUDGET")?,
+    }
+
+    let top_p: String = cliclack::input("Top p, between 0 and 1 (empty for the model default):")
+        .required(false)
+        .placeholder("0.9")
+        .validate(|input: &String| match input.parse::<f32>() {
+            _ if input.is_empty() => Ok(()),
+            Ok(top_p) if (0.0..=1.0).contains(&top_p) => Ok(()),
+            _ => Err("Please enter a number between 0 and 1"),
+        })
+        .interact()?;
+    match top_p.parse::<f32>() {
+        Ok(top_p) => config.set_param("GOOSE_TOP_P", Value::from(top_p))?,
+        Err(_) => config.delete("GOOSE_TOP_P")?,
+    }
+
+    let stop_sequences: String =
+        cliclack::input("Stop sequences, separated by commas (empty for none):")
+            .required(false)
+            .interact()?;
+    let stop_sequences: Vec<String> = stop_sequences
+        .split(',')
+        .map(str::trim)
+        .filter(|sequence| !sequence.is_empty())
+        .map(String::from)
+        .collect();
+    if stop_sequences.is_empty() {
+        config.delete("GOOSE_STOP_SEQUENCES")?;
+    } else {
+        config.set_param("GOOSE_STOP_SEQUENCES", Value::from(stop_sequences))?;
+    }
+
+    cliclack::outro("Model parameters updated")?;
+    Ok(())
+}
+
 pub fn configure_tool_output_dialog() -> Result<(), Box<dyn Error>> {
     let config = Config::global();
     // Check if GOOSE_CLI_MIN_PRIORITY is set as an environment variable
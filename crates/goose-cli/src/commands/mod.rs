@@ -4,5 +4,6 @@ pub mod info;
 pub mod mcp;
 pub mod project;
 pub mod recipe;
+pub mod schedule;
 pub mod session;
 pub mod update;
@@ -1,8 +1,12 @@
 use anyhow::Result;
 use base64::Engine;
 use console::style;
+use goose::config::Config;
+use std::path::Path;
 
 use crate::recipes::recipe::load_recipe;
+use crate::recipes::recipe_test::run_recipe_tests;
+use crate::recipes::registry::{RecipeRegistry, Verification};
 
 /// Validates a recipe file
 ///
@@ -57,3 +61,127 @@ pub fn handle_deeplink(recipe_name: &str) -> Result<()> {
         }
     }
 }
+
+/// Runs the test cases of a recipe
+///
+/// # Arguments
+///
+/// * `recipe_name` - Name or path of the recipe to test
+/// * `cases` - Path to the test cases file
+/// * `record` - Call the configured provider and record its responses
+///
+/// # Returns
+///
+/// Result indicating whether all cases passed
+pub async fn handle_test(recipe_name: &str, cases: &Path, record: bool) -> Result<()> {
+    let provider = if record {
+        let config = Config::global();
+        let provider_name: String = config.get_param("GOOSE_PROVIDER")?;
+        let model: String = config.get_param("GOOSE_MODEL")?;
+        Some(goose::providers::create(
+            &provider_name,
+            goose::model::ModelConfig::new(model),
+        )?)
+    } else {
+        None
+    };
+    run_recipe_tests(recipe_name, cases, provider).await
+}
+
+fn verification_label(verification: Verification) -> console::StyledObject<&'static str> {
+    match verification {
+        Verification::Verified => style("verified").green(),
+        Verification::Untrusted => style("signed by an untrusted author").yellow(),
+        Verification::Unsigned => style("unsigned").yellow(),
+    }
+}
+
+/// Lists installed recipes, or the recipes in the configured registries
+///
+/// # Arguments
+///
+/// * `available` - List the recipes in the registries instead of the installed ones
+///
+/// # Returns
+///
+/// Result indicating success or failure
+pub async fn handle_list(available: bool) -> Result<()> {
+    let registry = RecipeRegistry::from_config()?;
+    if available {
+        if registry.sources().is_empty() {
+            println!("No recipe registries configured");
+        }
+        for (source, index) in registry.available().await? {
+            println!("{}", style(source).bold());
+            for (name, entries) in index.recipes {
+                let versions: Vec<String> = entries.into_iter().map(|e| e.version).collect();
+                println!("  {} ({})", name, versions.join(", "));
+            }
+        }
+        return Ok(());
+    }
+
+    let installed = registry.installed()?;
+    if installed.is_empty() {
+        println!("No recipes installed");
+    }
+    for recipe in installed {
+        println!(
+            "{} {} - {}, from {}",
+            style(&recipe.name).bold(),
+            recipe.version,
+            verification_label(recipe.verification),
+            recipe.source
+        );
+    }
+    Ok(())
+}
+
+/// Installs a recipe from the configured registries
+///
+/// # Arguments
+///
+/// * `spec` - Recipe name, optionally with a version requirement like `name@^1.2`
+///
+/// # Returns
+///
+/// Result indicating success or failure
+pub async fn handle_install(spec: &str) -> Result<()> {
+    let recipe = RecipeRegistry::from_config()?.install(spec).await?;
+    println!(
+        "{} Installed {} {} ({})",
+        style("✓").green().bold(),
+        style(&recipe.name).bold(),
+        recipe.version,
+        verification_label(recipe.verification)
+    );
+    println!("Run it with: goose run --recipe {}", recipe.name);
+    Ok(())
+}
+
+/// Updates installed recipes to the newest versions within their version requirements
+///
+/// # Arguments
+///
+/// * `name` - Only update this recipe
+///
+/// # Returns
+///
+/// Result indicating success or failure
+pub async fn handle_update(name: Option<&str>) -> Result<()> {
+    let updated = RecipeRegistry::from_config()?.update(name).await?;
+    if updated.is_empty() {
+        println!("All recipes are up to date");
+    }
+    for (previous, recipe) in updated {
+        println!(
+            "{} Updated {} {} -> {} ({})",
+            style("✓").green().bold(),
+            style(&recipe.name).bold(),
+            previous,
+            recipe.version,
+            verification_label(recipe.verification)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use console::style;
+use goose::scheduler::{CommandExecutor, RunStatus, ScheduleStore, ScheduledJob, Scheduler};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::logging::setup_logging;
+use crate::recipes::recipe::{render_recipe, RECIPE_FILE_EXTENSIONS};
+
+/// The recipe to store in a job, local files are stored by their absolute path so the job runs the
+/// same recipe from any directory
+fn resolve_recipe(recipe_name: &str) -> Result<String> {
+    let mut candidates = vec![PathBuf::from(recipe_name)];
+    candidates.extend(
+        RECIPE_FILE_EXTENSIONS
+            .iter()
+            .map(|ext| PathBuf::from(format!("{}.{}", recipe_name, ext))),
+    );
+    match candidates.into_iter().find(|path| path.is_file()) {
+        Some(path) => Ok(std::fs::canonicalize(path)?.to_string_lossy().to_string()),
+        None => Ok(recipe_name.to_string()),
+    }
+}
+
+fn default_job_id(recipe: &str) -> String {
+    Path::new(recipe)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| recipe.to_string())
+}
+
+/// Schedules a recipe to run in the current directory
+///
+/// The recipe is rendered with the parameters first, so a job that could never run isn't added.
+pub fn handle_schedule_add(
+    recipe_name: &str,
+    cron: String,
+    id: Option<String>,
+    params: Vec<(String, String)>,
+) -> Result<()> {
+    render_recipe(recipe_name, params.clone())?;
+
+    let recipe = resolve_recipe(recipe_name)?;
+    let id = id.unwrap_or_else(|| default_job_id(&recipe));
+    let job = ScheduledJob::new(
+        id,
+        recipe,
+        cron,
+        params.into_iter().collect::<BTreeMap<_, _>>(),
+        std::env::current_dir()?,
+    )?;
+    let next_run = job.next_run(Local::now());
+    ScheduleStore::from_config_dir()?.add_job(job.clone())?;
+
+    println!(
+        "{} Scheduled {} ({})",
+        style("✓").green().bold(),
+        style(&job.id).bold(),
+        job.cron
+    );
+    match next_run {
+        Some(next_run) => println!("Next run: {}", next_run.format("%Y-%m-%d %H:%M")),
+        None => println!("The schedule never runs"),
+    }
+    println!("Jobs run while the goose desktop app or `goose schedule daemon` is running");
+    Ok(())
+}
+
+pub fn handle_schedule_list() -> Result<()> {
+    let jobs = ScheduleStore::from_config_dir()?.jobs()?;
+    if jobs.is_empty() {
+        println!("No scheduled jobs");
+        return Ok(());
+    }
+
+    let now = Local::now();
+    for job in jobs {
+        let next_run = job
+            .next_run(now)
+            .map(|next_run| next_run.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        println!(
+            "{} - {} ({}), next run {}",
+            style(&job.id).bold(),
+            job.recipe,
+            job.cron,
+            next_run
+        );
+        if !job.params.is_empty() {
+            let params: Vec<String> = job
+                .params
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            println!("    params: {}", params.join(" "));
+        }
+        println!("    in {}", job.working_dir.display());
+    }
+    Ok(())
+}
+
+pub fn handle_schedule_remove(id: &str) -> Result<()> {
+    ScheduleStore::from_config_dir()?.remove_job(id)?;
+    println!("Scheduled job `{}` removed.", id);
+    Ok(())
+}
+
+pub async fn handle_schedule_run(id: &str) -> Result<()> {
+    let store = ScheduleStore::from_config_dir()?;
+    let job = store.job(id)?;
+    let executor = CommandExecutor::new(std::env::current_exe()?);
+    let scheduler = Scheduler::new(store, Arc::new(executor));
+
+    println!("Running {}...", style(id).bold());
+    let run = scheduler.run_job(job).await?;
+    println!("Session saved to {}", run.session_file.display());
+    match run.error {
+        Some(error) => Err(anyhow!("Run failed: {}", error)),
+        None => {
+            println!("{} Run finished", style("✓").green().bold());
+            Ok(())
+        }
+    }
+}
+
+pub fn handle_schedule_runs(id: Option<&str>) -> Result<()> {
+    let runs = ScheduleStore::from_config_dir()?.runs(id)?;
+    if runs.is_empty() {
+        println!("No runs yet");
+        return Ok(());
+    }
+
+    for run in runs {
+        let status = match run.status {
+            RunStatus::Running => style("running").yellow(),
+            RunStatus::Succeeded => style("succeeded").green(),
+            RunStatus::Failed => style("failed").red(),
+        };
+        println!(
+            "{} - {} {}",
+            run.started_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            style(&run.job_id).bold(),
+            status
+        );
+        if let Some(error) = run.error {
+            println!("    {}", error);
+        }
+        println!("    session: {}", run.session_file.display());
+    }
+    Ok(())
+}
+
+/// Runs scheduled jobs until the process is stopped
+pub async fn handle_schedule_daemon() -> Result<()> {
+    setup_logging(Some("schedule"), None)?;
+    let executor = CommandExecutor::new(std::env::current_exe()?);
+    let scheduler = Arc::new(Scheduler::new(
+        ScheduleStore::from_config_dir()?,
+        Arc::new(executor),
+    ));
+
+    println!("Running scheduled jobs, press Ctrl+C to stop");
+    scheduler.run().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_recipe() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("standup.yaml");
+        std::fs::write(&path, "title: Standup").unwrap();
+
+        let resolved = resolve_recipe(path.to_str().unwrap()).unwrap();
+        assert!(Path::new(&resolved).is_absolute());
+        assert_eq!(default_job_id(&resolved), "standup");
+        assert_eq!(
+            resolve_recipe("not-a-local-recipe").unwrap(),
+            "not-a-local-recipe"
+        );
+    }
+}
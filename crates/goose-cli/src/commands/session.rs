@@ -1,5 +1,8 @@
+use crate::session::turn_starts;
 use anyhow::{Context, Result};
 use goose::session::info::{get_session_info, SessionInfo, SortOrder};
+use goose::session::{self, Identifier};
+use goose_mcp::CheckpointStore;
 use regex::Regex;
 use std::fs;
 
@@ -115,3 +118,118 @@ pub fn handle_session_list(verbose: bool, format: String, ascending: bool) -> Re
     }
     Ok(())
 }
+
+pub fn handle_session_rewind(identifier: Option<Identifier>, turn: Option<usize>) -> Result<()> {
+    let session_file = match identifier {
+        Some(identifier) => session::get_path(identifier),
+        None => session::get_most_recent_session()?,
+    };
+    if !session_file.exists() {
+        return Err(anyhow::anyhow!(
+            "Session file '{}' not found.",
+            session_file.display()
+        ));
+    }
+
+    let messages = session::read_messages(&session_file)?;
+    let metadata = session::read_metadata(&session_file)?;
+    let turns = turn_starts(&messages);
+    // Checkpoints are kept per session, under the name of the session file. Only restoring
+    // files needs them, outside a git repository the conversation is still rewound.
+    let session_id = session_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let store = CheckpointStore::open(&metadata.working_dir, &session_id).ok();
+
+    let Some(turn) = turn else {
+        // Without a target, list the turns that can be rewound to
+        let checkpoints = match &store {
+            Some(store) => store.list()?,
+            None => Vec::new(),
+        };
+        println!("Turns in {}:", session_file.display());
+        for (n, &index) in turns.iter().enumerate() {
+            // A turn changed files when the working tree differs after it, at the checkpoint
+            // of the next turn with one or now
+            let changed_files = match (&store, checkpoints.iter().position(|c| c.turn == n + 1)) {
+                (Some(store), Some(position)) => match checkpoints.get(position + 1) {
+                    Some(next) => next.tree != checkpoints[position].tree,
+                    None => !store.changed_since(&checkpoints[position])?.is_empty(),
+                },
+                _ => false,
+            };
+            let text = messages[index].as_concat_text();
+            let first_line = text.lines().next().unwrap_or_default();
+            println!(
+                "{:>4}: {}{}",
+                n + 1,
+                first_line.chars().take(80).collect::<String>(),
+                if changed_files {
+                    " (changed files)"
+                } else {
+                    ""
+                }
+            );
+        }
+        return Ok(());
+    };
+
+    if turn == 0 || turn > turns.len() {
+        return Err(anyhow::anyhow!(
+            "Turn {} does not exist, the session has {} turns",
+            turn,
+            turns.len()
+        ));
+    }
+    let turn_index = turns[turn - 1];
+    let checkpoint = match &store {
+        Some(store) => store.first_from(turn)?,
+        None => None,
+    };
+
+    println!(
+        "Rewinding to before turn {} removes the last {} messages of the session{}.",
+        turn,
+        messages.len() - turn_index,
+        match (&store, &checkpoint) {
+            (Some(store), Some(_)) => format!(
+                " and restores the files in {} to how they were",
+                store.work_tree().display()
+            ),
+            _ => String::new(),
+        }
+    );
+    let should_rewind = cliclack::confirm("Are you sure you want to rewind? (yes/no):")
+        .initial_value(false)
+        .interact()?;
+    if !should_rewind {
+        println!("Skipping the rewind.");
+        return Ok(());
+    }
+
+    match (&store, checkpoint) {
+        (Some(store), Some(checkpoint)) => {
+            store.restore(&checkpoint)?;
+            store.discard_from(turn)?;
+            println!("Files restored to before turn {}.", turn);
+        }
+        (Some(_), None) => println!("No files were changed by goose since turn {}.", turn),
+        (None, _) => println!(
+            "Files are left as they are, checkpoints are only taken in git repositories and {} is not one.",
+            metadata.working_dir.display()
+        ),
+    }
+    session::storage::save_messages_with_metadata(
+        &session_file,
+        &metadata,
+        &messages[..turn_index],
+    )?;
+    println!(
+        "Session rewound, the prompt of turn {} was:\n{}",
+        turn,
+        messages[turn_index].as_concat_text()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::path::Path;
+
+use crate::recipes::recipe::{BUILT_IN_RECIPE_DIR_PARAM, RECIPE_FILE_EXTENSIONS};
+use crate::recipes::search_recipe::retrieve_recipe_file;
+
+/// How many levels of `extends` and `include` are followed before giving up
+const MAX_COMPOSE_DEPTH: usize = 10;
+
+/// Resolves `extends` and `include` of a recipe file into a single recipe.
+///
+/// The recipe's own fields take precedence over the recipe it extends. Included fragments only
+/// fill in fields the recipe does not set, except for `instructions` which are appended. Lists
+/// are merged: extensions and sub-recipes by name, parameters by key, context and activities
+/// are concatenated. Model settings are merged setting by setting. Relative paths in `extends`,
+/// `include` and `sub_recipes` are resolved against the directory of the file that contains
+/// them.
+///
+/// Content without any of these fields is returned unchanged.
+pub fn compose_recipe_content(content: String, recipe_dir: &Path) -> Result<String> {
+    let value: YamlValue = serde_yaml::from_str(&content)?;
+    let needs_composing = ["extends", "include", "sub_recipes"]
+        .iter()
+        .any(|key| value.get(key).is_some());
+    if !needs_composing {
+        return Ok(content);
+    }
+
+    let mut stack = Vec::new();
+    let composed = compose_value(value, recipe_dir, &mut stack)?;
+    Ok(serde_yaml::to_string(&composed)?)
+}
+
+fn compose_value(value: YamlValue, dir: &Path, stack: &mut Vec<String>) -> Result<YamlValue> {
+    let YamlValue::Mapping(mut recipe) = value else {
+        return Err(anyhow!("A recipe must be a mapping of fields"));
+    };
+
+    resolve_sub_recipe_paths(&mut recipe, dir);
+
+    let extends = recipe.remove("extends");
+    let include = recipe.remove("include");
+
+    if let Some(extends) = extends {
+        let reference = extends
+            .as_str()
+            .ok_or_else(|| anyhow!("'extends' must be a recipe name or path"))?;
+        let base = load_fragment(reference, dir, stack)?;
+        recipe = merge(base, recipe, false);
+    }
+
+    if let Some(include) = include {
+        let references = include
+            .as_sequence()
+            .ok_or_else(|| anyhow!("'include' must be a list of recipe names or paths"))?;
+        for reference in references {
+            let reference = reference
+                .as_str()
+                .ok_or_else(|| anyhow!("'include' must be a list of recipe names or paths"))?;
+            let fragment = load_fragment(reference, dir, stack)?;
+            recipe = merge(fragment, recipe, true);
+        }
+    }
+
+    Ok(YamlValue::Mapping(recipe))
+}
+
+fn load_fragment(reference: &str, dir: &Path, stack: &mut Vec<String>) -> Result<Mapping> {
+    let reference = resolve_reference(reference, dir);
+    let key = std::fs::canonicalize(&reference)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| reference.clone());
+    if stack.contains(&key) {
+        return Err(anyhow!(
+            "Recipe {} extends or includes itself: {} -> {}",
+            reference,
+            stack.join(" -> "),
+            key
+        ));
+    }
+    if stack.len() >= MAX_COMPOSE_DEPTH {
+        return Err(anyhow!(
+            "Recipes are nested more than {} levels deep at {}",
+            MAX_COMPOSE_DEPTH,
+            reference
+        ));
+    }
+
+    let (content, fragment_dir) = retrieve_recipe_file(&reference)?;
+    // Other files' recipe_dir refers to their own directory, not the one of the recipe being run
+    let recipe_dir_pattern =
+        Regex::new(&format!(r"\{{\{{\s*{}\s*\}}\}}", BUILT_IN_RECIPE_DIR_PARAM)).unwrap();
+    let content = recipe_dir_pattern.replace_all(&content, fragment_dir.to_string_lossy());
+    let value: YamlValue = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse recipe {}: {}", reference, e))?;
+
+    stack.push(key);
+    let composed = compose_value(value, &fragment_dir, stack);
+    stack.pop();
+
+    match composed? {
+        YamlValue::Mapping(mapping) => Ok(mapping),
+        _ => unreachable!("compose_value always returns a mapping"),
+    }
+}
+
+/// Recipe files are referenced relative to the file referencing them, names are looked up as usual
+fn resolve_reference(reference: &str, dir: &Path) -> String {
+    let is_file = RECIPE_FILE_EXTENSIONS
+        .iter()
+        .any(|ext| reference.ends_with(&format!(".{}", ext)));
+    if is_file && Path::new(reference).is_relative() {
+        dir.join(reference).display().to_string()
+    } else {
+        reference.to_string()
+    }
+}
+
+fn resolve_sub_recipe_paths(recipe: &mut Mapping, dir: &Path) {
+    let Some(YamlValue::Sequence(sub_recipes)) = recipe.get_mut("sub_recipes") else {
+        return;
+    };
+    for sub_recipe in sub_recipes {
+        if let Some(path) = sub_recipe.get_mut("path") {
+            if let Some(reference) = path.as_str() {
+                *path = YamlValue::String(resolve_reference(reference, dir));
+            }
+        }
+    }
+}
+
+/// Merges `overlay` on top of `base`, see [`compose_recipe_content`] for the rules
+fn merge(mut base: Mapping, overlay: Mapping, append_instructions: bool) -> Mapping {
+    for (key, value) in overlay {
+        let merged = match (key.as_str(), base.remove(&key)) {
+            (Some("extensions" | "sub_recipes"), Some(existing)) => {
+                merge_list_by(existing, value, "name")
+            }
+            (Some("parameters"), Some(existing)) => merge_list_by(existing, value, "key"),
+            (Some("context" | "activities"), Some(existing)) => concat_lists(existing, value),
+            (Some("settings"), Some(YamlValue::Mapping(mut existing))) => match value {
+                YamlValue::Mapping(own) => {
+                    existing.extend(own);
+                    YamlValue::Mapping(existing)
+                }
+                other => other,
+            },
+            (Some("instructions"), Some(YamlValue::String(existing))) if append_instructions => {
+                match value {
+                    YamlValue::String(own) => YamlValue::String(format!("{}\n\n{}", own, existing)),
+                    other => other,
+                }
+            }
+            _ => value,
+        };
+        base.insert(key, merged);
+    }
+    base
+}
+
+/// Entries of `overlay` replace entries of `base` with the same `field`, new ones are appended
+fn merge_list_by(base: YamlValue, overlay: YamlValue, field: &str) -> YamlValue {
+    let (YamlValue::Sequence(mut merged), YamlValue::Sequence(items)) = (base, &overlay) else {
+        return overlay;
+    };
+    for item in items.iter().cloned() {
+        let existing = item.get(field).and_then(|id| {
+            merged
+                .iter()
+                .position(|candidate| candidate.get(field) == Some(id))
+        });
+        match existing {
+            Some(index) => merged[index] = item,
+            None => merged.push(item),
+        }
+    }
+    YamlValue::Sequence(merged)
+}
+
+fn concat_lists(base: YamlValue, overlay: YamlValue) -> YamlValue {
+    let (YamlValue::Sequence(mut merged), YamlValue::Sequence(items)) = (base, &overlay) else {
+        return overlay;
+    };
+    for item in items.iter().cloned() {
+        if !merged.contains(&item) {
+            merged.push(item);
+        }
+    }
+    YamlValue::Sequence(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::model::ReasoningEffort;
+    use goose::recipe::Recipe;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    #[test]
+    fn test_extends_and_include() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "base.yaml",
+            r#"
+version: 1.0.0
+title: Base
+description: Base recipe
+instructions: Base instructions from {{ recipe_dir }}
+prompt: Base prompt
+extensions:
+  - type: builtin
+    name: developer
+    display_name: Developer
+    timeout: 300
+    bundled: true
+activities:
+  - Review code
+settings:
+  temperature: 0.2
+  reasoning_effort: low
+"#,
+        );
+        write(
+            dir.path(),
+            "style.yaml",
+            r#"
+instructions: Follow the style guide
+extensions:
+  - type: builtin
+    name: memory
+    display_name: Memory
+    timeout: 300
+    bundled: true
+activities:
+  - Review code
+  - Write docs
+"#,
+        );
+        write(
+            dir.path(),
+            "child.yaml",
+            r#"
+extends: base.yaml
+include:
+  - style.yaml
+version: 1.0.0
+title: Child
+description: Child recipe
+instructions: Child instructions with {{ name }}
+parameters:
+  - key: name
+    input_type: string
+    requirement: required
+    description: A name
+sub_recipes:
+  - name: summarize
+    path: summarize.yaml
+settings:
+  reasoning_effort: high
+"#,
+        );
+
+        let content = std::fs::read_to_string(dir.path().join("child.yaml")).unwrap();
+        let composed = compose_recipe_content(content, dir.path()).unwrap();
+        let recipe: Recipe = serde_yaml::from_str(&composed).unwrap();
+
+        assert_eq!(recipe.title, "Child");
+        assert_eq!(recipe.prompt.as_deref(), Some("Base prompt"));
+        assert_eq!(
+            recipe.instructions.as_deref(),
+            Some("Child instructions with {{ name }}\n\nFollow the style guide")
+        );
+        let extension_names: Vec<_> = recipe
+            .extensions
+            .unwrap()
+            .iter()
+            .map(|extension| extension.name())
+            .collect();
+        assert_eq!(extension_names, vec!["memory", "developer"]);
+        assert_eq!(
+            recipe.activities.unwrap(),
+            vec!["Review code".to_string(), "Write docs".to_string()]
+        );
+        let settings = recipe.settings.unwrap();
+        assert_eq!(settings.temperature, Some(0.2));
+        assert_eq!(settings.reasoning_effort, Some(ReasoningEffort::High));
+        assert!(recipe.extends.is_none() && recipe.include.is_none());
+        assert_eq!(
+            recipe.sub_recipes.unwrap()[0].path,
+            dir.path().join("summarize.yaml").display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_base_recipe_dir_is_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("shared")).unwrap();
+        write(
+            &dir.path().join("shared"),
+            "base.yaml",
+            "instructions: Read {{ recipe_dir }}/notes.md\n",
+        );
+        let content =
+            "version: 1.0.0\ntitle: Child\ndescription: Child\nextends: shared/base.yaml\n";
+
+        let composed = compose_recipe_content(content.to_string(), dir.path()).unwrap();
+        let recipe: Recipe = serde_yaml::from_str(&composed).unwrap();
+        let shared_dir = dir.path().join("shared").canonicalize().unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            format!("Read {}/notes.md", shared_dir.display())
+        );
+    }
+
+    #[test]
+    fn test_cycle_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.yaml", "extends: b.yaml\n");
+        write(dir.path(), "b.yaml", "extends: a.yaml\n");
+
+        let content = std::fs::read_to_string(dir.path().join("a.yaml")).unwrap();
+        let err = compose_recipe_content(content, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("extends or includes itself"));
+    }
+
+    #[test]
+    fn test_content_without_composition_is_unchanged() {
+        let content = r#"{"version": "1.0.0", "title": "T", "description": "D"}"#.to_string();
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            compose_recipe_content(content.clone(), dir.path()).unwrap(),
+            content
+        );
+    }
+}
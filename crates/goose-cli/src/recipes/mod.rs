@@ -1,4 +1,8 @@
+pub mod compose;
 pub mod github_recipe;
 pub mod print_recipe;
 pub mod recipe;
+pub mod recipe_test;
+pub mod registry;
 pub mod search_recipe;
+pub mod sub_recipe;
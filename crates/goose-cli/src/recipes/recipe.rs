@@ -1,12 +1,15 @@
 use anyhow::Result;
 use console::style;
 
+use crate::recipes::compose::compose_recipe_content;
 use crate::recipes::print_recipe::{
     missing_parameters_command_line, print_parameters_with_values, print_recipe_explanation,
     print_required_parameters_for_template,
 };
 use crate::recipes::search_recipe::retrieve_recipe_file;
-use goose::recipe::{Recipe, RecipeParameter, RecipeParameterRequirement};
+use goose::recipe::{
+    split_list_value, Recipe, RecipeParameter, RecipeParameterInputType, RecipeParameterRequirement,
+};
 use minijinja::{Environment, Error, Template, UndefinedBehavior};
 use serde_json::Value as JsonValue;
 use serde_yaml::Value as YamlValue;
@@ -32,12 +35,12 @@ pub const RECIPE_FILE_EXTENSIONS: &[&str] = &["yaml", "json"];
 /// - Recipe is not valid
 /// - The required fields are missing
 pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>) -> Result<Recipe> {
-    let (recipe_file_content, recipe_parent_dir) = retrieve_recipe_file(recipe_name)?;
-
-    let recipe = validate_recipe_file_parameters(&recipe_file_content)?;
-
-    let (params_for_template, missing_params) =
-        apply_values_to_parameters(&params, recipe.parameters, recipe_parent_dir, true)?;
+    let PreparedRecipe {
+        content: recipe_file_content,
+        recipe: raw_recipe,
+        params: params_for_template,
+        missing_params,
+    } = prepare_recipe_template(recipe_name, &params, true)?;
     if !missing_params.is_empty() {
         return Err(anyhow::anyhow!(
             "Please provide the following parameters in the command line: {}",
@@ -45,9 +48,8 @@ pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>)
         ));
     }
 
-    let rendered_content = render_content_with_params(&recipe_file_content, &params_for_template)?;
-
-    let recipe = parse_recipe_content(&rendered_content)?;
+    let template_values = resolve_template_values(&raw_recipe, &params_for_template)?;
+    let recipe = render_recipe_content(&recipe_file_content, &template_values)?;
 
     // Display information about the loaded recipe
     println!(
@@ -65,6 +67,85 @@ pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>)
     Ok(recipe)
 }
 
+/// Loads and renders a recipe without any output or prompting, for sub-recipes and runs whose
+/// output is read by other programs
+pub fn render_recipe(recipe_name: &str, params: Vec<(String, String)>) -> Result<Recipe> {
+    let PreparedRecipe {
+        content: recipe_file_content,
+        recipe: raw_recipe,
+        params: params_for_template,
+        missing_params,
+    } = prepare_recipe_template(recipe_name, &params, false)?;
+    if !missing_params.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Missing values for parameters: {}",
+            missing_params.join(", ")
+        ));
+    }
+
+    let template_values = resolve_template_values(&raw_recipe, &params_for_template)?;
+    render_recipe_content(&recipe_file_content, &template_values)
+}
+
+/// A recipe file ready to be rendered
+struct PreparedRecipe {
+    content: String,
+    /// The recipe before rendering, for its parameter definitions
+    recipe: Recipe,
+    /// Values for the parameters, as given or from the defaults
+    params: HashMap<String, String>,
+    missing_params: Vec<String>,
+}
+
+/// Retrieves and validates a recipe file, and collects the values for its parameters
+fn prepare_recipe_template(
+    recipe_name: &str,
+    params: &[(String, String)],
+    enable_user_prompt: bool,
+) -> Result<PreparedRecipe> {
+    let (recipe_file_content, recipe_parent_dir) = retrieve_recipe(recipe_name)?;
+
+    let recipe = validate_recipe_file_parameters(&recipe_file_content)?;
+
+    let (params_for_template, missing_params) = apply_values_to_parameters(
+        params,
+        recipe.parameters.clone(),
+        recipe_parent_dir,
+        enable_user_prompt,
+    )?;
+    Ok(PreparedRecipe {
+        content: recipe_file_content,
+        recipe,
+        params: params_for_template,
+        missing_params,
+    })
+}
+
+/// Checks the parameter values and converts them to the values used in the template, so invalid
+/// values are reported before the session starts
+fn resolve_template_values(
+    recipe: &Recipe,
+    params: &HashMap<String, String>,
+) -> Result<HashMap<String, JsonValue>> {
+    recipe.resolve_parameters(params).map_err(|errors| {
+        anyhow::anyhow!(
+            "Invalid values for parameters:\n{}",
+            errors
+                .iter()
+                .map(|(key, error)| format!("- {}: {}", key, error))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    })
+}
+
+/// Retrieves a recipe file with its `extends` and `include` resolved
+fn retrieve_recipe(recipe_name: &str) -> Result<(String, PathBuf)> {
+    let (recipe_file_content, recipe_parent_dir) = retrieve_recipe_file(recipe_name)?;
+    let recipe_file_content = compose_recipe_content(recipe_file_content, &recipe_parent_dir)?;
+    Ok((recipe_file_content, recipe_parent_dir))
+}
+
 /// Loads and validates a recipe from a YAML or JSON file
 ///
 /// # Arguments
@@ -84,7 +165,7 @@ pub fn load_recipe_as_template(recipe_name: &str, params: Vec<(String, String)>)
 /// - The YAML/JSON is invalid
 /// - The parameter definition does not match the template variables in the recipe file
 pub fn load_recipe(recipe_name: &str) -> Result<Recipe> {
-    let (recipe_file_content, _) = retrieve_recipe_file(recipe_name)?;
+    let (recipe_file_content, _) = retrieve_recipe(recipe_name)?;
 
     validate_recipe_file_parameters(&recipe_file_content)
 }
@@ -93,7 +174,7 @@ pub fn explain_recipe_with_parameters(
     recipe_name: &str,
     params: Vec<(String, String)>,
 ) -> Result<()> {
-    let (recipe_file_content, recipe_parent_dir) = retrieve_recipe_file(recipe_name)?;
+    let (recipe_file_content, recipe_parent_dir) = retrieve_recipe(recipe_name)?;
 
     let raw_recipe = validate_recipe_file_parameters(&recipe_file_content)?;
     print_recipe_explanation(&raw_recipe);
@@ -105,9 +186,11 @@ pub fn explain_recipe_with_parameters(
     Ok(())
 }
 
-fn validate_recipe_file_parameters(recipe_file_content: &str) -> Result<Recipe> {
+pub(crate) fn validate_recipe_file_parameters(recipe_file_content: &str) -> Result<Recipe> {
     let recipe_from_recipe_file: Recipe = parse_recipe_content(recipe_file_content)?;
     validate_optional_parameters(&recipe_from_recipe_file)?;
+    validate_parameter_definitions(&recipe_from_recipe_file)?;
+    validate_response(&recipe_from_recipe_file)?;
     validate_parameters_in_template(&recipe_from_recipe_file.parameters, recipe_file_content)?;
     Ok(recipe_from_recipe_file)
 }
@@ -183,6 +266,88 @@ fn validate_optional_parameters(recipe: &Recipe) -> Result<()> {
     }
 }
 
+fn validate_parameter_definitions(recipe: &Recipe) -> Result<()> {
+    let errors: Vec<String> = recipe
+        .parameters
+        .iter()
+        .flatten()
+        .filter_map(|param| {
+            param
+                .validate_definition()
+                .err()
+                .map(|e| format!("- {}: {}", param.key, e))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Invalid parameter definitions in the recipe:\n{}",
+            errors.join("\n")
+        ))
+    }
+}
+
+fn validate_response(recipe: &Recipe) -> Result<()> {
+    let Some(json_schema) = recipe
+        .response
+        .as_ref()
+        .and_then(|r| r.json_schema.as_ref())
+    else {
+        return Ok(());
+    };
+    if json_schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+        return Err(anyhow::anyhow!(
+            "The response json_schema must describe an object, with \"type\": \"object\""
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a recipe file, then renders the templates in its strings with the parameter values
+///
+/// Rendering after parsing keeps values such as file contents from being read as YAML or JSON,
+/// whatever quotes, colons or lines they hold.
+fn render_recipe_content<V: serde::Serialize>(
+    content: &str,
+    params: &HashMap<String, V>,
+) -> Result<Recipe> {
+    let mut value: JsonValue = if serde_json::from_str::<JsonValue>(content).is_ok() {
+        serde_json::from_str(content)?
+    } else if serde_yaml::from_str::<YamlValue>(content).is_ok() {
+        serde_yaml::from_str(content)?
+    } else {
+        return Err(anyhow::anyhow!(
+            "Unsupported file format for recipe file. Expected .yaml or .json"
+        ));
+    };
+    render_strings(&mut value, params)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+fn render_strings<V: serde::Serialize>(
+    value: &mut JsonValue,
+    params: &HashMap<String, V>,
+) -> Result<()> {
+    match value {
+        JsonValue::String(text) if ["{{", "{%", "{#"].iter().any(|t| text.contains(t)) => {
+            *text = render_content_with_params(text, params)?;
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                render_strings(item, params)?;
+            }
+        }
+        JsonValue::Object(fields) => {
+            for field in fields.values_mut() {
+                render_strings(field, params)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 fn parse_recipe_content(content: &str) -> Result<Recipe> {
     if serde_json::from_str::<JsonValue>(content).is_ok() {
         Ok(serde_json::from_str(content)?)
@@ -212,7 +377,25 @@ fn apply_values_to_parameters(
     recipe_parent_dir: PathBuf,
     enable_user_prompt: bool,
 ) -> Result<(HashMap<String, String>, Vec<String>)> {
-    let mut param_map: HashMap<String, String> = user_params.iter().cloned().collect();
+    let recipe_parameters = recipe_parameters.unwrap_or_default();
+    let mut param_map: HashMap<String, String> = HashMap::new();
+    for (key, value) in user_params {
+        let is_list = recipe_parameters
+            .iter()
+            .any(|p| &p.key == key && matches!(p.input_type, RecipeParameterInputType::List));
+        match param_map.get_mut(key) {
+            // Repeating a list parameter adds items to it, kept as a JSON array so the items
+            // can hold commas
+            Some(existing) if is_list => {
+                let mut items = split_list_value(existing);
+                items.extend(split_list_value(value));
+                *existing = serde_json::to_string(&items)?;
+            }
+            _ => {
+                param_map.insert(key.clone(), value.clone());
+            }
+        }
+    }
     let recipe_parent_dir_str = recipe_parent_dir
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid UTF-8 in recipe_dir"))?;
@@ -221,16 +404,22 @@ fn apply_values_to_parameters(
         recipe_parent_dir_str.to_string(),
     );
     let mut missing_params: Vec<String> = Vec::new();
-    for param in recipe_parameters.unwrap_or_default() {
+    for param in recipe_parameters {
         if !param_map.contains_key(&param.key) {
             match (&param.default, &param.requirement) {
                 (Some(default), _) => param_map.insert(param.key.clone(), default.clone()),
                 (None, RecipeParameterRequirement::UserPrompt) if enable_user_prompt => {
-                    let input_value = cliclack::input(format!(
-                        "Please enter {} ({})",
-                        param.key, param.description
-                    ))
-                    .interact()?;
+                    let prompt = format!("Please enter {} ({})", param.key, param.description);
+                    let input_value = match (&param.options, &param.input_type) {
+                        (Some(options), RecipeParameterInputType::String) => {
+                            let mut select = cliclack::select(prompt);
+                            for option in options {
+                                select = select.item(option.clone(), option, "");
+                            }
+                            select.interact()?
+                        }
+                        _ => cliclack::input(prompt).interact()?,
+                    };
                     param_map.insert(param.key.clone(), input_value)
                 }
                 _ => {
@@ -243,10 +432,14 @@ fn apply_values_to_parameters(
     Ok((param_map, missing_params))
 }
 
-fn render_content_with_params(content: &str, params: &HashMap<String, String>) -> Result<String> {
+fn render_content_with_params<V: serde::Serialize>(
+    content: &str,
+    params: &HashMap<String, V>,
+) -> Result<String> {
     // Create a minijinja environment and context
     let mut env = minijinja::Environment::new();
     env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
     let template: Template<'_, '_> = env
         .template_from_str(content)
         .map_err(|e: Error| anyhow::anyhow!("Invalid template syntax: {}", e.to_string()))?;
@@ -312,7 +505,7 @@ mod tests {
 
         // Test missing parameter results in error
         let content = "Hello {{ missing }}!";
-        let params = HashMap::new();
+        let params: HashMap<String, String> = HashMap::new();
         let err = render_content_with_params(content, &params).unwrap_err();
         assert!(err
             .to_string()
@@ -320,7 +513,7 @@ mod tests {
 
         // Test invalid template syntax results in error
         let content = "Hello {{ unclosed";
-        let params = HashMap::new();
+        let params: HashMap<String, String> = HashMap::new();
         let err = render_content_with_params(content, &params).unwrap_err();
         assert!(err.to_string().contains("Invalid template syntax"));
     }
@@ -529,4 +722,99 @@ mod tests {
         assert_eq!(recipe.instructions.unwrap(), "Test instructions");
         assert!(recipe.parameters.is_none());
     }
+
+    #[test]
+    fn test_load_recipe_as_template_invalid_parameter_values() {
+        let instructions_and_parameters = r#"
+            "instructions": "Review {{ count }} files on {{ branch }}",
+            "parameters": [
+                {
+                    "key": "count",
+                    "input_type": "number",
+                    "requirement": "required",
+                    "description": "How many files",
+                    "min": 1
+                },
+                {
+                    "key": "branch",
+                    "input_type": "string",
+                    "requirement": "required",
+                    "description": "Branch to review",
+                    "options": ["main", "develop"]
+                }
+            ]"#;
+        let (_temp_dir, recipe_path) = setup_recipe_file(instructions_and_parameters);
+
+        let params = vec![
+            ("count".to_string(), "0".to_string()),
+            ("branch".to_string(), "feature".to_string()),
+        ];
+        let err = load_recipe_as_template(recipe_path.to_str().unwrap(), params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid values for parameters:\n\
+             - branch: 'feature' is not one of: main, develop\n\
+             - count: 0 is less than the minimum of 1"
+        );
+    }
+
+    #[test]
+    fn test_load_recipe_as_template_list_and_file_parameters() {
+        let instructions_and_parameters = r#"
+            "instructions": "Label {% for label in labels %}[{{ label }}]{% endfor %} using {{ guide }}",
+            "parameters": [
+                {
+                    "key": "labels",
+                    "input_type": "list",
+                    "requirement": "required",
+                    "description": "Labels to apply"
+                },
+                {
+                    "key": "guide",
+                    "input_type": "file",
+                    "requirement": "required",
+                    "description": "Labeling guide"
+                }
+            ]"#;
+        let (temp_dir, recipe_path) = setup_recipe_file(instructions_and_parameters);
+        let guide_path = temp_dir.path().join("guide.md");
+        std::fs::write(&guide_path, "the \"guide\": {x}\n- item\n").unwrap();
+
+        let params = vec![
+            ("labels".to_string(), "bug, docs".to_string()),
+            ("labels".to_string(), r#"["ui, ux"]"#.to_string()),
+            (
+                "guide".to_string(),
+                guide_path.to_str().unwrap().to_string(),
+            ),
+        ];
+        let recipe = render_recipe(recipe_path.to_str().unwrap(), params).unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            "Label [bug][docs][ui, ux] using the \"guide\": {x}\n- item\n"
+        );
+    }
+
+    #[test]
+    fn test_load_recipe_invalid_parameter_definition() {
+        let instructions_and_parameters = r#"
+            "instructions": "Deploy {{ dry_run }}",
+            "parameters": [
+                {
+                    "key": "dry_run",
+                    "input_type": "boolean",
+                    "requirement": "optional",
+                    "description": "Only print what would happen",
+                    "default": "maybe"
+                }
+            ]"#;
+        let (_temp_dir, recipe_path) = setup_recipe_file(instructions_and_parameters);
+
+        let err = load_recipe(recipe_path.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid parameter definitions in the recipe:\n\
+             - dry_run: invalid default value: 'maybe' is not true or false"
+        );
+    }
 }
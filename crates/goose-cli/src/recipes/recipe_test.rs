@@ -0,0 +1,603 @@
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use goose::agents::{run_child_agent, ToolMock};
+use goose::message::{Message, MessageContent};
+use goose::providers::base::Provider;
+use goose::providers::replay::{Fixtures, RecordingProvider, ReplayProvider};
+use mcp_core::role::Role;
+use mcp_core::tool::ToolCall;
+use mcp_core::{Content, ToolError, ToolResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::recipes::recipe::render_recipe;
+use crate::recipes::sub_recipe::create_recipe_agent;
+
+/// A file of test cases for a recipe
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestCases {
+    /// Where the recorded responses are kept, relative to the cases file. Defaults to
+    /// `<cases file name>.fixtures.json` next to the cases file
+    pub fixtures: Option<String>,
+    pub cases: Vec<TestCase>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    pub name: String,
+    /// Values for the recipe parameters
+    #[serde(default)]
+    pub params: BTreeMap<String, Value>,
+    /// Overrides the recipe's prompt
+    pub prompt: Option<String>,
+    /// Files to create in the working directory before the run, by relative path
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Results to use instead of running the matching tool calls
+    #[serde(default)]
+    pub tool_outputs: Vec<ToolOutput>,
+    /// Model responses to use in order, instead of recorded ones
+    pub responses: Option<Vec<ScriptedResponse>>,
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+/// Matches tool calls by name, and by arguments when given
+///
+/// Only the arguments listed must match, nested objects are matched the same way.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCallPattern {
+    pub tool: String,
+    pub arguments: Option<Value>,
+}
+
+impl ToolCallPattern {
+    fn matches(&self, tool_call: &ToolCall) -> bool {
+        call_matches(&self.tool, self.arguments.as_ref(), tool_call)
+    }
+}
+
+impl std::fmt::Display for ToolCallPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.arguments {
+            Some(arguments) => write!(f, "{} {}", self.tool, arguments),
+            None => write!(f, "{}", self.tool),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ToolOutput {
+    pub tool: String,
+    pub arguments: Option<Value>,
+    pub output: Option<String>,
+    /// Makes the tool call fail with this message
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedResponse {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallPattern>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Expectations {
+    /// Tool calls that must be made, in this order. Other calls may happen in between
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallPattern>,
+    /// The exact set of files created, modified or deleted by the run
+    pub files_changed: Option<Vec<String>>,
+    /// Text the final reply must contain
+    #[serde(default)]
+    pub final_text_contains: Vec<String>,
+}
+
+/// What a test case run did
+struct CaseRun {
+    tool_calls: Vec<ToolCall>,
+    files_changed: BTreeSet<String>,
+    final_text: String,
+    /// Requests that had no recorded response
+    misses: usize,
+}
+
+/// Answers tool calls from a test case's tool outputs
+struct CaseToolMock {
+    outputs: Vec<ToolOutput>,
+}
+
+impl ToolMock for CaseToolMock {
+    fn mock(&self, tool_call: &ToolCall) -> Option<ToolResult<Vec<Content>>> {
+        let output = self
+            .outputs
+            .iter()
+            .find(|output| call_matches(&output.tool, output.arguments.as_ref(), tool_call))?;
+        Some(match &output.error {
+            Some(error) => Err(ToolError::ExecutionError(error.clone())),
+            None => Ok(vec![Content::text(
+                output.output.clone().unwrap_or_default(),
+            )]),
+        })
+    }
+}
+
+/// Runs the test cases of a recipe and reports the results
+///
+/// Model responses are replayed from the fixtures file. With `record`, the configured provider
+/// is called instead and its responses are saved to the fixtures file.
+pub async fn run_recipe_tests(
+    recipe_name: &str,
+    cases_path: &Path,
+    record: Option<Arc<dyn Provider>>,
+) -> Result<()> {
+    let content = std::fs::read_to_string(cases_path)
+        .with_context(|| format!("Failed to read test cases {}", cases_path.display()))?;
+    let test_cases: TestCases = serde_yaml::from_str(&content)
+        .with_context(|| format!("Invalid test cases {}", cases_path.display()))?;
+    let fixtures_path = fixtures_path(cases_path, test_cases.fixtures.as_deref());
+
+    let mut fixtures = if fixtures_path.exists() {
+        let content = std::fs::read_to_string(&fixtures_path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid fixtures {}", fixtures_path.display()))?
+    } else {
+        Fixtures::new()
+    };
+
+    let mut failed = 0;
+    for case in &test_cases.cases {
+        let failures = match run_case(recipe_name, case, &mut fixtures, record.clone()).await {
+            Ok(run) => check_expectations(&case.expect, &run),
+            Err(e) => vec![e.to_string()],
+        };
+        if failures.is_empty() {
+            println!("{} {}", style("✔").green().bold(), case.name);
+        } else {
+            failed += 1;
+            println!("{} {}", style("✘").red().bold(), case.name);
+            for failure in failures {
+                println!("    {}", failure);
+            }
+        }
+    }
+
+    if record.is_some() {
+        std::fs::write(&fixtures_path, serde_json::to_string_pretty(&fixtures)?)?;
+        println!("Recorded responses to {}", fixtures_path.display());
+    }
+
+    let passed = test_cases.cases.len() - failed;
+    println!("\n{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} cases failed",
+            failed,
+            test_cases.cases.len()
+        ));
+    }
+    Ok(())
+}
+
+fn fixtures_path(cases_path: &Path, fixtures: Option<&str>) -> PathBuf {
+    let dir = cases_path.parent().unwrap_or(Path::new("."));
+    match fixtures {
+        Some(fixtures) => dir.join(fixtures),
+        None => {
+            let stem = cases_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("cases");
+            dir.join(format!("{}.fixtures.json", stem))
+        }
+    }
+}
+
+/// The parameters of a case as recipe parameter values, lists become JSON arrays
+fn case_params(case: &TestCase) -> Result<Vec<(String, String)>> {
+    case.params
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => Ok((key.clone(), s.clone())),
+            Value::Number(_) | Value::Bool(_) => Ok((key.clone(), value.to_string())),
+            Value::Array(items)
+                if items
+                    .iter()
+                    .all(|item| !item.is_array() && !item.is_object() && !item.is_null()) =>
+            {
+                Ok((key.clone(), value.to_string()))
+            }
+            _ => Err(anyhow!(
+                "Parameter '{}' must be a string, number, boolean or list of those",
+                key
+            )),
+        })
+        .collect()
+}
+
+/// Writes the files of a case into its working directory, their paths must stay inside it
+fn seed_files(dir: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    for (path, content) in files {
+        let relative = Path::new(path);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "File '{}' must be a relative path inside the working directory",
+                path
+            ));
+        }
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Runs one case in a fresh working directory seeded with the case's files
+async fn run_case(
+    recipe_name: &str,
+    case: &TestCase,
+    fixtures: &mut Fixtures,
+    record: Option<Arc<dyn Provider>>,
+) -> Result<CaseRun> {
+    let params = case_params(case)?;
+    // Rendered before changing directory, so the recipe is found relative to where goose runs
+    let recipe = render_recipe(recipe_name, params)?;
+    let prompt = case
+        .prompt
+        .clone()
+        .or_else(|| recipe.prompt.clone())
+        .ok_or_else(|| anyhow!("The case has no prompt, and neither has the recipe"))?;
+
+    let workdir = tempfile::tempdir()?;
+    seed_files(workdir.path(), &case.files)?;
+    let workdir_path = workdir.path().canonicalize()?;
+    let workdir_str = workdir_path.to_string_lossy().to_string();
+    let before = snapshot(&workdir_path)?;
+
+    let mut replay = None;
+    let mut recording = None;
+    let provider: Arc<dyn Provider> = match (&case.responses, record) {
+        (Some(responses), _) => {
+            Arc::new(ReplayProvider::new(Fixtures::new()).with_script(scripted_messages(responses)))
+        }
+        (None, Some(inner)) => {
+            let provider = Arc::new(RecordingProvider::new(inner).with_workdir(&workdir_str));
+            recording = Some(provider.clone());
+            provider
+        }
+        (None, None) => {
+            let provider =
+                Arc::new(ReplayProvider::new(fixtures.clone()).with_workdir(&workdir_str));
+            replay = Some(provider.clone());
+            provider
+        }
+    };
+
+    // Extensions are started in the current directory, so they work in the case's directory
+    let original_dir = std::env::current_dir()?;
+    std::env::set_current_dir(&workdir_path)?;
+    let result = async {
+        let agent = create_recipe_agent(provider, &recipe).await?;
+        if let Some(json_schema) = recipe.response.and_then(|r| r.json_schema) {
+            agent.add_final_output_tool(json_schema).await?;
+        }
+        agent
+            .set_tool_mock(Arc::new(CaseToolMock {
+                outputs: case.tool_outputs.clone(),
+            }))
+            .await;
+        // Scripted cases end with their script, recorded ones with the model
+        run_child_agent(&agent, &prompt, None)
+            .await
+            .map(|run| run.messages)
+    }
+    .await;
+    std::env::set_current_dir(original_dir)?;
+    let replies = result?;
+
+    if let Some(recording) = recording {
+        fixtures.extend(recording.fixtures());
+    }
+
+    let after = snapshot(&workdir_path)?;
+    Ok(CaseRun {
+        tool_calls: tool_calls(&replies),
+        files_changed: changed_files(&before, &after),
+        final_text: final_text(&replies),
+        misses: replay.map(|replay| replay.misses().len()).unwrap_or(0),
+    })
+}
+
+fn scripted_messages(responses: &[ScriptedResponse]) -> Vec<Message> {
+    responses
+        .iter()
+        .enumerate()
+        .map(|(index, response)| {
+            let mut message = Message::assistant();
+            if !response.text.is_empty() {
+                message = message.with_text(&response.text);
+            }
+            for (call_index, call) in response.tool_calls.iter().enumerate() {
+                message = message.with_tool_request(
+                    format!("call_{}_{}", index, call_index),
+                    Ok(ToolCall::new(
+                        &call.tool,
+                        call.arguments
+                            .clone()
+                            .unwrap_or(Value::Object(Default::default())),
+                    )),
+                );
+            }
+            message
+        })
+        .collect()
+}
+
+fn tool_calls(replies: &[Message]) -> Vec<ToolCall> {
+    replies
+        .iter()
+        .filter(|message| message.role == Role::Assistant)
+        .flat_map(|message| &message.content)
+        .filter_map(|content| match content {
+            MessageContent::ToolRequest(request) => request.tool_call.clone().ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn final_text(replies: &[Message]) -> String {
+    replies
+        .iter()
+        .rev()
+        .filter(|message| message.role == Role::Assistant)
+        .map(|message| message.as_concat_text())
+        .find(|text| !text.trim().is_empty())
+        .unwrap_or_default()
+}
+
+/// Contents of the files under `dir`, by path relative to `dir`
+fn snapshot(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let relative = path.strip_prefix(dir)?.to_string_lossy().replace('\\', "/");
+                files.insert(relative, std::fs::read(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn changed_files(
+    before: &BTreeMap<String, Vec<u8>>,
+    after: &BTreeMap<String, Vec<u8>>,
+) -> BTreeSet<String> {
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|path| before.get(*path) != after.get(*path))
+        .cloned()
+        .collect()
+}
+
+fn call_matches(tool: &str, arguments: Option<&Value>, tool_call: &ToolCall) -> bool {
+    tool == tool_call.name
+        && arguments.is_none_or(|arguments| value_matches(arguments, &tool_call.arguments))
+}
+
+/// Whether `actual` contains everything in `expected`
+fn value_matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| value_matches(value, actual))
+        }),
+        _ => expected == actual,
+    }
+}
+
+/// Describes each way the run did not meet the expectations
+fn check_expectations(expect: &Expectations, run: &CaseRun) -> Vec<String> {
+    let mut failures = Vec::new();
+    if run.misses > 0 {
+        failures.push(format!(
+            "{} model request(s) had no recorded response, run with --record to update the fixtures",
+            run.misses
+        ));
+    }
+
+    let mut remaining = run.tool_calls.iter();
+    for pattern in &expect.tool_calls {
+        if !remaining.any(|tool_call| pattern.matches(tool_call)) {
+            failures.push(format!("expected a call to {}", pattern));
+            break;
+        }
+    }
+    if !expect.tool_calls.is_empty() && !failures.is_empty() {
+        let made: Vec<String> = run
+            .tool_calls
+            .iter()
+            .map(|tool_call| format!("{} {}", tool_call.name, tool_call.arguments))
+            .collect();
+        failures.push(format!("tool calls made: [{}]", made.join(", ")));
+    }
+
+    if let Some(files_changed) = &expect.files_changed {
+        let expected: BTreeSet<String> = files_changed.iter().cloned().collect();
+        if expected != run.files_changed {
+            failures.push(format!(
+                "expected files changed {:?}, got {:?}",
+                expected, run.files_changed
+            ));
+        }
+    }
+
+    for text in &expect.final_text_contains {
+        if !run.final_text.contains(text) {
+            failures.push(format!(
+                "expected the final text to contain {:?}, got {:?}",
+                text, run.final_text
+            ));
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run() -> CaseRun {
+        CaseRun {
+            tool_calls: vec![
+                ToolCall::new("developer__shell", json!({"command": "cargo test"})),
+                ToolCall::new(
+                    "developer__text_editor",
+                    json!({"command": "write", "path": "src/lib.rs", "file_text": "fn main() {}"}),
+                ),
+            ],
+            files_changed: BTreeSet::from(["src/lib.rs".to_string()]),
+            final_text: "All tests pass".to_string(),
+            misses: 0,
+        }
+    }
+
+    fn expectations(yaml: &str) -> Expectations {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_check_expectations() {
+        let expect = expectations(
+            r#"
+tool_calls:
+  - tool: developer__shell
+  - tool: developer__text_editor
+    arguments: {command: write, path: src/lib.rs}
+files_changed: [src/lib.rs]
+final_text_contains: [tests pass]
+"#,
+        );
+        assert!(check_expectations(&expect, &run()).is_empty());
+
+        // Calls must be made in order, and all listed arguments must match
+        let expect = expectations(
+            r#"
+tool_calls:
+  - tool: developer__text_editor
+  - tool: developer__shell
+files_changed: []
+final_text_contains: [failed]
+"#,
+        );
+        let failures = check_expectations(&expect, &run());
+        assert_eq!(failures.len(), 4);
+        assert_eq!(failures[0], "expected a call to developer__shell");
+        assert!(failures[2].starts_with("expected files changed {}"));
+    }
+
+    #[test]
+    fn test_tool_mock() {
+        let mock = CaseToolMock {
+            outputs: serde_yaml::from_str(
+                r#"
+- tool: developer__shell
+  arguments: {command: cargo test}
+  output: "test result: ok"
+- tool: developer__shell
+  error: not allowed
+"#,
+            )
+            .unwrap(),
+        };
+
+        let result = mock
+            .mock(&ToolCall::new(
+                "developer__shell",
+                json!({"command": "cargo test"}),
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(result[0].as_text(), Some("test result: ok"));
+        assert!(mock
+            .mock(&ToolCall::new(
+                "developer__shell",
+                json!({"command": "rm -rf /"})
+            ))
+            .unwrap()
+            .is_err());
+        assert!(mock
+            .mock(&ToolCall::new("developer__text_editor", json!({})))
+            .is_none());
+    }
+
+    #[test]
+    fn test_case_params_and_files() {
+        let case: TestCase = serde_yaml::from_str(
+            "{name: c, params: {tags: [a, 'b, c', 3], words: 50}, files: {src/lib.rs: ''}}",
+        )
+        .unwrap();
+        assert_eq!(
+            case_params(&case).unwrap(),
+            vec![
+                ("tags".to_string(), r#"["a","b, c",3]"#.to_string()),
+                ("words".to_string(), "50".to_string())
+            ]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        seed_files(dir.path(), &case.files).unwrap();
+        assert!(dir.path().join("src/lib.rs").is_file());
+        for path in ["../escape.txt", "/tmp/escape.txt", "src/../../escape.txt"] {
+            let files = BTreeMap::from([(path.to_string(), String::new())]);
+            assert!(seed_files(dir.path(), &files).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "old").unwrap();
+        std::fs::write(dir.path().join("README.md"), "readme").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "notes").unwrap();
+        let before = snapshot(dir.path()).unwrap();
+
+        std::fs::write(dir.path().join("src/lib.rs"), "new").unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "main").unwrap();
+        std::fs::remove_file(dir.path().join("notes.md")).unwrap();
+        let after = snapshot(dir.path()).unwrap();
+
+        assert_eq!(
+            changed_files(&before, &after),
+            BTreeSet::from([
+                "notes.md".to_string(),
+                "src/lib.rs".to_string(),
+                "src/main.rs".to_string()
+            ])
+        );
+    }
+}
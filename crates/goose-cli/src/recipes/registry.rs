@@ -0,0 +1,659 @@
+//! Installs recipes from registries
+//!
+//! A registry is a directory or an HTTPS location with an `index.json` listing the versions of
+//! each recipe:
+//!
+//! ```json
+//! {"recipes": {"code-review": [
+//!     {"version": "1.2.0", "path": "code-review/1.2.0.yaml", "sha256": "…", "signature": "…"}
+//! ]}}
+//! ```
+//!
+//! Paths are relative to the index and may not leave its directory. Each version is downloaded
+//! once and kept in the data directory, installing a recipe records which of the cached versions
+//! is used. Signatures are base64 ed25519 signatures of the recipe name, version and file, see
+//! [`signed_message`], checked against the key trusted for the recipe's author contact. Signing
+//! the name and version keeps a signed file from being served as another recipe or version.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use goose::config::{Config, APP_STRATEGY};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::recipe::{validate_recipe_file_parameters, RECIPE_FILE_EXTENSIONS};
+
+pub const GOOSE_RECIPE_REGISTRIES_CONFIG_KEY: &str = "GOOSE_RECIPE_REGISTRIES";
+pub const GOOSE_RECIPE_TRUSTED_KEYS_CONFIG_KEY: &str = "GOOSE_RECIPE_TRUSTED_KEYS";
+pub const GOOSE_RECIPE_REQUIRE_SIGNATURE_CONFIG_KEY: &str = "GOOSE_RECIPE_REQUIRE_SIGNATURE";
+
+const INDEX_FILE: &str = "index.json";
+const INSTALLED_FILE: &str = "installed.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RegistryIndex {
+    #[serde(default)]
+    pub recipes: BTreeMap<String, Vec<IndexEntry>>,
+}
+
+/// A version of a recipe in a registry
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexEntry {
+    pub version: String,
+    /// Location of the recipe file, relative to the index
+    pub path: String,
+    /// Hex sha256 of the recipe file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Base64 ed25519 signature of the [`signed_message`] of the recipe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Where a registry is, from the `GOOSE_RECIPE_REGISTRIES` config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistrySource {
+    Url(String),
+    Directory(PathBuf),
+}
+
+impl RegistrySource {
+    pub fn parse(location: &str) -> Result<Self> {
+        if location.starts_with("https://") {
+            Ok(Self::Url(location.trim_end_matches('/').to_string()))
+        } else if location.contains("://") {
+            Err(anyhow!(
+                "Recipe registry {} must be an https URL or a local directory",
+                location
+            ))
+        } else {
+            Ok(Self::Directory(PathBuf::from(location)))
+        }
+    }
+
+    async fn fetch(&self, path: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Url(base) => {
+                let url = format!("{}/{}", base, path);
+                let response = reqwest::get(&url)
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .with_context(|| format!("Failed to download {}", url))?;
+                Ok(response.bytes().await?.to_vec())
+            }
+            Self::Directory(dir) => {
+                let file = dir.join(path);
+                fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))
+            }
+        }
+    }
+
+    pub async fn index(&self) -> Result<RegistryIndex> {
+        let content = self.fetch(INDEX_FILE).await?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Invalid recipe registry index in {}", self))
+    }
+}
+
+impl std::fmt::Display for RegistrySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(url) => write!(f, "{}", url),
+            Self::Directory(dir) => write!(f, "{}", dir.display()),
+        }
+    }
+}
+
+/// Whether an installed recipe was signed by an author whose key is trusted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verification {
+    Verified,
+    /// Signed, but no key is trusted for the author
+    Untrusted,
+    Unsigned,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledRecipe {
+    pub name: String,
+    pub version: String,
+    /// The version requirement updates stay within
+    pub requirement: String,
+    pub source: String,
+    pub file: PathBuf,
+    pub verification: Verification,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub installed_at: DateTime<Utc>,
+}
+
+/// Splits `name@requirement` into the name and a semver requirement, any version by default
+pub fn parse_recipe_spec(spec: &str) -> Result<(String, VersionReq)> {
+    let (name, requirement) = match spec.split_once('@') {
+        Some((name, requirement)) => (
+            name,
+            VersionReq::parse(requirement)
+                .with_context(|| format!("Invalid version requirement '{}'", requirement))?,
+        ),
+        None => (spec, VersionReq::STAR),
+    };
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(anyhow!("Invalid recipe name '{}'", name));
+    }
+    Ok((name.to_string(), requirement))
+}
+
+/// What the author of a recipe signs: the name and version of the recipe on a line each,
+/// after a `goose-recipe` line, followed by the recipe file
+pub fn signed_message(name: &str, version: &Version, content: &[u8]) -> Vec<u8> {
+    let mut message = format!("goose-recipe\n{}\n{}\n", name, version).into_bytes();
+    message.extend_from_slice(content);
+    message
+}
+
+/// Checks that the path of an index entry stays within the registry
+fn check_entry_path(path: &str) -> Result<()> {
+    let escapes = path.is_empty()
+        || path.starts_with(['/', '\\'])
+        || path.split(['/', '\\']).any(|part| part == "..")
+        || Path::new(path)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(anyhow!(
+            "Recipe path '{}' must be relative to the registry index, without '..'",
+            path
+        ));
+    }
+    Ok(())
+}
+
+pub struct RecipeRegistry {
+    sources: Vec<RegistrySource>,
+    /// Where downloaded versions and the installed recipes are kept
+    root: PathBuf,
+    /// Base64 ed25519 public keys by author contact
+    trusted_keys: HashMap<String, String>,
+    require_signature: bool,
+}
+
+impl RecipeRegistry {
+    pub fn new(
+        sources: Vec<RegistrySource>,
+        root: PathBuf,
+        trusted_keys: HashMap<String, String>,
+        require_signature: bool,
+    ) -> Self {
+        Self {
+            sources,
+            root,
+            trusted_keys,
+            require_signature,
+        }
+    }
+
+    pub fn from_config() -> Result<Self> {
+        let config = Config::global();
+        let sources = config
+            .get_param::<Vec<String>>(GOOSE_RECIPE_REGISTRIES_CONFIG_KEY)
+            .unwrap_or_default()
+            .iter()
+            .map(|location| RegistrySource::parse(location))
+            .collect::<Result<Vec<_>>>()?;
+        let root = choose_app_strategy(APP_STRATEGY.clone())
+            .expect("goose requires a home dir")
+            .data_dir()
+            .join("recipes");
+        Ok(Self::new(
+            sources,
+            root,
+            config
+                .get_param(GOOSE_RECIPE_TRUSTED_KEYS_CONFIG_KEY)
+                .unwrap_or_default(),
+            config
+                .get_param(GOOSE_RECIPE_REQUIRE_SIGNATURE_CONFIG_KEY)
+                .unwrap_or(false),
+        ))
+    }
+
+    pub fn sources(&self) -> &[RegistrySource] {
+        &self.sources
+    }
+
+    pub fn installed(&self) -> Result<Vec<InstalledRecipe>> {
+        Ok(self.read_installed()?.into_values().collect())
+    }
+
+    /// The content and directory of an installed recipe
+    ///
+    /// With a version requirement, the newest downloaded version that meets it is used instead
+    /// of the installed one.
+    pub fn find_installed(&self, spec: &str) -> Result<Option<(String, PathBuf)>> {
+        let Ok((name, requirement)) = parse_recipe_spec(spec) else {
+            return Ok(None);
+        };
+        let file = if spec.contains('@') {
+            self.cached_versions(&name)?
+                .into_iter()
+                .filter(|(version, _)| requirement.matches(version))
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, file)| file)
+        } else {
+            self.read_installed()?
+                .remove(&name)
+                .map(|installed| installed.file)
+        };
+        let Some(file) = file else {
+            return Ok(None);
+        };
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read recipe file {}", file.display()))?;
+        let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(Some((content, dir)))
+    }
+
+    /// All versions of each recipe in each registry
+    pub async fn available(&self) -> Result<Vec<(RegistrySource, RegistryIndex)>> {
+        let mut indexes = Vec::new();
+        for source in &self.sources {
+            indexes.push((source.clone(), source.index().await?));
+        }
+        Ok(indexes)
+    }
+
+    /// Installs the newest version meeting the requirement, from any registry
+    pub async fn install(&self, spec: &str) -> Result<InstalledRecipe> {
+        let (name, requirement) = parse_recipe_spec(spec)?;
+        self.install_matching(&name, &requirement).await
+    }
+
+    /// Installs newer versions of installed recipes, within the requirement they were installed
+    /// with
+    ///
+    /// Returns the recipes that changed version, with the version they had before.
+    pub async fn update(&self, name: Option<&str>) -> Result<Vec<(String, InstalledRecipe)>> {
+        let installed = self.read_installed()?;
+        if let Some(name) = name {
+            if !installed.contains_key(name) {
+                return Err(anyhow!("Recipe '{}' is not installed", name));
+            }
+        }
+
+        let mut updated = Vec::new();
+        for recipe in installed.into_values() {
+            if name.is_some_and(|name| name != recipe.name) {
+                continue;
+            }
+            let requirement = VersionReq::parse(&recipe.requirement)?;
+            let new = self.install_matching(&recipe.name, &requirement).await?;
+            if new.version != recipe.version {
+                updated.push((recipe.version, new));
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn install_matching(
+        &self,
+        name: &str,
+        requirement: &VersionReq,
+    ) -> Result<InstalledRecipe> {
+        if self.sources.is_empty() {
+            return Err(anyhow!(
+                "No recipe registries configured, add them to {} in your config",
+                GOOSE_RECIPE_REGISTRIES_CONFIG_KEY
+            ));
+        }
+
+        let mut best: Option<(Version, &RegistrySource, IndexEntry)> = None;
+        for source in &self.sources {
+            let index = source.index().await?;
+            for entry in index.recipes.get(name).into_iter().flatten() {
+                let version = Version::parse(&entry.version).with_context(|| {
+                    format!(
+                        "Invalid version {} of {} in {}",
+                        entry.version, name, source
+                    )
+                })?;
+                if requirement.matches(&version)
+                    && best.as_ref().is_none_or(|(best, _, _)| version > *best)
+                {
+                    best = Some((version, source, entry.clone()));
+                }
+            }
+        }
+        let Some((version, source, entry)) = best else {
+            return Err(anyhow!(
+                "No version of recipe '{}' matching '{}' in the configured registries",
+                name,
+                requirement
+            ));
+        };
+
+        check_entry_path(&entry.path)
+            .with_context(|| format!("Invalid entry for {} {} in {}", name, version, source))?;
+        let extension = Path::new(&entry.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| RECIPE_FILE_EXTENSIONS.contains(ext))
+            .unwrap_or("yaml");
+        let file = self
+            .root
+            .join(name)
+            .join(format!("{}.{}", version, extension));
+        let content = match fs::read(&file) {
+            Ok(content) => content,
+            Err(_) => source.fetch(&entry.path).await?,
+        };
+
+        let (verification, author) = self.verify(name, &version, &entry, &content)?;
+        if !file.exists() {
+            fs::create_dir_all(file.parent().expect("recipe file has a parent"))?;
+            fs::write(&file, &content)?;
+        }
+
+        let installed = InstalledRecipe {
+            name: name.to_string(),
+            version: version.to_string(),
+            requirement: requirement.to_string(),
+            source: source.to_string(),
+            file,
+            verification,
+            author,
+            installed_at: Utc::now(),
+        };
+        let mut all = self.read_installed()?;
+        all.insert(name.to_string(), installed.clone());
+        fs::write(
+            self.root.join(INSTALLED_FILE),
+            serde_json::to_string_pretty(&all)?,
+        )?;
+        Ok(installed)
+    }
+
+    /// Checks a downloaded recipe against its index entry and the trusted keys
+    fn verify(
+        &self,
+        name: &str,
+        version: &Version,
+        entry: &IndexEntry,
+        content: &[u8],
+    ) -> Result<(Verification, Option<String>)> {
+        if let Some(expected) = &entry.sha256 {
+            let actual = format!("{:x}", Sha256::digest(content));
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(anyhow!(
+                    "Checksum of {} {} does not match the registry index",
+                    name,
+                    version
+                ));
+            }
+        }
+
+        let text = std::str::from_utf8(content)
+            .with_context(|| format!("Recipe {} {} is not text", name, version))?;
+        let recipe = validate_recipe_file_parameters(text)
+            .with_context(|| format!("Recipe {} {} is invalid", name, version))?;
+        let author = recipe.author.and_then(|author| author.contact);
+
+        let verification = match (&entry.signature, &author) {
+            (None, _) => Verification::Unsigned,
+            (Some(signature), Some(author)) if self.trusted_keys.contains_key(author) => {
+                let key = base64::engine::general_purpose::STANDARD
+                    .decode(&self.trusted_keys[author])
+                    .with_context(|| format!("Invalid trusted key for {}", author))?;
+                let signature = base64::engine::general_purpose::STANDARD
+                    .decode(signature)
+                    .map_err(|_| anyhow!("Invalid signature for {} {}", name, version))?;
+                UnparsedPublicKey::new(&ED25519, key)
+                    .verify(&signed_message(name, version, content), &signature)
+                    .map_err(|_| {
+                        anyhow!(
+                            "Signature of {} {} does not match the key trusted for {}",
+                            name,
+                            version,
+                            author
+                        )
+                    })?;
+                Verification::Verified
+            }
+            (Some(_), _) => Verification::Untrusted,
+        };
+
+        if self.require_signature && verification != Verification::Verified {
+            return Err(anyhow!(
+                "Recipe {} {} is not signed by a trusted author, and {} is set",
+                name,
+                version,
+                GOOSE_RECIPE_REQUIRE_SIGNATURE_CONFIG_KEY
+            ));
+        }
+        Ok((verification, author))
+    }
+
+    fn read_installed(&self) -> Result<BTreeMap<String, InstalledRecipe>> {
+        let path = self.root.join(INSTALLED_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn cached_versions(&self, name: &str) -> Result<Vec<(Version, PathBuf)>> {
+        let dir = self.root.join(name);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let version = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| Version::parse(stem).ok());
+            if let Some(version) = version {
+                versions.push((version, path));
+            }
+        }
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::json;
+
+    fn recipe(version: &str) -> String {
+        format!(
+            "title: Review\ndescription: Reviews code\nprompt: Review version {}\nauthor:\n  contact: alice\n",
+            version
+        )
+    }
+
+    fn write_index(dir: &Path, entries: Vec<serde_json::Value>) {
+        fs::write(
+            dir.join(INDEX_FILE),
+            json!({"recipes": {"review": entries}}).to_string(),
+        )
+        .unwrap();
+    }
+
+    fn registry(
+        registry_dir: &Path,
+        root: &Path,
+        trusted_keys: HashMap<String, String>,
+    ) -> RecipeRegistry {
+        RecipeRegistry::new(
+            vec![RegistrySource::Directory(registry_dir.to_path_buf())],
+            root.to_path_buf(),
+            trusted_keys,
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_install_and_update() {
+        let registry_dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let mut entries = Vec::new();
+        for version in ["1.0.0", "1.2.0", "2.0.0"] {
+            fs::write(
+                registry_dir.path().join(format!("{}.yaml", version)),
+                recipe(version),
+            )
+            .unwrap();
+            entries.push(json!({"version": version, "path": format!("{}.yaml", version)}));
+        }
+        write_index(registry_dir.path(), entries.clone());
+        let registry = registry(registry_dir.path(), root.path(), HashMap::new());
+
+        let installed = registry.install("review@^1").await.unwrap();
+        assert_eq!(installed.version, "1.2.0");
+        assert_eq!(installed.verification, Verification::Unsigned);
+        assert_eq!(installed.author.as_deref(), Some("alice"));
+        assert!(registry.install("review@3").await.is_err());
+        assert!(registry.install("missing").await.is_err());
+
+        let (content, _) = registry.find_installed("review").unwrap().unwrap();
+        assert!(content.contains("version 1.2.0"));
+        registry.install("review@2").await.unwrap();
+        registry.install("review@=1.0.0").await.unwrap();
+        let (content, _) = registry.find_installed("review@^2").unwrap().unwrap();
+        assert!(content.contains("version 2.0.0"));
+        assert!(registry.find_installed("other").unwrap().is_none());
+
+        // Updates stay within the requirement the recipe was installed with
+        registry.install("review@^1").await.unwrap();
+        fs::write(registry_dir.path().join("1.3.0.yaml"), recipe("1.3.0")).unwrap();
+        entries.push(json!({"version": "1.3.0", "path": "1.3.0.yaml"}));
+        write_index(registry_dir.path(), entries);
+        let updated = registry.update(None).await.unwrap();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].0, "1.2.0");
+        assert_eq!(updated[0].1.version, "1.3.0");
+        assert!(registry.update(Some("other")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verification() {
+        let registry_dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key =
+            base64::engine::general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+
+        let content = recipe("1.0.0");
+        fs::write(registry_dir.path().join("1.0.0.yaml"), &content).unwrap();
+        let version = Version::parse("1.0.0").unwrap();
+        let signature = base64::engine::general_purpose::STANDARD.encode(
+            key_pair
+                .sign(&signed_message("review", &version, content.as_bytes()))
+                .as_ref(),
+        );
+        write_index(
+            registry_dir.path(),
+            vec![json!({
+                "version": "1.0.0",
+                "path": "1.0.0.yaml",
+                "sha256": format!("{:x}", Sha256::digest(content.as_bytes())),
+                "signature": signature,
+            })],
+        );
+
+        let trusted = HashMap::from([("alice".to_string(), public_key)]);
+        let installed = registry(registry_dir.path(), root.path(), trusted.clone())
+            .install("review")
+            .await
+            .unwrap();
+        assert_eq!(installed.verification, Verification::Verified);
+
+        let untrusted = tempfile::tempdir().unwrap();
+        let installed = registry(registry_dir.path(), untrusted.path(), HashMap::new())
+            .install("review")
+            .await
+            .unwrap();
+        assert_eq!(installed.verification, Verification::Untrusted);
+        let mut strict = registry(registry_dir.path(), untrusted.path(), HashMap::new());
+        strict.require_signature = true;
+        assert!(strict.install("review").await.is_err());
+
+        // A changed recipe fails the checksum, and the signature without it
+        let tampered = tempfile::tempdir().unwrap();
+        fs::write(registry_dir.path().join("1.0.0.yaml"), recipe("6.6.6")).unwrap();
+        let error = registry(registry_dir.path(), tampered.path(), trusted.clone())
+            .install("review")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Checksum"));
+        write_index(
+            registry_dir.path(),
+            vec![json!({"version": "1.0.0", "path": "1.0.0.yaml", "signature": signature})],
+        );
+        let error = registry(registry_dir.path(), tampered.path(), trusted.clone())
+            .install("review")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("does not match the key"));
+
+        // The signature of a version does not hold for the same file served as another version
+        fs::write(registry_dir.path().join("1.0.0.yaml"), &content).unwrap();
+        write_index(
+            registry_dir.path(),
+            vec![json!({"version": "1.0.1", "path": "1.0.0.yaml", "signature": signature})],
+        );
+        let replayed = tempfile::tempdir().unwrap();
+        let error = registry(registry_dir.path(), replayed.path(), trusted)
+            .install("review")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("does not match the key"));
+    }
+
+    #[tokio::test]
+    async fn test_entry_paths_stay_in_the_registry() {
+        for path in ["1.0.0.yaml", "review/1.0.0.yaml", "./review/1.0.0.yaml"] {
+            assert!(check_entry_path(path).is_ok(), "{}", path);
+        }
+        for path in [
+            "",
+            "../1.0.0.yaml",
+            "review/../../1.0.0.yaml",
+            "/etc/recipe.yaml",
+            "..\\1.0.0.yaml",
+            "\\server\\recipe.yaml",
+        ] {
+            assert!(check_entry_path(path).is_err(), "{}", path);
+        }
+
+        let registry_dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        write_index(
+            registry_dir.path(),
+            vec![json!({"version": "1.0.0", "path": "../outside.yaml"})],
+        );
+        let error = registry(registry_dir.path(), root.path(), HashMap::new())
+            .install("review")
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("must be relative"));
+    }
+}
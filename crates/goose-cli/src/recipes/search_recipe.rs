@@ -6,6 +6,7 @@ use std::{env, fs};
 use crate::recipes::recipe::RECIPE_FILE_EXTENSIONS;
 
 use super::github_recipe::{retrieve_recipe_from_github, GOOSE_RECIPE_GITHUB_REPO_CONFIG_KEY};
+use super::registry::RecipeRegistry;
 
 const GOOSE_RECIPE_PATH_ENV_VAR: &str = "GOOSE_RECIPE_PATH";
 
@@ -19,6 +20,12 @@ pub fn retrieve_recipe_file(recipe_name: &str) -> Result<(String, PathBuf)> {
         return read_recipe_file(path);
     }
     retrieve_recipe_from_local_path(recipe_name).or_else(|e| {
+        // Recipes installed from a registry, by name or name@version
+        if let Ok(registry) = RecipeRegistry::from_config() {
+            if let Some(installed) = registry.find_installed(recipe_name)? {
+                return Ok(installed);
+            }
+        }
         if let Some(recipe_repo_full_name) = configured_github_recipe_repo() {
             retrieve_recipe_from_github(recipe_name, &recipe_repo_full_name)
         } else {
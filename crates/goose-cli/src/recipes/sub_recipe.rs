@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use goose::agents::{run_child_agent, Agent, SubRecipeRunner};
+use goose::config::ExtensionConfigManager;
+use goose::providers::base::Provider;
+use goose::providers::create_with_lead;
+use goose::recipe::{Recipe, SubRecipe};
+use std::sync::Arc;
+
+use crate::recipes::recipe::{load_recipe, render_recipe};
+
+/// How deep sub-recipes may call other sub-recipes
+const MAX_SUB_RECIPE_DEPTH: usize = 3;
+
+/// Model responses a sub-recipe may take before it is stopped
+const MAX_SUB_RECIPE_TURNS: usize = 50;
+
+/// Prompt used when a sub-recipe has no prompt of its own
+const DEFAULT_SUB_RECIPE_PROMPT: &str = "Complete the task described in your instructions.";
+
+/// Runs sub-recipes in a child agent that shares the parent's provider
+///
+/// A child recipe with its own `settings` gets a provider of the same kind, with the parent's
+/// model config and the child's settings on top. The child does not see the parent's
+/// conversation. It runs in the same GOOSE_MODE as the parent, but there is no way to ask the
+/// user from inside it, so the tool calls that need approval are declined.
+pub struct ChildAgentRunner {
+    provider: Arc<dyn Provider>,
+    /// The name the provider was created with, needed to create one with other settings
+    provider_name: Option<String>,
+    depth: usize,
+}
+
+impl ChildAgentRunner {
+    pub fn new(provider_name: String, provider: Arc<dyn Provider>) -> Self {
+        Self {
+            provider,
+            provider_name: Some(provider_name),
+            depth: 1,
+        }
+    }
+}
+
+/// Loads the recipes the sub-recipes point to, so their parameters can be offered as tool inputs
+pub fn load_sub_recipes(sub_recipes: Vec<SubRecipe>) -> Result<Vec<(SubRecipe, Recipe)>> {
+    sub_recipes
+        .into_iter()
+        .map(|sub_recipe| {
+            if sub_recipe.name.is_empty()
+                || !sub_recipe
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(anyhow!(
+                    "Invalid sub-recipe name '{}', use only letters, digits, '_' and '-'",
+                    sub_recipe.name
+                ));
+            }
+            let recipe = load_recipe(&sub_recipe.path)
+                .map_err(|e| anyhow!("Failed to load sub-recipe {}: {}", sub_recipe.name, e))?;
+            Ok((sub_recipe, recipe))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SubRecipeRunner for ChildAgentRunner {
+    async fn run(&self, sub_recipe: &SubRecipe, params: Vec<(String, String)>) -> Result<String> {
+        let recipe = render_recipe(&sub_recipe.path, params)?;
+        let provider = match (&recipe.settings, &self.provider_name) {
+            (Some(settings), Some(provider_name)) => create_with_lead(
+                provider_name,
+                settings.apply(self.provider.get_model_config()),
+            )?,
+            _ => self.provider.clone(),
+        };
+        let agent = create_agent(provider, self.provider_name.clone(), &recipe, self.depth).await?;
+
+        let prompt = recipe
+            .prompt
+            .unwrap_or_else(|| DEFAULT_SUB_RECIPE_PROMPT.to_string());
+        let run = run_child_agent(&agent, &prompt, Some(MAX_SUB_RECIPE_TURNS)).await?;
+        if run.exhausted {
+            return Err(anyhow!(
+                "The sub-recipe used its budget of {} turns before finishing",
+                MAX_SUB_RECIPE_TURNS
+            ));
+        }
+        run.last_text()
+            .ok_or_else(|| anyhow!("The sub-recipe finished without an answer"))
+    }
+}
+
+/// Creates an agent with a recipe's extensions, instructions and sub-recipes
+///
+/// Uses the recipe's extensions, or the enabled extensions if the recipe doesn't list any.
+/// Without a provider name no provider can be created for other settings, so sub-recipes run on
+/// `provider` as it is and their `settings` are ignored; [`ChildAgentRunner::new`] applies them.
+pub async fn create_recipe_agent(provider: Arc<dyn Provider>, recipe: &Recipe) -> Result<Agent> {
+    create_agent(provider, None, recipe, 1).await
+}
+
+async fn create_agent(
+    provider: Arc<dyn Provider>,
+    provider_name: Option<String>,
+    recipe: &Recipe,
+    depth: usize,
+) -> Result<Agent> {
+    let agent = Agent::new();
+    agent.update_provider(provider.clone()).await?;
+
+    let extensions = match &recipe.extensions {
+        Some(extensions) => extensions.clone(),
+        None => ExtensionConfigManager::get_all()?
+            .into_iter()
+            .filter(|ext| ext.enabled)
+            .map(|ext| ext.config)
+            .collect(),
+    };
+    for extension in extensions {
+        let name = extension.name();
+        agent
+            .add_extension(extension)
+            .await
+            .map_err(|e| anyhow!("Failed to start extension {}: {}", name, e))?;
+    }
+
+    if let Some(instructions) = &recipe.instructions {
+        agent.extend_system_prompt(instructions.clone()).await;
+    }
+
+    if let Some(sub_recipes) = &recipe.sub_recipes {
+        if depth >= MAX_SUB_RECIPE_DEPTH {
+            return Err(anyhow!(
+                "Sub-recipes are nested more than {} levels deep",
+                MAX_SUB_RECIPE_DEPTH
+            ));
+        }
+        let runner = Arc::new(ChildAgentRunner {
+            provider,
+            provider_name,
+            depth: depth + 1,
+        });
+        agent
+            .add_sub_recipes(load_sub_recipes(sub_recipes.clone())?, runner)
+            .await;
+    }
+
+    Ok(agent)
+}
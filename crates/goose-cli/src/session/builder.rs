@@ -2,14 +2,18 @@ use console::style;
 use goose::agents::extension::ExtensionError;
 use goose::agents::Agent;
 use goose::config::{Config, ExtensionConfig, ExtensionConfigManager};
-use goose::providers::create;
+use goose::providers::create_with_lead;
+use goose::recipe::{Response, Settings, SubRecipe};
 use goose::session;
 use goose::session::Identifier;
 use mcp_client::transport::Error as McpClientError;
 use std::process;
+use std::sync::Arc;
+
+use crate::recipes::sub_recipe::{load_sub_recipes, ChildAgentRunner};
 
 use super::output;
-use super::Session;
+use super::{OutputFormat, Session};
 
 /// Configuration for building a new Goose session
 ///
@@ -33,6 +37,14 @@ pub struct SessionBuilderConfig {
     pub extensions_override: Option<Vec<ExtensionConfig>>,
     /// Any additional system prompt to append to the default
     pub additional_system_prompt: Option<String>,
+    /// Recipes the agent can run as tools
+    pub sub_recipes: Option<Vec<SubRecipe>>,
+    /// The format the agent's final answer must have
+    pub response: Option<Response>,
+    /// Model settings overriding the configured ones
+    pub settings: Option<Settings>,
+    /// How headless runs report progress and results
+    pub output_format: OutputFormat,
     /// Enable debug printing
     pub debug: bool,
     /// Maximum number of consecutive identical tool calls allowed
@@ -40,6 +52,9 @@ pub struct SessionBuilderConfig {
 }
 
 pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
+    // Json output formats keep stdout for their events, the prompts are on stderr already
+    output::set_json_output(session_config.output_format != OutputFormat::Text);
+
     // Load config and get provider/model
     let config = Config::global();
 
@@ -50,12 +65,28 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
     let model: String = config
         .get_param("GOOSE_MODEL")
         .expect("No model configured. Run 'goose configure' first");
-    let model_config = goose::model::ModelConfig::new(model.clone());
+    let mut model_config = goose::model::ModelConfig::new_with_catalog(
+        model.clone(),
+        goose::providers::catalog::ModelCatalog::global(),
+    )
+    .with_configured_overrides();
+    if let Some(settings) = &session_config.settings {
+        model_config = settings.apply(model_config);
+    }
 
     // Create the agent
     let agent: Agent = Agent::new();
-    let new_provider = create(&provider_name, model_config).unwrap();
-    let _ = agent.update_provider(new_provider).await;
+    let new_provider = match create_with_lead(&provider_name, model_config) {
+        Ok(provider) => provider,
+        Err(e) => {
+            output::render_error(&format!(
+                "Failed to create the {} provider: {}",
+                provider_name, e
+            ));
+            process::exit(1);
+        }
+    };
+    let _ = agent.update_provider(new_provider.clone()).await;
 
     // Configure tool monitoring if max_tool_repetitions is set
     if let Some(max_repetitions) = session_config.max_tool_repetitions {
@@ -106,6 +137,7 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session::get_path(id)
     };
 
+    let mut resumed_plan = None;
     if session_config.resume && !session_config.no_session {
         // Read the session metadata
         let metadata = session::read_metadata(&session_file).unwrap_or_else(|e| {
@@ -135,6 +167,7 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
                 }
             }
         }
+        resumed_plan = metadata.plan;
     }
 
     // Setup extensions for the agent
@@ -166,8 +199,33 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         }
     }
 
+    if let Some(sub_recipes) = session_config.sub_recipes {
+        let sub_recipes = load_sub_recipes(sub_recipes).unwrap_or_else(|e| {
+            output::render_error(&e.to_string());
+            process::exit(1);
+        });
+        agent
+            .add_sub_recipes(
+                sub_recipes,
+                Arc::new(ChildAgentRunner::new(provider_name.clone(), new_provider)),
+            )
+            .await;
+    }
+
+    if let Some(json_schema) = session_config.response.and_then(|r| r.json_schema) {
+        if let Err(e) = agent.add_final_output_tool(json_schema).await {
+            output::render_error(&e.to_string());
+            process::exit(1);
+        }
+    }
+
     // Create new session
-    let mut session = Session::new(agent, session_file.clone(), session_config.debug);
+    let mut session = Session::new(
+        agent,
+        session_file.clone(),
+        session_config.debug,
+        session_config.output_format,
+    );
 
     // Add extensions if provided
     for extension_str in session_config.extensions {
@@ -211,6 +269,15 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
         session.agent.override_system_prompt(override_prompt).await;
     }
 
-    output::display_session_info(session_config.resume, &provider_name, &model, &session_file);
+    if session_config.output_format == OutputFormat::Text {
+        output::display_session_info(session_config.resume, &provider_name, &model, &session_file);
+    }
+
+    // Pick up the plan where the session left off
+    if let Some(plan) = resumed_plan {
+        if let Err(e) = session.set_plan(Some(plan)).await {
+            output::render_error(&format!("Failed to restore the plan: {}", e));
+        }
+    }
     session
 }
@@ -0,0 +1,160 @@
+use goose::message::{Message, MessageContent};
+use goose::session::SessionMetadata;
+use mcp_core::role::Role;
+use serde::Serialize;
+use serde_json::Value;
+
+/// How a headless run reports its progress and result
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Rendered for people reading a terminal
+    #[default]
+    Text,
+    /// A single JSON object with the result once the run is done
+    Json,
+    /// Newline-delimited JSON events as the run progresses, ending with the result
+    StreamJson,
+}
+
+/// An event of a run, printed as one line of JSON
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputEvent {
+    Message {
+        role: Role,
+        text: String,
+    },
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    ToolResult {
+        id: String,
+        is_error: bool,
+        output: String,
+    },
+    /// Tokens used by the latest model call, and by the whole session so far
+    Usage {
+        input_tokens: Option<i32>,
+        output_tokens: Option<i32>,
+        total_tokens: Option<i32>,
+        accumulated_total_tokens: Option<i32>,
+    },
+    /// The outcome of the run, `result` is the final output for recipes with a response
+    /// schema, the final text reply otherwise
+    Result {
+        is_error: bool,
+        result: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        session_file: Option<String>,
+    },
+}
+
+impl OutputEvent {
+    /// Splits a message into the events it contains
+    pub fn from_message(message: &Message) -> Vec<OutputEvent> {
+        let mut events = Vec::new();
+        let text = message.as_concat_text();
+        if !text.trim().is_empty() {
+            events.push(OutputEvent::Message {
+                role: message.role.clone(),
+                text,
+            });
+        }
+
+        for content in &message.content {
+            match content {
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(tool_call) = &request.tool_call {
+                        events.push(OutputEvent::ToolCall {
+                            id: request.id.clone(),
+                            name: tool_call.name.clone(),
+                            arguments: tool_call.arguments.clone(),
+                        });
+                    }
+                }
+                MessageContent::ToolResponse(response) => {
+                    let (is_error, output) = match &response.tool_result {
+                        Ok(contents) => (
+                            false,
+                            contents
+                                .iter()
+                                .filter_map(|content| content.as_text())
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        ),
+                        Err(e) => (true, e.to_string()),
+                    };
+                    events.push(OutputEvent::ToolResult {
+                        id: response.id.clone(),
+                        is_error,
+                        output,
+                    });
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    pub fn from_metadata(metadata: &SessionMetadata) -> Self {
+        OutputEvent::Usage {
+            input_tokens: metadata.input_tokens,
+            output_tokens: metadata.output_tokens,
+            total_tokens: metadata.total_tokens,
+            accumulated_total_tokens: metadata.accumulated_total_tokens,
+        }
+    }
+
+    pub fn emit(&self) {
+        println!(
+            "{}",
+            serde_json::to_string(self).expect("events serialize to JSON")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use mcp_core::{Content, ToolError};
+    use serde_json::json;
+
+    #[test]
+    fn test_events_from_message() {
+        let message = Message::assistant()
+            .with_text("Running the tests")
+            .with_tool_request(
+                "1",
+                Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+            );
+        let events: Vec<Value> = OutputEvent::from_message(&message)
+            .iter()
+            .map(|event| serde_json::to_value(event).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                json!({"type": "message", "role": "assistant", "text": "Running the tests"}),
+                json!({"type": "tool_call", "id": "1", "name": "developer__shell", "arguments": {"command": "ls"}}),
+            ]
+        );
+
+        let message = Message::user()
+            .with_tool_response("1", Ok(vec![Content::text("a.txt")]))
+            .with_tool_response("2", Err(ToolError::ExecutionError("boom".to_string())));
+        let events: Vec<Value> = OutputEvent::from_message(&message)
+            .iter()
+            .map(|event| serde_json::to_value(event).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                json!({"type": "tool_result", "id": "1", "is_error": false, "output": "a.txt"}),
+                json!({"type": "tool_result", "id": "2", "is_error": true, "output": "Execution failed: boom"}),
+            ]
+        );
+    }
+}
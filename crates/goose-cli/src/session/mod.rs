@@ -1,5 +1,6 @@
 mod builder;
 mod completion;
+mod events;
 mod input;
 mod output;
 mod prompt;
@@ -7,6 +8,7 @@ mod thinking;
 
 pub use builder::{build_session, SessionBuilderConfig};
 use console::Color;
+pub use events::OutputFormat;
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::permission::Permission;
 use goose::permission::PermissionConfirmation;
@@ -17,11 +19,13 @@ use anyhow::{Context, Result};
 use completion::GooseCompleter;
 use etcetera::choose_app_strategy;
 use etcetera::AppStrategy;
+use events::OutputEvent;
 use goose::agents::extension::{Envs, ExtensionConfig};
-use goose::agents::{Agent, SessionConfig};
+use goose::agents::{Agent, Plan, SessionConfig};
 use goose::config::Config;
 use goose::message::{Message, MessageContent};
 use goose::session;
+use goose_mcp::CheckpointStore;
 use input::InputResult;
 use mcp_core::handler::ToolError;
 use mcp_core::prompt::PromptMessage;
@@ -29,7 +33,7 @@ use mcp_core::prompt::PromptMessage;
 use rand::{distributions::Alphanumeric, Rng};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio;
@@ -47,6 +51,12 @@ pub struct Session {
     completion_cache: Arc<std::sync::RwLock<CompletionCache>>,
     debug: bool, // New field for debug mode
     run_mode: RunMode,
+    output_format: OutputFormat,
+    // The plan as it was last rendered, to show the checklist again when it changes
+    rendered_plan: Option<Plan>,
+    // Snapshots of the working tree for `goose session rewind`, and the turn of the last one
+    checkpoints: Option<Arc<CheckpointStore>>,
+    checkpointed_turn: Option<usize>,
 }
 
 // Cache structure for completion data
@@ -102,8 +112,65 @@ pub async fn classify_planner_response(
     }
 }
 
+/// Show the plan as a checklist when the agent changed it since it was last shown
+async fn render_plan_if_changed(agent: &Agent, rendered_plan: &mut Option<Plan>) {
+    let plan = agent.plan().await;
+    if plan != *rendered_plan {
+        if let Some(plan) = &plan {
+            output::render_plan(plan);
+        }
+        *rendered_plan = plan;
+    }
+}
+
+fn is_no_session_file(session_file: &Path) -> bool {
+    matches!(session_file.to_str(), Some("/dev/null") | Some("NUL"))
+}
+
+/// Indices of the messages that start a turn: user messages with text, not tool responses
+pub fn turn_starts(messages: &[Message]) -> Vec<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| {
+            m.role == mcp_core::role::Role::User && !m.as_concat_text().trim().is_empty()
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The checkpoints of a recorded session in a git repository, unless turned off with
+/// GOOSE_CHECKPOINTS=false
+fn open_checkpoints(session_file: &Path) -> Option<Arc<CheckpointStore>> {
+    let enabled = Config::global()
+        .get_param::<bool>("GOOSE_CHECKPOINTS")
+        .unwrap_or(true);
+    if !enabled || is_no_session_file(session_file) {
+        return None;
+    }
+    let session_id = session_file.file_stem()?.to_string_lossy();
+    let working_dir = std::env::current_dir().ok()?;
+    CheckpointStore::open(&working_dir, &session_id)
+        .ok()
+        .map(Arc::new)
+}
+
+/// Snapshots the working tree as the checkpoint of `turn`, git runs off the async executor
+async fn checkpoint_turn(checkpoints: Arc<CheckpointStore>, turn: usize) {
+    match tokio::task::spawn_blocking(move || checkpoints.create(turn)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::warn!("Failed to checkpoint the working tree: {}", e),
+        Err(e) => tracing::warn!("Failed to checkpoint the working tree: {}", e),
+    }
+}
+
 impl Session {
-    pub fn new(agent: Agent, session_file: PathBuf, debug: bool) -> Self {
+    pub fn new(
+        agent: Agent,
+        session_file: PathBuf,
+        debug: bool,
+        output_format: OutputFormat,
+    ) -> Self {
         let messages = match session::read_messages(&session_file) {
             Ok(msgs) => msgs,
             Err(e) => {
@@ -115,13 +182,40 @@ impl Session {
         Session {
             agent,
             messages,
-            session_file,
             completion_cache: Arc::new(std::sync::RwLock::new(CompletionCache::new())),
             debug,
             run_mode: RunMode::Normal,
+            output_format,
+            rendered_plan: None,
+            checkpoints: open_checkpoints(&session_file),
+            checkpointed_turn: None,
+            session_file,
         }
     }
 
+    /// Whether the session is not recorded, with --no-session
+    fn no_session(&self) -> bool {
+        is_no_session_file(&self.session_file)
+    }
+
+    /// Set the plan the agent works through and save it in the session
+    pub async fn set_plan(&mut self, plan: Option<Plan>) -> Result<()> {
+        self.agent.set_plan(plan.clone()).await;
+        if !self.no_session() {
+            let mut metadata = session::read_metadata(&self.session_file)?;
+            metadata.plan = plan;
+            session::storage::save_messages_with_metadata(
+                &self.session_file,
+                &metadata,
+                &self.messages,
+            )?;
+        }
+        if self.output_format == OutputFormat::Text {
+            render_plan_if_changed(&self.agent, &mut self.rendered_plan).await;
+        }
+        Ok(())
+    }
+
     /// Add a stdio extension to the session
     ///
     /// # Arguments
@@ -585,6 +679,11 @@ impl Session {
             }
         }
 
+        if let Err(e) = self.extract_memories().await {
+            output::hide_thinking();
+            eprintln!("Failed to remember facts from this session: {}", e);
+        }
+
         println!(
             "\nClosing session. Recorded to {}",
             self.session_file.display()
@@ -592,6 +691,71 @@ impl Session {
         Ok(())
     }
 
+    /// Offers to remember the durable facts of the session, when GOOSE_MEMORY_EXTRACTION is on
+    ///
+    /// The facts are stored without asking when GOOSE_MEMORY_EXTRACTION_CONFIRM is false.
+    async fn extract_memories(&self) -> Result<()> {
+        if !session::memory_extraction_enabled() {
+            return Ok(());
+        }
+        let provider = self.agent.provider().await?;
+
+        output::show_thinking();
+        let memories = session::extract_memories(&self.messages, provider).await?;
+        output::hide_thinking();
+        if memories.is_empty() {
+            return Ok(());
+        }
+
+        let confirm = Config::global()
+            .get_param::<bool>("GOOSE_MEMORY_EXTRACTION_CONFIRM")
+            .unwrap_or(true);
+        let selected: Vec<usize> = if confirm {
+            cliclack::multiselect(
+                "remember for future sessions: (use \"space\" to toggle and \"enter\" to submit)",
+            )
+            .required(false)
+            .items(
+                &memories
+                    .iter()
+                    .enumerate()
+                    .map(|(i, memory)| (i, memory.data.as_str(), memory.category.as_str()))
+                    .collect::<Vec<_>>(),
+            )
+            .initial_values((0..memories.len()).collect())
+            .interact()?
+        } else {
+            (0..memories.len()).collect()
+        };
+        if selected.is_empty() {
+            return Ok(());
+        }
+
+        let context = self
+            .session_file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let router = crate::commands::mcp::memory_router();
+        for memory in selected.iter().map(|&i| &memories[i]) {
+            let tags: Vec<&str> = memory.tags.iter().map(String::as_str).collect();
+            router
+                .remember(
+                    context,
+                    &memory.category,
+                    &memory.data,
+                    &tags,
+                    memory.is_global,
+                )
+                .await?;
+        }
+        println!(
+            "{}",
+            console::style(format!("Remembered {} facts.", selected.len())).green()
+        );
+        Ok(())
+    }
+
     async fn plan_with_reasoner_model(
         &mut self,
         plan_messages: Vec<Message>,
@@ -629,8 +793,11 @@ impl Session {
                     // clear the messages before acting on the plan
                     self.messages.clear();
                     // add the plan response as a user message
-                    let plan_message = Message::user().with_text(plan_response.as_concat_text());
+                    let plan_text = plan_response.as_concat_text();
+                    let plan_message = Message::user().with_text(&plan_text);
                     self.messages.push(plan_message);
+                    // track the steps of the plan as a checklist
+                    self.set_plan(Plan::from_text(&plan_text)).await?;
                     // act on the plan
                     output::show_thinking();
                     self.process_agent_response(true).await?;
@@ -659,8 +826,43 @@ impl Session {
     }
 
     /// Process a single message and exit
+    ///
+    /// Fails if the agent was required to end with a final output but did not produce one.
     pub async fn headless(&mut self, message: String) -> Result<()> {
-        self.process_message(message).await
+        self.process_message(message).await?;
+
+        let (is_error, result) = if self.agent.requires_final_output().await {
+            match self.agent.final_output().await {
+                Some(final_output) => (false, final_output),
+                None => (
+                    true,
+                    Value::String("The agent did not produce a valid final output".to_string()),
+                ),
+            }
+        } else {
+            let final_text = self
+                .messages
+                .iter()
+                .rev()
+                .find(|message| message.role == mcp_core::role::Role::Assistant)
+                .map(|message| message.as_concat_text())
+                .unwrap_or_default();
+            (false, Value::String(final_text))
+        };
+
+        if self.output_format != OutputFormat::Text {
+            OutputEvent::Result {
+                is_error,
+                result: result.clone(),
+                session_file: (!self.no_session()).then(|| self.session_file.display().to_string()),
+            }
+            .emit();
+        }
+
+        if is_error {
+            return Err(anyhow::anyhow!("{}", result.as_str().unwrap_or_default()));
+        }
+        Ok(())
     }
 
     async fn process_agent_response(&mut self, interactive: bool) -> Result<()> {
@@ -751,14 +953,46 @@ impl Session {
                             }
                             // otherwise we have a model/tool to render
                             else {
+                                // Snapshot the working tree once per turn, before its first
+                                // tool call can change files
+                                if message.is_tool_call() {
+                                    let turn = turn_starts(&self.messages).len();
+                                    if turn > 0 && self.checkpointed_turn != Some(turn) {
+                                        self.checkpointed_turn = Some(turn);
+                                        if let Some(checkpoints) = self.checkpoints.clone() {
+                                            checkpoint_turn(checkpoints, turn).await;
+                                        }
+                                    }
+                                }
                                 self.messages.push(message.clone());
 
                                 // No need to update description on assistant messages
                                 session::persist_messages(&self.session_file, &self.messages, None).await?;
 
-                                if interactive {output::hide_thinking()};
-                                output::render_message(&message, self.debug);
-                                if interactive {output::show_thinking()};
+                                match self.output_format {
+                                    OutputFormat::Text => {
+                                        if interactive {output::hide_thinking()};
+                                        output::render_message(&message, self.debug);
+                                        if message.content.iter().any(|content| matches!(content, MessageContent::ToolResponse(_))) {
+                                            render_plan_if_changed(&self.agent, &mut self.rendered_plan).await;
+                                        }
+                                        if interactive {output::show_thinking()};
+                                    }
+                                    OutputFormat::StreamJson => {
+                                        for event in OutputEvent::from_message(&message) {
+                                            event.emit();
+                                        }
+                                        if message.role == mcp_core::role::Role::Assistant {
+                                            // The agent records the usage of each model call in the session metadata
+                                            if let Ok(metadata) = session::read_metadata(&self.session_file) {
+                                                if metadata.total_tokens.is_some() {
+                                                    OutputEvent::from_metadata(&metadata).emit();
+                                                }
+                                            }
+                                        }
+                                    }
+                                    OutputFormat::Json => {}
+                                }
                             }
                         }
                         Some(Err(e)) => {
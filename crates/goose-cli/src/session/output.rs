@@ -1,5 +1,6 @@
 use bat::WrappingMode;
 use console::{style, Color};
+use goose::agents::{Plan, StepStatus};
 use goose::config::Config;
 use goose::message::{Message, MessageContent, ToolRequest, ToolResponse};
 use mcp_core::prompt::PromptArgument;
@@ -8,6 +9,7 @@ use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // Re-export theme for use in main
 #[derive(Clone, Copy)]
@@ -45,6 +47,25 @@ impl Theme {
     }
 }
 
+/// Whether stdout is kept for the events of a json output format, with other output on stderr
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Keep stdout for json output, sending text and errors to stderr like the prompts
+pub fn set_json_output(json_output: bool) {
+    JSON_OUTPUT.store(json_output, Ordering::Relaxed);
+}
+
+/// Prints a line to stdout, or to stderr when stdout is kept for json output
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if JSON_OUTPUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
 thread_local! {
     static CURRENT_THEME: RefCell<Theme> = RefCell::new(
         std::env::var("GOOSE_CLI_THEME").ok()
@@ -153,7 +174,7 @@ pub fn render_text(text: &str, color: Option<Color>, dim: bool) {
     } else {
         styled_text = styled_text.green();
     }
-    println!("\n{}\n", styled_text);
+    outln!("\n{}\n", styled_text);
 }
 
 pub fn render_enter_plan_mode() {
@@ -179,8 +200,25 @@ pub fn render_exit_plan_mode() {
     println!("\n{}\n", style("Exiting plan mode.").green().bold());
 }
 
+/// Render the plan as a checklist
+pub fn render_plan(plan: &Plan) {
+    println!("\n{}", style("Plan").green().bold());
+    for (index, step) in plan.steps.iter().enumerate() {
+        let line = format!("{}. {}", index + 1, step.description);
+        match step.status {
+            StepStatus::Pending => println!("  [ ] {}", line),
+            StepStatus::InProgress => {
+                println!("  {} {}", style("[~]").yellow(), style(line).bold())
+            }
+            StepStatus::Completed => println!("  {} {}", style("[x]").green(), line),
+            StepStatus::Skipped => println!("  {} {}", style("[-]").dim(), style(line).dim()),
+        }
+    }
+    println!();
+}
+
 pub fn goose_mode_message(text: &str) {
-    println!("\n{}", style(text).yellow(),);
+    outln!("\n{}", style(text).yellow(),);
 }
 
 fn render_tool_request(req: &ToolRequest, theme: Theme, debug: bool) {
@@ -231,7 +269,7 @@ fn render_tool_response(resp: &ToolResponse, theme: Theme, debug: bool) {
 }
 
 pub fn render_error(message: &str) {
-    println!("\n  {} {}\n", style("error:").red().bold(), message);
+    outln!("\n  {} {}\n", style("error:").red().bold(), message);
 }
 
 pub fn render_prompts(prompts: &HashMap<String, Vec<String>>) {
@@ -280,36 +318,36 @@ pub fn render_prompt_info(info: &PromptInfo) {
 }
 
 pub fn render_extension_success(name: &str) {
-    println!();
-    println!(
+    outln!();
+    outln!(
         "  {} extension `{}`",
         style("added").green(),
         style(name).cyan(),
     );
-    println!();
+    outln!();
 }
 
 pub fn render_extension_error(name: &str, error: &str) {
-    println!();
-    println!(
+    outln!();
+    outln!(
         "  {} to add extension {}",
         style("failed").red(),
         style(name).red()
     );
-    println!();
-    println!("{}", style(error).dim());
-    println!();
+    outln!();
+    outln!("{}", style(error).dim());
+    outln!();
 }
 
 pub fn render_builtin_success(names: &str) {
-    println!();
-    println!(
+    outln!();
+    outln!(
         "  {} builtin{}: {}",
         style("added").green(),
         if names.contains(',') { "s" } else { "" },
         style(names).cyan()
     );
-    println!();
+    outln!();
 }
 
 pub fn render_builtin_error(names: &str, error: &str) {
@@ -0,0 +1,393 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+use thiserror::Error;
+
+/// Checkpoints are stored as commits under this ref namespace, one ref per turn under a
+/// directory per session, so they never show up in the branch history or touch the user's index
+/// and stash
+const CHECKPOINT_REF_PREFIX: &str = "refs/goose/checkpoints/";
+
+/// Number of checkpoints kept per session, those of the earliest turns are pruned
+const MAX_CHECKPOINTS: usize = 1000;
+
+const CHECKPOINT_SUBJECT: &str = "goose checkpoint";
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("{0} is not inside a git repository")]
+    NotARepository(PathBuf),
+    #[error("Failed to run git: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("git {command} failed: {stderr}")]
+    Git { command: String, stderr: String },
+}
+
+/// A snapshot of the working tree, taken before the first tool call of a turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// The checkpoint commit
+    pub commit: String,
+    /// A tree with the files of the working tree that git doesn't ignore, tracked or not
+    pub tree: String,
+    /// The turn of the session the checkpoint was taken in, counting from 1
+    pub turn: usize,
+}
+
+/// Snapshots of a git working tree, one per turn of a session.
+///
+/// Snapshots are built in a private index file inside the git directory, so taking one
+/// leaves the user's staging area, stash and branches untouched. A store only sees the
+/// checkpoints of its own session, sessions sharing a repository never restore each other's
+/// snapshots. Files git ignores are neither snapshotted nor restored.
+pub struct CheckpointStore {
+    work_tree: PathBuf,
+    index_file: PathBuf,
+    ref_prefix: String,
+    lock: Mutex<()>,
+}
+
+/// Session ids are names chosen by the user, keep the characters refs allow and escape the rest
+fn ref_component(session_id: &str) -> String {
+    session_id
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl CheckpointStore {
+    /// Opens the store of `session_id` for the repository containing `dir`
+    pub fn open(dir: &Path, session_id: &str) -> Result<Self, CheckpointError> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel", "--absolute-git-dir"])
+            .current_dir(dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(CheckpointError::NotARepository(dir.to_path_buf()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        let (Some(work_tree), Some(git_dir)) = (lines.next(), lines.next()) else {
+            // Bare repositories have no working tree to snapshot
+            return Err(CheckpointError::NotARepository(dir.to_path_buf()));
+        };
+
+        let session = ref_component(session_id);
+        Ok(Self {
+            work_tree: PathBuf::from(work_tree),
+            index_file: Path::new(git_dir).join(format!("goose-checkpoint-index-{}", session)),
+            ref_prefix: format!("{}{}/", CHECKPOINT_REF_PREFIX, session),
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn work_tree(&self) -> &Path {
+        &self.work_tree
+    }
+
+    /// Lists the checkpoints of the session, earliest turn first
+    pub fn list(&self) -> Result<Vec<Checkpoint>, CheckpointError> {
+        let output = self.git(
+            &[
+                "for-each-ref",
+                "--format=%(objectname) %(tree) %(refname)",
+                &self.ref_prefix,
+            ],
+            false,
+        )?;
+
+        let mut checkpoints: Vec<Checkpoint> = output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, ' ');
+                let commit = parts.next()?.to_string();
+                let tree = parts.next()?.to_string();
+                let turn = parts.next()?.strip_prefix(&self.ref_prefix)?.parse().ok()?;
+                Some(Checkpoint { commit, tree, turn })
+            })
+            .collect();
+        checkpoints.sort_by_key(|checkpoint| checkpoint.turn);
+        Ok(checkpoints)
+    }
+
+    /// Returns the checkpoint of the earliest turn from `turn` on
+    pub fn first_from(&self, turn: usize) -> Result<Option<Checkpoint>, CheckpointError> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|checkpoint| checkpoint.turn >= turn))
+    }
+
+    /// Snapshots the working tree as it is now as the checkpoint of `turn`, replacing the one
+    /// the turn had
+    pub fn create(&self, turn: usize) -> Result<Checkpoint, CheckpointError> {
+        let _guard = self.lock.lock().unwrap();
+        let tree = self.snapshot()?;
+        let commit = self
+            .git(
+                &[
+                    "-c",
+                    "user.name=goose",
+                    "-c",
+                    "user.email=goose@localhost",
+                    "commit-tree",
+                    &tree,
+                    "-m",
+                    &format!("{} of turn {}", CHECKPOINT_SUBJECT, turn),
+                ],
+                false,
+            )?
+            .trim()
+            .to_string();
+        self.git(
+            &[
+                "update-ref",
+                &format!("{}{}", self.ref_prefix, turn),
+                &commit,
+            ],
+            false,
+        )?;
+
+        let checkpoints = self.list()?;
+        if checkpoints.len() > MAX_CHECKPOINTS {
+            let excess = checkpoints.len() - MAX_CHECKPOINTS;
+            for old in &checkpoints[..excess] {
+                self.delete(old)?;
+            }
+        }
+
+        Ok(Checkpoint { commit, tree, turn })
+    }
+
+    /// The files that differ between the working tree and `checkpoint`, sorted
+    pub fn changed_since(&self, checkpoint: &Checkpoint) -> Result<Vec<String>, CheckpointError> {
+        let _guard = self.lock.lock().unwrap();
+        let current = self.snapshot()?;
+        Ok(self
+            .diff(&checkpoint.tree, &current)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    /// Restores the working tree to how it was when `checkpoint` was taken.
+    ///
+    /// Files that changed since are restored and files created since are removed. Files git
+    /// ignores, the index and HEAD are left as they are.
+    pub fn restore(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        let _guard = self.lock.lock().unwrap();
+        let current = self.snapshot()?;
+        let mut restored = Vec::new();
+        for (status, path) in self.diff(&current, &checkpoint.tree)? {
+            if status == 'D' {
+                let path = self.work_tree.join(path);
+                if path.is_file() || path.is_symlink() {
+                    std::fs::remove_file(&path)?;
+                    self.remove_empty_parents(&path);
+                }
+            } else {
+                restored.push(path);
+            }
+        }
+
+        if !restored.is_empty() {
+            self.read_tree_into_index(&checkpoint.tree)?;
+            let mut checkout_index = vec!["checkout-index", "--force", "--"];
+            checkout_index.extend(restored.iter().map(String::as_str));
+            self.git(&checkout_index, true)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the checkpoints of `turn` and the turns after it, the turns a rewind removes
+    pub fn discard_from(&self, turn: usize) -> Result<(), CheckpointError> {
+        for checkpoint in self.list()? {
+            if checkpoint.turn >= turn {
+                self.delete(&checkpoint)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        self.git(
+            &[
+                "update-ref",
+                "-d",
+                &format!("{}{}", self.ref_prefix, checkpoint.turn),
+            ],
+            false,
+        )?;
+        Ok(())
+    }
+
+    /// Writes the files of the working tree that git doesn't ignore to a tree
+    fn snapshot(&self) -> Result<String, CheckpointError> {
+        // The private index is kept between snapshots, so unchanged files aren't hashed again
+        self.git(&["add", "--all", "--", "."], true)?;
+        Ok(self.git(&["write-tree"], true)?.trim().to_string())
+    }
+
+    /// The changes from tree `from` to tree `to`, as the status letter of `git diff` and path
+    fn diff(&self, from: &str, to: &str) -> Result<Vec<(char, String)>, CheckpointError> {
+        let output = self.git(
+            &[
+                "diff-tree",
+                "-r",
+                "-z",
+                "--no-renames",
+                "--name-status",
+                from,
+                to,
+            ],
+            false,
+        )?;
+        let mut fields = output.split('\0').filter(|field| !field.is_empty());
+        let mut changes = Vec::new();
+        while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+            changes.push((status.chars().next().unwrap_or('M'), path.to_string()));
+        }
+        Ok(changes)
+    }
+
+    /// Replaces the private index with `tree`
+    fn read_tree_into_index(&self, tree: &str) -> Result<(), CheckpointError> {
+        self.git(&["read-tree", tree], true)?;
+        Ok(())
+    }
+
+    fn remove_empty_parents(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if current == self.work_tree || std::fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+
+    fn git(&self, args: &[&str], private_index: bool) -> Result<String, CheckpointError> {
+        let mut command = Command::new("git");
+        command
+            .args(args)
+            .current_dir(&self.work_tree)
+            // Paths are file names, not patterns
+            .env("GIT_LITERAL_PATHSPECS", "1");
+        if private_index {
+            command.env("GIT_INDEX_FILE", &self.index_file);
+        }
+
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(CheckpointError::Git {
+                command: args.join(" "),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.path().join("tracked.txt"), "original\n").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        git(&["add", "."]);
+        git(&[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "initial",
+        ]);
+        dir
+    }
+
+    #[test]
+    fn test_checkpoints_are_kept_per_turn() {
+        let dir = init_repo();
+        let store = CheckpointStore::open(dir.path(), "session").unwrap();
+        let path = |name: &str| dir.path().join(name);
+
+        let first = store.create(1).unwrap();
+        std::fs::write(path("tracked.txt"), "edited\n").unwrap();
+        assert_eq!(store.changed_since(&first).unwrap(), vec!["tracked.txt"]);
+
+        // Taking the checkpoint of a turn again replaces it
+        store.create(3).unwrap();
+        let third = store.create(3).unwrap();
+        assert_eq!(store.list().unwrap(), vec![first.clone(), third.clone()]);
+        assert!(store.changed_since(&third).unwrap().is_empty());
+        assert_eq!(store.first_from(2).unwrap(), Some(third));
+
+        store.discard_from(2).unwrap();
+        assert_eq!(store.list().unwrap(), vec![first]);
+    }
+
+    #[test]
+    fn test_restore_reverts_the_working_tree() {
+        let dir = init_repo();
+        let store = CheckpointStore::open(dir.path(), "session").unwrap();
+        let path = |name: &str| dir.path().join(name);
+        let read = |name: &str| std::fs::read_to_string(path(name)).unwrap();
+        std::fs::write(path("untracked.txt"), "keep me\n").unwrap();
+
+        let checkpoint = store.create(1).unwrap();
+        std::fs::write(path("tracked.txt"), "edited\n").unwrap();
+        std::fs::create_dir_all(path("new/nested")).unwrap();
+        std::fs::write(path("new/nested/file.txt"), "new\n").unwrap();
+        store.create(2).unwrap();
+        std::fs::write(path("tracked.txt"), "edited twice\n").unwrap();
+        std::fs::remove_file(path("untracked.txt")).unwrap();
+
+        // Files git ignores are left alone
+        std::fs::write(path("ignored.txt"), "ignored\n").unwrap();
+
+        store.restore(&checkpoint).unwrap();
+
+        assert_eq!(read("tracked.txt"), "original\n");
+        assert_eq!(read("untracked.txt"), "keep me\n");
+        assert_eq!(read("ignored.txt"), "ignored\n");
+        assert!(!path("new").exists());
+        assert!(store.changed_since(&checkpoint).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sessions_have_their_own_checkpoints() {
+        let dir = init_repo();
+        let store = CheckpointStore::open(dir.path(), "20250101_1").unwrap();
+        let other = CheckpointStore::open(dir.path(), "my session/2").unwrap();
+
+        let checkpoint = store.create(1).unwrap();
+        other.create(1).unwrap();
+        other.create(2).unwrap();
+        assert_eq!(store.list().unwrap(), vec![checkpoint]);
+        assert_eq!(other.list().unwrap().len(), 2);
+
+        other.discard_from(1).unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
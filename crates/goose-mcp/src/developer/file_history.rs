@@ -0,0 +1,195 @@
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Number of previous versions kept for each file
+const MAX_VERSIONS_PER_FILE: usize = 20;
+
+/// Serializes the read-modify-write of the records between extension processes
+const LOCK_FILE: &str = "history.lock";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryRecord {
+    path: PathBuf,
+    /// `None` for a version where the file did not exist
+    versions: Vec<Option<String>>,
+}
+
+/// Undo history for files edited by the text editor.
+///
+/// Every version is mirrored to a JSON file in the history directory (one per edited
+/// path), so `undo_edit` keeps working after the extension is restarted and sees the versions
+/// recorded by other processes. Records are updated under a lock on the history directory and
+/// replaced atomically, so concurrent edits neither lose versions nor read a partial record.
+pub struct FileHistory {
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<PathBuf, Vec<Option<String>>>>,
+}
+
+impl FileHistory {
+    /// Creates a history persisted under the given directory
+    pub fn persistent(dir: PathBuf) -> Self {
+        let _ = std::fs::create_dir_all(&dir);
+        Self {
+            dir: Some(dir),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a history that only lives as long as the process
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records `content` as the most recent previous version of `path`, `None` when the file
+    /// did not exist
+    pub fn push(&self, path: &Path, content: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        let _lock = self.lock();
+        let versions = self.load_entry(&mut entries, path);
+        versions.push(content);
+        if versions.len() > MAX_VERSIONS_PER_FILE {
+            let excess = versions.len() - MAX_VERSIONS_PER_FILE;
+            versions.drain(..excess);
+        }
+        let versions = versions.clone();
+        self.persist(path, &versions);
+    }
+
+    /// Removes and returns the most recent previous version of `path`
+    pub fn pop(&self, path: &Path) -> Option<Option<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        let _lock = self.lock();
+        let versions = self.load_entry(&mut entries, path);
+        let previous = versions.pop()?;
+        let versions = versions.clone();
+        self.persist(path, &versions);
+        Some(previous)
+    }
+
+    /// Locks the history directory until the returned file is dropped, the history is used
+    /// unlocked if the lock can't be taken
+    fn lock(&self) -> Option<File> {
+        let dir = self.dir.as_ref()?;
+        let file =
+            File::create(dir.join(LOCK_FILE)).and_then(|file| file.lock_exclusive().map(|_| file));
+        match file {
+            Ok(file) => Some(file),
+            Err(e) => {
+                tracing::warn!("Failed to lock the file history: {}", e);
+                None
+            }
+        }
+    }
+
+    fn load_entry<'a>(
+        &self,
+        entries: &'a mut HashMap<PathBuf, Vec<Option<String>>>,
+        path: &Path,
+    ) -> &'a mut Vec<Option<String>> {
+        let versions = entries.entry(path.to_path_buf()).or_default();
+        if self.dir.is_some() {
+            // A persisted history is read again every time, other extension processes (lsp
+            // renames) record versions in it too
+            *versions = self.read_from_disk(path);
+        }
+        versions
+    }
+
+    fn record_path(&self, path: &Path) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+        let name: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Some(dir.join(format!("{}.json", name)))
+    }
+
+    fn read_from_disk(&self, path: &Path) -> Vec<Option<String>> {
+        self.record_path(path)
+            .and_then(|record_path| std::fs::read_to_string(record_path).ok())
+            .and_then(|data| serde_json::from_str::<HistoryRecord>(&data).ok())
+            // Guard against hash collisions between different paths
+            .filter(|record| record.path == path)
+            .map(|record| record.versions)
+            .unwrap_or_default()
+    }
+
+    fn persist(&self, path: &Path, versions: &[Option<String>]) {
+        let Some(record_path) = self.record_path(path) else {
+            return;
+        };
+
+        if versions.is_empty() {
+            let _ = std::fs::remove_file(record_path);
+            return;
+        }
+
+        let record = HistoryRecord {
+            path: path.to_path_buf(),
+            versions: versions.to_vec(),
+        };
+        match serde_json::to_string(&record) {
+            Ok(data) => {
+                let tmp_path = record_path.with_extension("json.tmp");
+                let written = std::fs::write(&tmp_path, data)
+                    .and_then(|_| std::fs::rename(&tmp_path, &record_path));
+                if let Err(e) = written {
+                    let _ = std::fs::remove_file(tmp_path);
+                    tracing::warn!("Failed to persist file history: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to serialize file history: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("edited.txt");
+
+        let history = FileHistory::persistent(dir.path().join("history"));
+        history.push(&file, None);
+        history.push(&file, Some("first".to_string()));
+        history.push(&file, Some("second".to_string()));
+        drop(history);
+
+        let history = FileHistory::persistent(dir.path().join("history"));
+        assert_eq!(history.pop(&file), Some(Some("second".to_string())));
+        assert_eq!(history.pop(&file), Some(Some("first".to_string())));
+        assert_eq!(history.pop(&file), Some(None));
+        assert_eq!(history.pop(&file), None);
+
+        // Versions recorded by another process are seen
+        let other = FileHistory::persistent(dir.path().join("history"));
+        other.push(&file, Some("renamed".to_string()));
+        assert_eq!(history.pop(&file), Some(Some("renamed".to_string())));
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let history = FileHistory::in_memory();
+        let file = PathBuf::from("/tmp/capped.txt");
+        for i in 0..(MAX_VERSIONS_PER_FILE + 5) {
+            history.push(&file, Some(i.to_string()));
+        }
+
+        let mut count = 0;
+        while history.pop(&file).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MAX_VERSIONS_PER_FILE);
+    }
+}
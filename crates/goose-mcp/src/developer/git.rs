@@ -0,0 +1,463 @@
+use mcp_core::handler::ToolError;
+use serde::Serialize;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Runs git in `dir` and returns its stdout
+pub async fn run_git(dir: &Path, args: &[&str]) -> Result<String, ToolError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ToolError::ExecutionError(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Rejects revisions that git would parse as an option, such as `--output=<file>`
+pub fn check_revision<'a>(param: &str, revision: &'a str) -> Result<&'a str, ToolError> {
+    if revision.starts_with('-') {
+        return Err(ToolError::InvalidParameters(format!(
+            "'{}' must be a revision, not an option: {}",
+            param, revision
+        )));
+    }
+    Ok(revision)
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct StatusEntry {
+    pub path: String,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct GitStatus {
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub staged: Vec<StatusEntry>,
+    pub unstaged: Vec<StatusEntry>,
+    pub untracked: Vec<String>,
+    pub conflicted: Vec<String>,
+}
+
+fn status_name(code: char) -> Option<&'static str> {
+    match code {
+        'M' => Some("modified"),
+        'T' => Some("type_changed"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        _ => None,
+    }
+}
+
+/// Parses the output of `git status --porcelain=v2 --branch -z`
+pub fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            match header.split_once(' ') {
+                Some(("branch.head", head)) if head != "(detached)" => {
+                    status.branch = Some(head.to_string())
+                }
+                Some(("branch.upstream", upstream)) => status.upstream = Some(upstream.to_string()),
+                Some(("branch.ab", counts)) => {
+                    for count in counts.split(' ') {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            status.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            status.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (kind, rest) = record.split_at(1);
+        let rest = rest.trim_start();
+        match kind {
+            "?" => status.untracked.push(rest.to_string()),
+            "u" => {
+                if let Some(path) = rest.splitn(10, ' ').nth(9) {
+                    status.conflicted.push(path.to_string());
+                }
+            }
+            "1" | "2" => {
+                // Renames carry a similarity score field and the original path as the next record
+                let fields = if kind == "1" { 8 } else { 9 };
+                let mut parts = rest.splitn(fields, ' ');
+                let codes: Vec<char> = parts.next().unwrap_or("..").chars().collect();
+                let Some(path) = parts.nth(fields - 2) else {
+                    continue;
+                };
+                let original_path = if kind == "2" {
+                    records.next().map(str::to_string)
+                } else {
+                    None
+                };
+
+                let entry = |code: char| {
+                    status_name(code).map(|name| StatusEntry {
+                        path: path.to_string(),
+                        status: name,
+                        original_path: original_path.clone(),
+                    })
+                };
+                if let Some(entry) = codes.first().and_then(|c| entry(*c)) {
+                    status.staged.push(entry);
+                }
+                if let Some(entry) = codes.get(1).and_then(|c| entry(*c)) {
+                    status.unstaged.push(entry);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+    pub status: &'static str,
+    pub binary: bool,
+    pub additions: u32,
+    pub deletions: u32,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Parses `-a,b` or `+c,d` from a hunk header, the line count defaults to 1
+fn parse_hunk_range(range: &str) -> (u32, u32) {
+    let range = &range[1..];
+    match range.split_once(',') {
+        Some((start, lines)) => (start.parse().unwrap_or(0), lines.parse().unwrap_or(0)),
+        None => (range.parse().unwrap_or(0), 1),
+    }
+}
+
+/// Parses the output of `git diff` (unified format with `diff --git` headers)
+pub fn parse_diff(output: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    // Lines of the current hunk still to come, `--- a/...` may directly follow a hunk
+    let (mut old_remaining, mut new_remaining) = (0u32, 0u32);
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            // Paths are refined by the ---/+++ and rename lines, this is a fallback
+            let path = header
+                .rsplit_once(" b/")
+                .map(|(_, b)| b.to_string())
+                .unwrap_or_else(|| header.to_string());
+            files.push(FileDiff {
+                path,
+                status: "modified",
+                ..Default::default()
+            });
+            (old_remaining, new_remaining) = (0, 0);
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if let Some(hunk) = file.hunks.last_mut() {
+            let in_hunk = old_remaining > 0 || new_remaining > 0;
+            let belongs = match line.chars().next() {
+                Some('+') if in_hunk => {
+                    new_remaining = new_remaining.saturating_sub(1);
+                    file.additions += 1;
+                    true
+                }
+                Some('-') if in_hunk => {
+                    old_remaining = old_remaining.saturating_sub(1);
+                    file.deletions += 1;
+                    true
+                }
+                Some(' ') if in_hunk => {
+                    old_remaining = old_remaining.saturating_sub(1);
+                    new_remaining = new_remaining.saturating_sub(1);
+                    true
+                }
+                // "\ No newline at end of file"
+                Some('\\') => true,
+                _ => false,
+            };
+            if belongs {
+                hunk.lines.push(line.to_string());
+                continue;
+            }
+        }
+
+        if line.starts_with("@@ ") {
+            let mut parts = line.split(' ');
+            let (old_start, old_lines) = parse_hunk_range(parts.nth(1).unwrap_or("-0"));
+            let (new_start, new_lines) = parse_hunk_range(parts.next().unwrap_or("+0"));
+            (old_remaining, new_remaining) = (old_lines, new_lines);
+            file.hunks.push(DiffHunk {
+                header: line.to_string(),
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            });
+        } else if line.starts_with("new file mode") {
+            file.status = "added";
+        } else if line.starts_with("deleted file mode") {
+            file.status = "deleted";
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            file.status = "renamed";
+            file.original_path = Some(from.to_string());
+        } else if let Some(to) = line.strip_prefix("rename to ") {
+            file.path = to.to_string();
+        } else if let Some(to) = line.strip_prefix("+++ b/") {
+            file.path = to.to_string();
+        } else if line.starts_with("Binary files ") {
+            file.binary = true;
+        }
+    }
+
+    files
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct LogEntry {
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    pub date: String,
+    pub subject: String,
+}
+
+/// Format for `git log` that `parse_log` understands, fields and records use ASCII separators
+pub const LOG_FORMAT: &str = "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e";
+
+/// Parses the output of `git log` with `LOG_FORMAT`
+pub fn parse_log(output: &str) -> Vec<LogEntry> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').split('\x1f');
+            Some(LogEntry {
+                commit: fields.next().filter(|hash| !hash.is_empty())?.to_string(),
+                author: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct BlameLine {
+    pub line: u32,
+    pub commit: String,
+    pub author: String,
+    pub date: String,
+    pub summary: String,
+    pub text: String,
+}
+
+/// Parses the output of `git blame --line-porcelain`
+pub fn parse_blame(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current = BlameLine::default();
+
+    for line in output.lines() {
+        if let Some(text) = line.strip_prefix('\t') {
+            current.text = text.to_string();
+            lines.push(std::mem::take(&mut current));
+        } else if let Some(author) = line.strip_prefix("author ") {
+            current.author = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            current.date = time
+                .parse()
+                .ok()
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default();
+        } else if let Some(summary) = line.strip_prefix("summary ") {
+            current.summary = summary.to_string();
+        } else if current.commit.is_empty() {
+            // The header of each line: <commit> <original line> <final line> [<group size>]
+            let mut parts = line.split(' ');
+            if let (Some(commit), Some(_), Some(final_line)) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                if commit.len() >= 40 {
+                    current.commit = commit.to_string();
+                    current.line = final_line.parse().unwrap_or(0);
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_revision() {
+        assert_eq!(check_revision("base", "HEAD~2").unwrap(), "HEAD~2");
+        assert_eq!(
+            check_revision("revision", "main..dev").unwrap(),
+            "main..dev"
+        );
+        assert!(check_revision("base", "--output=/tmp/file").is_err());
+        assert!(check_revision("revision", "-p").is_err());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let output = [
+            "# branch.oid 1234",
+            "# branch.head main",
+            "# branch.upstream origin/main",
+            "# branch.ab +2 -1",
+            "1 M. N... 100644 100644 100644 aaa bbb src/lib.rs",
+            "1 .D N... 100644 100644 000000 aaa bbb old file.txt",
+            "2 R. N... 100644 100644 100644 aaa bbb R100 new.rs",
+            "old.rs",
+            "u UU N... 100644 100644 100644 100644 aaa bbb ccc conflict.rs",
+            "? notes.md",
+            "",
+        ]
+        .join("\0");
+
+        let status = parse_status(&output);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(
+            status.staged,
+            vec![
+                StatusEntry {
+                    path: "src/lib.rs".into(),
+                    status: "modified",
+                    original_path: None
+                },
+                StatusEntry {
+                    path: "new.rs".into(),
+                    status: "renamed",
+                    original_path: Some("old.rs".into())
+                },
+            ]
+        );
+        assert_eq!(
+            status.unstaged,
+            vec![StatusEntry {
+                path: "old file.txt".into(),
+                status: "deleted",
+                original_path: None
+            }]
+        );
+        assert_eq!(status.conflicted, vec!["conflict.rs"]);
+        assert_eq!(status.untracked, vec!["notes.md"]);
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        let output = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@ fn main() {
+ one
+--- two
++two
+ three
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++hello
+\\ No newline at end of file
+diff --git a/logo.png b/logo.png
+index 4444444..5555555 100644
+Binary files a/logo.png and b/logo.png differ
+";
+
+        let files = parse_diff(output);
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert_eq!((files[0].additions, files[0].deletions), (1, 1));
+        assert_eq!(files[0].hunks[0].header, "@@ -1,3 +1,3 @@ fn main() {");
+        assert_eq!(
+            files[0].hunks[0].lines,
+            vec![" one", "--- two", "+two", " three"]
+        );
+
+        assert_eq!(files[1].status, "added");
+        assert_eq!(
+            (files[1].hunks[0].new_start, files[1].hunks[0].new_lines),
+            (1, 1)
+        );
+        assert_eq!(files[1].hunks[0].lines.len(), 2);
+
+        assert!(files[2].binary);
+        assert!(files[2].hunks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_log_and_blame() {
+        let log = "abc\x1fAda\x1fada@example.com\x1f2024-01-01T00:00:00+00:00\x1fFix bug\x1e\n\
+                   def\x1fBob\x1fbob@example.com\x1f2024-01-02T00:00:00+00:00\x1fAdd feature\x1e\n";
+        let entries = parse_log(log);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].commit, "def");
+        assert_eq!(entries[1].subject, "Add feature");
+
+        let commit = "a".repeat(40);
+        let blame = format!(
+            "{commit} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 0\nsummary Initial\nfilename a.rs\n\tfn main() {{\n\
+             {commit} 2 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 0\nsummary Initial\nfilename a.rs\n\t}}\n"
+        );
+        let lines = parse_blame(&blame);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, 1);
+        assert_eq!(lines[0].text, "fn main() {");
+        assert_eq!(lines[1].line, 2);
+        assert_eq!(lines[1].author, "Ada");
+        assert_eq!(lines[1].date, "1970-01-01T00:00:00+00:00");
+    }
+}
@@ -1,5 +1,10 @@
+pub mod checkpoint;
+mod file_history;
+mod git;
 mod lang;
 mod shell;
+mod symbols;
+mod text_edit;
 
 use anyhow::Result;
 use base64::Engine;
@@ -33,17 +38,27 @@ use mcp_server::Router;
 
 use mcp_core::role::Role;
 
+pub(crate) use self::file_history::FileHistory;
 use self::shell::{
     expand_path, format_command_for_platform, get_shell_config, is_absolute_path,
     normalize_line_endings,
 };
+use self::symbols::{SourceLanguage, SymbolKind};
+use self::text_edit::{
+    apply_edits, diff_preview, edit_snippet_result, insert_at_line, parse_edits, parse_view_range,
+    read_existing_file, read_file, replace_once, unified_diff,
+};
+use crate::lsp::LspManager;
 use indoc::indoc;
 use std::process::Stdio;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use xcap::{Monitor, Window};
 
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
+/// Set to `false` to stop appending language server diagnostics to the results of file edits
+const LSP_DIAGNOSTICS_ENV: &str = "GOOSE_LSP_DIAGNOSTICS";
+
 // Embeds the prompts directory to the build
 static PROMPTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/developer/prompts");
 
@@ -90,12 +105,67 @@ pub fn load_prompt_files() -> HashMap<String, Prompt> {
     prompts
 }
 
+/// The `.gooseignore` patterns of the user and of the project in `cwd`, files matching them are
+/// off limits to the tools
+pub(crate) fn load_ignore_patterns(cwd: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(cwd);
+    let mut has_ignore_file = false;
+    // Initialize ignore patterns
+    // - macOS/Linux: ~/.config/goose/
+    // - Windows:     ~\AppData\Roaming\Block\goose\config\
+    let global_ignore_path = choose_app_strategy(crate::APP_STRATEGY.clone())
+        .map(|strategy| strategy.in_config_dir(".gooseignore"))
+        .unwrap_or_else(|_| {
+            PathBuf::from(shellexpand::tilde("~/.config/goose/.gooseignore").to_string())
+        });
+
+    // Create the directory if it doesn't exist
+    let _ = std::fs::create_dir_all(global_ignore_path.parent().unwrap());
+
+    // Read global ignores if they exist
+    if global_ignore_path.is_file() {
+        let _ = builder.add(global_ignore_path);
+        has_ignore_file = true;
+    }
+
+    // Check for local ignores in current directory
+    let local_ignore_path = cwd.join(".gooseignore");
+
+    // Read local ignores if they exist
+    if local_ignore_path.is_file() {
+        let _ = builder.add(local_ignore_path);
+        has_ignore_file = true;
+    }
+
+    // Only use default patterns if no .gooseignore files were found
+    // If the file is empty, we will not ignore any file
+    if !has_ignore_file {
+        // Add some sensible defaults
+        let _ = builder.add_line(None, "**/.env");
+        let _ = builder.add_line(None, "**/.env.*");
+        let _ = builder.add_line(None, "**/secrets.*");
+    }
+
+    builder.build().expect("Failed to build ignore patterns")
+}
+
+/// Undo history kept on disk, so it survives restarts of the extension and covers the renames
+/// of the lsp extension
+pub(crate) fn default_file_history() -> FileHistory {
+    // - macOS/Linux: ~/.local/share/goose/file_history/
+    // - Windows:     ~\AppData\Roaming\Block\goose\data\file_history\
+    choose_app_strategy(crate::APP_STRATEGY.clone())
+        .map(|strategy| FileHistory::persistent(strategy.in_data_dir("file_history")))
+        .unwrap_or_else(|_| FileHistory::in_memory())
+}
+
 pub struct DeveloperRouter {
     tools: Vec<Tool>,
     prompts: Arc<HashMap<String, Prompt>>,
     instructions: String,
-    file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
+    file_history: Arc<FileHistory>,
     ignore_patterns: Arc<Gitignore>,
+    lsp: Option<Arc<LspManager>>,
 }
 
 impl Default for DeveloperRouter {
@@ -106,6 +176,15 @@ impl Default for DeveloperRouter {
 
 impl DeveloperRouter {
     pub fn new() -> Self {
+        Self::with_file_history(default_file_history())
+    }
+
+    /// A router keeping its undo history in `dir` instead of the data directory
+    pub fn with_file_history_dir(dir: PathBuf) -> Self {
+        Self::with_file_history(FileHistory::persistent(dir))
+    }
+
+    fn with_file_history(file_history: FileHistory) -> Self {
         // TODO consider rust native search tools, we could use
         // https://docs.rs/ignore/latest/ignore/
 
@@ -173,17 +252,32 @@ impl DeveloperRouter {
                 Perform text editing operations on files.
 
                 The `command` parameter specifies the operation to perform. Allowed options are:
-                - `view`: View the content of a file.
+                - `view`: View the content of a file, optionally limited to `view_range`.
                 - `write`: Create or overwrite a file with the given content
                 - `str_replace`: Replace a string in a file with a new string.
+                - `insert`: Insert text after a given line of a file.
+                - `multi_edit`: Apply several string replacements to a file at once.
                 - `undo_edit`: Undo the last edit made to a file.
 
+                To use the view command with `view_range`, pass `[start_line, end_line]` (1-indexed, inclusive).
+                Use -1 as the end line to read to the end of the file. Lines are returned with their line numbers.
+
                 To use the write command, you must specify `file_text` which will become the new content of the file. Be careful with
                 existing files! This is a full overwrite, so you must include everything - not just sections you are modifying.
 
                 To use the str_replace command, you must specify both `old_str` and `new_str` - the `old_str` needs to exactly match one
                 unique section of the original file, including any whitespace. Make sure to include enough context that the match is not
                 ambiguous. The entire original string will be replaced with `new_str`.
+
+                To use the insert command, you must specify `insert_line` and `new_str`. The text is inserted after `insert_line`,
+                use 0 to insert at the beginning of the file.
+
+                To use the multi_edit command, you must specify `edits`, a list of `{old_str, new_str}` pairs applied in order.
+                Each `old_str` must match exactly once in the file as it reads after the previous edits. If any edit fails
+                the file is left untouched.
+
+                Set `preview` to true on `write`, `str_replace`, `insert` or `multi_edit` to get a unified diff of the change
+                without modifying the file.
             "#}.to_string(),
             json!({
                 "type": "object",
@@ -195,8 +289,37 @@ impl DeveloperRouter {
                     },
                     "command": {
                         "type": "string",
-                        "enum": ["view", "write", "str_replace", "undo_edit"],
-                        "description": "Allowed options are: `view`, `write`, `str_replace`, undo_edit`."
+                        "enum": ["view", "write", "str_replace", "insert", "multi_edit", "undo_edit"],
+                        "description": "Allowed options are: `view`, `write`, `str_replace`, `insert`, `multi_edit`, `undo_edit`."
+                    },
+                    "view_range": {
+                        "type": "array",
+                        "items": {"type": "integer"},
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "description": "Optional line range for `view`, e.g. [11, 20]. Use -1 as the end to read to the end of the file."
+                    },
+                    "insert_line": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Line after which `new_str` is inserted by `insert`."
+                    },
+                    "edits": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["old_str", "new_str"],
+                            "properties": {
+                                "old_str": {"type": "string"},
+                                "new_str": {"type": "string"}
+                            }
+                        },
+                        "description": "Replacements applied in order by `multi_edit`."
+                    },
+                    "preview": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Return a unified diff of the change instead of applying it."
                     },
                     "old_str": {"type": "string"},
                     "new_str": {"type": "string"},
@@ -206,6 +329,269 @@ impl DeveloperRouter {
             None,
         );
 
+        let list_symbols_tool = Tool::new(
+            "list_symbols",
+            indoc! {r#"
+                List the definitions (functions, methods, classes, structs, traits, ...) in a source file.
+
+                Returns one line per symbol with its line range, kind, name and enclosing container.
+                Prefer this over viewing a whole file when you only need to know how it is organized,
+                then use `text_editor` with `view_range` to read the parts you need.
+
+                Supported languages: Rust, Python, TypeScript/JavaScript, Go and Java.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the source file"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("List symbols in a file".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let find_definition_tool = Tool::new(
+            "find_definition",
+            indoc! {r#"
+                Find where a symbol (function, method, type, constant, ...) is defined.
+
+                Searches the source files under `path` (defaults to the current directory), respecting
+                .gitignore, and returns `file:start-end kind name` for every matching definition.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["symbol"],
+                "properties": {
+                    "symbol": {
+                        "type": "string",
+                        "description": "Exact name of the symbol, e.g. `DeveloperRouter` or `call_tool`"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the directory or file to search, defaults to the current directory"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Find symbol definition".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let find_references_tool = Tool::new(
+            "find_references",
+            indoc! {r#"
+                Find every usage of an identifier in the source files under `path` (defaults to the
+                current directory), respecting .gitignore.
+
+                Unlike a text search this only matches whole identifiers in code, not comments or strings.
+                Returns `file:line:column: source line` for each occurrence, definitions are marked.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["symbol"],
+                "properties": {
+                    "symbol": {
+                        "type": "string",
+                        "description": "Exact name of the identifier"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the directory or file to search, defaults to the current directory"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Find symbol references".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let repo_map_tool = Tool::new(
+            "repo_map",
+            indoc! {r#"
+                Outline a repository: every supported source file under `path` (defaults to the current
+                directory) with the types and functions it defines, nested by container.
+
+                Use this to get oriented in an unfamiliar codebase before reading individual files.
+                Set `max_depth` to 0 to list only top-level definitions in large repositories.
+            "#},
+            json!({
+                "type": "object",
+                "required": [],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the directory to outline, defaults to the current directory"
+                    },
+                    "max_depth": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Maximum nesting depth of listed symbols, unlimited by default"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Outline repository".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let git_status_tool = Tool::new(
+            "git_status",
+            indoc! {r#"
+                Show the state of a git repository as JSON: the current branch, its upstream and
+                how far ahead/behind it is, staged and unstaged changes, untracked and conflicted files.
+
+                Paths are relative to the repository root. Pass `path` to limit the status to a
+                file or directory, it defaults to the current directory.
+            "#},
+            json!({
+                "type": "object",
+                "required": [],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to a file or directory in the repository"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Git status".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let git_diff_tool = Tool::new(
+            "git_diff",
+            indoc! {r#"
+                Show changes in a git repository as JSON: one entry per file with its status,
+                added/deleted line counts and the diff hunks.
+
+                By default this shows unstaged changes in the working tree. Set `staged` to see
+                changes staged for the next commit, or `base` to compare the working tree against a
+                commit, branch or tag (e.g. "main" or "HEAD~3").
+            "#},
+            json!({
+                "type": "object",
+                "required": [],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to a file or directory to limit the diff to"
+                    },
+                    "staged": {
+                        "type": "boolean",
+                        "description": "Show staged changes instead of unstaged ones"
+                    },
+                    "base": {
+                        "type": "string",
+                        "description": "Commit, branch or tag to compare against"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Git diff".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let git_log_tool = Tool::new(
+            "git_log",
+            indoc! {r#"
+                List commits as JSON, newest first, with their hash, author, date and subject.
+                Pass `path` to only list commits that touched a file or directory.
+            "#},
+            json!({
+                "type": "object",
+                "required": [],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to a file or directory in the repository"
+                    },
+                    "revision": {
+                        "type": "string",
+                        "description": "Branch, tag or revision range to list, defaults to HEAD"
+                    },
+                    "max_count": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Maximum number of commits to list, defaults to 20"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Git log".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
+        let git_blame_tool = Tool::new(
+            "git_blame",
+            indoc! {r#"
+                Show who last changed each line of a file as JSON: the line number, commit, author,
+                date and commit summary along with the line text.
+                Use `start_line` and `end_line` (1-indexed, inclusive) to blame part of a large file.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path to the file"
+                    },
+                    "start_line": {
+                        "type": "integer",
+                        "minimum": 1
+                    },
+                    "end_line": {
+                        "type": "integer",
+                        "minimum": 1
+                    },
+                    "revision": {
+                        "type": "string",
+                        "description": "Blame the file as of this revision instead of the working tree"
+                    }
+                }
+            }),
+            Some(ToolAnnotations {
+                title: Some("Git blame".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        );
+
         let list_windows_tool = Tool::new(
             "list_windows",
             indoc! {r#"
@@ -377,58 +763,35 @@ impl DeveloperRouter {
             format!("{base_instructions}\n{hints}")
         };
 
-        let mut builder = GitignoreBuilder::new(cwd.clone());
-        let mut has_ignore_file = false;
-        // Initialize ignore patterns
-        // - macOS/Linux: ~/.config/goose/
-        // - Windows:     ~\AppData\Roaming\Block\goose\config\
-        let global_ignore_path = choose_app_strategy(crate::APP_STRATEGY.clone())
-            .map(|strategy| strategy.in_config_dir(".gooseignore"))
-            .unwrap_or_else(|_| {
-                PathBuf::from(shellexpand::tilde("~/.config/goose/.gooseignore").to_string())
-            });
-
-        // Create the directory if it doesn't exist
-        let _ = std::fs::create_dir_all(global_ignore_path.parent().unwrap());
+        let ignore_patterns = load_ignore_patterns(&cwd);
 
-        // Read global ignores if they exist
-        if global_ignore_path.is_file() {
-            let _ = builder.add(global_ignore_path);
-            has_ignore_file = true;
-        }
-
-        // Check for local ignores in current directory
-        let local_ignore_path = cwd.join(".gooseignore");
-
-        // Read local ignores if they exist
-        if local_ignore_path.is_file() {
-            let _ = builder.add(local_ignore_path);
-            has_ignore_file = true;
-        }
-
-        // Only use default patterns if no .gooseignore files were found
-        // If the file is empty, we will not ignore any file
-        if !has_ignore_file {
-            // Add some sensible defaults
-            let _ = builder.add_line(None, "**/.env");
-            let _ = builder.add_line(None, "**/.env.*");
-            let _ = builder.add_line(None, "**/secrets.*");
-        }
-
-        let ignore_patterns = builder.build().expect("Failed to build ignore patterns");
+        // Language server diagnostics on edits are on unless turned off, servers are shared
+        // with the lsp extension
+        let lsp = (!std::env::var(LSP_DIAGNOSTICS_ENV)
+            .is_ok_and(|v| v == "0" || v.eq_ignore_ascii_case("false")))
+        .then(|| LspManager::shared(cwd.clone()));
 
         Self {
             tools: vec![
                 bash_tool,
                 text_editor_tool,
+                list_symbols_tool,
+                find_definition_tool,
+                find_references_tool,
+                repo_map_tool,
+                git_status_tool,
+                git_diff_tool,
+                git_log_tool,
+                git_blame_tool,
                 list_windows_tool,
                 screen_capture_tool,
                 image_processor_tool,
             ],
             prompts: Arc::new(load_prompt_files()),
             instructions,
-            file_history: Arc::new(Mutex::new(HashMap::new())),
+            file_history: Arc::new(file_history),
             ignore_patterns: Arc::new(ignore_patterns),
+            lsp,
         }
     }
 
@@ -553,8 +916,19 @@ impl DeveloperRouter {
             )));
         }
 
-        match command {
-            "view" => self.text_editor_view(&path).await,
+        let preview = params
+            .get("preview")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let result = match command {
+            "view" => {
+                let view_range = match params.get("view_range") {
+                    None | Some(Value::Null) => None,
+                    Some(value) => Some(parse_view_range(value)?),
+                };
+                self.text_editor_view(&path, view_range).await
+            }
             "write" => {
                 let file_text = params
                     .get("file_text")
@@ -563,6 +937,15 @@ impl DeveloperRouter {
                         ToolError::InvalidParameters("Missing 'file_text' parameter".into())
                     })?;
 
+                if preview {
+                    let old_content = if path.exists() {
+                        read_file(&path)?
+                    } else {
+                        String::new()
+                    };
+                    return Ok(diff_preview(&path, &old_content, file_text));
+                }
+
                 self.text_editor_write(&path, file_text).await
             }
             "str_replace" => {
@@ -579,17 +962,71 @@ impl DeveloperRouter {
                         ToolError::InvalidParameters("Missing 'new_str' parameter".into())
                     })?;
 
+                if preview {
+                    let content = read_existing_file(&path)?;
+                    let new_content = replace_once(&content, old_str, new_str, "old_str")?;
+                    return Ok(diff_preview(&path, &content, &new_content));
+                }
+
                 self.text_editor_replace(&path, old_str, new_str).await
             }
+            "insert" => {
+                let insert_line = params
+                    .get("insert_line")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| {
+                        ToolError::InvalidParameters("Missing 'insert_line' parameter".into())
+                    })? as usize;
+                let new_str = params
+                    .get("new_str")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ToolError::InvalidParameters("Missing 'new_str' parameter".into())
+                    })?;
+
+                if preview {
+                    let content = read_existing_file(&path)?;
+                    let new_content = insert_at_line(&content, insert_line, new_str)?;
+                    return Ok(diff_preview(&path, &content, &new_content));
+                }
+
+                self.text_editor_insert(&path, insert_line, new_str).await
+            }
+            "multi_edit" => {
+                let edits = parse_edits(&params)?;
+
+                if preview {
+                    let content = read_existing_file(&path)?;
+                    let new_content = apply_edits(&content, &edits)?;
+                    return Ok(diff_preview(&path, &content, &new_content));
+                }
+
+                self.text_editor_multi_edit(&path, &edits).await
+            }
             "undo_edit" => self.text_editor_undo(&path).await,
             _ => Err(ToolError::InvalidParameters(format!(
                 "Unknown command '{}'",
                 command
             ))),
+        };
+
+        let mut contents = result?;
+        // Let the model see compile errors introduced by the edit right away
+        if matches!(command, "write" | "str_replace" | "insert" | "multi_edit") {
+            if let Some(lsp) = &self.lsp {
+                if let Some(report) = lsp.diagnostics_report(&path).await {
+                    contents.push(Content::text(report).with_audience(vec![Role::Assistant]));
+                }
+            }
         }
+        Ok(contents)
     }
 
-    async fn text_editor_view(&self, path: &PathBuf) -> Result<Vec<Content>, ToolError> {
+    async fn text_editor_view(
+        &self,
+        path: &PathBuf,
+        view_range: Option<(usize, Option<usize>)>,
+    ) -> Result<Vec<Content>, ToolError> {
         if path.is_file() {
             // Check file size first (400KB limit)
             const MAX_FILE_SIZE: u64 = 400 * 1024; // 400KB in bytes
@@ -601,7 +1038,8 @@ impl DeveloperRouter {
                 })?
                 .len();
 
-            if file_size > MAX_FILE_SIZE {
+            // A line range keeps the output small, so only whole-file views are size limited
+            if file_size > MAX_FILE_SIZE && view_range.is_none() {
                 return Err(ToolError::ExecutionError(format!(
                     "File '{}' is too large ({:.2}KB). Maximum size is 400KB to prevent memory issues.",
                     path.display(),
@@ -613,8 +1051,29 @@ impl DeveloperRouter {
                 .map_err(|_| ToolError::ExecutionError("Invalid file path".into()))?
                 .to_string();
 
-            let content = std::fs::read_to_string(path)
-                .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+            let content = read_file(path)?;
+
+            let content = match view_range {
+                Some((start, end)) => {
+                    let lines: Vec<&str> = content.lines().collect();
+                    let end = end.unwrap_or(lines.len()).min(lines.len());
+                    if start > lines.len() || start > end {
+                        return Err(ToolError::InvalidParameters(format!(
+                            "Invalid view_range [{}, {}]: the file has {} lines",
+                            start,
+                            end,
+                            lines.len()
+                        )));
+                    }
+                    lines[start - 1..end]
+                        .iter()
+                        .enumerate()
+                        .map(|(i, line)| format!("{}: {}", start + i, line))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                None => content,
+            };
 
             let char_count = content.chars().count();
             if char_count > MAX_CHAR_COUNT {
@@ -659,6 +1118,9 @@ impl DeveloperRouter {
         path: &PathBuf,
         file_text: &str,
     ) -> Result<Vec<Content>, ToolError> {
+        // Save history for undo
+        self.save_file_history(path)?;
+
         // Normalize line endings based on platform
         let normalized_text = normalize_line_endings(file_text);
 
@@ -695,125 +1157,590 @@ impl DeveloperRouter {
         old_str: &str,
         new_str: &str,
     ) -> Result<Vec<Content>, ToolError> {
-        // Check if file exists and is active
-        if !path.exists() {
-            return Err(ToolError::InvalidParameters(format!(
-                "File '{}' does not exist, you can write a new file with the `write` command",
-                path.display()
+        // Read content, checking the file exists first
+        let content = read_existing_file(path)?;
+
+        // Ensure 'old_str' appears exactly once
+        let new_content = replace_once(&content, old_str, new_str, "old_str")?;
+
+        // Save history for undo
+        self.save_file_history(path)?;
+
+        // Write back with platform-specific line endings
+        let normalized_content = normalize_line_endings(&new_content);
+        std::fs::write(path, &normalized_content)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+
+        // Count newlines before the replacement to find the line number
+        let replacement_line = content
+            .split(old_str)
+            .next()
+            .expect("should split on already matched content")
+            .matches('\n')
+            .count();
+
+        Ok(edit_snippet_result(
+            path,
+            &new_content,
+            replacement_line,
+            new_str.matches('\n').count(),
+        ))
+    }
+
+    async fn text_editor_insert(
+        &self,
+        path: &PathBuf,
+        insert_line: usize,
+        new_str: &str,
+    ) -> Result<Vec<Content>, ToolError> {
+        let content = read_existing_file(path)?;
+        let new_content = insert_at_line(&content, insert_line, new_str)?;
+
+        // Save history for undo
+        self.save_file_history(path)?;
+
+        let normalized_content = normalize_line_endings(&new_content);
+        std::fs::write(path, &normalized_content)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+
+        Ok(edit_snippet_result(
+            path,
+            &new_content,
+            insert_line,
+            new_str.lines().count().saturating_sub(1),
+        ))
+    }
+
+    async fn text_editor_multi_edit(
+        &self,
+        path: &PathBuf,
+        edits: &[(String, String)],
+    ) -> Result<Vec<Content>, ToolError> {
+        let content = read_existing_file(path)?;
+
+        // All edits are applied in memory first so a failing edit leaves the file untouched
+        let new_content = apply_edits(&content, edits)?;
+
+        // A single history entry lets one undo revert the whole batch
+        self.save_file_history(path)?;
+
+        let normalized_content = normalize_line_endings(&new_content);
+        std::fs::write(path, &normalized_content)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+
+        let diff = unified_diff(path, &content, &new_content);
+        let success_message = formatdoc! {r#"
+            The file {} has been edited with {} replacements:
+            ```diff
+            {}
+            ```
+            Review the changes above for errors. Undo and edit the file again if necessary!
+            "#,
+            path.display(),
+            edits.len(),
+            diff
+        };
+
+        Ok(vec![
+            Content::text(success_message).with_audience(vec![Role::Assistant]),
+            Content::text(format!("```diff\n{}```", diff))
+                .with_audience(vec![Role::User])
+                .with_priority(0.2),
+        ])
+    }
+
+    async fn text_editor_undo(&self, path: &PathBuf) -> Result<Vec<Content>, ToolError> {
+        match self.file_history.pop(path) {
+            Some(Some(previous_content)) => {
+                // Write previous content back to file
+                std::fs::write(path, previous_content).map_err(|e| {
+                    ToolError::ExecutionError(format!("Failed to write file: {}", e))
+                })?;
+                Ok(vec![Content::text("Undid the last edit")])
+            }
+            // The edit created the file
+            Some(None) => {
+                if path.exists() {
+                    std::fs::remove_file(path).map_err(|e| {
+                        ToolError::ExecutionError(format!("Failed to remove file: {}", e))
+                    })?;
+                }
+                Ok(vec![Content::text(
+                    "Undid the last edit, which created the file, so it was removed",
+                )])
+            }
+            None => Err(ToolError::InvalidParameters(
+                "No edit history available to undo".into(),
+            )),
+        }
+    }
+
+    fn save_file_history(&self, path: &Path) -> Result<(), ToolError> {
+        let content = if path.exists() {
+            Some(read_file(path)?)
+        } else {
+            None
+        };
+        self.file_history.push(path, content);
+        Ok(())
+    }
+
+    // Resolves the optional search root of the code intelligence tools
+    fn resolve_search_root(&self, params: &Value) -> Result<PathBuf, ToolError> {
+        let root = match params.get("path").and_then(|v| v.as_str()) {
+            Some(path_str) => self.resolve_path(path_str)?,
+            None => std::env::current_dir().expect("should have a current working dir"),
+        };
+
+        if self.is_ignored(&root) {
+            return Err(ToolError::ExecutionError(format!(
+                "Access to '{}' is restricted by .gooseignore",
+                root.display()
             )));
         }
+        if !root.exists() {
+            return Err(ToolError::ExecutionError(format!(
+                "The path '{}' does not exist",
+                root.display()
+            )));
+        }
+        Ok(root)
+    }
 
-        // Read content
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+    // Walks the source files under `root` and hands them to `scan`, the walk and the parsing
+    // block so they run off the async executor
+    async fn scan_source_files<T: Send + 'static>(
+        &self,
+        root: PathBuf,
+        scan: impl FnOnce(Vec<PathBuf>) -> T + Send + 'static,
+    ) -> Result<T, ToolError> {
+        let ignore_patterns = self.ignore_patterns.clone();
+        tokio::task::spawn_blocking(move || {
+            scan(symbols::source_files(&root, |path| {
+                ignore_patterns.matched(path, false).is_ignore()
+            }))
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to scan source files: {}", e)))
+    }
 
-        // Ensure 'old_str' appears exactly once
-        if content.matches(old_str).count() > 1 {
-            return Err(ToolError::InvalidParameters(
-                "'old_str' must appear exactly once in the file, but it appears multiple times"
-                    .into(),
-            ));
+    async fn list_symbols(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let path_str = params
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
+        let path = self.resolve_path(path_str)?;
+
+        if self.is_ignored(&path) {
+            return Err(ToolError::ExecutionError(format!(
+                "Access to '{}' is restricted by .gooseignore",
+                path.display()
+            )));
         }
-        if content.matches(old_str).count() == 0 {
-            return Err(ToolError::InvalidParameters(
-                "'old_str' must appear exactly once in the file, but it does not appear in the file. Make sure the string exactly matches existing file content, including whitespace!".into(),
+
+        let language = SourceLanguage::from_path(&path).ok_or_else(|| {
+            ToolError::InvalidParameters(format!(
+                "Unsupported file type for '{}', supported languages are Rust, Python, TypeScript/JavaScript, Go and Java",
+                path.display()
+            ))
+        })?;
+        let symbols = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                let source = read_existing_file(&path)?;
+                symbols::list_symbols(language, &source).map_err(ToolError::ExecutionError)
+            })
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to list symbols: {}", e)))??
+        };
+
+        let output = if symbols.is_empty() {
+            format!("No symbols found in {}", path.display())
+        } else {
+            symbols
+                .iter()
+                .map(|symbol| format!("{}{}", "  ".repeat(symbol.depth), symbol))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn find_definition(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let symbol = params
+            .get("symbol")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'symbol' parameter".into()))?;
+        let root = self.resolve_search_root(&params)?;
+
+        let matches = {
+            let (root, symbol) = (root.clone(), symbol.to_string());
+            self.scan_source_files(root.clone(), move |files| {
+                let mut matches = Vec::new();
+                for file in files {
+                    let Ok(source) = std::fs::read_to_string(&file) else {
+                        continue;
+                    };
+                    // Cheap pre-filter so only candidate files are parsed
+                    if !source.contains(&symbol) {
+                        continue;
+                    }
+                    let Some(language) = SourceLanguage::from_path(&file) else {
+                        continue;
+                    };
+                    let Ok(found) = symbols::list_symbols(language, &source) else {
+                        continue;
+                    };
+                    for definition in found.into_iter().filter(|s| {
+                        s.name == symbol
+                            // Trait impls are named `Trait for Type`, match either side
+                            || (s.kind == SymbolKind::Impl
+                                && s.name.split(" for ").any(|n| n == symbol))
+                    }) {
+                        matches.push(format!("{}:{}", display_path(&file, &root), definition));
+                    }
+                }
+                matches
+            })
+            .await?
+        };
+
+        let output = if matches.is_empty() {
+            format!(
+                "No definition of '{}' found under {}",
+                symbol,
+                root.display()
+            )
+        } else {
+            matches.join("\n")
+        };
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn find_references(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        const MAX_REFERENCES: usize = 200;
+
+        let symbol = params
+            .get("symbol")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'symbol' parameter".into()))?;
+        let root = self.resolve_search_root(&params)?;
+
+        let (matches, total) = {
+            let (root, symbol) = (root.clone(), symbol.to_string());
+            self.scan_source_files(root.clone(), move |files| {
+                let mut matches = Vec::new();
+                let mut total = 0;
+                for file in files {
+                    let Ok(source) = std::fs::read_to_string(&file) else {
+                        continue;
+                    };
+                    if !source.contains(&symbol) {
+                        continue;
+                    }
+                    let Some(language) = SourceLanguage::from_path(&file) else {
+                        continue;
+                    };
+                    let Ok(references) = symbols::find_references(language, &source, &symbol)
+                    else {
+                        continue;
+                    };
+                    total += references.len();
+                    for reference in references {
+                        if matches.len() >= MAX_REFERENCES {
+                            break;
+                        }
+                        matches.push(format!(
+                            "{}:{}:{}: {}{}",
+                            display_path(&file, &root),
+                            reference.line,
+                            reference.column,
+                            reference.line_text,
+                            if reference.is_definition {
+                                " [definition]"
+                            } else {
+                                ""
+                            }
+                        ));
+                    }
+                }
+                (matches, total)
+            })
+            .await?
+        };
+
+        let mut output = if matches.is_empty() {
+            format!(
+                "No references to '{}' found under {}",
+                symbol,
+                root.display()
+            )
+        } else {
+            matches.join("\n")
+        };
+        if total > matches.len() {
+            output.push_str(&format!(
+                "\n... {} more references omitted, narrow the search with `path`",
+                total - matches.len()
             ));
         }
 
-        // Save history for undo
-        self.save_file_history(path)?;
-
-        // Replace and write back with platform-specific line endings
-        let new_content = content.replace(old_str, new_str);
-        let normalized_content = normalize_line_endings(&new_content);
-        std::fs::write(path, &normalized_content)
-            .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
 
-        // Try to detect the language from the file extension
-        let language = lang::get_language_identifier(path);
+    async fn repo_map(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        const MAX_CHAR_COUNT: usize = 100_000;
+
+        let root = self.resolve_search_root(&params)?;
+        let max_depth = params
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .map(|d| d as usize);
+
+        let (mut output, truncated) = {
+            let root = root.clone();
+            self.scan_source_files(root.clone(), move |files| {
+                let mut output = String::new();
+                for file in files {
+                    let Ok(source) = std::fs::read_to_string(&file) else {
+                        continue;
+                    };
+                    let Some(language) = SourceLanguage::from_path(&file) else {
+                        continue;
+                    };
+                    let Ok(found) = symbols::list_symbols(language, &source) else {
+                        continue;
+                    };
+
+                    let mut section = format!("{}\n", display_path(&file, &root));
+                    for symbol in found
+                        .iter()
+                        .filter(|s| max_depth.is_none_or(|depth| s.depth <= depth))
+                    {
+                        section.push_str(&format!(
+                            "  {}{} {} :{}\n",
+                            "  ".repeat(symbol.depth),
+                            symbol.kind,
+                            symbol.name,
+                            symbol.line
+                        ));
+                    }
 
-        // Show a snippet of the changed content with context
-        const SNIPPET_LINES: usize = 4;
+                    if output.len() + section.len() > MAX_CHAR_COUNT {
+                        return (output, true);
+                    }
+                    output.push_str(&section);
+                }
+                (output, false)
+            })
+            .await?
+        };
 
-        // Count newlines before the replacement to find the line number
-        let replacement_line = content
-            .split(old_str)
-            .next()
-            .expect("should split on already matched content")
-            .matches('\n')
-            .count();
+        if output.is_empty() {
+            output = format!("No supported source files found under {}", root.display());
+        }
+        if truncated {
+            output.push_str(
+                "... output truncated, use a narrower `path` or a lower `max_depth` to see more\n",
+            );
+        }
 
-        // Calculate start and end lines for the snippet
-        let start_line = replacement_line.saturating_sub(SNIPPET_LINES);
-        let end_line = replacement_line + SNIPPET_LINES + new_str.matches('\n').count();
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
 
-        // Get the relevant lines for our snippet
-        let lines: Vec<&str> = new_content.lines().collect();
-        let snippet = lines
-            .iter()
-            .skip(start_line)
-            .take(end_line - start_line + 1)
-            .cloned()
-            .collect::<Vec<&str>>()
-            .join("\n");
-
-        let output = formatdoc! {r#"
-            ```{language}
-            {snippet}
-            ```
-            "#,
-            language=language,
-            snippet=snippet
-        };
+    // Resolves the optional `path` of the git tools to the directory git runs in and a pathspec
+    fn resolve_git_path(&self, params: &Value) -> Result<(PathBuf, Option<PathBuf>), ToolError> {
+        match params.get("path").and_then(|v| v.as_str()) {
+            Some(_) => {
+                let path = self.resolve_search_root(params)?;
+                let dir = if path.is_dir() {
+                    path.clone()
+                } else {
+                    path.parent().map(Path::to_path_buf).unwrap_or_default()
+                };
+                Ok((dir, Some(path)))
+            }
+            None => Ok((
+                std::env::current_dir().expect("should have a current working dir"),
+                None,
+            )),
+        }
+    }
 
-        let success_message = formatdoc! {r#"
-            The file {} has been edited, and the section now reads:
-            {}
-            Review the changes above for errors. Undo and edit the file again if necessary!
-            "#,
-            path.display(),
-            output
-        };
+    // Repository root, paths in git output are relative to it
+    async fn git_toplevel(&self, dir: &Path) -> Result<PathBuf, ToolError> {
+        let toplevel = git::run_git(dir, &["rev-parse", "--show-toplevel"]).await?;
+        Ok(PathBuf::from(toplevel.trim()))
+    }
 
+    fn git_result(&self, value: &impl serde::Serialize) -> Result<Vec<Content>, ToolError> {
+        let output = serde_json::to_string_pretty(value)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to serialize: {}", e)))?;
         Ok(vec![
-            Content::text(success_message).with_audience(vec![Role::Assistant]),
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
             Content::text(output)
                 .with_audience(vec![Role::User])
-                .with_priority(0.2),
+                .with_priority(0.0),
         ])
     }
 
-    async fn text_editor_undo(&self, path: &PathBuf) -> Result<Vec<Content>, ToolError> {
-        let mut history = self.file_history.lock().unwrap();
-        if let Some(contents) = history.get_mut(path) {
-            if let Some(previous_content) = contents.pop() {
-                // Write previous content back to file
-                std::fs::write(path, previous_content).map_err(|e| {
-                    ToolError::ExecutionError(format!("Failed to write file: {}", e))
-                })?;
-                Ok(vec![Content::text("Undid the last edit")])
-            } else {
-                Err(ToolError::InvalidParameters(
-                    "No edit history available to undo".into(),
-                ))
+    async fn git_status(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let (dir, pathspec) = self.resolve_git_path(&params)?;
+        let pathspec = pathspec.map(|p| p.to_string_lossy().to_string());
+
+        let mut args = vec!["status", "--porcelain=v2", "--branch", "-z"];
+        if let Some(pathspec) = &pathspec {
+            args.extend(["--", pathspec]);
+        }
+        let mut status = git::parse_status(&git::run_git(&dir, &args).await?);
+
+        let root = self.git_toplevel(&dir).await?;
+        status
+            .staged
+            .retain(|entry| !self.is_ignored(&root.join(&entry.path)));
+        status
+            .unstaged
+            .retain(|entry| !self.is_ignored(&root.join(&entry.path)));
+        status
+            .untracked
+            .retain(|path| !self.is_ignored(&root.join(path)));
+
+        self.git_result(&status)
+    }
+
+    async fn git_diff(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        const MAX_CHAR_COUNT: usize = 100_000;
+
+        let (dir, pathspec) = self.resolve_git_path(&params)?;
+        let pathspec = pathspec.map(|p| p.to_string_lossy().to_string());
+        let staged = params
+            .get("staged")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let base = params
+            .get("base")
+            .and_then(|v| v.as_str())
+            .map(|base| git::check_revision("base", base))
+            .transpose()?;
+
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+        if staged {
+            args.push("--cached");
+        }
+        if let Some(base) = base {
+            args.push(base);
+        }
+        if let Some(pathspec) = &pathspec {
+            args.extend(["--", pathspec]);
+        }
+        let mut files = git::parse_diff(&git::run_git(&dir, &args).await?);
+
+        let root = self.git_toplevel(&dir).await?;
+        files.retain(|file| !self.is_ignored(&root.join(&file.path)));
+
+        let mut result = json!({ "files": files });
+        if result.to_string().len() > MAX_CHAR_COUNT {
+            // Keep the per-file summary, the hunks can be fetched with a narrower path
+            for file in &mut files {
+                file.hunks.clear();
             }
-        } else {
-            Err(ToolError::InvalidParameters(
-                "No edit history available to undo".into(),
-            ))
+            result = json!({
+                "files": files,
+                "note": "The diff is too large to show the hunks, use `path` to diff fewer files"
+            });
         }
+        self.git_result(&result)
     }
 
-    fn save_file_history(&self, path: &PathBuf) -> Result<(), ToolError> {
-        let mut history = self.file_history.lock().unwrap();
-        let content = if path.exists() {
-            std::fs::read_to_string(path)
-                .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?
-        } else {
-            String::new()
+    async fn git_log(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        const MAX_COUNT: u64 = 200;
+
+        let (dir, pathspec) = self.resolve_git_path(&params)?;
+        let pathspec = pathspec.map(|p| p.to_string_lossy().to_string());
+        let max_count = params
+            .get("max_count")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(20)
+            .clamp(1, MAX_COUNT)
+            .to_string();
+        let revision = params
+            .get("revision")
+            .and_then(|v| v.as_str())
+            .map(|revision| git::check_revision("revision", revision))
+            .transpose()?;
+
+        let mut args = vec!["log", git::LOG_FORMAT, "--max-count", &max_count];
+        if let Some(revision) = revision {
+            args.push(revision);
+        }
+        if let Some(pathspec) = &pathspec {
+            args.extend(["--", pathspec]);
+        }
+        let commits = git::parse_log(&git::run_git(&dir, &args).await?);
+
+        self.git_result(&commits)
+    }
+
+    async fn git_blame(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let (dir, path) = self.resolve_git_path(&params)?;
+        let path = path.filter(|path| path.is_file()).ok_or_else(|| {
+            ToolError::InvalidParameters("'path' must be an existing file".into())
+        })?;
+        let path = path.to_string_lossy().to_string();
+
+        let start_line = params.get("start_line").and_then(|v| v.as_u64());
+        let end_line = params.get("end_line").and_then(|v| v.as_u64());
+        let range = match (start_line, end_line) {
+            (Some(start), Some(end)) if end < start => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "end_line {} is before start_line {}",
+                    end, start
+                )))
+            }
+            (Some(start), Some(end)) => Some(format!("{},{}", start, end)),
+            (Some(start), None) => Some(format!("{},", start)),
+            (None, Some(end)) => Some(format!("1,{}", end)),
+            (None, None) => None,
         };
-        history.entry(path.clone()).or_default().push(content);
-        Ok(())
+        let revision = params
+            .get("revision")
+            .and_then(|v| v.as_str())
+            .map(|revision| git::check_revision("revision", revision))
+            .transpose()?;
+
+        let mut args = vec!["blame", "--line-porcelain"];
+        if let Some(range) = &range {
+            args.extend(["-L", range]);
+        }
+        if let Some(revision) = revision {
+            args.push(revision);
+        }
+        args.extend(["--", &path]);
+        let lines = git::parse_blame(&git::run_git(&dir, &args).await?);
+
+        self.git_result(&lines)
     }
 
     async fn list_windows(&self, _params: Value) -> Result<Vec<Content>, ToolError> {
@@ -1024,6 +1951,16 @@ impl DeveloperRouter {
     }
 }
 
+// Paths in code intelligence results are shown relative to the search root to keep them compact
+fn display_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
 impl Router for DeveloperRouter {
     fn name(&self) -> String {
         "developer".to_string()
@@ -1055,6 +1992,14 @@ impl Router for DeveloperRouter {
             match tool_name.as_str() {
                 "shell" => this.bash(arguments).await,
                 "text_editor" => this.text_editor(arguments).await,
+                "list_symbols" => this.list_symbols(arguments).await,
+                "find_definition" => this.find_definition(arguments).await,
+                "find_references" => this.find_references(arguments).await,
+                "repo_map" => this.repo_map(arguments).await,
+                "git_status" => this.git_status(arguments).await,
+                "git_diff" => this.git_diff(arguments).await,
+                "git_log" => this.git_log(arguments).await,
+                "git_blame" => this.git_blame(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
                 "image_processor" => this.image_processor(arguments).await,
@@ -1115,6 +2060,7 @@ impl Clone for DeveloperRouter {
             instructions: self.instructions.clone(),
             file_history: Arc::clone(&self.file_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
+            lsp: self.lsp.clone(),
         }
     }
 }
@@ -1125,9 +2071,17 @@ mod tests {
     use serde_json::json;
     use serial_test::serial;
     use std::fs;
+    use std::sync::LazyLock;
     use tempfile::TempDir;
     use tokio::sync::OnceCell;
 
+    /// Undo history of the routers in tests, kept away from the user's history
+    static FILE_HISTORY_DIR: LazyLock<TempDir> = LazyLock::new(|| TempDir::new().unwrap());
+
+    fn new_router() -> DeveloperRouter {
+        DeveloperRouter::with_file_history_dir(FILE_HISTORY_DIR.path().to_path_buf())
+    }
+
     #[test]
     #[serial]
     fn test_global_goosehints() {
@@ -1149,7 +2103,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         std::env::set_current_dir(dir.path()).unwrap();
 
-        let router = DeveloperRouter::new();
+        let router = new_router();
         let instructions = router.instructions();
 
         assert!(instructions.contains("### Global Hints"));
@@ -1169,7 +2123,7 @@ mod tests {
         std::env::set_current_dir(dir.path()).unwrap();
 
         fs::write(".goosehints", "Test hint content").unwrap();
-        let router = DeveloperRouter::new();
+        let router = new_router();
         let instructions = router.instructions();
 
         assert!(instructions.contains("Test hint content"));
@@ -1181,7 +2135,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         std::env::set_current_dir(dir.path()).unwrap();
 
-        let router = DeveloperRouter::new();
+        let router = new_router();
         let instructions = router.instructions();
 
         assert!(!instructions.contains("Project Hints"));
@@ -1190,9 +2144,7 @@ mod tests {
     static DEV_ROUTER: OnceCell<DeveloperRouter> = OnceCell::const_new();
 
     async fn get_router() -> &'static DeveloperRouter {
-        DEV_ROUTER
-            .get_or_init(|| async { DeveloperRouter::new() })
-            .await
+        DEV_ROUTER.get_or_init(|| async { new_router() }).await
     }
 
     #[tokio::test]
@@ -1500,6 +2452,285 @@ mod tests {
             .unwrap();
         assert!(text.contains("First line"));
 
+        // Undoing the write that created the file removes it
+        router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "undo_edit",
+                    "path": file_path_str
+                }),
+            )
+            .await
+            .unwrap();
+        assert!(!file_path.exists());
+
+        temp_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_text_editor_view_range_and_insert() {
+        let router = get_router().await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        let file_path_str = file_path.to_str().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+
+        std::fs::write(&file_path, "one\ntwo\nthree\nfour\n").unwrap();
+
+        router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "insert",
+                    "path": file_path_str,
+                    "insert_line": 2,
+                    "new_str": "two and a half"
+                }),
+            )
+            .await
+            .unwrap();
+
+        let view_result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "view",
+                    "path": file_path_str,
+                    "view_range": [2, 3]
+                }),
+            )
+            .await
+            .unwrap();
+
+        let text = view_result
+            .iter()
+            .find(|c| {
+                c.audience()
+                    .is_some_and(|roles| roles.contains(&Role::User))
+            })
+            .unwrap()
+            .as_text()
+            .unwrap();
+        assert!(text.contains("2: two\n3: two and a half"));
+        assert!(!text.contains("four"));
+
+        temp_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_text_editor_multi_edit_preview_and_undo() {
+        let router = get_router().await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        let file_path_str = file_path.to_str().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+
+        std::fs::write(&file_path, "let a = 1;\nlet b = 2;\n").unwrap();
+        let edits = json!([
+            {"old_str": "let a = 1;", "new_str": "let a = 10;"},
+            {"old_str": "let b = 2;", "new_str": "let b = 20;"}
+        ]);
+
+        // A preview returns a diff and leaves the file alone
+        let preview_result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "multi_edit",
+                    "path": file_path_str,
+                    "edits": edits,
+                    "preview": true
+                }),
+            )
+            .await
+            .unwrap();
+        let text = preview_result.first().unwrap().as_text().unwrap();
+        assert!(text.contains("+let a = 10;"));
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "let a = 1;\nlet b = 2;\n"
+        );
+
+        // A failing edit leaves the file untouched
+        let result = router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "multi_edit",
+                    "path": file_path_str,
+                    "edits": [
+                        {"old_str": "let a = 1;", "new_str": "let a = 10;"},
+                        {"old_str": "let c = 3;", "new_str": "let c = 30;"}
+                    ]
+                }),
+            )
+            .await;
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "let a = 1;\nlet b = 2;\n"
+        );
+
+        router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "multi_edit",
+                    "path": file_path_str,
+                    "edits": edits
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "let a = 10;\nlet b = 20;\n"
+        );
+
+        // One undo reverts the whole batch
+        router
+            .call_tool(
+                "text_editor",
+                json!({
+                    "command": "undo_edit",
+                    "path": file_path_str
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "let a = 1;\nlet b = 2;\n"
+        );
+
+        temp_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_code_intelligence_tools() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+
+        let router = get_router().await;
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("src/lib.rs"),
+            "pub struct Engine;\n\nimpl Engine {\n    pub fn start(&self) {}\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("main.py"),
+            "from engine import Engine\n\ndef run():\n    Engine().start()\n",
+        )
+        .unwrap();
+
+        let assistant_text = |result: Vec<Content>| {
+            result
+                .iter()
+                .find(|c| {
+                    c.audience()
+                        .is_some_and(|roles| roles.contains(&Role::Assistant))
+                })
+                .unwrap()
+                .as_text()
+                .unwrap()
+                .to_string()
+        };
+
+        let definition = router
+            .call_tool("find_definition", json!({"symbol": "start"}))
+            .await
+            .unwrap();
+        assert_eq!(
+            assistant_text(definition),
+            format!(
+                "src{}lib.rs:4-4 method start (in Engine)",
+                std::path::MAIN_SEPARATOR
+            )
+        );
+
+        let references = assistant_text(
+            router
+                .call_tool("find_references", json!({"symbol": "Engine"}))
+                .await
+                .unwrap(),
+        );
+        assert!(references.contains("main.py:4:5: Engine().start()"));
+        assert!(references.contains("lib.rs:1:12: pub struct Engine; [definition]"));
+
+        let map = assistant_text(router.call_tool("repo_map", json!({})).await.unwrap());
+        assert!(map.contains("fn run :3"));
+        assert!(map.contains("    method start :4"));
+
+        temp_dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_git_tools() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+        };
+        git(&["init", "-q", "-b", "main"]);
+        let file = temp_dir.path().join("notes.txt");
+        fs::write(&file, "first\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "Add notes"]);
+
+        let router = new_router();
+        let json_result = |result: Vec<Content>| -> Value {
+            serde_json::from_str(result[0].as_text().unwrap()).unwrap()
+        };
+
+        router
+            .call_tool(
+                "text_editor",
+                json!({"command": "write", "path": file.to_str().unwrap(), "file_text": "second\n"}),
+            )
+            .await
+            .unwrap();
+
+        let status = json_result(router.call_tool("git_status", json!({})).await.unwrap());
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["unstaged"][0]["path"], "notes.txt");
+        assert_eq!(status["unstaged"][0]["status"], "modified");
+
+        let diff = json_result(router.call_tool("git_diff", json!({})).await.unwrap());
+        assert_eq!(diff["files"][0]["additions"], 1);
+        assert_eq!(
+            diff["files"][0]["hunks"][0]["lines"],
+            json!(["-first", "+second"])
+        );
+
+        let log = json_result(router.call_tool("git_log", json!({})).await.unwrap());
+        assert_eq!(log[0]["subject"], "Add notes");
+
+        let blame = json_result(
+            router
+                .call_tool(
+                    "git_blame",
+                    json!({"path": file.to_str().unwrap(), "revision": "HEAD"}),
+                )
+                .await
+                .unwrap(),
+        );
+        assert_eq!(blame[0]["author"], "Ada");
+        assert_eq!(blame[0]["text"], "first");
+
         temp_dir.close().unwrap();
     }
 
@@ -1520,8 +2751,9 @@ mod tests {
             tools: vec![],
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
-            file_history: Arc::new(Mutex::new(HashMap::new())),
+            file_history: Arc::new(FileHistory::in_memory()),
             ignore_patterns: Arc::new(ignore_patterns),
+            lsp: None,
         };
 
         // Test basic file matching
@@ -1567,11 +2799,12 @@ mod tests {
         let ignore_patterns = builder.build().unwrap();
 
         let router = DeveloperRouter {
-            tools: DeveloperRouter::new().tools, // Reuse default tools
+            tools: new_router().tools, // Reuse default tools
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
-            file_history: Arc::new(Mutex::new(HashMap::new())),
+            file_history: Arc::new(FileHistory::in_memory()),
             ignore_patterns: Arc::new(ignore_patterns),
+            lsp: None,
         };
 
         // Try to write to an ignored file
@@ -1624,11 +2857,12 @@ mod tests {
         let ignore_patterns = builder.build().unwrap();
 
         let router = DeveloperRouter {
-            tools: DeveloperRouter::new().tools, // Reuse default tools
+            tools: new_router().tools, // Reuse default tools
             prompts: Arc::new(HashMap::new()),
             instructions: String::new(),
-            file_history: Arc::new(Mutex::new(HashMap::new())),
+            file_history: Arc::new(FileHistory::in_memory()),
             ignore_patterns: Arc::new(ignore_patterns),
+            lsp: None,
         };
 
         // Create an ignored file
@@ -0,0 +1,473 @@
+use ignore::WalkBuilder;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Language, Node, Parser};
+
+/// Files larger than this are skipped when scanning a repository
+const MAX_SOURCE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Upper bound on the number of files visited when scanning a repository
+const MAX_SOURCE_FILES: usize = 10_000;
+
+/// Languages with tree-sitter support in the developer extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceLanguage {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    Go,
+    Java,
+}
+
+impl SourceLanguage {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rs") => Some(Self::Rust),
+            Some("py") | Some("pyi") => Some(Self::Python),
+            Some("ts") | Some("mts") | Some("cts") => Some(Self::TypeScript),
+            Some("tsx") | Some("js") | Some("jsx") | Some("mjs") | Some("cjs") => Some(Self::Tsx),
+            Some("go") => Some(Self::Go),
+            Some("java") => Some(Self::Java),
+            _ => None,
+        }
+    }
+
+    fn grammar(&self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::Java => tree_sitter_java::LANGUAGE.into(),
+        }
+    }
+
+    /// Maps a syntax node to the kind of symbol it defines, if any
+    fn symbol_kind(&self, node: &Node) -> Option<SymbolKind> {
+        let kind = match (self, node.kind()) {
+            (Self::Rust, "function_item" | "function_signature_item") => SymbolKind::Function,
+            (Self::Rust, "struct_item" | "union_item") => SymbolKind::Struct,
+            (Self::Rust, "enum_item") => SymbolKind::Enum,
+            (Self::Rust, "trait_item") => SymbolKind::Trait,
+            (Self::Rust, "impl_item") => SymbolKind::Impl,
+            (Self::Rust, "mod_item") => SymbolKind::Module,
+            (Self::Rust, "const_item" | "static_item") => SymbolKind::Constant,
+            (Self::Rust, "type_item") => SymbolKind::Type,
+            (Self::Rust, "macro_definition") => SymbolKind::Macro,
+
+            (Self::Python, "function_definition") => SymbolKind::Function,
+            (Self::Python, "class_definition") => SymbolKind::Class,
+
+            (
+                Self::TypeScript | Self::Tsx,
+                "function_declaration" | "generator_function_declaration",
+            ) => SymbolKind::Function,
+            (Self::TypeScript | Self::Tsx, "class_declaration" | "abstract_class_declaration") => {
+                SymbolKind::Class
+            }
+            (
+                Self::TypeScript | Self::Tsx,
+                "method_definition" | "method_signature" | "abstract_method_signature",
+            ) => SymbolKind::Method,
+            (Self::TypeScript | Self::Tsx, "interface_declaration") => SymbolKind::Interface,
+            (Self::TypeScript | Self::Tsx, "type_alias_declaration") => SymbolKind::Type,
+            (Self::TypeScript | Self::Tsx, "enum_declaration") => SymbolKind::Enum,
+            (Self::TypeScript | Self::Tsx, "internal_module" | "module") => SymbolKind::Module,
+            // Only `const foo = () => ...` style declarations are worth listing
+            (Self::TypeScript | Self::Tsx, "variable_declarator") => {
+                let value = node.child_by_field_name("value")?;
+                match value.kind() {
+                    "arrow_function" | "function_expression" | "function" => SymbolKind::Function,
+                    _ => return None,
+                }
+            }
+
+            (Self::Go, "function_declaration") => SymbolKind::Function,
+            (Self::Go, "method_declaration") => SymbolKind::Method,
+            (Self::Go, "type_spec") => match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => SymbolKind::Struct,
+                Some("interface_type") => SymbolKind::Interface,
+                _ => SymbolKind::Type,
+            },
+            (Self::Go, "type_alias") => SymbolKind::Type,
+            (Self::Go, "const_spec") => SymbolKind::Constant,
+
+            (Self::Java, "class_declaration" | "record_declaration") => SymbolKind::Class,
+            (Self::Java, "interface_declaration" | "annotation_type_declaration") => {
+                SymbolKind::Interface
+            }
+            (Self::Java, "enum_declaration") => SymbolKind::Enum,
+            (Self::Java, "method_declaration" | "constructor_declaration") => SymbolKind::Method,
+
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Class,
+    Struct,
+    Enum,
+    Interface,
+    Trait,
+    Impl,
+    Module,
+    Type,
+    Constant,
+    Macro,
+}
+
+impl SymbolKind {
+    fn is_callable(&self) -> bool {
+        matches!(self, Self::Function | Self::Method)
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Function => "fn",
+            Self::Method => "method",
+            Self::Class => "class",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Interface => "interface",
+            Self::Trait => "trait",
+            Self::Impl => "impl",
+            Self::Module => "mod",
+            Self::Type => "type",
+            Self::Constant => "const",
+            Self::Macro => "macro",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A definition found in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-indexed first line of the definition
+    pub line: usize,
+    /// 1-indexed last line of the definition
+    pub end_line: usize,
+    /// Name of the enclosing class, impl, trait or module
+    pub parent: Option<String>,
+    /// Nesting depth, 0 for top level definitions
+    pub depth: usize,
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {} {}",
+            self.line, self.end_line, self.kind, self.name
+        )?;
+        if let Some(parent) = &self.parent {
+            write!(f, " (in {})", parent)?;
+        }
+        Ok(())
+    }
+}
+
+/// An occurrence of an identifier in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// 1-indexed line
+    pub line: usize,
+    /// 1-indexed column
+    pub column: usize,
+    pub line_text: String,
+    pub is_definition: bool,
+}
+
+fn parse(language: SourceLanguage, source: &str) -> Result<tree_sitter::Tree, String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|e| format!("Failed to load {:?} grammar: {}", language, e))?;
+    parser
+        .parse(source, None)
+        .ok_or_else(|| "Failed to parse source".to_string())
+}
+
+/// Lists the definitions in a source file in the order they appear
+pub fn list_symbols(language: SourceLanguage, source: &str) -> Result<Vec<Symbol>, String> {
+    let tree = parse(language, source)?;
+    let mut symbols = Vec::new();
+    collect_symbols(
+        language,
+        tree.root_node(),
+        source.as_bytes(),
+        None,
+        0,
+        &mut symbols,
+    );
+    Ok(symbols)
+}
+
+fn collect_symbols(
+    language: SourceLanguage,
+    node: Node,
+    source: &[u8],
+    parent: Option<&str>,
+    depth: usize,
+    symbols: &mut Vec<Symbol>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        let Some(mut kind) = language.symbol_kind(&child) else {
+            collect_symbols(language, child, source, parent, depth, symbols);
+            continue;
+        };
+
+        let Some(name) = symbol_name(language, &child, source) else {
+            collect_symbols(language, child, source, parent, depth, symbols);
+            continue;
+        };
+
+        // Functions declared inside a class-like container are methods
+        if kind == SymbolKind::Function && parent.is_some() && language != SourceLanguage::Go {
+            kind = SymbolKind::Method;
+        }
+
+        let parent_name = match (language, child.kind()) {
+            (SourceLanguage::Go, "method_declaration") => go_receiver_type(&child, source),
+            _ => parent.map(str::to_string),
+        };
+
+        symbols.push(Symbol {
+            name: name.clone(),
+            kind,
+            line: child.start_position().row + 1,
+            end_line: child.end_position().row + 1,
+            parent: parent_name,
+            depth,
+        });
+
+        // Bodies of functions are implementation details, containers are walked for members
+        if !kind.is_callable() {
+            collect_symbols(language, child, source, Some(&name), depth + 1, symbols);
+        }
+    }
+}
+
+fn node_text(node: &Node, source: &[u8]) -> Option<String> {
+    node.utf8_text(source).ok().map(str::to_string)
+}
+
+fn symbol_name(language: SourceLanguage, node: &Node, source: &[u8]) -> Option<String> {
+    if language == SourceLanguage::Rust && node.kind() == "impl_item" {
+        let type_name = node_text(&node.child_by_field_name("type")?, source)?;
+        return Some(match node.child_by_field_name("trait") {
+            Some(trait_node) => format!("{} for {}", node_text(&trait_node, source)?, type_name),
+            None => type_name,
+        });
+    }
+    node_text(&node.child_by_field_name("name")?, source)
+}
+
+fn go_receiver_type(node: &Node, source: &[u8]) -> Option<String> {
+    let receiver = node.child_by_field_name("receiver")?;
+    find_descendant(receiver, "type_identifier").and_then(|n| node_text(&n, source))
+}
+
+fn find_descendant<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+    if node.kind() == kind {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node<'a>> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_descendant(child, kind))
+}
+
+/// Finds every identifier in a source file that matches `name`
+pub fn find_references(
+    language: SourceLanguage,
+    source: &str,
+    name: &str,
+) -> Result<Vec<Reference>, String> {
+    let tree = parse(language, source)?;
+    let lines: Vec<&str> = source.lines().collect();
+    let mut references = Vec::new();
+    collect_references(
+        language,
+        tree.root_node(),
+        source.as_bytes(),
+        name,
+        &lines,
+        &mut references,
+    );
+    Ok(references)
+}
+
+fn collect_references(
+    language: SourceLanguage,
+    node: Node,
+    source: &[u8],
+    name: &str,
+    lines: &[&str],
+    references: &mut Vec<Reference>,
+) {
+    if node.child_count() == 0 {
+        if node.kind().ends_with("identifier") && node.utf8_text(source).ok() == Some(name) {
+            let is_definition = node.parent().is_some_and(|parent| {
+                language.symbol_kind(&parent).is_some()
+                    && parent.child_by_field_name("name") == Some(node)
+            });
+            let position = node.start_position();
+            references.push(Reference {
+                line: position.row + 1,
+                column: position.column + 1,
+                line_text: lines
+                    .get(position.row)
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_default(),
+                is_definition,
+            });
+        }
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_references(language, child, source, name, lines, references);
+    }
+}
+
+/// Source files with a supported language under `root`, honoring .gitignore files
+///
+/// `skip` lets the caller exclude additional paths, e.g. those matched by .gooseignore
+pub fn source_files(root: &Path, skip: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+
+    let mut files: Vec<PathBuf> = WalkBuilder::new(root)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| SourceLanguage::from_path(path).is_some() && !skip(path))
+        .filter(|path| std::fs::metadata(path).is_ok_and(|meta| meta.len() <= MAX_SOURCE_FILE_SIZE))
+        .take(MAX_SOURCE_FILES)
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r#"
+mod config;
+
+pub struct Router {
+    name: String,
+}
+
+impl Router {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Router::new("default".to_string())
+    }
+}
+
+fn helper() {}
+"#;
+
+    #[test]
+    fn test_rust_symbols() {
+        let symbols = list_symbols(SourceLanguage::Rust, RUST_SOURCE).unwrap();
+        let summary: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            summary,
+            vec![
+                "2-2 mod config",
+                "4-6 struct Router",
+                "8-12 impl Router",
+                "9-11 method new (in Router)",
+                "14-18 impl Default for Router",
+                "15-17 method default (in Default for Router)",
+                "20-20 fn helper",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python_symbols() {
+        let source = "class Greeter:\n    def greet(self):\n        def inner():\n            pass\n\ndef main():\n    Greeter().greet()\n";
+        let symbols = list_symbols(SourceLanguage::Python, source).unwrap();
+        let names: Vec<(&str, SymbolKind)> =
+            symbols.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Greeter", SymbolKind::Class),
+                ("greet", SymbolKind::Method),
+                ("main", SymbolKind::Function),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_method_receiver() {
+        let source = "package main\n\ntype Server struct{}\n\nfunc (s *Server) Start() error {\n\treturn nil\n}\n";
+        let symbols = list_symbols(SourceLanguage::Go, source).unwrap();
+        assert_eq!(symbols[0].kind, SymbolKind::Struct);
+        assert_eq!(symbols[1].name, "Start");
+        assert_eq!(symbols[1].parent.as_deref(), Some("Server"));
+    }
+
+    #[test]
+    fn test_typescript_and_java_symbols() {
+        let ts = "export interface Props {}\nexport const render = (p: Props) => p;\nclass View { draw() {} }\n";
+        let names: Vec<String> = list_symbols(SourceLanguage::TypeScript, ts)
+            .unwrap()
+            .iter()
+            .map(|s| format!("{} {}", s.kind, s.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["interface Props", "fn render", "class View", "method draw"]
+        );
+
+        let java = "class App {\n  App() {}\n  void run() {}\n}\n";
+        let names: Vec<String> = list_symbols(SourceLanguage::Java, java)
+            .unwrap()
+            .iter()
+            .map(|s| format!("{} {}", s.kind, s.name))
+            .collect();
+        assert_eq!(names, vec!["class App", "method App", "method run"]);
+    }
+
+    #[test]
+    fn test_find_references() {
+        let references = find_references(SourceLanguage::Rust, RUST_SOURCE, "Router").unwrap();
+        let lines: Vec<(usize, bool)> = references
+            .iter()
+            .map(|r| (r.line, r.is_definition))
+            .collect();
+        assert_eq!(lines, vec![(4, true), (8, false), (14, false), (16, false)]);
+        assert_eq!(
+            references[3].line_text,
+            "Router::new(\"default\".to_string())"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use indoc::formatdoc;
+use mcp_core::{handler::ToolError, role::Role, Content};
+use serde_json::Value;
+use similar::TextDiff;
+use std::path::Path;
+
+use super::lang;
+
+/// Number of lines of context shown around an edit
+const SNIPPET_LINES: usize = 4;
+
+pub fn read_file(path: &Path) -> Result<String, ToolError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))
+}
+
+/// Reads a file that an edit command expects to already exist
+pub fn read_existing_file(path: &Path) -> Result<String, ToolError> {
+    if !path.exists() {
+        return Err(ToolError::InvalidParameters(format!(
+            "File '{}' does not exist, you can write a new file with the `write` command",
+            path.display()
+        )));
+    }
+    read_file(path)
+}
+
+/// Replaces the single occurrence of `old_str` in `content`, `label` names the parameter in errors
+pub fn replace_once(
+    content: &str,
+    old_str: &str,
+    new_str: &str,
+    label: &str,
+) -> Result<String, ToolError> {
+    match content.matches(old_str).count() {
+        1 => Ok(content.replacen(old_str, new_str, 1)),
+        0 => Err(ToolError::InvalidParameters(format!(
+            "'{}' must appear exactly once in the file, but it does not appear in the file. Make sure the string exactly matches existing file content, including whitespace!",
+            label
+        ))),
+        _ => Err(ToolError::InvalidParameters(format!(
+            "'{}' must appear exactly once in the file, but it appears multiple times",
+            label
+        ))),
+    }
+}
+
+/// Inserts `new_str` after line `insert_line` (0 inserts at the start of the file)
+pub fn insert_at_line(
+    content: &str,
+    insert_line: usize,
+    new_str: &str,
+) -> Result<String, ToolError> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    if insert_line > lines.len() {
+        return Err(ToolError::InvalidParameters(format!(
+            "Invalid insert_line {}: the file has {} lines",
+            insert_line,
+            lines.len()
+        )));
+    }
+
+    let mut new_content = lines[..insert_line].concat();
+    if !new_content.is_empty() && !new_content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    new_content.push_str(new_str);
+    if !new_str.ends_with('\n') && insert_line < lines.len() {
+        new_content.push('\n');
+    }
+    new_content.push_str(&lines[insert_line..].concat());
+    Ok(new_content)
+}
+
+/// Parses the `edits` parameter of `multi_edit` into (old, new) pairs
+pub fn parse_edits(params: &Value) -> Result<Vec<(String, String)>, ToolError> {
+    let edits = params
+        .get("edits")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| ToolError::InvalidParameters("Missing 'edits' parameter".into()))?;
+
+    if edits.is_empty() {
+        return Err(ToolError::InvalidParameters(
+            "'edits' must contain at least one edit".into(),
+        ));
+    }
+
+    edits
+        .iter()
+        .enumerate()
+        .map(|(i, edit)| {
+            let field = |name: &str| {
+                edit.get(name)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| {
+                        ToolError::InvalidParameters(format!(
+                            "Edit {} is missing the '{}' field",
+                            i + 1,
+                            name
+                        ))
+                    })
+            };
+            Ok((field("old_str")?, field("new_str")?))
+        })
+        .collect()
+}
+
+/// Applies all edits in order, failing without side effects if any of them does not apply
+pub fn apply_edits(content: &str, edits: &[(String, String)]) -> Result<String, ToolError> {
+    edits
+        .iter()
+        .enumerate()
+        .try_fold(content.to_string(), |current, (i, (old_str, new_str))| {
+            replace_once(&current, old_str, new_str, &format!("edits[{}].old_str", i))
+        })
+}
+
+/// Parses `view_range` into a 1-indexed start line and an optional inclusive end line
+pub fn parse_view_range(value: &Value) -> Result<(usize, Option<usize>), ToolError> {
+    let invalid = || {
+        ToolError::InvalidParameters(
+            "'view_range' must be an array of two integers, e.g. [1, 10] or [5, -1]".into(),
+        )
+    };
+
+    let range = value
+        .as_array()
+        .filter(|r| r.len() == 2)
+        .ok_or_else(invalid)?;
+    let start = range[0].as_i64().ok_or_else(invalid)?;
+    let end = range[1].as_i64().ok_or_else(invalid)?;
+
+    if start < 1 {
+        return Err(ToolError::InvalidParameters(
+            "'view_range' lines are 1-indexed, the start line must be at least 1".into(),
+        ));
+    }
+
+    match end {
+        -1 => Ok((start as usize, None)),
+        end if end >= start => Ok((start as usize, Some(end as usize))),
+        _ => Err(ToolError::InvalidParameters(format!(
+            "Invalid view_range [{}, {}]: the end line must be -1 or not before the start line",
+            start, end
+        ))),
+    }
+}
+
+pub fn unified_diff(path: &Path, old_content: &str, new_content: &str) -> String {
+    let path = path.display().to_string();
+    TextDiff::from_lines(old_content, new_content)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a{}", path), &format!("b{}", path))
+        .to_string()
+}
+
+/// Result of a `preview` edit: the diff of the change, the file is not modified
+pub fn diff_preview(path: &Path, old_content: &str, new_content: &str) -> Vec<Content> {
+    let diff = unified_diff(path, old_content, new_content);
+    let diff = if diff.is_empty() {
+        "No changes".to_string()
+    } else {
+        format!("```diff\n{}```", diff)
+    };
+
+    vec![
+        Content::text(format!(
+            "Preview of the change to {} (the file was not modified):\n{}",
+            path.display(),
+            diff
+        ))
+        .with_audience(vec![Role::Assistant]),
+        Content::text(diff)
+            .with_audience(vec![Role::User])
+            .with_priority(0.2),
+    ]
+}
+
+/// Result of an edit showing the changed section with some surrounding context
+pub fn edit_snippet_result(
+    path: &Path,
+    new_content: &str,
+    edit_line: usize,
+    added_lines: usize,
+) -> Vec<Content> {
+    // Try to detect the language from the file extension
+    let language = lang::get_language_identifier(path);
+
+    // Calculate start and end lines for the snippet
+    let start_line = edit_line.saturating_sub(SNIPPET_LINES);
+    let end_line = edit_line + SNIPPET_LINES + added_lines;
+
+    // Get the relevant lines for our snippet
+    let snippet = new_content
+        .lines()
+        .skip(start_line)
+        .take(end_line - start_line + 1)
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    let output = formatdoc! {r#"
+        ```{language}
+        {snippet}
+        ```
+        "#,
+        language=language,
+        snippet=snippet
+    };
+
+    let success_message = formatdoc! {r#"
+        The file {} has been edited, and the section now reads:
+        {}
+        Review the changes above for errors. Undo and edit the file again if necessary!
+        "#,
+        path.display(),
+        output
+    };
+
+    vec![
+        Content::text(success_message).with_audience(vec![Role::Assistant]),
+        Content::text(output)
+            .with_audience(vec![Role::User])
+            .with_priority(0.2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_insert_at_line() {
+        let content = "one\ntwo\nthree\n";
+        assert_eq!(
+            insert_at_line(content, 0, "zero").unwrap(),
+            "zero\none\ntwo\nthree\n"
+        );
+        assert_eq!(
+            insert_at_line(content, 2, "two and a half\n").unwrap(),
+            "one\ntwo\ntwo and a half\nthree\n"
+        );
+        assert_eq!(
+            insert_at_line("one\ntwo", 2, "three").unwrap(),
+            "one\ntwo\nthree"
+        );
+        assert!(insert_at_line(content, 4, "four").is_err());
+    }
+
+    #[test]
+    fn test_apply_edits_is_all_or_nothing() {
+        let edits = vec![
+            ("alpha".to_string(), "ALPHA".to_string()),
+            ("ALPHA beta".to_string(), "ALPHA BETA".to_string()),
+        ];
+        assert_eq!(
+            apply_edits("alpha beta gamma", &edits).unwrap(),
+            "ALPHA BETA gamma"
+        );
+
+        let failing = vec![
+            ("alpha".to_string(), "ALPHA".to_string()),
+            ("delta".to_string(), "DELTA".to_string()),
+        ];
+        let err = apply_edits("alpha beta gamma", &failing).unwrap_err();
+        assert!(err.to_string().contains("edits[1].old_str"));
+    }
+
+    #[test]
+    fn test_parse_view_range() {
+        assert_eq!(parse_view_range(&json!([3, 7])).unwrap(), (3, Some(7)));
+        assert_eq!(parse_view_range(&json!([3, -1])).unwrap(), (3, None));
+        assert!(parse_view_range(&json!([0, 5])).is_err());
+        assert!(parse_view_range(&json!([7, 3])).is_err());
+        assert!(parse_view_range(&json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff(Path::new("/repo/file.txt"), "a\nb\nc\n", "a\nB\nc\n");
+        assert!(diff.contains("-b\n"));
+        assert!(diff.contains("+B\n"));
+        assert!(diff.contains("a/repo/file.txt"));
+    }
+}
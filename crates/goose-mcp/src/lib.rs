@@ -11,12 +11,15 @@ pub mod computercontroller;
 mod developer;
 pub mod google_drive;
 mod jetbrains;
+mod lsp;
 mod memory;
 mod tutorial;
 
 pub use computercontroller::ComputerControllerRouter;
+pub use developer::checkpoint::{Checkpoint, CheckpointError, CheckpointStore};
 pub use developer::DeveloperRouter;
 pub use google_drive::GoogleDriveRouter;
 pub use jetbrains::JetBrainsRouter;
-pub use memory::MemoryRouter;
+pub use lsp::LspRouter;
+pub use memory::{MemoryEmbedder, MemoryRouter};
 pub use tutorial::TutorialRouter;
@@ -0,0 +1,502 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, watch, Mutex};
+use url::Url;
+
+/// How long to wait for a response to a request before giving up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Error codes servers use while they are still loading the project, the request can be retried
+const CONTENT_MODIFIED: i64 = -32801;
+const SERVER_CANCELLED: i64 = -32802;
+const MAX_RETRIES: u32 = 5;
+
+#[derive(Debug, Error)]
+pub enum LspError {
+    #[error("Failed to start language server `{0}`: {1}")]
+    Spawn(String, std::io::Error),
+    #[error("Language server I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Language server returned an error for {method}: {message}")]
+    Response {
+        method: String,
+        code: i64,
+        message: String,
+    },
+    #[error("Timed out waiting for the language server to answer {0}")]
+    Timeout(String),
+    #[error("Language server exited")]
+    Closed,
+    #[error("Invalid path: {0}")]
+    InvalidPath(String),
+}
+
+/// A JSON-RPC error as (code, message)
+type ResponseError = (i64, String);
+
+type PendingRequests = Arc<Mutex<HashMap<i64, oneshot::Sender<Result<Value, ResponseError>>>>>;
+
+/// What the server has told us through notifications
+#[derive(Debug, Default, Clone)]
+struct ServerState {
+    /// Latest diagnostics published for each document URI
+    diagnostics: HashMap<String, Vec<Value>>,
+    /// Bumped on every publish so callers can wait for fresh results
+    generation: u64,
+    /// Tokens of `$/progress` work that has begun but not ended (indexing, `cargo check`, ...)
+    active_work: HashSet<String>,
+}
+
+/// A JSON-RPC connection to a language server running over stdio
+pub struct LspClient {
+    _child: Child,
+    stdin: Mutex<ChildStdin>,
+    next_id: AtomicI64,
+    pending: PendingRequests,
+    state: watch::Receiver<ServerState>,
+    /// Version of each document opened with `didOpen`
+    documents: Mutex<HashMap<PathBuf, i64>>,
+}
+
+impl LspClient {
+    /// Starts the server and performs the `initialize` handshake for `root`
+    pub async fn start(
+        name: &str,
+        command: &str,
+        args: &[String],
+        root: &Path,
+    ) -> Result<Arc<Self>, LspError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| LspError::Spawn(command.to_string(), e))?;
+
+        let stdin = child.stdin.take().ok_or(LspError::Closed)?;
+        let stdout = child.stdout.take().ok_or(LspError::Closed)?;
+
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let (state_tx, state_rx) = watch::channel(ServerState::default());
+        let stdin = Mutex::new(stdin);
+
+        let client = Self {
+            _child: child,
+            stdin,
+            next_id: AtomicI64::new(1),
+            pending: pending.clone(),
+            state: state_rx,
+            documents: Mutex::new(HashMap::new()),
+        };
+
+        let (server_requests_tx, mut server_requests_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(read_loop(
+            BufReader::new(stdout),
+            pending,
+            state_tx,
+            server_requests_tx,
+        ));
+
+        let client = Arc::new(client);
+
+        // Servers may ask the client things (configuration, capability registration, ...),
+        // they must get an answer or some of them stall
+        let responder = Arc::downgrade(&client);
+        tokio::spawn(async move {
+            while let Some((id, method)) = server_requests_rx.recv().await {
+                let Some(client) = responder.upgrade() else {
+                    break;
+                };
+                let result = match method.as_str() {
+                    "workspace/configuration" => json!([]),
+                    _ => Value::Null,
+                };
+                let _ = client
+                    .send(&json!({"jsonrpc": "2.0", "id": id, "result": result}))
+                    .await;
+            }
+        });
+
+        let root_uri = path_to_uri(root)?;
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": root_uri,
+                    "workspaceFolders": [{"uri": root_uri, "name": root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()}],
+                    "capabilities": {
+                        "textDocument": {
+                            "synchronization": {"didSave": true},
+                            "publishDiagnostics": {"relatedInformation": false},
+                            "hover": {"contentFormat": ["markdown", "plaintext"]},
+                            "definition": {"linkSupport": true},
+                            "rename": {"prepareSupport": false}
+                        },
+                        "window": {"workDoneProgress": true},
+                        "workspace": {
+                            "workspaceEdit": {"documentChanges": true},
+                            "configuration": true
+                        }
+                    }
+                }),
+            )
+            .await?;
+        client.notify("initialized", json!({})).await?;
+        tracing::debug!(
+            "Language server {} initialized for {}",
+            name,
+            root.display()
+        );
+
+        Ok(client)
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), LspError> {
+        let body = serde_json::to_string(message).expect("JSON values always serialize");
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes())
+            .await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Sends a request, retrying while the server reports that it is still loading
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, LspError> {
+        let mut attempt = 0;
+        loop {
+            match self.request_once(method, params.clone()).await {
+                Err(LspError::Response { code, .. })
+                    if (code == CONTENT_MODIFIED || code == SERVER_CANCELLED)
+                        && attempt < MAX_RETRIES =>
+                {
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt))).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn request_once(&self, method: &str, params: Value) -> Result<Value, LspError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        self.send(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+            .await?;
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err((code, message)))) => Err(LspError::Response {
+                method: method.to_string(),
+                code,
+                message,
+            }),
+            Ok(Err(_)) => Err(LspError::Closed),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(LspError::Timeout(method.to_string()))
+            }
+        }
+    }
+
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), LspError> {
+        self.send(&json!({"jsonrpc": "2.0", "method": method, "params": params}))
+            .await
+    }
+
+    /// Sends the current content of a file to the server, opening it on first use
+    pub async fn sync_document(
+        &self,
+        path: &Path,
+        language_id: &str,
+        text: &str,
+    ) -> Result<(), LspError> {
+        let uri = path_to_uri(path)?;
+        let mut documents = self.documents.lock().await;
+        match documents.get_mut(path) {
+            Some(version) => {
+                *version += 1;
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": {"uri": uri, "version": *version},
+                        "contentChanges": [{"text": text}]
+                    }),
+                )
+                .await?;
+            }
+            None => {
+                documents.insert(path.to_path_buf(), 1);
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": language_id,
+                            "version": 1,
+                            "text": text
+                        }
+                    }),
+                )
+                .await?;
+            }
+        }
+        // Some servers (e.g. rust-analyzer running `cargo check`) only refresh on save
+        self.notify(
+            "textDocument/didSave",
+            json!({"textDocument": {"uri": uri}}),
+        )
+        .await
+    }
+
+    /// Generation counter of published diagnostics, pass it to `wait_for_diagnostics`
+    pub fn diagnostics_generation(&self) -> u64 {
+        self.state.borrow().generation
+    }
+
+    /// Waits until diagnostics for `path` are published after `generation` and the server has
+    /// no work in progress, then until nothing changes for `settle`, and returns the latest ones
+    pub async fn wait_for_diagnostics(
+        &self,
+        path: &Path,
+        generation: u64,
+        timeout: Duration,
+        settle: Duration,
+    ) -> Result<Vec<Value>, LspError> {
+        let uri = path_to_uri(path)?;
+        let mut receiver = self.state.clone();
+
+        let ready = |state: &ServerState| {
+            state.generation > generation
+                && state.diagnostics.contains_key(&uri)
+                && state.active_work.is_empty()
+        };
+
+        let _ = tokio::time::timeout(timeout, async {
+            loop {
+                if ready(&receiver.borrow_and_update()) {
+                    break;
+                }
+                if receiver.changed().await.is_err() {
+                    return;
+                }
+            }
+            // Follow-up publishes (e.g. from `cargo check` on save) arrive shortly after,
+            // so wait for a quiet period with no work in progress
+            loop {
+                match tokio::time::timeout(settle, receiver.changed()).await {
+                    Ok(Ok(())) => continue,
+                    Ok(Err(_)) => return,
+                    Err(_) if receiver.borrow().active_work.is_empty() => return,
+                    Err(_) => continue,
+                }
+            }
+        })
+        .await;
+
+        let state = self.state.borrow();
+        Ok(state.diagnostics.get(&uri).cloned().unwrap_or_default())
+    }
+}
+
+async fn read_loop<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    pending: PendingRequests,
+    state: watch::Sender<ServerState>,
+    server_requests: tokio::sync::mpsc::UnboundedSender<(Value, String)>,
+) {
+    while let Ok(Some(message)) = read_message(&mut reader).await {
+        let method = message.get("method").and_then(|m| m.as_str());
+        let id = message.get("id").cloned();
+
+        match (method, id) {
+            // A response to one of our requests
+            (None, Some(id)) => {
+                let Some(id) = id.as_i64() else { continue };
+                if let Some(sender) = pending.lock().await.remove(&id) {
+                    let result = match message.get("error") {
+                        Some(error) => Err((
+                            error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+                            error
+                                .get("message")
+                                .and_then(|m| m.as_str())
+                                .unwrap_or("unknown error")
+                                .to_string(),
+                        )),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                    };
+                    let _ = sender.send(result);
+                }
+            }
+            // A request from the server
+            (Some(method), Some(id)) => {
+                let _ = server_requests.send((id, method.to_string()));
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                let params = message.get("params").cloned().unwrap_or_default();
+                let Some(uri) = params.get("uri").and_then(|u| u.as_str()) else {
+                    continue;
+                };
+                let items = params
+                    .get("diagnostics")
+                    .and_then(|d| d.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                state.send_modify(|state| {
+                    state.diagnostics.insert(uri.to_string(), items);
+                    state.generation += 1;
+                });
+            }
+            (Some("$/progress"), None) => {
+                let params = message.get("params").cloned().unwrap_or_default();
+                let token = params["token"].to_string();
+                match params["value"]["kind"].as_str() {
+                    Some("begin") => state.send_modify(|state| {
+                        state.active_work.insert(token);
+                    }),
+                    Some("end") => state.send_modify(|state| {
+                        state.active_work.remove(&token);
+                    }),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Fail everything still waiting so callers do not hang until their timeout
+    pending.lock().await.clear();
+}
+
+/// Reads one `Content-Length` framed message, `None` at end of stream
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> std::io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = content_length else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "message without Content-Length header",
+        ));
+    };
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+pub fn path_to_uri(path: &Path) -> Result<String, LspError> {
+    Url::from_file_path(path)
+        .map(|url| url.to_string())
+        .map_err(|_| LspError::InvalidPath(path.display().to_string()))
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_message_framing() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"result":null}"#;
+        let input = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut reader = BufReader::new(input.as_bytes());
+
+        let message = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(message["id"], 1);
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_loop_routes_messages() {
+        let messages = [
+            json!({"jsonrpc": "2.0", "id": 7, "result": {"ok": true}}),
+            json!({"jsonrpc": "2.0", "id": 8, "error": {"code": -32601, "message": "nope"}}),
+            json!({"jsonrpc": "2.0", "id": "srv-1", "method": "window/workDoneProgress/create"}),
+            json!({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics",
+                   "params": {"uri": "file:///a.rs", "diagnostics": [{"message": "boom"}]}}),
+            json!({"jsonrpc": "2.0", "method": "$/progress",
+                   "params": {"token": "indexing", "value": {"kind": "begin", "title": "Indexing"}}}),
+            json!({"jsonrpc": "2.0", "method": "$/progress",
+                   "params": {"token": 3, "value": {"kind": "begin", "title": "cargo check"}}}),
+            json!({"jsonrpc": "2.0", "method": "$/progress",
+                   "params": {"token": 3, "value": {"kind": "end"}}}),
+        ];
+        let input: String = messages
+            .iter()
+            .map(|m| {
+                let body = m.to_string();
+                format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+            })
+            .collect();
+
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let (ok_tx, ok_rx) = oneshot::channel();
+        let (err_tx, err_rx) = oneshot::channel();
+        pending.lock().await.insert(7, ok_tx);
+        pending.lock().await.insert(8, err_tx);
+        let (state_tx, state_rx) = watch::channel(ServerState::default());
+        let (requests_tx, mut requests_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        read_loop(
+            BufReader::new(input.as_bytes()),
+            pending,
+            state_tx,
+            requests_tx,
+        )
+        .await;
+
+        assert_eq!(ok_rx.await.unwrap().unwrap(), json!({"ok": true}));
+        assert_eq!(
+            err_rx.await.unwrap().unwrap_err(),
+            (-32601, "nope".to_string())
+        );
+        let (id, method) = requests_rx.recv().await.unwrap();
+        assert_eq!(id, json!("srv-1"));
+        assert_eq!(method, "window/workDoneProgress/create");
+        let state = state_rx.borrow();
+        assert_eq!(state.generation, 1);
+        assert_eq!(state.diagnostics["file:///a.rs"][0]["message"], "boom");
+        assert_eq!(
+            state.active_work,
+            HashSet::from(["\"indexing\"".to_string()])
+        );
+    }
+}
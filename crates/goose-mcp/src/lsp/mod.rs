@@ -0,0 +1,981 @@
+mod client;
+
+use indoc::{formatdoc, indoc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+use mcp_core::{
+    handler::{PromptError, ResourceError, ToolError},
+    prompt::Prompt,
+    protocol::ServerCapabilities,
+    resource::Resource,
+    role::Role,
+    tool::{Tool, ToolAnnotations},
+    Content,
+};
+use mcp_server::router::CapabilitiesBuilder;
+use mcp_server::Router;
+
+use ignore::gitignore::Gitignore;
+
+use self::client::{path_to_uri, uri_to_path, LspClient, LspError};
+use crate::developer::{default_file_history, load_ignore_patterns, FileHistory};
+
+/// Environment variable with a JSON object of extra or overridden language servers
+pub const LSP_SERVERS_ENV: &str = "GOOSE_LSP_SERVERS";
+
+/// How long to wait for the first diagnostics after a change, servers that are still
+/// indexing the project can take a while
+const DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long an edit waits for diagnostics, `lsp_diagnostics` waits longer for slow servers
+const EDIT_DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Quiet period after which no more follow-up diagnostics (e.g. from `cargo check`) are expected
+const DIAGNOSTICS_SETTLE: Duration = Duration::from_millis(500);
+
+/// Quiet period used right after a server started, servers pause between loading stages
+const STARTUP_SETTLE: Duration = Duration::from_secs(3);
+
+/// How to launch a language server and which files it handles
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct LanguageServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// File extensions handled by this server, without the leading dot
+    pub extensions: Vec<String>,
+    /// The LSP `languageId` sent when opening documents
+    pub language_id: String,
+}
+
+fn default_servers() -> HashMap<String, LanguageServerConfig> {
+    let server = |command: &str, args: &[&str], extensions: &[&str], language_id: &str| {
+        LanguageServerConfig {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            language_id: language_id.to_string(),
+        }
+    };
+
+    HashMap::from([
+        (
+            "rust-analyzer".to_string(),
+            server("rust-analyzer", &[], &["rs"], "rust"),
+        ),
+        (
+            "pyright".to_string(),
+            server("pyright-langserver", &["--stdio"], &["py", "pyi"], "python"),
+        ),
+        ("gopls".to_string(), server("gopls", &[], &["go"], "go")),
+    ])
+}
+
+/// The built-in servers merged with the ones configured in `GOOSE_LSP_SERVERS`, e.g.
+/// `{"tsserver": {"command": "typescript-language-server", "args": ["--stdio"],
+/// "extensions": ["ts", "tsx"], "language_id": "typescript"}}`
+fn configured_servers() -> HashMap<String, LanguageServerConfig> {
+    let mut servers = default_servers();
+    if let Ok(raw) = std::env::var(LSP_SERVERS_ENV) {
+        match serde_json::from_str::<HashMap<String, LanguageServerConfig>>(&raw) {
+            Ok(configured) => servers.extend(configured),
+            Err(e) => tracing::warn!("Ignoring invalid {}: {}", LSP_SERVERS_ENV, e),
+        }
+    }
+    servers
+}
+
+static SHARED_MANAGER: OnceLock<Arc<LspManager>> = OnceLock::new();
+
+/// Starts language servers on demand and keeps them running for the project root
+pub struct LspManager {
+    root: PathBuf,
+    servers: HashMap<String, LanguageServerConfig>,
+    clients: Mutex<HashMap<String, Arc<LspClient>>>,
+}
+
+impl LspManager {
+    pub fn new(root: PathBuf) -> Self {
+        Self::with_servers(root, configured_servers())
+    }
+
+    /// The manager of this process, so the developer and lsp routers use the same servers
+    pub fn shared(root: PathBuf) -> Arc<Self> {
+        SHARED_MANAGER
+            .get_or_init(|| Arc::new(Self::new(root)))
+            .clone()
+    }
+
+    pub fn with_servers(root: PathBuf, servers: HashMap<String, LanguageServerConfig>) -> Self {
+        Self {
+            root,
+            servers,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn server_for(&self, path: &Path) -> Option<(&String, &LanguageServerConfig)> {
+        let extension = path.extension()?.to_str()?;
+        self.servers
+            .iter()
+            .find(|(_, config)| config.extensions.iter().any(|e| e == extension))
+    }
+
+    pub fn handles(&self, path: &Path) -> bool {
+        self.server_for(path).is_some()
+    }
+
+    /// Returns the running client for a file, starting its server if needed, and sends it
+    /// the file's current content
+    async fn open(&self, path: &Path) -> Result<Arc<LspClient>, ToolError> {
+        let (name, config) = self.server_for(path).ok_or_else(|| {
+            ToolError::InvalidParameters(format!(
+                "No language server is configured for '{}'",
+                path.display()
+            ))
+        })?;
+
+        let client = {
+            let mut clients = self.clients.lock().await;
+            match clients.get(name) {
+                Some(client) => client.clone(),
+                None => {
+                    let client = LspClient::start(name, &config.command, &config.args, &self.root)
+                        .await
+                        .map_err(lsp_error)?;
+                    clients.insert(name.clone(), client.clone());
+                    client
+                }
+            }
+        };
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+        client
+            .sync_document(path, &config.language_id, &text)
+            .await
+            .map_err(lsp_error)?;
+        Ok(client)
+    }
+
+    /// Sends the current content of `path` to its server and returns the diagnostics it reports
+    pub async fn diagnostics(&self, path: &Path) -> Result<Vec<Value>, ToolError> {
+        self.diagnostics_within(path, DIAGNOSTICS_TIMEOUT).await
+    }
+
+    async fn diagnostics_within(
+        &self,
+        path: &Path,
+        timeout: Duration,
+    ) -> Result<Vec<Value>, ToolError> {
+        // Capture the generation before syncing so diagnostics for this change are not missed
+        let generation = {
+            let clients = self.clients.lock().await;
+            self.server_for(path)
+                .and_then(|(name, _)| clients.get(name))
+                .map(|client| client.diagnostics_generation())
+        };
+        let settle = match generation {
+            Some(_) => DIAGNOSTICS_SETTLE,
+            None => STARTUP_SETTLE,
+        };
+        let client = self.open(path).await?;
+        client
+            .wait_for_diagnostics(path, generation.unwrap_or(0), timeout, settle)
+            .await
+            .map_err(lsp_error)
+    }
+
+    /// Diagnostics for `path` after an edit formatted for the model, `None` if no server handles
+    /// the file or its server isn't running yet
+    ///
+    /// Edits don't wait for a server to start, it is started in the background and reports on
+    /// the next edit.
+    pub async fn diagnostics_report(self: &Arc<Self>, path: &Path) -> Option<String> {
+        let (name, _) = self.server_for(path)?;
+        let name = name.clone();
+        // The clients are locked while a server starts
+        let running = self.clients.try_lock().ok()?.contains_key(&name);
+        if !running {
+            let manager = Arc::clone(self);
+            let path = path.to_path_buf();
+            tokio::spawn(async move {
+                if let Err(e) = manager.open(&path).await {
+                    tracing::warn!("Failed to start a language server: {}", e);
+                }
+            });
+            return None;
+        }
+
+        match self
+            .diagnostics_within(path, EDIT_DIAGNOSTICS_TIMEOUT)
+            .await
+        {
+            Ok(diagnostics) if diagnostics.is_empty() => Some(format!(
+                "{} reports no problems in {}",
+                name,
+                path.display()
+            )),
+            Ok(diagnostics) => Some(format!(
+                "{} reports the following problems:\n{}",
+                name,
+                format_diagnostics(path, &diagnostics)
+            )),
+            Err(e) => {
+                tracing::warn!("Failed to get diagnostics from {}: {}", name, e);
+                None
+            }
+        }
+    }
+}
+
+fn lsp_error(error: LspError) -> ToolError {
+    ToolError::ExecutionError(error.to_string())
+}
+
+/// Resolves symlinks so paths from language servers compare with the project root
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Converts a 1-indexed line and character column into an LSP position (UTF-16 offsets)
+fn to_lsp_position(text: &str, line: usize, column: usize) -> Value {
+    let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let character: usize = line_text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(char::len_utf16)
+        .sum();
+    json!({"line": line.saturating_sub(1), "character": character})
+}
+
+/// Byte offset in `text` of an LSP position, clamped to the end of the line
+fn byte_offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+    let line_start: usize = text.split_inclusive('\n').take(line).map(str::len).sum();
+    let line_text = text[line_start..].split('\n').next().unwrap_or("");
+
+    let mut utf16 = 0;
+    for (offset, c) in line_text.char_indices() {
+        if utf16 >= character {
+            return line_start + offset;
+        }
+        utf16 += c.len_utf16();
+    }
+    line_start + line_text.len()
+}
+
+/// 1-indexed (line, column) of an LSP position in `text`
+fn display_position(text: &str, position: &Value) -> (usize, usize) {
+    let offset = byte_offset(text, position);
+    let line_start = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (
+        position["line"].as_u64().unwrap_or(0) as usize + 1,
+        text[line_start..offset].chars().count() + 1,
+    )
+}
+
+/// Byte offset in `text` of an LSP position of an edit, `None` when the line is past the end
+fn edit_offset(text: &str, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    position["character"].as_u64()?;
+    (line <= text.matches('\n').count()).then(|| byte_offset(text, position))
+}
+
+/// Applies LSP `TextEdit`s to `text`, failing when a range is not in the text or ranges overlap
+fn apply_text_edits(text: &str, edits: &[Value]) -> Result<String, ToolError> {
+    let mut ranges = Vec::new();
+    for edit in edits {
+        let range = &edit["range"];
+        match (
+            edit_offset(text, &range["start"]),
+            edit_offset(text, &range["end"]),
+        ) {
+            (Some(start), Some(end)) if start <= end => {
+                ranges.push((start, end, edit["newText"].as_str().unwrap_or("")))
+            }
+            _ => {
+                return Err(ToolError::ExecutionError(format!(
+                    "The language server sent an edit with an invalid range: {}",
+                    range
+                )))
+            }
+        }
+    }
+    // Apply from the end so earlier offsets stay valid
+    ranges.sort_by_key(|range| std::cmp::Reverse(range.0));
+    if ranges.windows(2).any(|pair| pair[1].1 > pair[0].0) {
+        return Err(ToolError::ExecutionError(
+            "The language server sent overlapping edits".into(),
+        ));
+    }
+
+    let mut result = text.to_string();
+    for (start, end, new_text) in ranges {
+        result.replace_range(start..end, new_text);
+    }
+    Ok(result)
+}
+
+/// Locations from a definition response, which may be a `Location`, a list of them or a
+/// list of `LocationLink`s
+fn parse_locations(result: &Value) -> Vec<(PathBuf, Value)> {
+    let items = match result {
+        Value::Array(items) => items.clone(),
+        Value::Null => Vec::new(),
+        other => vec![other.clone()],
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let uri = item
+                .get("uri")
+                .or_else(|| item.get("targetUri"))?
+                .as_str()?;
+            let range = item
+                .get("range")
+                .or_else(|| item.get("targetSelectionRange"))?;
+            Some((uri_to_path(uri)?, range["start"].clone()))
+        })
+        .collect()
+}
+
+/// Text of a hover response, whose contents can be markup, a marked string or a list of those
+fn hover_text(result: &Value) -> Option<String> {
+    fn marked(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(o) => o.get("value").and_then(|v| v.as_str()).map(|s| {
+                match o.get("language").and_then(|l| l.as_str()) {
+                    Some(language) => format!("```{}\n{}\n```", language, s),
+                    None => s.to_string(),
+                }
+            }),
+            _ => None,
+        }
+    }
+
+    let text = match result.get("contents")? {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(marked)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        contents => marked(contents)?,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// The text edits of a `WorkspaceEdit` grouped by file
+///
+/// Fails when the edit holds resource operations (create/rename/delete file), which are not
+/// supported, so that a rename is never applied halfway.
+fn workspace_edit_changes(edit: &Value) -> Result<Vec<(PathBuf, Vec<Value>)>, ToolError> {
+    let mut changes = Vec::new();
+
+    if let Some(document_changes) = edit.get("documentChanges").and_then(|d| d.as_array()) {
+        for change in document_changes {
+            let (Some(uri), Some(edits)) = (
+                change["textDocument"]["uri"].as_str(),
+                change["edits"].as_array(),
+            ) else {
+                return Err(ToolError::ExecutionError(format!(
+                    "The rename needs to {} files, which is not supported",
+                    change["kind"]
+                        .as_str()
+                        .unwrap_or("create, rename or delete")
+                )));
+            };
+            if let Some(path) = uri_to_path(uri) {
+                changes.push((path, edits.clone()));
+            }
+        }
+    } else if let Some(by_uri) = edit.get("changes").and_then(|c| c.as_object()) {
+        for (uri, edits) in by_uri {
+            if let (Some(path), Some(edits)) = (uri_to_path(uri), edits.as_array()) {
+                changes.push((path, edits.clone()));
+            }
+        }
+    }
+
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(changes)
+}
+
+/// One line per diagnostic: `path:line:column: severity: message`
+pub fn format_diagnostics(path: &Path, diagnostics: &[Value]) -> String {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let (line, column) = display_position(&text, &diagnostic["range"]["start"]);
+            let severity = match diagnostic["severity"].as_u64() {
+                Some(1) => "error",
+                Some(2) => "warning",
+                Some(3) => "info",
+                _ => "hint",
+            };
+            let code = match &diagnostic["code"] {
+                Value::String(code) => format!(" [{}]", code),
+                Value::Number(code) => format!(" [{}]", code),
+                _ => String::new(),
+            };
+            format!(
+                "{}:{}:{}: {}: {}{}",
+                path.display(),
+                line,
+                column,
+                severity,
+                diagnostic["message"].as_str().unwrap_or("").trim(),
+                code
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Clone)]
+pub struct LspRouter {
+    tools: Vec<Tool>,
+    instructions: String,
+    manager: Arc<LspManager>,
+    ignore_patterns: Arc<Gitignore>,
+    file_history: Arc<FileHistory>,
+}
+
+impl Default for LspRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LspRouter {
+    pub fn new() -> Self {
+        let position_properties = json!({
+            "path": {
+                "type": "string",
+                "description": "Absolute path to the source file"
+            },
+            "line": {
+                "type": "integer",
+                "minimum": 1,
+                "description": "1-indexed line of the symbol"
+            },
+            "column": {
+                "type": "integer",
+                "minimum": 1,
+                "description": "1-indexed column of any character in the symbol"
+            }
+        });
+
+        let read_only = |title: &str| {
+            Some(ToolAnnotations {
+                title: Some(title.to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            })
+        };
+
+        let diagnostics_tool = Tool::new(
+            "lsp_diagnostics",
+            indoc! {r#"
+                Get the compiler errors and warnings for a file from its language server.
+
+                Much faster than running a full build, use it after editing a file to check that it still compiles.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path"],
+                "properties": {
+                    "path": position_properties["path"]
+                }
+            }),
+            read_only("Get diagnostics"),
+        );
+
+        let definition_tool = Tool::new(
+            "lsp_definition",
+            indoc! {r#"
+                Go to the definition of the symbol at a position, as resolved by the language server.
+
+                Unlike a text search this follows imports, re-exports and method dispatch.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path", "line", "column"],
+                "properties": position_properties
+            }),
+            read_only("Go to definition"),
+        );
+
+        let hover_tool = Tool::new(
+            "lsp_hover",
+            indoc! {r#"
+                Show the type signature and documentation of the symbol at a position.
+
+                Useful to check inferred types and the signature of functions before calling them.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path", "line", "column"],
+                "properties": position_properties
+            }),
+            read_only("Hover"),
+        );
+
+        let mut rename_properties = position_properties.clone();
+        rename_properties["new_name"] = json!({
+            "type": "string",
+            "description": "The new name of the symbol"
+        });
+        let rename_tool = Tool::new(
+            "lsp_rename",
+            indoc! {r#"
+                Rename the symbol at a position everywhere it is used in the project.
+
+                The language server computes the edits so only real references are renamed, the changed
+                files are written to disk and can be restored with `undo_edit` of the text editor.
+            "#},
+            json!({
+                "type": "object",
+                "required": ["path", "line", "column", "new_name"],
+                "properties": rename_properties
+            }),
+            Some(ToolAnnotations {
+                title: Some("Rename symbol".to_string()),
+                read_only_hint: false,
+                destructive_hint: false,
+                idempotent_hint: false,
+                open_world_hint: false,
+            }),
+        );
+
+        let cwd = std::env::current_dir().expect("should have a current working dir");
+        let manager = LspManager::shared(cwd.clone());
+
+        let mut servers: Vec<String> = manager
+            .servers
+            .iter()
+            .map(|(name, config)| format!("- {}: .{}", name, config.extensions.join(", .")))
+            .collect();
+        servers.sort();
+
+        let instructions = formatdoc! {r#"
+            The lsp extension connects to language servers to give you semantic information about code:
+            compiler diagnostics, go-to-definition, type information on hover and project-wide renames.
+
+            Positions are 1-indexed lines and columns, as shown by `text_editor` with `view_range`.
+            Servers are started on first use, the first request for a language may take a while.
+
+            Configured language servers:
+            {servers}
+
+            project root: {cwd}
+            "#,
+            servers=servers.join("\n"),
+            cwd=cwd.display(),
+        };
+
+        Self {
+            tools: vec![diagnostics_tool, definition_tool, hover_tool, rename_tool],
+            instructions,
+            manager,
+            ignore_patterns: Arc::new(load_ignore_patterns(&cwd)),
+            file_history: Arc::new(default_file_history()),
+        }
+    }
+
+    fn path_param(params: &Value) -> Result<PathBuf, ToolError> {
+        let path = params
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'path' parameter".into()))?;
+        let path = PathBuf::from(path);
+        if !path.is_absolute() {
+            return Err(ToolError::InvalidParameters(format!(
+                "The path {} is not an absolute path",
+                path.display()
+            )));
+        }
+        if !path.is_file() {
+            return Err(ToolError::InvalidParameters(format!(
+                "The path '{}' does not exist or is not a file.",
+                path.display()
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Opens the file from `params` and returns the client with the request's text position
+    async fn position_params(
+        &self,
+        params: &Value,
+    ) -> Result<(Arc<LspClient>, PathBuf, Value), ToolError> {
+        let path = Self::path_param(params)?;
+        let number = |name: &str| {
+            params
+                .get(name)
+                .and_then(|v| v.as_u64())
+                .filter(|n| *n >= 1)
+                .map(|n| n as usize)
+                .ok_or_else(|| {
+                    ToolError::InvalidParameters(format!(
+                        "Missing or invalid '{}' parameter, it must be a 1-indexed number",
+                        name
+                    ))
+                })
+        };
+        let (line, column) = (number("line")?, number("column")?);
+
+        let client = self.manager.open(&path).await?;
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+        let uri = path_to_uri(&path).map_err(lsp_error)?;
+
+        Ok((
+            client,
+            path,
+            json!({
+                "textDocument": {"uri": uri},
+                "position": to_lsp_position(&text, line, column)
+            }),
+        ))
+    }
+
+    async fn lsp_diagnostics(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let path = Self::path_param(&params)?;
+        let diagnostics = self.manager.diagnostics(&path).await?;
+
+        let output = if diagnostics.is_empty() {
+            format!("No problems reported in {}", path.display())
+        } else {
+            format_diagnostics(&path, &diagnostics)
+        };
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn lsp_definition(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let (client, _, request) = self.position_params(&params).await?;
+        let result = client
+            .request("textDocument/definition", request)
+            .await
+            .map_err(lsp_error)?;
+
+        let locations: Vec<String> = parse_locations(&result)
+            .into_iter()
+            .map(|(path, start)| {
+                let text = std::fs::read_to_string(&path).unwrap_or_default();
+                let (line, column) = display_position(&text, &start);
+                let source_line = text.lines().nth(line - 1).unwrap_or("").trim();
+                format!("{}:{}:{}: {}", path.display(), line, column, source_line)
+            })
+            .collect();
+
+        let output = if locations.is_empty() {
+            "No definition found at this position".to_string()
+        } else {
+            locations.join("\n")
+        };
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn lsp_hover(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let (client, _, request) = self.position_params(&params).await?;
+        let result = client
+            .request("textDocument/hover", request)
+            .await
+            .map_err(lsp_error)?;
+
+        let output = hover_text(&result)
+            .unwrap_or_else(|| "No information available at this position".to_string());
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
+    async fn lsp_rename(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let new_name = params
+            .get("new_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'new_name' parameter".into()))?
+            .to_string();
+        let (client, _, mut request) = self.position_params(&params).await?;
+        request["newName"] = json!(new_name);
+
+        let result = client
+            .request("textDocument/rename", request)
+            .await
+            .map_err(lsp_error)?;
+
+        let changes = workspace_edit_changes(&result)?;
+        if changes.is_empty() {
+            return Err(ToolError::ExecutionError(
+                "The language server did not produce any edits for this rename".into(),
+            ));
+        }
+
+        // Nothing is written when one of the files is off limits
+        if let Some((path, _)) = changes
+            .iter()
+            .find(|(path, _)| self.ignore_patterns.matched(path, false).is_ignore())
+        {
+            return Err(ToolError::ExecutionError(format!(
+                "The rename would edit '{}', which is restricted by .gooseignore",
+                path.display()
+            )));
+        }
+
+        let root = canonical(self.manager.root());
+        if let Some((path, _)) = changes
+            .iter()
+            .find(|(path, _)| !canonical(path).starts_with(&root))
+        {
+            return Err(ToolError::ExecutionError(format!(
+                "The rename would edit '{}', which is outside of the project root {}",
+                path.display(),
+                root.display()
+            )));
+        }
+
+        // Edits are checked against every file before any of them is written
+        let mut edited = Vec::new();
+        for (path, edits) in &changes {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to read file: {}", e)))?;
+            let new_text = apply_text_edits(&text, edits)?;
+            edited.push((path, edits, text, new_text));
+        }
+
+        let mut summary = Vec::new();
+        for (path, edits, text, new_text) in edited {
+            self.file_history.push(path, Some(text));
+            std::fs::write(path, new_text)
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to write file: {}", e)))?;
+            summary.push(format!("- {} ({} edits)", path.display(), edits.len()));
+
+            // Keep the server's view of open documents in sync with disk
+            if self.manager.handles(path) {
+                self.manager.open(path).await?;
+            }
+        }
+
+        let output = format!(
+            "Renamed to `{}` in {} files:\n{}",
+            new_name,
+            changes.len(),
+            summary.join("\n")
+        );
+
+        Ok(vec![
+            Content::text(output.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(output)
+                .with_audience(vec![Role::User])
+                .with_priority(0.2),
+        ])
+    }
+}
+
+impl Router for LspRouter {
+    fn name(&self) -> String {
+        "lsp".to_string()
+    }
+
+    fn instructions(&self) -> String {
+        self.instructions.clone()
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        CapabilitiesBuilder::new().with_tools(false).build()
+    }
+
+    fn list_tools(&self) -> Vec<Tool> {
+        self.tools.clone()
+    }
+
+    fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        let this = self.clone();
+        let tool_name = tool_name.to_string();
+        Box::pin(async move {
+            match tool_name.as_str() {
+                "lsp_diagnostics" => this.lsp_diagnostics(arguments).await,
+                "lsp_definition" => this.lsp_definition(arguments).await,
+                "lsp_hover" => this.lsp_hover(arguments).await,
+                "lsp_rename" => this.lsp_rename(arguments).await,
+                _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
+            }
+        })
+    }
+
+    fn list_resources(&self) -> Vec<Resource> {
+        Vec::new()
+    }
+
+    fn read_resource(
+        &self,
+        _uri: &str,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
+        Box::pin(async move { Ok("".to_string()) })
+    }
+
+    fn list_prompts(&self) -> Vec<Prompt> {
+        vec![]
+    }
+
+    fn get_prompt(
+        &self,
+        prompt_name: &str,
+    ) -> Pin<Box<dyn Future<Output = Result<String, PromptError>> + Send + 'static>> {
+        let prompt_name = prompt_name.to_string();
+        Box::pin(async move {
+            Err(PromptError::NotFound(format!(
+                "Prompt {} not found",
+                prompt_name
+            )))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_use_utf16_offsets() {
+        let text = "let é = 1;\nlet 🦀 = crab;\n";
+        // 🦀 is two UTF-16 code units, so `crab` on line 2 column 9 is at character 9
+        let position = to_lsp_position(text, 2, 9);
+        assert_eq!(position, json!({"line": 1, "character": 9}));
+        assert_eq!(&text[byte_offset(text, &position)..], "crab;\n");
+        assert_eq!(display_position(text, &position), (2, 9));
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        let text = "fn old() {}\nfn main() { old(); old(); }\n";
+        let edit = |line: u64, start: u64, end: u64| {
+            json!({
+                "range": {
+                    "start": {"line": line, "character": start},
+                    "end": {"line": line, "character": end}
+                },
+                "newText": "renamed"
+            })
+        };
+        let edits = vec![edit(0, 3, 6), edit(1, 12, 15), edit(1, 19, 22)];
+        assert_eq!(
+            apply_text_edits(text, &edits).unwrap(),
+            "fn renamed() {}\nfn main() { renamed(); renamed(); }\n"
+        );
+
+        // Ranges that are reversed, past the end of the text or overlapping are refused
+        assert!(apply_text_edits(text, &[edit(0, 6, 3)]).is_err());
+        assert!(apply_text_edits(text, &[edit(7, 0, 1)]).is_err());
+        assert!(apply_text_edits(text, &[edit(0, 0, 5), edit(0, 3, 6)]).is_err());
+        // The end of the text is a valid position
+        assert_eq!(
+            apply_text_edits(text, &[edit(2, 0, 0)]).unwrap(),
+            format!("{}renamed", text)
+        );
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        let locations = parse_locations(&json!([{
+            "targetUri": "file:///repo/src/lib.rs",
+            "targetRange": {"start": {"line": 0, "character": 0}, "end": {"line": 5, "character": 1}},
+            "targetSelectionRange": {"start": {"line": 2, "character": 7}, "end": {"line": 2, "character": 10}}
+        }]));
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].0, PathBuf::from("/repo/src/lib.rs"));
+        assert_eq!(locations[0].1, json!({"line": 2, "character": 7}));
+
+        let hover = hover_text(&json!({
+            "contents": {"kind": "markdown", "value": "```rust\nfn main()\n```"}
+        }));
+        assert_eq!(hover.as_deref(), Some("```rust\nfn main()\n```"));
+        assert_eq!(hover_text(&json!({"contents": []})), None);
+
+        let changes = workspace_edit_changes(&json!({
+            "documentChanges": [
+                {"textDocument": {"uri": "file:///repo/b.rs", "version": 1}, "edits": [{}]},
+                {"textDocument": {"uri": "file:///repo/a.rs", "version": 1}, "edits": [{}, {}]}
+            ]
+        }))
+        .unwrap();
+        let files: Vec<(PathBuf, usize)> = changes
+            .into_iter()
+            .map(|(path, edits)| (path, edits.len()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (PathBuf::from("/repo/a.rs"), 2),
+                (PathBuf::from("/repo/b.rs"), 1)
+            ]
+        );
+
+        // Resource operations fail the whole rename
+        assert!(workspace_edit_changes(&json!({
+            "documentChanges": [
+                {"textDocument": {"uri": "file:///repo/b.rs", "version": 1}, "edits": [{}]},
+                {"kind": "rename", "oldUri": "file:///repo/x.rs", "newUri": "file:///repo/y.rs"}
+            ]
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_servers_from_env() {
+        temp_env::with_var(
+            LSP_SERVERS_ENV,
+            Some(
+                r#"{"tsserver": {"command": "typescript-language-server", "args": ["--stdio"], "extensions": ["ts"], "language_id": "typescript"}}"#,
+            ),
+            || {
+                let manager = LspManager::new(PathBuf::from("/repo"));
+                assert!(manager.handles(Path::new("/repo/app.ts")));
+                assert!(manager.handles(Path::new("/repo/main.rs")));
+                assert!(!manager.handles(Path::new("/repo/notes.md")));
+            },
+        );
+    }
+}
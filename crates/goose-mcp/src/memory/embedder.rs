@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+
+/// Turns memories and queries into vectors that are close when their meaning is
+#[async_trait]
+pub trait MemoryEmbedder: Send + Sync {
+    /// Identifies the model, vectors of different models are not compared
+    fn model_id(&self) -> String;
+
+    /// One vector per text, in the order of the texts
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+const HASHING_DIMENSIONS: usize = 512;
+
+/// Embeds texts by hashing their words and word pairs, so memories can be searched without
+/// an embedding model
+///
+/// Texts sharing words are close, but unlike a model it does not know synonyms.
+pub struct HashingEmbedder;
+
+impl HashingEmbedder {
+    pub fn embed_text(text: &str) -> Vec<f32> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect();
+        let pairs = words
+            .windows(2)
+            .map(|pair| format!("{} {}", pair[0], pair[1]));
+
+        let mut vector = vec![0.0; HASHING_DIMENSIONS];
+        for feature in words.iter().cloned().chain(pairs) {
+            let hash = fnv1a(feature.as_bytes());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % HASHING_DIMENSIONS as u64) as usize] += sign;
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        vector
+    }
+}
+
+#[async_trait]
+impl MemoryEmbedder for HashingEmbedder {
+    fn model_id(&self) -> String {
+        format!("hashing-{}", HASHING_DIMENSIONS)
+    }
+
+    async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| Self::embed_text(text)).collect())
+    }
+}
+
+/// A hash that is the same across runs and platforms, unlike the std hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+const INDEX_FILE: &str = "memories.jsonl";
+/// Category files of older versions are moved here once they are in the index
+const MIGRATED_DIR: &str = "migrated";
+
+/// A memory with its metadata and the vector it is searched by
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryRecord {
+    pub category: String,
+    pub data: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The model the vector was embedded with, vectors of other models are embedded again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vector: Vec<f32>,
+}
+
+impl MemoryRecord {
+    pub fn new(category: &str, data: &str, tags: &[&str]) -> Self {
+        let now = Utc::now();
+        Self {
+            category: category.to_string(),
+            data: data.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            created_at: now,
+            updated_at: now,
+            expires_at: None,
+            embedding_model: None,
+            vector: Vec::new(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
+
+/// An exclusive lock on an index, released when dropped
+pub struct IndexLock {
+    _file: fs::File,
+}
+
+/// The memories of one scope, stored as one json record per line
+pub struct MemoryIndex {
+    dir: PathBuf,
+}
+
+impl MemoryIndex {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    /// Locked by every process while it changes the index. It is next to the directory rather
+    /// than in it, so the lock is still held while the directory is removed.
+    fn lock_path(&self) -> PathBuf {
+        let mut name = self.dir.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        self.dir.with_file_name(name)
+    }
+
+    /// Waits until no other process is changing the index and keeps them out until the returned
+    /// lock is dropped. Blocks the thread while waiting.
+    pub fn lock(&self) -> io::Result<IndexLock> {
+        let lock_path = self.lock_path();
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)?;
+        file.lock_exclusive()?;
+        Ok(IndexLock { _file: file })
+    }
+
+    /// The memories that have not expired, with those of the category files of older versions
+    /// that are not migrated yet. Only reads, see [`MemoryIndex::migrate`].
+    pub fn load(&self) -> io::Result<Vec<MemoryRecord>> {
+        let mut records = self.read()?;
+        records.extend(
+            self.category_files()?
+                .into_iter()
+                .flat_map(|(_, file)| file),
+        );
+        Ok(records)
+    }
+
+    fn read(&self) -> io::Result<Vec<MemoryRecord>> {
+        let path = self.path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for line in io::BufReader::new(fs::File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<MemoryRecord>(&line) {
+                Ok(record) if !record.is_expired() => records.push(record),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Skipping an invalid memory: {}", e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Replaces the stored memories, dropping the expired ones
+    pub fn save(&self, records: &[MemoryRecord]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let tmp_path = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        let mut file = io::BufWriter::new(fs::File::create(&tmp_path)?);
+        for record in records.iter().filter(|record| !record.is_expired()) {
+            serde_json::to_writer(&mut file, record)?;
+            writeln!(file)?;
+        }
+        file.flush()?;
+        drop(file);
+        fs::rename(tmp_path, self.path())
+    }
+
+    /// Moves the memories of the `<category>.txt` files of older versions into the index, which
+    /// rewrites it, so only while holding its lock
+    pub fn migrate(&self, _lock: &IndexLock) -> io::Result<()> {
+        let files = self.category_files()?;
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut records = self.read()?;
+        let migrated_dir = self.dir.join(MIGRATED_DIR);
+        fs::create_dir_all(&migrated_dir)?;
+        let mut paths = Vec::new();
+        for (path, file) in files {
+            records.extend(file);
+            paths.push(path);
+        }
+        self.save(&records)?;
+
+        for path in paths {
+            if let Some(name) = path.file_name() {
+                fs::rename(&path, migrated_dir.join(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The `<category>.txt` files of older versions with their memories
+    fn category_files(&self) -> io::Result<Vec<(PathBuf, Vec<MemoryRecord>)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "txt") {
+                continue;
+            }
+            let Some(category) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let created_at = fs::metadata(&path)?
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now());
+            let records = parse_category_file(category, &fs::read_to_string(&path)?)
+                .into_iter()
+                .map(|record| MemoryRecord {
+                    created_at,
+                    updated_at: created_at,
+                    ..record
+                })
+                .collect();
+            files.push((path, records));
+        }
+        Ok(files)
+    }
+}
+
+/// Reads the memories of a category file, where each memory is separated by a blank line
+/// and may start with a `# tag1 tag2` line
+fn parse_category_file(category: &str, content: &str) -> Vec<MemoryRecord> {
+    content
+        .split("\n\n")
+        .filter_map(|entry| {
+            let mut lines = entry.lines().peekable();
+            let tags: Vec<&str> = match lines.peek() {
+                Some(line) if line.starts_with('#') => {
+                    let tags = line[1..].split_whitespace().collect();
+                    lines.next();
+                    tags
+                }
+                _ => Vec::new(),
+            };
+            let data = lines.collect::<Vec<_>>().join("\n");
+            let data = data.trim();
+            (!data.is_empty()).then(|| MemoryRecord::new(category, data, &tags))
+        })
+        .collect()
+}
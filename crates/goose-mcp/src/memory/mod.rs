@@ -1,4 +1,8 @@
+mod embedder;
+mod index;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
 use etcetera::{choose_app_strategy, AppStrategy};
 use indoc::formatdoc;
 use serde_json::{json, Value};
@@ -6,10 +10,12 @@ use std::{
     collections::HashMap,
     fs,
     future::Future,
-    io::{self, Read, Write},
-    path::PathBuf,
+    io,
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::Arc,
 };
+use tokio::sync::{Mutex, MutexGuard};
 
 use mcp_core::{
     handler::{PromptError, ResourceError, ToolError},
@@ -22,6 +28,31 @@ use mcp_core::{
 use mcp_server::router::CapabilitiesBuilder;
 use mcp_server::Router;
 
+pub use embedder::{HashingEmbedder, MemoryEmbedder};
+pub use index::MemoryRecord;
+
+use embedder::cosine_similarity;
+use index::{IndexLock, MemoryIndex};
+
+/// Memories at least this similar are taken to be the same fact
+const DUPLICATE_SIMILARITY: f32 = 0.95;
+const DEFAULT_SEARCH_RESULTS: usize = 5;
+
+/// Whether remembering added a memory or updated a near-identical one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Remembered {
+    Added,
+    Updated,
+}
+
+/// A memory found by a search, with how similar it is to the query
+#[derive(Debug, Clone)]
+pub struct MemoryMatch {
+    pub record: MemoryRecord,
+    pub score: f32,
+    pub is_global: bool,
+}
+
 // MemoryRouter implementation
 #[derive(Clone)]
 pub struct MemoryRouter {
@@ -29,6 +60,9 @@ pub struct MemoryRouter {
     instructions: String,
     global_memory_dir: PathBuf,
     local_memory_dir: PathBuf,
+    embedder: Arc<dyn MemoryEmbedder>,
+    /// Changes rewrite the index, so they are made one at a time, see [`MemoryRouter::lock`]
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl Default for MemoryRouter {
@@ -39,16 +73,41 @@ impl Default for MemoryRouter {
 
 impl MemoryRouter {
     pub fn new() -> Self {
+        // Check for .goose/memory in current directory
+        let working_dir = std::env::var("GOOSE_WORKING_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::current_dir().unwrap());
+        Self::for_working_dir(&working_dir)
+    }
+
+    /// Keeps local memories in the `.goose/memory` directory of a project other than the
+    /// current one
+    pub fn for_working_dir(working_dir: &Path) -> Self {
+        let local_memory_dir = working_dir.join(".goose").join("memory");
+
+        // choose_app_strategy().config_dir()
+        // - macOS/Linux: ~/.config/goose/memory/
+        // - Windows:     ~\AppData\Roaming\Block\goose\config\memory
+        // if it fails, fall back to `.config/goose/memory` (relative to the current dir)
+        let global_memory_dir = choose_app_strategy(crate::APP_STRATEGY.clone())
+            .map(|strategy| strategy.in_config_dir("memory"))
+            .unwrap_or_else(|_| PathBuf::from(".config/goose/memory"));
+
+        Self::with_memory_dirs(global_memory_dir, local_memory_dir)
+    }
+
+    fn with_memory_dirs(global_memory_dir: PathBuf, local_memory_dir: PathBuf) -> Self {
         let remember_memory = Tool::new(
             "remember_memory",
-            "Stores a memory with optional tags in a specified category",
+            "Stores a memory with optional tags in a specified category. A memory that repeats a stored one updates it instead.",
             json!({
                 "type": "object",
                 "properties": {
                     "category": {"type": "string"},
                     "data": {"type": "string"},
                     "tags": {"type": "array", "items": {"type": "string"}},
-                    "is_global": {"type": "boolean"}
+                    "is_global": {"type": "boolean"},
+                    "expires_in_days": {"type": "integer", "description": "Forget the memory after this many days, for facts that won't stay true"}
                 },
                 "required": ["category", "data", "is_global"]
             }),
@@ -81,6 +140,29 @@ impl MemoryRouter {
             }),
         );
 
+        let search_memories = Tool::new(
+            "search_memories",
+            "Finds the memories closest in meaning to a query, across categories",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "k": {"type": "integer", "description": "Maximum number of memories, 5 by default"},
+                    "tags": {"type": "array", "items": {"type": "string"}, "description": "Only memories with all of these tags"},
+                    "category": {"type": "string", "description": "Only memories of this category"},
+                    "is_global": {"type": "boolean", "description": "Only global or only local memories, both by default"}
+                },
+                "required": ["query"]
+            }),
+            Some(ToolAnnotations {
+                title: Some("Search Memories".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: false,
+                open_world_hint: false,
+            }),
+        );
+
         let remove_memory_category = Tool::new(
             "remove_memory_category",
             "Removes all memories within a specified category",
@@ -190,6 +272,9 @@ impl MemoryRouter {
              Assistant: "I'll store this in the 'github' category. Any specific tags to add? Suggestions: #comments #gh"
              Retrieving Memories:
              To access stored information, utilize the memory retrieval protocols:
+             - **Search by Meaning**:
+               - Finds the memories most related to a question, whatever their category.
+               - Use: `search_memories(query="code formatting tools", k=5)`
              - **Search by Category**:
                - Provides all memories within the specified context.
                - Use: `retrieve_memories(category="development", is_global=False)`
@@ -225,21 +310,6 @@ impl MemoryRouter {
              - Acknowledge the user about what is stored and where, for transparency and ease of future retrieval.
             "#};
 
-        // Check for .goose/memory in current directory
-        let local_memory_dir = std::env::var("GOOSE_WORKING_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| std::env::current_dir().unwrap())
-            .join(".goose")
-            .join("memory");
-
-        // choose_app_strategy().config_dir()
-        // - macOS/Linux: ~/.config/goose/memory/
-        // - Windows:     ~\AppData\Roaming\Block\goose\config\memory
-        // if it fails, fall back to `.config/goose/memory` (relative to the current dir)
-        let global_memory_dir = choose_app_strategy(crate::APP_STRATEGY.clone())
-            .map(|strategy| strategy.in_config_dir("memory"))
-            .unwrap_or_else(|_| PathBuf::from(".config/goose/memory"));
-
         fs::create_dir_all(&global_memory_dir).unwrap();
         fs::create_dir_all(&local_memory_dir).unwrap();
 
@@ -247,12 +317,15 @@ impl MemoryRouter {
             tools: vec![
                 remember_memory,
                 retrieve_memories,
+                search_memories,
                 remove_memory_category,
                 remove_specific_memory,
             ],
             instructions: instructions.clone(),
             global_memory_dir,
             local_memory_dir,
+            embedder: Arc::new(HashingEmbedder),
+            write_lock: Arc::new(Mutex::new(())),
         };
 
         let retrieved_global_memories = memory_router.retrieve_all(true);
@@ -309,40 +382,48 @@ impl MemoryRouter {
         &self.instructions
     }
 
-    fn get_memory_file(&self, category: &str, is_global: bool) -> PathBuf {
+    /// Embed memories with this embedder instead of hashing their words
+    pub fn with_embedder(self, embedder: Arc<dyn MemoryEmbedder>) -> Self {
+        Self { embedder, ..self }
+    }
+
+    /// Locks the index of a scope for a change, against the other tasks of this router and the
+    /// other processes using the same memories (the memory extension and the memory extraction
+    /// of the CLI and goose-server). The category files of older versions are migrated first.
+    async fn lock(&self, is_global: bool) -> io::Result<(MutexGuard<'_, ()>, IndexLock)> {
+        let guard = self.write_lock.lock().await;
+        let index = self.index(is_global);
+        let lock = tokio::task::spawn_blocking(move || {
+            let lock = index.lock()?;
+            index.migrate(&lock)?;
+            Ok::<_, io::Error>(lock)
+        })
+        .await
+        .map_err(io::Error::other)??;
+        Ok((guard, lock))
+    }
+
+    fn index(&self, is_global: bool) -> MemoryIndex {
         // Defaults to local memory if no is_global flag is provided
-        let base_dir = if is_global {
-            &self.global_memory_dir
+        if is_global {
+            MemoryIndex::new(&self.global_memory_dir)
         } else {
-            &self.local_memory_dir
-        };
-        base_dir.join(format!("{}.txt", category))
+            MemoryIndex::new(&self.local_memory_dir)
+        }
     }
 
     pub fn retrieve_all(&self, is_global: bool) -> io::Result<HashMap<String, Vec<String>>> {
-        let base_dir = if is_global {
-            &self.global_memory_dir
-        } else {
-            &self.local_memory_dir
-        };
-        let mut memories = HashMap::new();
-        if base_dir.exists() {
-            for entry in fs::read_dir(base_dir)? {
-                let entry = entry?;
-                if entry.file_type()?.is_file() {
-                    let category = entry.file_name().to_string_lossy().replace(".txt", "");
-                    let category_memories = self.retrieve(&category, is_global)?;
-                    memories.insert(
-                        category,
-                        category_memories.into_iter().flat_map(|(_, v)| v).collect(),
-                    );
-                }
-            }
+        let mut memories: HashMap<String, Vec<String>> = HashMap::new();
+        for record in self.index(is_global).load()? {
+            memories
+                .entry(record.category)
+                .or_default()
+                .push(record.data);
         }
         Ok(memories)
     }
 
-    pub fn remember(
+    pub async fn remember(
         &self,
         _context: &str,
         category: &str,
@@ -350,18 +431,64 @@ impl MemoryRouter {
         tags: &[&str],
         is_global: bool,
     ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-
-        let mut file = fs::OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(&memory_file_path)?;
-        if !tags.is_empty() {
-            writeln!(file, "# {}", tags.join(" "))?;
-        }
-        writeln!(file, "{}\n", data)?;
+        self.remember_with_expiry(category, data, tags, is_global, None)
+            .await
+            .map(|_| ())
+    }
 
-        Ok(())
+    /// Stores a memory, or updates a stored one of the category that says the same thing
+    pub async fn remember_with_expiry(
+        &self,
+        category: &str,
+        data: &str,
+        tags: &[&str],
+        is_global: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> io::Result<Remembered> {
+        // Embedding calls the provider, it happens before taking the lock so that other
+        // processes don't wait for it
+        let index = self.index(is_global);
+        let vectors = self.embed_missing(&index.load()?).await?;
+        let vector = self
+            .embed(&[data.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let _lock = self.lock(is_global).await?;
+        let mut records = index.load()?;
+        self.apply_vectors(&mut records, &vectors);
+
+        let duplicate = records.iter_mut().find(|record| {
+            record.category == category
+                && (normalize(&record.data) == normalize(data)
+                    || cosine_similarity(&record.vector, &vector) >= DUPLICATE_SIMILARITY)
+        });
+        let remembered = match duplicate {
+            Some(record) => {
+                record.data = data.to_string();
+                for tag in tags {
+                    if !record.tags.iter().any(|t| t == tag) {
+                        record.tags.push(tag.to_string());
+                    }
+                }
+                record.updated_at = Utc::now();
+                record.expires_at = expires_at.or(record.expires_at);
+                record.vector = vector;
+                Remembered::Updated
+            }
+            None => {
+                records.push(MemoryRecord {
+                    expires_at,
+                    embedding_model: Some(self.embedder.model_id()),
+                    vector,
+                    ..MemoryRecord::new(category, data, tags)
+                });
+                Remembered::Added
+            }
+        };
+        index.save(&records)?;
+        Ok(remembered)
     }
 
     pub fn retrieve(
@@ -369,77 +496,97 @@ impl MemoryRouter {
         category: &str,
         is_global: bool,
     ) -> io::Result<HashMap<String, Vec<String>>> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
-            return Ok(HashMap::new());
+        let mut memories: HashMap<String, Vec<String>> = HashMap::new();
+        for record in self.index(is_global).load()? {
+            if record.category != category {
+                continue;
+            }
+            let tags = if record.tags.is_empty() {
+                "untagged".to_string()
+            } else {
+                record.tags.join(" ")
+            };
+            memories.entry(tags).or_default().push(record.data);
         }
+        Ok(memories)
+    }
 
-        let mut file = fs::File::open(memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-
-        let mut memories = HashMap::new();
-        for entry in content.split("\n\n") {
-            let mut lines = entry.lines();
-            if let Some(first_line) = lines.next() {
-                if let Some(stripped) = first_line.strip_prefix('#') {
-                    let tags = stripped
-                        .split_whitespace()
-                        .map(String::from)
-                        .collect::<Vec<_>>();
-                    memories.insert(tags.join(" "), lines.map(String::from).collect());
-                } else {
-                    let entry_data: Vec<String> = std::iter::once(first_line.to_string())
-                        .chain(lines.map(String::from))
-                        .collect();
-                    memories
-                        .entry("untagged".to_string())
-                        .or_insert_with(Vec::new)
-                        .extend(entry_data);
+    /// The `k` memories closest in meaning to the query, from both scopes unless `is_global`
+    /// picks one
+    pub async fn search_memories(
+        &self,
+        query: &str,
+        k: usize,
+        tags: &[&str],
+        category: Option<&str>,
+        is_global: Option<bool>,
+    ) -> io::Result<Vec<MemoryMatch>> {
+        let query_vector = self
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        let scopes = match is_global {
+            Some(is_global) => vec![is_global],
+            None => vec![false, true],
+        };
+
+        let mut matches = Vec::new();
+        for is_global in scopes {
+            let index = self.index(is_global);
+            let mut records = index.load()?;
+            let vectors = self.embed_missing(&records).await?;
+            if !vectors.is_empty() {
+                let _lock = self.lock(is_global).await?;
+                records = index.load()?;
+                if self.apply_vectors(&mut records, &vectors) {
+                    index.save(&records)?;
                 }
             }
+            matches.extend(
+                records
+                    .into_iter()
+                    .filter(|record| category.is_none_or(|category| record.category == category))
+                    .filter(|record| {
+                        tags.iter()
+                            .all(|tag| record.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                    })
+                    .map(|record| MemoryMatch {
+                        score: cosine_similarity(&record.vector, &query_vector),
+                        record,
+                        is_global,
+                    }),
+            );
         }
-
-        Ok(memories)
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(k);
+        Ok(matches)
     }
 
-    pub fn remove_specific_memory(
+    pub async fn remove_specific_memory(
         &self,
         category: &str,
         memory_content: &str,
         is_global: bool,
     ) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if !memory_file_path.exists() {
-            return Ok(());
-        }
-
-        let mut file = fs::File::open(&memory_file_path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-
-        let memories: Vec<&str> = content.split("\n\n").collect();
-        let new_content: Vec<String> = memories
-            .into_iter()
-            .filter(|entry| !entry.contains(memory_content))
-            .map(|s| s.to_string())
-            .collect();
-
-        fs::write(memory_file_path, new_content.join("\n\n"))?;
-
-        Ok(())
+        let _lock = self.lock(is_global).await?;
+        let index = self.index(is_global);
+        let mut records = index.load()?;
+        records
+            .retain(|record| record.category != category || !record.data.contains(memory_content));
+        index.save(&records)
     }
 
-    pub fn clear_memory(&self, category: &str, is_global: bool) -> io::Result<()> {
-        let memory_file_path = self.get_memory_file(category, is_global);
-        if memory_file_path.exists() {
-            fs::remove_file(memory_file_path)?;
-        }
-
-        Ok(())
+    pub async fn clear_memory(&self, category: &str, is_global: bool) -> io::Result<()> {
+        let _lock = self.lock(is_global).await?;
+        let index = self.index(is_global);
+        let mut records = index.load()?;
+        records.retain(|record| record.category != category);
+        index.save(&records)
     }
 
-    pub fn clear_all_global_or_local_memories(&self, is_global: bool) -> io::Result<()> {
+    pub async fn clear_all_global_or_local_memories(&self, is_global: bool) -> io::Result<()> {
+        let _lock = self.lock(is_global).await?;
         let base_dir = if is_global {
             &self.global_memory_dir
         } else {
@@ -449,6 +596,58 @@ impl MemoryRouter {
         Ok(())
     }
 
+    async fn embed(&self, texts: &[String]) -> io::Result<Vec<Vec<f32>>> {
+        self.embedder
+            .embed(texts)
+            .await
+            .map_err(|e| io::Error::other(format!("Failed to embed memories: {}", e)))
+    }
+
+    /// Embeds the memories without a vector of the current embedder, by their text
+    async fn embed_missing(
+        &self,
+        records: &[MemoryRecord],
+    ) -> io::Result<HashMap<String, Vec<f32>>> {
+        let model = self.embedder.model_id();
+        let mut texts: Vec<String> = records
+            .iter()
+            .filter(|record| {
+                record.vector.is_empty() || record.embedding_model.as_ref() != Some(&model)
+            })
+            .map(|record| record.data.clone())
+            .collect();
+        texts.sort();
+        texts.dedup();
+        if texts.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let vectors = self.embed(&texts).await?;
+        Ok(texts.into_iter().zip(vectors).collect())
+    }
+
+    /// Gives the memories without a vector of the current embedder the vector embedded for
+    /// their text, returns whether any changed. Memories added since the vectors were embedded
+    /// are left for the next change.
+    fn apply_vectors(
+        &self,
+        records: &mut [MemoryRecord],
+        vectors: &HashMap<String, Vec<f32>>,
+    ) -> bool {
+        let model = self.embedder.model_id();
+        let mut changed = false;
+        for record in records {
+            if !record.vector.is_empty() && record.embedding_model.as_ref() == Some(&model) {
+                continue;
+            }
+            if let Some(vector) = vectors.get(&record.data) {
+                record.vector = vector.clone();
+                record.embedding_model = Some(model.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
     async fn execute_tool_call(&self, tool_call: ToolCall) -> Result<String, io::Error> {
         match tool_call.name.as_str() {
             "remember_memory" => {
@@ -459,8 +658,26 @@ impl MemoryRouter {
                         "Data must exist when remembering a memory",
                     )
                 })?;
-                self.remember("context", args.category, data, &args.tags, args.is_global)?;
-                Ok(format!("Stored memory in category: {}", args.category))
+                let expires_at = tool_call
+                    .arguments
+                    .get("expires_in_days")
+                    .and_then(|v| v.as_i64())
+                    .map(|days| Utc::now() + Duration::days(days));
+                let remembered = self
+                    .remember_with_expiry(
+                        args.category,
+                        data,
+                        &args.tags,
+                        args.is_global,
+                        expires_at,
+                    )
+                    .await?;
+                Ok(match remembered {
+                    Remembered::Added => format!("Stored memory in category: {}", args.category),
+                    Remembered::Updated => {
+                        "Updated an existing memory with the same content".to_string()
+                    }
+                })
             }
             "retrieve_memories" => {
                 let args = MemoryArgs::from_value(&tool_call.arguments)?;
@@ -471,23 +688,52 @@ impl MemoryRouter {
                 };
                 Ok(format!("Retrieved memories: {:?}", memories))
             }
+            "search_memories" => {
+                let query = tool_call.arguments["query"].as_str().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Query must be a string")
+                })?;
+                let k = tool_call.arguments["k"]
+                    .as_u64()
+                    .map_or(DEFAULT_SEARCH_RESULTS, |k| k as usize);
+                let tags: Vec<&str> = match &tool_call.arguments["tags"] {
+                    Value::Array(arr) => arr.iter().filter_map(|v| v.as_str()).collect(),
+                    Value::String(s) => vec![s.as_str()],
+                    _ => Vec::new(),
+                };
+                let category = tool_call.arguments["category"].as_str();
+                let is_global = tool_call.arguments["is_global"].as_bool();
+
+                let matches = self
+                    .search_memories(query, k, &tags, category, is_global)
+                    .await?;
+                if matches.is_empty() {
+                    return Ok("No memories found".to_string());
+                }
+                Ok(matches
+                    .iter()
+                    .map(format_match)
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
             "remove_memory_category" => {
                 let args = MemoryArgs::from_value(&tool_call.arguments)?;
                 if args.category == "*" {
-                    self.clear_all_global_or_local_memories(args.is_global)?;
+                    self.clear_all_global_or_local_memories(args.is_global)
+                        .await?;
                     Ok(format!(
                         "Cleared all memory {} categories",
                         if args.is_global { "global" } else { "local" }
                     ))
                 } else {
-                    self.clear_memory(args.category, args.is_global)?;
+                    self.clear_memory(args.category, args.is_global).await?;
                     Ok(format!("Cleared memories in category: {}", args.category))
                 }
             }
             "remove_specific_memory" => {
                 let args = MemoryArgs::from_value(&tool_call.arguments)?;
                 let memory_content = tool_call.arguments["memory_content"].as_str().unwrap();
-                self.remove_specific_memory(args.category, memory_content, args.is_global)?;
+                self.remove_specific_memory(args.category, memory_content, args.is_global)
+                    .await?;
                 Ok(format!(
                     "Removed specific memory from category: {}",
                     args.category
@@ -564,6 +810,36 @@ impl Router for MemoryRouter {
     }
 }
 
+/// Compares memories regardless of case, spacing and punctuation
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_match(memory: &MemoryMatch) -> String {
+    let record = &memory.record;
+    let mut details = vec![
+        if memory.is_global { "global" } else { "local" }.to_string(),
+        format!("saved {}", record.updated_at.format("%Y-%m-%d")),
+        format!("score {:.2}", memory.score),
+    ];
+    if !record.tags.is_empty() {
+        details.insert(0, format!("tags: {}", record.tags.join(", ")));
+    }
+    if let Some(expires_at) = record.expires_at {
+        details.push(format!("expires {}", expires_at.format("%Y-%m-%d")));
+    }
+    format!(
+        "- [{}] {} ({})",
+        record.category,
+        record.data,
+        details.join("; ")
+    )
+}
+
 #[derive(Debug)]
 struct MemoryArgs<'a> {
     category: &'a str,
@@ -615,3 +891,178 @@ impl<'a> MemoryArgs<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(dir: &tempfile::TempDir) -> MemoryRouter {
+        MemoryRouter::with_memory_dirs(dir.path().join("global"), dir.path().join("local"))
+    }
+
+    #[tokio::test]
+    async fn test_migrates_category_files() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let local = dir.path().join("local");
+        fs::create_dir_all(&local)?;
+        fs::write(
+            local.join("development.txt"),
+            "# formatting tools\nWe use black for code formatting\n\nThe tests run with pytest\n\n",
+        )?;
+
+        let router = router(&dir);
+        let memories = router.retrieve("development", false)?;
+        assert_eq!(
+            memories["formatting tools"],
+            vec!["We use black for code formatting"]
+        );
+        assert_eq!(memories["untagged"], vec!["The tests run with pytest"]);
+        assert!(router
+            .instructions()
+            .contains("- The tests run with pytest"));
+
+        // Reading leaves the files alone, the first change migrates them under the lock
+        assert!(local.join("development.txt").exists());
+        router
+            .remember("", "development", "CI runs on every push", &[], false)
+            .await?;
+        assert!(!local.join("development.txt").exists());
+        assert!(local.join("migrated").join("development.txt").exists());
+        assert_eq!(router.retrieve("development", false)?.len(), 2);
+        assert_eq!(router.index(false).load()?.len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_memories() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let router = router(&dir);
+        router
+            .remember(
+                "context",
+                "development",
+                "We use black for code formatting",
+                &["formatting"],
+                false,
+            )
+            .await?;
+        router
+            .remember("context", "personal", "My name is Alex", &[], true)
+            .await?;
+        router
+            .remember(
+                "context",
+                "github",
+                "Use gh pr view --comments to read review comments",
+                &["gh"],
+                true,
+            )
+            .await?;
+
+        let matches = router
+            .search_memories("which tool formats the code", 2, &[], None, None)
+            .await?;
+        assert_eq!(matches[0].record.data, "We use black for code formatting");
+        assert!(!matches[0].is_global);
+
+        let matches = router
+            .search_memories("comments", 5, &["gh"], None, Some(true))
+            .await?;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].record.category, "github");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_routers_share_the_index() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        // Like the memory extension and the memory extraction, each with its own write_lock
+        let tasks: Vec<_> = (0..2)
+            .map(|r| {
+                let router = router(&dir);
+                tokio::spawn(async move {
+                    for i in 0..10 {
+                        router
+                            .remember("context", "facts", &format!("r{}fact{}", r, i), &[], false)
+                            .await?;
+                    }
+                    io::Result::Ok(())
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.map_err(io::Error::other)??;
+        }
+        assert_eq!(router(&dir).index(false).load()?.len(), 20);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clear_all_keeps_the_lock_file() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let router = router(&dir);
+        router
+            .remember("context", "facts", "The sky is blue", &[], false)
+            .await?;
+
+        router.clear_all_global_or_local_memories(false).await?;
+        assert!(!dir.path().join("local").exists());
+        assert!(dir.path().join("local.lock").exists());
+
+        router
+            .remember("context", "facts", "The grass is green", &[], false)
+            .await?;
+        assert_eq!(router.index(false).load()?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deduplicates_and_expires_memories() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let router = router(&dir);
+        let remembered = router
+            .remember_with_expiry(
+                "development",
+                "We use black for formatting",
+                &["tools"],
+                false,
+                None,
+            )
+            .await?;
+        assert_eq!(remembered, Remembered::Added);
+        let remembered = router
+            .remember_with_expiry(
+                "development",
+                "we use Black for formatting.",
+                &["python"],
+                false,
+                None,
+            )
+            .await?;
+        assert_eq!(remembered, Remembered::Updated);
+
+        let records = router.index(false).load()?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].data, "we use Black for formatting.");
+        assert_eq!(records[0].tags, vec!["tools", "python"]);
+
+        // The same words in another category are another memory
+        let remembered = router
+            .remember_with_expiry("style", "We use black for formatting", &[], false, None)
+            .await?;
+        assert_eq!(remembered, Remembered::Added);
+
+        router
+            .remember_with_expiry(
+                "status",
+                "The release is blocked on review",
+                &[],
+                false,
+                Some(Utc::now() - Duration::seconds(1)),
+            )
+            .await?;
+        assert!(router.retrieve("status", false)?.is_empty());
+        assert_eq!(router.index(false).load()?.len(), 2);
+        Ok(())
+    }
+}
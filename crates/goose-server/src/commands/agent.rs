@@ -21,7 +21,20 @@ pub async fn run() -> Result<()> {
     let new_agent = Agent::new();
 
     // Create app state with agent
-    let state = state::AppState::new(Arc::new(new_agent), secret_key.clone()).await;
+    let state = state::AppState::new(Arc::new(new_agent), secret_key.clone()).await?;
+
+    // Run scheduled recipes while the server is up
+    match &state.scheduler_unavailable {
+        None => {
+            tokio::spawn(state.scheduler.clone().run());
+        }
+        Some(reason) => tracing::warn!("{}", reason),
+    }
+
+    // Remember what idle sessions learned, when asked to
+    if goose::session::memory_extraction_enabled() {
+        tokio::spawn(crate::memory_extraction::run(state.clone()));
+    }
 
     // Create router with CORS support
     let cors = CorsLayer::new()
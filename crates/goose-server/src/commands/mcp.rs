@@ -1,12 +1,55 @@
 use anyhow::Result;
+use async_trait::async_trait;
+use goose::providers::base::EmbeddingProvider;
+use goose::providers::embeddings::create_embedding_provider;
 use goose_mcp::{
-    ComputerControllerRouter, DeveloperRouter, GoogleDriveRouter, JetBrainsRouter, MemoryRouter,
-    TutorialRouter,
+    ComputerControllerRouter, DeveloperRouter, GoogleDriveRouter, JetBrainsRouter, LspRouter,
+    MemoryEmbedder, MemoryRouter, TutorialRouter,
 };
 use mcp_server::router::RouterService;
 use mcp_server::{BoundedService, ByteTransport, Server};
+use std::path::Path;
+use std::sync::Arc;
 use tokio::io::{stdin, stdout};
 
+/// Embeds memories with the configured embedding provider
+struct ProviderMemoryEmbedder(Arc<dyn EmbeddingProvider>);
+
+#[async_trait]
+impl MemoryEmbedder for ProviderMemoryEmbedder {
+    fn model_id(&self) -> String {
+        self.0.model_id()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(self.0.embed(texts).await?)
+    }
+}
+
+/// The memory extension, searching with GOOSE_EMBEDDING_PROVIDER when it is configured
+pub(crate) fn memory_router() -> MemoryRouter {
+    with_configured_embedder(MemoryRouter::new())
+}
+
+/// The memory extension of a session, with local memories in its working directory
+pub(crate) fn session_memory_router(working_dir: &Path) -> MemoryRouter {
+    with_configured_embedder(MemoryRouter::for_working_dir(working_dir))
+}
+
+fn with_configured_embedder(router: MemoryRouter) -> MemoryRouter {
+    match create_embedding_provider() {
+        Ok(Some(provider)) => router.with_embedder(Arc::new(ProviderMemoryEmbedder(provider))),
+        Ok(None) => router,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to create the embedding provider for memories: {}",
+                e
+            );
+            router
+        }
+    }
+}
+
 pub async fn run(name: &str) -> Result<()> {
     // Initialize logging
     crate::logging::setup_logging(Some(&format!("mcp-{name}")))?;
@@ -20,7 +63,8 @@ pub async fn run(name: &str) -> Result<()> {
             let router = GoogleDriveRouter::new().await;
             Some(Box::new(RouterService(router)))
         }
-        "memory" => Some(Box::new(RouterService(MemoryRouter::new()))),
+        "memory" => Some(Box::new(RouterService(memory_router()))),
+        "lsp" => Some(Box::new(RouterService(LspRouter::new()))),
         "tutorial" => Some(Box::new(RouterService(TutorialRouter::new()))),
         _ => None,
     };
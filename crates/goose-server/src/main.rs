@@ -11,6 +11,7 @@ mod commands;
 mod configuration;
 mod error;
 mod logging;
+mod memory_extraction;
 mod openapi;
 mod routes;
 mod state;
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use goose::config::Config;
+use goose::session;
+
+use crate::commands::mcp::session_memory_router;
+use crate::state::AppState;
+
+/// Sessions without a reply for this long are idle, unless
+/// GOOSE_MEMORY_EXTRACTION_IDLE_SECONDS says otherwise
+const DEFAULT_IDLE_SECONDS: u64 = 600;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Remembers the durable facts of sessions once they go idle
+pub async fn run(state: Arc<AppState>) {
+    let idle_for = Duration::from_secs(
+        Config::global()
+            .get_param("GOOSE_MEMORY_EXTRACTION_IDLE_SECONDS")
+            .unwrap_or(DEFAULT_IDLE_SECONDS),
+    );
+    let mut interval =
+        tokio::time::interval(CHECK_INTERVAL.min(idle_for).max(Duration::from_secs(1)));
+    loop {
+        interval.tick().await;
+        for session_id in state.session_activity.take_idle(idle_for) {
+            if let Err(e) = extract(&state, &session_id).await {
+                tracing::warn!(
+                    "Failed to extract memories from session {}: {}",
+                    session_id,
+                    e
+                );
+            }
+        }
+    }
+}
+
+async fn extract(state: &AppState, session_id: &str) -> Result<()> {
+    let provider = state.get_agent().await?.provider().await?;
+    let session_path = session::get_path(session::Identifier::Name(session_id.to_string()));
+    let messages = session::read_messages(&session_path)?;
+    let working_dir = session::read_metadata(&session_path)?.working_dir;
+
+    // Only the messages since the last extraction, the earlier ones were already looked at
+    let activity = &state.session_activity;
+    let new_messages = messages
+        .get(activity.extracted(session_id)..)
+        .unwrap_or(&messages);
+    let memories = session::extract_memories(new_messages, provider).await?;
+    let router = session_memory_router(&working_dir);
+    for memory in &memories {
+        let tags: Vec<&str> = memory.tags.iter().map(String::as_str).collect();
+        router
+            .remember(
+                session_id,
+                &memory.category,
+                &memory.data,
+                &tags,
+                memory.is_global,
+            )
+            .await?;
+    }
+    activity.set_extracted(session_id, messages.len());
+    tracing::info!(
+        "Remembered {} facts from session {}",
+        memories.len(),
+        session_id
+    );
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use goose::agents::extension::Envs;
 use goose::agents::extension::ToolInfo;
 use goose::agents::ExtensionConfig;
+use goose::agents::{Plan, PlanStep, StepStatus};
 use goose::config::permission::PermissionLevel;
 use goose::config::ExtensionEntry;
 use goose::message::{
@@ -8,6 +9,7 @@ use goose::message::{
     SummarizationRequested, ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
 };
 use goose::permission::permission_confirmation::PrincipalType;
+use goose::providers::base::Usage;
 use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
 use goose::session::info::SessionInfo;
 use goose::session::SessionMetadata;
@@ -85,6 +87,10 @@ use utoipa::OpenApi;
         ModelInfo,
         SessionInfo,
         SessionMetadata,
+        Plan,
+        PlanStep,
+        StepStatus,
+        Usage,
     ))
 )]
 pub struct ApiDoc;
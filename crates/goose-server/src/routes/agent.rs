@@ -9,7 +9,10 @@ use axum::{
 use goose::config::Config;
 use goose::config::PermissionManager;
 use goose::model::ModelConfig;
-use goose::providers::create;
+use goose::providers::base::ModelInfo;
+use goose::providers::catalog::{model_info, ModelCatalog};
+use goose::providers::create_with_lead;
+use goose::recipe::Settings;
 use goose::{
     agents::{extension::ToolInfo, extension_manager::get_parameter_names},
     config::permission::PermissionLevel,
@@ -47,6 +50,8 @@ struct ProviderDetails {
     name: String,
     description: String,
     models: Vec<String>,
+    /// The capabilities of the models, from the model catalog
+    model_info: Vec<ModelInfo>,
     required_keys: Vec<String>,
 }
 
@@ -60,6 +65,8 @@ struct ProviderList {
 struct UpdateProviderRequest {
     provider: String,
     model: Option<String>,
+    /// Model settings of the recipe the session runs, overriding the configured ones
+    settings: Option<Settings>,
 }
 
 #[derive(Deserialize)]
@@ -105,6 +112,7 @@ async fn list_providers() -> Json<Vec<ProviderList>> {
             details: ProviderDetails {
                 name: provider.name,
                 description: provider.description,
+                model_info: provider.models.iter().map(|m| model_info(m)).collect(),
                 models: provider.models,
                 required_keys: provider.required_keys,
             },
@@ -207,8 +215,15 @@ async fn update_agent_provider(
             .get_param("GOOSE_MODEL")
             .expect("Did not find a model on payload or in env to update provider with")
     });
-    let model_config = ModelConfig::new(model);
-    let new_provider = create(&payload.provider, model_config).unwrap();
+    let mut model_config =
+        ModelConfig::new_with_catalog(model, ModelCatalog::global()).with_configured_overrides();
+    if let Some(settings) = &payload.settings {
+        model_config = settings.apply(model_config);
+    }
+    let new_provider = create_with_lead(&payload.provider, model_config).map_err(|e| {
+        tracing::error!("Failed to create provider {}: {}", payload.provider, e);
+        StatusCode::BAD_REQUEST
+    })?;
     agent
         .update_provider(new_provider)
         .await
@@ -502,7 +502,8 @@ mod tests {
             Arc::new(goose::agents::Agent::default()),
             "test".to_string(),
         )
-        .await;
+        .await
+        .unwrap();
         let mut headers = HeaderMap::new();
         headers.insert("X-Secret-Key", "test".parse().unwrap());
 
@@ -6,6 +6,7 @@ pub mod extension;
 pub mod health;
 pub mod recipe;
 pub mod reply;
+pub mod schedule;
 pub mod session;
 pub mod utils;
 use std::sync::Arc;
@@ -23,4 +24,5 @@ pub fn configure(state: Arc<crate::state::AppState>) -> Router {
         .merge(config_management::routes(state.clone()))
         .merge(recipe::routes(state.clone()))
         .merge(session::routes(state.clone()))
+        .merge(schedule::routes(state.clone()))
 }
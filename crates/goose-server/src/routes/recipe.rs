@@ -1,10 +1,18 @@
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
 use goose::message::Message;
 use goose::recipe::Recipe;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+use super::utils::verify_secret_key;
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -84,8 +92,119 @@ async fn create_recipe(
     }
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecipeParametersRequest {
+    recipe: Recipe,
+    // Values to check, by parameter key. Leave out to only get the schema
+    #[serde(default)]
+    values: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecipeParametersResponse {
+    // JSON schema of an object with a value for each parameter
+    schema: Value,
+    // Problems with the parameter definitions or the given values, by parameter key
+    errors: BTreeMap<String, String>,
+}
+
+/// Describe the parameters of a recipe and check values for them, with the same rules the CLI
+/// applies before starting a session
+async fn recipe_parameters(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<RecipeParametersRequest>,
+) -> Result<Json<RecipeParametersResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let mut errors: BTreeMap<String, String> = request
+        .recipe
+        .parameters
+        .iter()
+        .flatten()
+        .filter_map(|param| {
+            param
+                .validate_definition()
+                .err()
+                .map(|e| (param.key.clone(), e.to_string()))
+        })
+        .collect();
+
+    // Values are only checked against valid definitions, the converted values aren't returned as
+    // they include the contents of file parameters
+    if let (Some(values), true) = (&request.values, errors.is_empty()) {
+        if let Err(value_errors) = request.recipe.check_parameters(values) {
+            errors.extend(value_errors);
+        }
+    }
+
+    Ok(Json(RecipeParametersResponse {
+        schema: request.recipe.parameters_schema(),
+        errors,
+    }))
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/recipe/create", post(create_recipe))
+        .route("/recipe/parameters", post(recipe_parameters))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use goose::agents::Agent;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_recipe_parameters() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string())
+            .await
+            .unwrap();
+        let recipe: Recipe = serde_json::from_value(json!({
+            "title": "Release",
+            "description": "Prepares a release",
+            "instructions": "Release {{ version }} {{ channel }}",
+            "parameters": [
+                {"key": "version", "input_type": "string", "requirement": "required",
+                 "description": "Version", "pattern": "[0-9]+\\.[0-9]+\\.[0-9]+"},
+                {"key": "channel", "input_type": "string", "requirement": "optional",
+                 "description": "Channel", "options": ["stable", "beta"], "default": "stable"}
+            ]
+        }))
+        .unwrap();
+
+        let request = Request::builder()
+            .uri("/recipe/parameters")
+            .method("POST")
+            .header("content-type", "application/json")
+            .header("x-secret-key", "test-secret")
+            .body(Body::from(
+                serde_json::to_string(&RecipeParametersRequest {
+                    recipe,
+                    values: Some(HashMap::from([("version".to_string(), "1.2".to_string())])),
+                })
+                .unwrap(),
+            ))
+            .unwrap();
+        let response = routes(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["schema"]["required"], json!(["version"]));
+        assert_eq!(
+            body["schema"]["properties"]["channel"]["enum"],
+            json!(["stable", "beta"])
+        );
+        assert_eq!(
+            body["errors"],
+            json!({"version": "'1.2' does not match the pattern [0-9]+\\.[0-9]+\\.[0-9]+"})
+        );
+    }
+}
@@ -255,6 +255,8 @@ async fn handler(
             }
         }
 
+        state.session_activity.touch(&session_id);
+
         // Send finish event
         let _ = stream_event(
             MessageEvent::Finish {
@@ -367,6 +369,8 @@ async fn ask_handler(
         }
     });
 
+    state.session_activity.touch(&session_id);
+
     Ok(Json(AskResponse {
         response: response_text.trim().to_string(),
     }))
@@ -533,7 +537,9 @@ mod tests {
             });
             let agent = Agent::new();
             let _ = agent.update_provider(mock_provider).await;
-            let state = AppState::new(Arc::new(agent), "test-secret".to_string()).await;
+            let state = AppState::new(Arc::new(agent), "test-secret".to_string())
+                .await
+                .unwrap();
 
             // Build router
             let app = routes(state);
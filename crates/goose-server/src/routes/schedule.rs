@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Local};
+use goose::scheduler::{ScheduleError, ScheduleRun, ScheduledJob};
+use serde::{Deserialize, Serialize};
+
+use super::utils::verify_secret_key;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateScheduleRequest {
+    id: String,
+    // Recipe name or absolute path
+    recipe: String,
+    cron: String,
+    #[serde(default)]
+    params: BTreeMap<String, String>,
+    // Directory the recipe runs in, the home directory when missing
+    #[serde(default)]
+    working_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    job: ScheduledJob,
+    next_run: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleListResponse {
+    jobs: Vec<ScheduleInfo>,
+    // Why the jobs don't run, when they can't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateScheduleResponse {
+    #[serde(flatten)]
+    job: ScheduledJob,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleRunsResponse {
+    runs: Vec<ScheduleRun>,
+}
+
+async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ScheduleListResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let now = Local::now();
+    let jobs = state
+        .scheduler
+        .store()
+        .jobs()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|job| ScheduleInfo {
+            next_run: job.next_run(now),
+            job,
+        })
+        .collect();
+    Ok(Json(ScheduleListResponse {
+        jobs,
+        warning: state.scheduler_unavailable.clone(),
+    }))
+}
+
+/// Schedule a recipe, responds with the problem when the id or cron expression is invalid
+async fn create_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateScheduleRequest>,
+) -> Result<Json<CreateScheduleResponse>, (StatusCode, String)> {
+    verify_secret_key(&headers, &state).map_err(|status| (status, String::new()))?;
+
+    let working_dir = request.working_dir.or_else(dirs::home_dir).ok_or((
+        StatusCode::BAD_REQUEST,
+        "working_dir is required".to_string(),
+    ))?;
+    let job = ScheduledJob::new(
+        request.id,
+        request.recipe,
+        request.cron,
+        request.params,
+        working_dir,
+    )
+    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    // The store is changed under a file lock, which blocks
+    let store = state.scheduler.store().clone();
+    let added = job.clone();
+    tokio::task::spawn_blocking(move || store.add_job(added))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| match e.downcast_ref::<ScheduleError>() {
+            Some(ScheduleError::DuplicateJob(_)) => (StatusCode::CONFLICT, e.to_string()),
+            _ => {
+                tracing::error!("Failed to add scheduled job: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+        })?;
+    Ok(Json(CreateScheduleResponse {
+        job,
+        warning: state.scheduler_unavailable.clone(),
+    }))
+}
+
+async fn delete_schedule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let store = state.scheduler.store().clone();
+    tokio::task::spawn_blocking(move || store.remove_job(&id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| match e.downcast_ref::<ScheduleError>() {
+            Some(ScheduleError::JobNotFound(_)) => StatusCode::NOT_FOUND,
+            _ => {
+                tracing::error!("Failed to remove scheduled job: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+    Ok(StatusCode::OK)
+}
+
+/// Start a run of a job in the background, its progress shows in the run history
+async fn run_schedule_now(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    verify_secret_key(&headers, &state).map_err(|status| (status, String::new()))?;
+
+    let job = state
+        .scheduler
+        .store()
+        .job(&id)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    if let Some(reason) = &state.scheduler_unavailable {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, reason.clone()));
+    }
+    let scheduler = state.scheduler.clone();
+    tokio::spawn(async move {
+        if let Err(e) = scheduler.run_job(job).await {
+            tracing::error!("Failed to run scheduled job: {}", e);
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn list_schedule_runs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ScheduleRunsResponse>, StatusCode> {
+    verify_secret_key(&headers, &state)?;
+
+    let runs = state
+        .scheduler
+        .store()
+        .runs(Some(&id))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ScheduleRunsResponse { runs }))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/schedule/list", get(list_schedules))
+        .route("/schedule/create", post(create_schedule))
+        .route("/schedule/delete/:id", delete(delete_schedule))
+        .route("/schedule/:id/run_now", post(run_schedule_now))
+        .route("/schedule/:id/runs", get(list_schedule_runs))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use goose::agents::Agent;
+    use goose::scheduler::{CommandExecutor, ScheduleStore, Scheduler};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    fn request(method: &str, uri: &str, body: Option<Value>) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("content-type", "application/json")
+            .header("x-secret-key", "test-secret")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_schedule_routes() {
+        let dir = tempfile::tempdir().unwrap();
+        let scheduler = Scheduler::new(
+            ScheduleStore::new(dir.path().join("schedules.json")),
+            Arc::new(CommandExecutor::new("goose")),
+        );
+        let state = AppState::with_scheduler(
+            Arc::new(Agent::new()),
+            "test-secret".to_string(),
+            Arc::new(scheduler),
+        )
+        .await;
+        let app = routes(state);
+
+        let create = json!({
+            "id": "standup",
+            "recipe": "/recipes/standup.yaml",
+            "cron": "0 9 * * 1-5",
+            "params": {"team": "core"},
+            "working_dir": "/projects/app"
+        });
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/schedule/create", Some(create.clone())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/schedule/create", Some(create)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let invalid = json!({"id": "nightly", "recipe": "r.yaml", "cron": "0 25 * * *"});
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/schedule/create", Some(invalid)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/schedule/list", None))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["jobs"].as_array().unwrap().len(), 1);
+        assert_eq!(body["jobs"][0]["id"], "standup");
+        assert_eq!(body["jobs"][0]["params"], json!({"team": "core"}));
+        assert!(body["jobs"][0]["next_run"].is_string());
+        assert!(body.get("warning").is_none());
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", "/schedule/delete/standup", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .oneshot(request("POST", "/schedule/standup/run_now", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
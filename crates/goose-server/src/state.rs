@@ -1,5 +1,10 @@
 use goose::agents::Agent;
-use std::sync::Arc;
+use goose::config::Config;
+use goose::scheduler::{CommandExecutor, ScheduleStore, Scheduler};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Shared reference to an Agent that can be cloned cheaply
 /// without cloning the underlying Agent object
@@ -16,13 +21,69 @@ pub struct AppState {
     // agent: SharedAgentStore,
     agent: Option<AgentRef>,
     pub secret_key: String,
+    pub scheduler: Arc<Scheduler>,
+    /// Why scheduled recipes don't run, when the CLI they run in was not found
+    pub scheduler_unavailable: Option<String>,
+    pub session_activity: Arc<SessionActivity>,
+}
+
+/// The CLI that scheduled recipes run in: GOOSE_CLI_PATH, the `goose` bundled next to this
+/// executable, or `goose` on the PATH
+fn find_cli() -> Option<PathBuf> {
+    if let Ok(path) = Config::global().get_param::<String>("GOOSE_CLI_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    let name = format!("goose{}", std::env::consts::EXE_SUFFIX);
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(&name)));
+    let on_path = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(&name));
+    bundled
+        .into_iter()
+        .chain(on_path)
+        .find(|path| path.is_file())
 }
 
 impl AppState {
-    pub async fn new(agent: AgentRef, secret_key: String) -> Arc<AppState> {
+    pub async fn new(agent: AgentRef, secret_key: String) -> anyhow::Result<Arc<AppState>> {
+        // Scheduled recipes run in the CLI, without it they can only be managed
+        let cli_path = find_cli();
+        let scheduler = Scheduler::new(
+            ScheduleStore::from_config_dir()?,
+            Arc::new(CommandExecutor::new(
+                cli_path.clone().unwrap_or_else(|| PathBuf::from("goose")),
+            )),
+        );
+        let scheduler_unavailable = cli_path.is_none().then(|| {
+            "Scheduled recipes don't run because the goose CLI was not found next to goosed \
+             or on the PATH, set GOOSE_CLI_PATH to its path"
+                .to_string()
+        });
+        Ok(Arc::new(Self {
+            agent: Some(agent.clone()),
+            secret_key,
+            scheduler: Arc::new(scheduler),
+            scheduler_unavailable,
+            session_activity: Arc::new(SessionActivity::default()),
+        }))
+    }
+
+    #[cfg(test)]
+    pub async fn with_scheduler(
+        agent: AgentRef,
+        secret_key: String,
+        scheduler: Arc<Scheduler>,
+    ) -> Arc<AppState> {
         Arc::new(Self {
             agent: Some(agent.clone()),
             secret_key,
+            scheduler,
+            scheduler_unavailable: None,
+            session_activity: Arc::new(SessionActivity::default()),
         })
     }
 
@@ -32,3 +93,51 @@ impl AppState {
             .ok_or_else(|| anyhow::anyhow!("Agent needs to be created first."))
     }
 }
+
+/// When each session last replied, to find the sessions that went idle, and how far their
+/// memories were extracted
+#[derive(Default)]
+pub struct SessionActivity {
+    last_reply: Mutex<HashMap<String, Instant>>,
+    extracted: Mutex<HashMap<String, usize>>,
+}
+
+impl SessionActivity {
+    pub fn touch(&self, session_id: &str) {
+        self.last_reply
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), Instant::now());
+    }
+
+    /// The sessions that have not replied for `idle_for`, forgotten until they reply again
+    pub fn take_idle(&self, idle_for: Duration) -> Vec<String> {
+        let mut last_reply = self.last_reply.lock().unwrap();
+        let idle: Vec<String> = last_reply
+            .iter()
+            .filter(|(_, at)| at.elapsed() >= idle_for)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            last_reply.remove(id);
+        }
+        idle
+    }
+
+    /// How many messages of the session its memories were already extracted from
+    pub fn extracted(&self, session_id: &str) -> usize {
+        self.extracted
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn set_extracted(&self, session_id: &str, message_count: usize) {
+        self.extracted
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), message_count);
+    }
+}
@@ -11,22 +11,29 @@ use crate::permission::permission_judge::check_tool_permissions;
 use crate::permission::PermissionConfirmation;
 use crate::providers::base::Provider;
 use crate::providers::errors::ProviderError;
-use crate::recipe::{Author, Recipe};
+use crate::recipe::{Author, Recipe, SubRecipe};
+use crate::session;
 use crate::tool_monitor::{ToolCall, ToolMonitor};
 use regex::Regex;
 use serde_json::Value;
 use tokio::sync::{mpsc, Mutex};
-use tracing::{debug, error, instrument};
+use tracing::{debug, error, instrument, warn};
 
 use crate::agents::extension::{ExtensionConfig, ExtensionResult, ToolInfo};
 use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
+use crate::agents::final_output_tool::{FinalOutputTool, FINAL_OUTPUT_TOOL_NAME};
+use crate::agents::plan::Plan;
 use crate::agents::platform_tools::{
-    PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
-    PLATFORM_READ_RESOURCE_TOOL_NAME, PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
+    PLATFORM_DELEGATE_TASK_TOOL_NAME, PLATFORM_LIST_RESOURCES_TOOL_NAME,
+    PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
+    PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME, PLATFORM_SEARCH_TOOLS_TOOL_NAME,
+    PLATFORM_UPDATE_PLAN_TOOL_NAME,
 };
 use crate::agents::prompt_manager::PromptManager;
+use crate::agents::sub_recipe::{SubRecipeManager, SubRecipeRunner};
+use crate::agents::tool_router::ToolRouter;
 use crate::agents::types::SessionConfig;
-use crate::agents::types::{FrontendTool, ToolResultReceiver};
+use crate::agents::types::{FrontendTool, ToolMock, ToolResultReceiver};
 use mcp_core::{
     prompt::Prompt, protocol::GetPromptResult, tool::Tool, Content, ToolError, ToolResult,
 };
@@ -46,6 +53,11 @@ pub struct Agent {
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
     pub(super) tool_monitor: Mutex<Option<ToolMonitor>>,
+    pub(super) sub_recipe_manager: Mutex<SubRecipeManager>,
+    pub(super) final_output_tool: Mutex<Option<FinalOutputTool>>,
+    pub(super) tool_mock: Mutex<Option<Arc<dyn ToolMock>>>,
+    pub(super) subagents_enabled: Mutex<bool>,
+    pub(super) tool_router: Option<ToolRouter>,
 }
 
 impl Agent {
@@ -65,6 +77,11 @@ impl Agent {
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
             tool_monitor: Mutex::new(None),
+            sub_recipe_manager: Mutex::new(SubRecipeManager::new()),
+            final_output_tool: Mutex::new(None),
+            tool_mock: Mutex::new(None),
+            subagents_enabled: Mutex::new(true),
+            tool_router: ToolRouter::from_config(),
         }
     }
 
@@ -129,11 +146,14 @@ impl Agent {
     }
 
     /// Dispatch a single tool call to the appropriate client
-    #[instrument(skip(self, tool_call, request_id), fields(input, output))]
+    ///
+    /// The session is the one of the reply making the call, tools that save to it use it.
+    #[instrument(skip(self, tool_call, request_id, session), fields(input, output))]
     pub(super) async fn dispatch_tool_call(
         &self,
         tool_call: mcp_core::tool::ToolCall,
         request_id: String,
+        session: Option<SessionConfig>,
     ) -> (String, Result<Vec<Content>, ToolError>) {
         // Check if this tool call should be allowed based on repetition monitoring
         if let Some(monitor) = self.tool_monitor.lock().await.as_mut() {
@@ -167,6 +187,59 @@ impl Agent {
                 .await;
         }
 
+        if tool_call.name == PLATFORM_UPDATE_PLAN_TOOL_NAME {
+            return (
+                request_id,
+                self.update_plan(tool_call.arguments, session.as_ref())
+                    .await,
+            );
+        }
+
+        if tool_call.name == FINAL_OUTPUT_TOOL_NAME {
+            let result = match self.final_output_tool.lock().await.as_mut() {
+                Some(final_output_tool) => final_output_tool.submit(tool_call.arguments),
+                None => Err(ToolError::NotFound(tool_call.name)),
+            };
+            return (request_id, result);
+        }
+
+        let tool_mock = self.tool_mock.lock().await.clone();
+        if let Some(result) = tool_mock.and_then(|tool_mock| tool_mock.mock(&tool_call)) {
+            return (request_id, result);
+        }
+
+        let sub_recipe_call = {
+            let sub_recipe_manager = self.sub_recipe_manager.lock().await;
+            sub_recipe_manager
+                .is_sub_recipe_tool(&tool_call.name)
+                .then(|| sub_recipe_manager.prepare_call(&tool_call.name, &tool_call.arguments))
+        };
+        if let Some(prepared) = sub_recipe_call {
+            // Sub-recipes run a whole child agent, so don't hold any locks while they run
+            let result = match prepared {
+                Ok(call) => call.run().await,
+                Err(e) => Err(e),
+            };
+            return (request_id, result);
+        }
+
+        if tool_call.name == PLATFORM_SEARCH_TOOLS_TOOL_NAME {
+            return (
+                request_id,
+                self.search_tools(tool_call.arguments, session.as_ref())
+                    .await,
+            );
+        }
+
+        if tool_call.name == PLATFORM_DELEGATE_TASK_TOOL_NAME {
+            // Like sub-recipes, subagents run without holding any locks
+            return (
+                request_id,
+                self.delegate_task(tool_call.arguments, session.as_ref())
+                    .await,
+            );
+        }
+
         let extension_manager = self.extension_manager.lock().await;
         let result = if tool_call.name == PLATFORM_READ_RESOURCE_TOOL_NAME {
             // Check if the tool is read_resource and handle it separately
@@ -310,11 +383,73 @@ impl Agent {
                 prefixed_tools.push(platform_tools::read_resource_tool());
                 prefixed_tools.push(platform_tools::list_resources_tool());
             }
+
+            if *self.subagents_enabled.lock().await {
+                prefixed_tools.push(platform_tools::delegate_task_tool());
+            }
+
+            if self.prompt_manager.lock().await.plan().is_some() {
+                prefixed_tools.push(platform_tools::update_plan_tool());
+            }
+
+            if self.tool_router.is_some() {
+                prefixed_tools.push(platform_tools::search_tools_tool());
+            }
+        }
+
+        if extension_name.is_none() {
+            prefixed_tools.extend(self.sub_recipe_manager.lock().await.tools());
+            if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
+                prefixed_tools.push(final_output_tool.tool());
+            }
         }
 
         prefixed_tools
     }
 
+    /// Require the agent to end with a final output matching `json_schema`
+    ///
+    /// Fails when `json_schema` is not a valid JSON schema.
+    pub async fn add_final_output_tool(&self, json_schema: Value) -> Result<()> {
+        let final_output_tool = FinalOutputTool::new(json_schema)?;
+        self.extend_system_prompt(final_output_tool.system_prompt())
+            .await;
+        *self.final_output_tool.lock().await = Some(final_output_tool);
+        Ok(())
+    }
+
+    /// Whether the agent must end with a final output
+    pub async fn requires_final_output(&self) -> bool {
+        self.final_output_tool.lock().await.is_some()
+    }
+
+    /// The validated final output, if a final output is required and was submitted
+    pub async fn final_output(&self) -> Option<Value> {
+        self.final_output_tool
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|final_output_tool| final_output_tool.final_output().cloned())
+    }
+
+    /// Answer tool calls from `tool_mock` where it has a result, instead of running the tools
+    pub async fn set_tool_mock(&self, tool_mock: Arc<dyn ToolMock>) {
+        *self.tool_mock.lock().await = Some(tool_mock);
+    }
+
+    /// Make recipes available as tools, each paired with the loaded recipe it points to
+    pub async fn add_sub_recipes(
+        &self,
+        sub_recipes: Vec<(SubRecipe, Recipe)>,
+        runner: Arc<dyn SubRecipeRunner>,
+    ) {
+        let mut sub_recipe_manager = self.sub_recipe_manager.lock().await;
+        sub_recipe_manager.set_runner(runner);
+        for (sub_recipe, recipe) in sub_recipes {
+            sub_recipe_manager.add(sub_recipe, &recipe);
+        }
+    }
+
     pub async fn remove_extension(&self, name: &str) {
         let mut extension_manager = self.extension_manager.lock().await;
         extension_manager
@@ -351,6 +486,11 @@ impl Agent {
         let mut messages = messages.to_vec();
         let reply_span = tracing::Span::current();
 
+        // Each reply has to submit its own final output
+        if let Some(final_output_tool) = self.final_output_tool.lock().await.as_mut() {
+            final_output_tool.reset();
+        }
+
         // Load settings from config
         let config = Config::global();
 
@@ -373,12 +513,16 @@ impl Agent {
 
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
+            // The tools the router ranked for this reply, kept so later turns hit the prompt cache
+            let mut ranked_tools = None;
             loop {
+                let turn_tools = self.route_tools(&tools, &messages, session.as_ref(), &mut ranked_tools).await?;
+                self.record_tool_router_usage(session.clone()).await?;
                 match Self::generate_response_from_provider(
                     self.provider().await?,
                     &system_prompt,
                     &messages,
-                    &tools,
+                    &turn_tools,
                     &toolshim_tools,
                 ).await {
                     Ok((response, usage)) => {
@@ -401,6 +545,15 @@ impl Agent {
 
                         let num_tool_requests = frontend_requests.len() + remaining_requests.len();
                         if num_tool_requests == 0 {
+                            // The model is done, unless it still owes a final output
+                            let reminder = self.final_output_tool.lock().await.as_mut().and_then(|tool| tool.reminder());
+                            if let Some(reminder) = reminder {
+                                let reminder = Message::user().with_text(reminder);
+                                yield reminder.clone();
+                                messages.push(response);
+                                messages.push(reminder);
+                                continue;
+                            }
                             break;
                         }
 
@@ -450,7 +603,7 @@ impl Agent {
                             // Skip the confirmation for approved tools
                             for request in &permission_check_result.approved {
                                 if let Ok(tool_call) = request.tool_call.clone() {
-                                    let tool_future = self.dispatch_tool_call(tool_call, request.id.clone());
+                                    let tool_future = self.dispatch_tool_call(tool_call, request.id.clone(), session.clone());
                                     tool_futures.push(Box::pin(tool_future));
                                 }
                             }
@@ -472,6 +625,7 @@ impl Agent {
                                 tool_futures_arc.clone(),
                                 &mut permission_manager,
                                 message_tool_response.clone(),
+                                session.clone(),
                             );
 
                             // We have a stream of tool_approval_requests to handle
@@ -504,6 +658,7 @@ impl Agent {
                             // Update system prompt and tools if installations were successful
                             if all_install_successful {
                                 (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+                                ranked_tools = None;
                             }
                         }
 
@@ -512,6 +667,16 @@ impl Agent {
 
                         messages.push(response);
                         messages.push(final_message_tool_resp);
+
+                        if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
+                            if let Some(final_output) = final_output_tool.final_output() {
+                                yield Message::assistant().with_text(serde_json::to_string_pretty(final_output)?);
+                                break;
+                            }
+                            if final_output_tool.is_exhausted() {
+                                break;
+                            }
+                        }
                     },
                     Err(ProviderError::ContextLengthExceeded(_)) => {
                         // At this point, the last message should be a user message
@@ -542,6 +707,88 @@ impl Agent {
         prompt_manager.add_system_prompt_extra(instruction);
     }
 
+    /// Set the plan the agent works through, or clear it
+    pub async fn set_plan(&self, plan: Option<Plan>) {
+        self.prompt_manager.lock().await.set_plan(plan);
+    }
+
+    pub async fn plan(&self) -> Option<Plan> {
+        self.prompt_manager.lock().await.plan().cloned()
+    }
+
+    /// Updates a step of the plan and saves the plan in the session metadata
+    async fn update_plan(
+        &self,
+        arguments: Value,
+        session: Option<&SessionConfig>,
+    ) -> ToolResult<Vec<Content>> {
+        let (result, plan) = {
+            let mut prompt_manager = self.prompt_manager.lock().await;
+            let plan = prompt_manager
+                .plan_mut()
+                .ok_or_else(|| ToolError::ExecutionError("There is no plan".to_string()))?;
+            (plan.update(arguments), plan.clone())
+        };
+
+        if let Some(session_config) = session {
+            let session_file = session::get_path(session_config.id.clone());
+            let saved = match session::read_metadata(&session_file) {
+                Ok(mut metadata) => {
+                    metadata.plan = Some(plan);
+                    session::storage::update_metadata(&session_file, &metadata).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = saved {
+                warn!("Failed to save the plan in the session: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Finds tools for the model when the tool router only sends some of them
+    async fn search_tools(
+        &self,
+        arguments: Value,
+        session: Option<&SessionConfig>,
+    ) -> ToolResult<Vec<Content>> {
+        let router = self.tool_router.as_ref().ok_or_else(|| {
+            ToolError::ExecutionError("All tools are already available".to_string())
+        })?;
+        let query = arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("The query is required".to_string()))?;
+        let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+        let provider = self
+            .provider()
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let tools = self
+            .get_prefixed_tools()
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        let session_file = session.map(|session| session::get_path(session.id.clone()));
+        let found = router
+            .search(&tools, query, limit, session_file.as_deref(), provider)
+            .await;
+        if found.is_empty() {
+            return Ok(vec![Content::text(format!(
+                "No tools found for '{}'",
+                query
+            ))]);
+        }
+        let descriptions: Vec<String> = found
+            .iter()
+            .map(|tool| format!("- {}: {}", tool.name, tool.description))
+            .collect();
+        Ok(vec![Content::text(format!(
+            "These tools are available from the next turn on:\n{}",
+            descriptions.join("\n")
+        ))])
+    }
+
     /// Update the provider used by this agent
     pub async fn update_provider(&self, provider: Arc<dyn Provider>) -> Result<()> {
         *self.provider.lock().await = Some(provider);
@@ -33,6 +33,7 @@ pub struct ExtensionManager {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    configs: HashMap<String, ExtensionConfig>,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -70,7 +71,7 @@ impl ResourceItem {
 
 /// Sanitizes a string by replacing invalid characters with underscores.
 /// Valid characters match [a-zA-Z0-9_-]
-fn normalize(input: String) -> String {
+pub(crate) fn normalize(input: String) -> String {
     let mut result = String::with_capacity(input.len());
     for c in input.chars() {
         result.push(match c {
@@ -103,6 +104,7 @@ impl ExtensionManager {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            configs: HashMap::new(),
         }
     }
 
@@ -262,10 +264,16 @@ impl ExtensionManager {
 
         self.clients
             .insert(sanitized_name.clone(), Arc::new(Mutex::new(client)));
+        self.configs.insert(sanitized_name, config);
 
         Ok(())
     }
 
+    /// The configs of the added extensions, by extension name
+    pub fn get_extension_configs(&self) -> &HashMap<String, ExtensionConfig> {
+        &self.configs
+    }
+
     /// Get extensions info
     pub async fn get_extensions_info(&self) -> Vec<ExtensionInfo> {
         self.clients
@@ -285,6 +293,7 @@ impl ExtensionManager {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.configs.remove(&sanitized_name);
         Ok(())
     }
 
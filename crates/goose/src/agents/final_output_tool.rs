@@ -0,0 +1,206 @@
+use anyhow::{anyhow, Result};
+use indoc::formatdoc;
+use jsonschema::Validator;
+use mcp_core::tool::{Tool, ToolAnnotations};
+use mcp_core::{Content, ToolError, ToolResult};
+use serde_json::Value;
+
+pub const FINAL_OUTPUT_TOOL_NAME: &str = "recipe__final_output";
+
+/// How many answers not matching the schema the model may submit before the run gives up
+pub const MAX_FINAL_OUTPUT_ATTEMPTS: usize = 3;
+/// How many times the model is reminded to submit an answer before the run gives up
+pub const MAX_FINAL_OUTPUT_REMINDERS: usize = 3;
+
+/// Forces the agent to end with an answer matching a JSON schema
+///
+/// The schema is offered as the input schema of a tool. Calls with arguments that don't match
+/// the schema are rejected with the validation errors, so the model can correct itself.
+pub struct FinalOutputTool {
+    schema: Value,
+    validator: Validator,
+    final_output: Option<Value>,
+    failed_attempts: usize,
+    reminders: usize,
+}
+
+impl FinalOutputTool {
+    /// Fails when `schema` is not a valid JSON schema of an object
+    ///
+    /// The schema becomes the input schema of the tool, and tool arguments are always objects.
+    pub fn new(schema: Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow!("The response json_schema is invalid: {}", e))?;
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return Err(anyhow!(
+                "The response json_schema must describe an object, with \"type\": \"object\", \
+                 wrap other answers in a property of an object"
+            ));
+        }
+        Ok(Self {
+            schema,
+            validator,
+            final_output: None,
+            failed_attempts: 0,
+            reminders: 0,
+        })
+    }
+
+    pub fn tool(&self) -> Tool {
+        Tool::new(
+            FINAL_OUTPUT_TOOL_NAME.to_string(),
+            "Submit the final answer of this task. The arguments are the answer, they must match the input schema exactly. Call this once, after all other work is done.".to_string(),
+            self.schema.clone(),
+            Some(ToolAnnotations {
+                title: Some("Submit final answer".to_string()),
+                read_only_hint: true,
+                destructive_hint: false,
+                idempotent_hint: true,
+                open_world_hint: false,
+            }),
+        )
+    }
+
+    pub fn system_prompt(&self) -> String {
+        formatdoc! {r#"
+            # Final answer
+
+            This task must end with a machine-readable answer. When you are done, call the
+            `{tool}` tool with your answer as its arguments. A text reply is not enough, the
+            task is only complete once `{tool}` has accepted your answer.
+        "#, tool = FINAL_OUTPUT_TOOL_NAME}
+    }
+
+    /// Validates and records the final output
+    pub fn submit(&mut self, arguments: Value) -> ToolResult<Vec<Content>> {
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&arguments)
+            .map(|error| {
+                let path = error.instance_path.as_str();
+                format!("{}: {}", if path.is_empty() { "/" } else { path }, error)
+            })
+            .collect();
+        if !errors.is_empty() {
+            self.failed_attempts += 1;
+            return Err(ToolError::InvalidParameters(format!(
+                "The answer does not match the schema, fix these errors and call {} again:\n- {}",
+                FINAL_OUTPUT_TOOL_NAME,
+                errors.join("\n- ")
+            )));
+        }
+
+        self.final_output = Some(arguments);
+        Ok(vec![Content::text("The final answer was accepted.")])
+    }
+
+    /// Forgets the output and attempts of the previous reply
+    pub fn reset(&mut self) {
+        self.final_output = None;
+        self.failed_attempts = 0;
+        self.reminders = 0;
+    }
+
+    pub fn final_output(&self) -> Option<&Value> {
+        self.final_output.as_ref()
+    }
+
+    /// Whether the model used up its attempts or reminders to produce a valid final output
+    pub fn is_exhausted(&self) -> bool {
+        self.final_output.is_none()
+            && (self.failed_attempts >= MAX_FINAL_OUTPUT_ATTEMPTS
+                || self.reminders >= MAX_FINAL_OUTPUT_REMINDERS)
+    }
+
+    /// A reminder for a model that ended its turn without a final output, if it has reminders left
+    pub fn reminder(&mut self) -> Option<String> {
+        if self.final_output.is_some() || self.is_exhausted() {
+            return None;
+        }
+        self.reminders += 1;
+        Some(format!(
+            "You have not submitted a final answer yet. Call the {} tool with your answer.",
+            FINAL_OUTPUT_TOOL_NAME
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["status", "failures"],
+            "additionalProperties": false,
+            "properties": {
+                "status": {"type": "string", "enum": ["pass", "fail"]},
+                "failures": {
+                    "type": "array",
+                    "items": {"type": "object", "required": ["test"], "properties": {"test": {"type": "string"}}}
+                },
+                "duration": {"type": "number", "minimum": 0}
+            }
+        })
+    }
+
+    #[test]
+    fn test_submit_validates() {
+        assert!(FinalOutputTool::new(json!({"type": "no-such-type"})).is_err());
+        let error = FinalOutputTool::new(json!({"type": "array", "items": {"type": "string"}}))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("must describe an object"));
+
+        let mut tool = FinalOutputTool::new(schema()).unwrap();
+        let error = tool
+            .submit(
+                json!({"status": "maybe", "failures": [{"name": "x"}], "duration": -1, "extra": 1}),
+            )
+            .unwrap_err()
+            .to_string();
+        for path in ["/status", "/failures/0", "/duration", "/: "] {
+            assert!(error.contains(path), "{} is not in {}", path, error);
+        }
+        assert!(tool.final_output().is_none());
+
+        tool.submit(json!({"status": "pass", "failures": []}))
+            .unwrap();
+        assert_eq!(tool.final_output().unwrap()["status"], "pass");
+    }
+
+    #[test]
+    fn test_submit_and_retries() {
+        let mut tool = FinalOutputTool::new(schema()).unwrap();
+
+        // Invalid answers and reminders are counted separately
+        assert!(tool.submit(json!({"status": "pass"})).is_err());
+        assert!(tool.submit(json!({"status": "pass"})).is_err());
+        assert!(tool.reminder().is_some());
+        assert!(tool.reminder().is_some());
+        assert!(!tool.is_exhausted());
+        assert!(tool.reminder().is_some());
+        assert!(tool.is_exhausted());
+        assert!(tool.reminder().is_none());
+
+        let mut tool = FinalOutputTool::new(schema()).unwrap();
+        for _ in 0..MAX_FINAL_OUTPUT_ATTEMPTS {
+            assert!(tool.submit(json!({})).is_err());
+        }
+        assert!(tool.is_exhausted());
+        assert!(tool.reminder().is_none());
+
+        let mut tool = FinalOutputTool::new(schema()).unwrap();
+        tool.submit(json!({"status": "fail", "failures": [{"test": "a"}]}))
+            .unwrap();
+        assert_eq!(tool.final_output().unwrap()["status"], "fail");
+        assert!(tool.reminder().is_none());
+
+        // The next reply starts over
+        tool.reset();
+        assert!(tool.final_output().is_none());
+        assert!(tool.reminder().is_some());
+    }
+}
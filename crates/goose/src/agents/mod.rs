@@ -2,14 +2,22 @@ mod agent;
 mod context;
 pub mod extension;
 pub mod extension_manager;
+pub mod final_output_tool;
+pub mod plan;
 pub mod platform_tools;
 pub mod prompt_manager;
 mod reply_parts;
+pub mod sub_recipe;
+mod subagent;
 mod tool_execution;
+pub mod tool_router;
 mod types;
 
 pub use agent::Agent;
 pub use extension::ExtensionConfig;
 pub use extension_manager::ExtensionManager;
+pub use plan::{Plan, PlanStep, StepStatus};
 pub use prompt_manager::PromptManager;
-pub use types::{FrontendTool, SessionConfig};
+pub use sub_recipe::SubRecipeRunner;
+pub use subagent::{run_child_agent, ChildRun};
+pub use types::{FrontendTool, SessionConfig, ToolMock};
@@ -0,0 +1,219 @@
+use std::fmt;
+
+use indoc::formatdoc;
+use mcp_core::{Content, ToolError, ToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::agents::platform_tools::PLATFORM_UPDATE_PLAN_TOOL_NAME;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Skipped,
+}
+
+impl StepStatus {
+    /// Whether the step needs no more work
+    pub fn is_done(&self) -> bool {
+        matches!(self, StepStatus::Completed | StepStatus::Skipped)
+    }
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            StepStatus::Pending => "pending",
+            StepStatus::InProgress => "in progress",
+            StepStatus::Completed => "completed",
+            StepStatus::Skipped => "skipped",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PlanStep {
+    pub description: String,
+    pub status: StepStatus,
+}
+
+/// A checklist of steps the agent works through
+///
+/// Plans are kept in the session metadata, so they survive resuming the session. The plan is shown
+/// to the model in the system prompt as it was set, and the model updates the status of the steps
+/// with the `platform__update_plan` tool, whose results show the current plan. The system prompt
+/// is not changed by updates, so it stays in the prompt cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    pub fn new(steps: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            steps: steps
+                .into_iter()
+                .map(|description| PlanStep {
+                    description,
+                    status: StepStatus::Pending,
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads the numbered steps out of a plan written by the planner
+    ///
+    /// Only the outermost numbered list counts, nested lists and other text are details of the
+    /// steps. Returns None when the text has no numbered steps.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let numbered: Vec<(usize, String)> = text.lines().filter_map(parse_numbered_line).collect();
+        let outer_indent = numbered.iter().map(|(indent, _)| *indent).min()?;
+        Some(Self::new(
+            numbered
+                .into_iter()
+                .filter(|(indent, _)| *indent == outer_indent)
+                .map(|(_, description)| description),
+        ))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|step| step.status.is_done())
+    }
+
+    /// Sets the status of a step, numbered from 1 like the checklist
+    pub fn update(&mut self, arguments: Value) -> ToolResult<Vec<Content>> {
+        let step = arguments
+            .get("step")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| ToolError::InvalidParameters("step must be a number".to_string()))?;
+        let status: StepStatus = arguments
+            .get("status")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .ok()
+            .flatten()
+            .ok_or_else(|| {
+                ToolError::InvalidParameters(
+                    "status must be one of pending, in_progress, completed, skipped".to_string(),
+                )
+            })?;
+
+        let count = self.steps.len();
+        let plan_step = (step as usize)
+            .checked_sub(1)
+            .and_then(|index| self.steps.get_mut(index))
+            .ok_or_else(|| {
+                ToolError::InvalidParameters(format!(
+                    "There is no step {}, the plan has steps 1 to {}",
+                    step, count
+                ))
+            })?;
+        plan_step.status = status;
+
+        let message = if self.is_complete() {
+            "All steps of the plan are done.".to_string()
+        } else {
+            format!("Step {} is {}. The plan is now:\n{}", step, status, self)
+        };
+        Ok(vec![Content::text(message)])
+    }
+
+    pub fn system_prompt(&self) -> String {
+        formatdoc! {r#"
+            # Plan
+
+            You are working through the plan below. Work on the steps in order. Before you start
+            a step, mark it in_progress with the `{tool}` tool, and mark it completed when it is
+            done, or skipped if it turns out not to be needed.
+
+            This is the plan as it was when the work started, the latest result of `{tool}`
+            shows the current status of the steps.
+
+            {plan}
+        "#, tool = PLATFORM_UPDATE_PLAN_TOOL_NAME, plan = self}
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            let check = match step.status {
+                StepStatus::Pending => "[ ]",
+                StepStatus::InProgress => "[~]",
+                StepStatus::Completed => "[x]",
+                StepStatus::Skipped => "[-]",
+            };
+            writeln!(f, "{} {}. {}", check, index + 1, step.description)?;
+        }
+        Ok(())
+    }
+}
+
+/// The indentation and text of a numbered list item, such as `1. Do this` or `### Step 2: Do that`
+fn parse_numbered_line(line: &str) -> Option<(usize, String)> {
+    let indent = line.len() - line.trim_start().len();
+    let mut rest = line.trim_start().trim_start_matches('#').trim_start();
+    rest = rest.trim_start_matches("**");
+    if let Some(stripped) = rest
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("step "))
+        .map(|_| &rest[5..])
+    {
+        rest = stripped;
+    }
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = rest[digits..].strip_prefix(['.', ')', ':'])?;
+    let description = rest.trim().trim_matches('*').trim();
+    (!description.is_empty()).then(|| (indent, description.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_text() {
+        let text = "Here is the plan:\n\n1. Read `src/lib.rs`\n   - check the exports\n   1. nested step\n2) **Add the parser**\n### Step 3: Run the tests\n\nQuestions? None.";
+        let plan = Plan::from_text(text).unwrap();
+        let steps: Vec<&str> = plan.steps.iter().map(|s| s.description.as_str()).collect();
+        assert_eq!(
+            steps,
+            vec!["Read `src/lib.rs`", "Add the parser", "Run the tests"]
+        );
+        assert!(Plan::from_text("What should the parser accept?").is_none());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut plan = Plan::new(vec!["Read".to_string(), "Write".to_string()]);
+        plan.update(json!({"step": 1, "status": "completed"}))
+            .unwrap();
+        plan.update(json!({"step": 2, "status": "in_progress"}))
+            .unwrap();
+        assert_eq!(plan.to_string(), "[x] 1. Read\n[~] 2. Write\n");
+        assert!(!plan.is_complete());
+
+        assert!(plan
+            .update(json!({"step": 3, "status": "completed"}))
+            .is_err());
+        assert!(plan
+            .update(json!({"step": 0, "status": "completed"}))
+            .is_err());
+        assert!(plan.update(json!({"step": 2, "status": "done"})).is_err());
+
+        plan.update(json!({"step": 2, "status": "skipped"}))
+            .unwrap();
+        assert!(plan.is_complete());
+    }
+}
@@ -7,6 +7,9 @@ pub const PLATFORM_LIST_RESOURCES_TOOL_NAME: &str = "platform__list_resources";
 pub const PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str =
     "platform__search_available_extensions";
 pub const PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME: &str = "platform__manage_extensions";
+pub const PLATFORM_DELEGATE_TASK_TOOL_NAME: &str = "platform__delegate_task";
+pub const PLATFORM_UPDATE_PLAN_TOOL_NAME: &str = "platform__update_plan";
+pub const PLATFORM_SEARCH_TOOLS_TOOL_NAME: &str = "platform__search_tools";
 
 pub fn read_resource_tool() -> Tool {
     Tool::new(
@@ -112,3 +115,101 @@ pub fn manage_extensions_tool() -> Tool {
         }),
     )
 }
+
+pub fn delegate_task_tool() -> Tool {
+    Tool::new(
+        PLATFORM_DELEGATE_TASK_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Delegate a task to a subagent, which works on it with a fresh context and returns only
+            a summary of its work.
+
+            Use this for self-contained tasks that need a lot of exploration, such as searching a
+            codebase or reading documentation, to keep this conversation focused. The subagent does
+            not see this conversation, so describe the task and everything it needs to know. Call
+            this tool several times at once to run subagents in parallel.
+        "#}
+        .to_string(),
+        json!({
+            "type": "object",
+            "required": ["task"],
+            "properties": {
+                "task": {"type": "string", "description": "What the subagent should do and what its summary should contain"},
+                "extensions": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Names of the extensions the subagent can use, all enabled extensions by default"
+                },
+                "model": {"type": "string", "description": "Optional model for the subagent, from the same provider"},
+                "max_turns": {"type": "integer", "description": "Optional limit on the number of model responses of the subagent"}
+            }
+        }),
+        Some(ToolAnnotations {
+            title: Some("Delegate a task to a subagent".to_string()),
+            read_only_hint: false,
+            destructive_hint: false,
+            idempotent_hint: false,
+            open_world_hint: false,
+        }),
+    )
+}
+
+pub fn update_plan_tool() -> Tool {
+    Tool::new(
+        PLATFORM_UPDATE_PLAN_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Update the status of a step of the plan you are working through.
+
+            Mark a step in_progress when you start it, and completed when it is done. Mark steps
+            that turn out not to be needed as skipped. The user sees the plan as a checklist.
+        "#}
+        .to_string(),
+        json!({
+            "type": "object",
+            "required": ["step", "status"],
+            "properties": {
+                "step": {"type": "integer", "description": "Number of the step, starting at 1"},
+                "status": {
+                    "type": "string",
+                    "enum": ["pending", "in_progress", "completed", "skipped"],
+                    "description": "New status of the step"
+                }
+            }
+        }),
+        Some(ToolAnnotations {
+            title: Some("Update the plan".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }),
+    )
+}
+
+pub fn search_tools_tool() -> Tool {
+    Tool::new(
+        PLATFORM_SEARCH_TOOLS_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Search the tools of the enabled extensions.
+
+            Only the tools most relevant to the conversation are available on each turn. When you
+            need a tool you don't have, describe what it should do. The tools found are available
+            from the next turn on.
+        "#}
+        .to_string(),
+        json!({
+            "type": "object",
+            "required": ["query"],
+            "properties": {
+                "query": {"type": "string", "description": "What the tool should do"},
+                "limit": {"type": "integer", "description": "Maximum number of tools to find, 5 by default"}
+            }
+        }),
+        Some(ToolAnnotations {
+            title: Some("Search tools".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: false,
+            open_world_hint: false,
+        }),
+    )
+}
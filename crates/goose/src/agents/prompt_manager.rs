@@ -3,6 +3,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::agents::extension::ExtensionInfo;
+use crate::agents::plan::Plan;
 use crate::providers::base::get_current_model;
 use crate::{config::Config, prompt_template};
 
@@ -10,6 +11,10 @@ pub struct PromptManager {
     system_prompt_override: Option<String>,
     system_prompt_extras: Vec<String>,
     current_date_timestamp: String,
+    plan: Option<Plan>,
+    /// The plan as it was set, updates of its steps reach the model as tool results so the
+    /// system prompt stays cached
+    plan_prompt: Option<String>,
 }
 
 impl Default for PromptManager {
@@ -25,9 +30,28 @@ impl PromptManager {
             system_prompt_extras: Vec::new(),
             // Use the fixed current date time so that prompt cache can be used.
             current_date_timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            plan: None,
+            plan_prompt: None,
         }
     }
 
+    /// Set the plan the agent is working through, it is shown in the system prompt as it is now
+    pub fn set_plan(&mut self, plan: Option<Plan>) {
+        self.plan_prompt = plan
+            .as_ref()
+            .filter(|plan| !plan.is_complete())
+            .map(Plan::system_prompt);
+        self.plan = plan;
+    }
+
+    pub fn plan(&self) -> Option<&Plan> {
+        self.plan.as_ref()
+    }
+
+    pub fn plan_mut(&mut self) -> Option<&mut Plan> {
+        self.plan.as_mut()
+    }
+
     /// Add an additional instruction to the system prompt
     pub fn add_system_prompt_extra(&mut self, instruction: String) {
         self.system_prompt_extras.push(instruction);
@@ -130,6 +154,10 @@ impl PromptManager {
                 .push("Right now you are *NOT* in the chat only mode and have access to tool use and system.".to_string());
         }
 
+        if let Some(plan_prompt) = &self.plan_prompt {
+            system_prompt_extras.push(plan_prompt.clone());
+        }
+
         if system_prompt_extras.is_empty() {
             base_prompt
         } else {
@@ -203,4 +231,28 @@ mod tests {
             "system.md"
         );
     }
+
+    #[test]
+    fn test_plan_updates_keep_the_system_prompt() {
+        let mut prompt_manager = PromptManager::new();
+        prompt_manager.set_plan(Some(Plan::new(vec![
+            "Read".to_string(),
+            "Write".to_string(),
+        ])));
+        let build = |prompt_manager: &PromptManager| {
+            prompt_manager.build_system_prompt(vec![], None, Value::Null, None)
+        };
+        let before = build(&prompt_manager);
+        assert!(before.contains("[ ] 1. Read"));
+
+        prompt_manager
+            .plan_mut()
+            .unwrap()
+            .update(serde_json::json!({"step": 1, "status": "completed"}))
+            .unwrap();
+        assert_eq!(build(&prompt_manager), before);
+
+        prompt_manager.set_plan(None);
+        assert!(!build(&prompt_manager).contains("# Plan"));
+    }
 }
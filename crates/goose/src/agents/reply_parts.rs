@@ -5,9 +5,7 @@ use std::sync::Arc;
 use crate::message::{Message, MessageContent, ToolRequest};
 use crate::providers::base::{Provider, ProviderUsage};
 use crate::providers::errors::ProviderError;
-use crate::providers::toolshim::{
-    augment_message_with_tool_calls, modify_system_prompt_for_tool_json, OllamaInterpreter,
-};
+use crate::providers::toolshim::{augment_message_with_tool_calls, create_interpreter};
 use crate::session;
 use mcp_core::tool::Tool;
 
@@ -36,19 +34,28 @@ impl Agent {
         let model_config = provider.get_model_config();
         let model_name = &model_config.model_name;
 
+        // The tool router keeps the tools sent small, so there is no need to ask the user
+        // to disable extensions
+        let suggest_disable_extensions_prompt = if self.tool_router.is_some() {
+            serde_json::Value::String(String::new())
+        } else {
+            extension_manager.suggest_disable_extensions_prompt().await
+        };
+
         let prompt_manager = self.prompt_manager.lock().await;
         let mut system_prompt = prompt_manager.build_system_prompt(
             extensions_info,
             self.frontend_instructions.lock().await.clone(),
-            extension_manager.suggest_disable_extensions_prompt().await,
+            suggest_disable_extensions_prompt,
             Some(model_name),
         );
 
         // Handle toolshim if enabled
         let mut toolshim_tools = vec![];
         if model_config.toolshim {
-            // If tool interpretation is enabled, modify the system prompt
-            system_prompt = modify_system_prompt_for_tool_json(&system_prompt, &tools);
+            // If tool interpretation is enabled, add the instructions the interpreter expects
+            let interpreter = create_interpreter(provider.clone())?;
+            system_prompt = interpreter.modify_system_prompt(&system_prompt, &tools);
             // Make a copy of tools before emptying
             toolshim_tools = tools.clone();
             // Empty the tools vector for provider completion
@@ -58,6 +65,60 @@ impl Agent {
         Ok((tools, toolshim_tools, system_prompt))
     }
 
+    /// The tools to send for the next turn, a relevant subset when the tool router is on
+    ///
+    /// Tools interpreted by the tool shim are described in the system prompt, so they are not routed.
+    /// `ranked` holds the tools ranked on the first turn of the reply, see [`ToolRouter::select`].
+    /// Tools found with the search tool are kept per session.
+    ///
+    /// [`ToolRouter::select`]: crate::agents::tool_router::ToolRouter::select
+    pub(crate) async fn route_tools(
+        &self,
+        tools: &[Tool],
+        messages: &[Message],
+        session: Option<&crate::agents::types::SessionConfig>,
+        ranked: &mut Option<HashSet<String>>,
+    ) -> anyhow::Result<Vec<Tool>> {
+        match &self.tool_router {
+            Some(router) if !tools.is_empty() => {
+                let session_file = session.map(|session| session::get_path(session.id.clone()));
+                Ok(router
+                    .select(
+                        tools,
+                        messages,
+                        session_file.as_deref(),
+                        self.provider().await?,
+                        ranked,
+                    )
+                    .await)
+            }
+            _ => Ok(tools.to_vec()),
+        }
+    }
+
+    /// Records the requests the tool router made to the model in the session's accumulated usage
+    pub(crate) async fn record_tool_router_usage(
+        &self,
+        session_config: Option<crate::agents::types::SessionConfig>,
+    ) -> Result<()> {
+        let Some(router) = &self.tool_router else {
+            return Ok(());
+        };
+        let usages = router.take_usage().await;
+        let Some(session_config) = session_config else {
+            return Ok(());
+        };
+        if usages.is_empty() {
+            return Ok(());
+        }
+        let session_file = session::get_path(session_config.id);
+        let mut metadata = session::read_metadata(&session_file)?;
+        for usage in &usages {
+            Self::accumulate_usage(&mut metadata, usage);
+        }
+        session::update_metadata(&session_file, &metadata).await
+    }
+
     /// Categorize tools based on their annotations
     /// Returns:
     /// - read_only_tools: Tools with read-only annotations
@@ -99,11 +160,11 @@ impl Agent {
 
         // Post-process / structure the response only if tool interpretation is enabled
         if config.toolshim {
-            let interpreter = OllamaInterpreter::new().map_err(|e| {
-                ProviderError::ExecutionError(format!("Failed to create OllamaInterpreter: {}", e))
+            let interpreter = create_interpreter(provider.clone()).map_err(|e| {
+                ProviderError::ExecutionError(format!("Failed to create tool interpreter: {}", e))
             })?;
 
-            response = augment_message_with_tool_calls(&interpreter, response, toolshim_tools)
+            response = augment_message_with_tool_calls(&*interpreter, response, toolshim_tools)
                 .await
                 .map_err(|e| {
                     ProviderError::ExecutionError(format!("Failed to augment message: {}", e))
@@ -199,6 +260,17 @@ impl Agent {
         // The message count does not include the tool response till next iteration
         metadata.message_count = messages_length + 1;
 
+        Self::accumulate_usage(&mut metadata, usage);
+        session::update_metadata(&session_file, &metadata).await?;
+
+        Ok(())
+    }
+
+    /// Adds a request's usage to the running sums of the session, in total and per model
+    fn accumulate_usage(
+        metadata: &mut session::SessionMetadata,
+        usage: &crate::providers::base::ProviderUsage,
+    ) {
         // Keep running sum of tokens to track cost over the entire session
         let accumulate = |a: Option<i32>, b: Option<i32>| -> Option<i32> {
             match (a, b) {
@@ -214,8 +286,25 @@ impl Agent {
             metadata.accumulated_output_tokens,
             usage.usage.output_tokens,
         );
-        session::update_metadata(&session_file, &metadata).await?;
 
-        Ok(())
+        // Track each model separately, lead and worker models have very different costs
+        let model_usage = metadata.model_usage.entry(usage.model.clone()).or_default();
+        model_usage.total_tokens = accumulate(model_usage.total_tokens, usage.usage.total_tokens);
+        model_usage.input_tokens = accumulate(model_usage.input_tokens, usage.usage.input_tokens);
+        model_usage.output_tokens =
+            accumulate(model_usage.output_tokens, usage.usage.output_tokens);
+        model_usage.cache_read_input_tokens = accumulate(
+            model_usage.cache_read_input_tokens,
+            usage.usage.cache_read_input_tokens,
+        );
+        model_usage.cache_creation_input_tokens = accumulate(
+            model_usage.cache_creation_input_tokens,
+            usage.usage.cache_creation_input_tokens,
+        );
+
+        // Models that took part in the request too, like a worker that escalated to the lead
+        for other in &usage.other_models {
+            Self::accumulate_usage(metadata, other);
+        }
     }
 }
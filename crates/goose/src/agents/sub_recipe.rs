@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::tool::{Tool, ToolAnnotations};
+use mcp_core::{Content, ToolError};
+use serde_json::{json, Map, Value};
+
+use crate::recipe::{Recipe, SubRecipe};
+
+pub const SUB_RECIPE_TOOL_NAME_PREFIX: &str = "subrecipe__";
+
+/// Runs a sub-recipe to completion and returns its final answer
+///
+/// Implemented by the frontends, which know how to locate recipe files and set up a child agent.
+#[async_trait]
+pub trait SubRecipeRunner: Send + Sync {
+    async fn run(&self, sub_recipe: &SubRecipe, params: Vec<(String, String)>) -> Result<String>;
+}
+
+/// The sub-recipes an agent can run as tools
+#[derive(Default)]
+pub struct SubRecipeManager {
+    sub_recipes: HashMap<String, (SubRecipe, Tool)>,
+    runner: Option<Arc<dyn SubRecipeRunner>>,
+}
+
+impl SubRecipeManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_runner(&mut self, runner: Arc<dyn SubRecipeRunner>) {
+        self.runner = Some(runner);
+    }
+
+    /// Registers a sub-recipe, `recipe` is the loaded recipe it points to
+    pub fn add(&mut self, sub_recipe: SubRecipe, recipe: &Recipe) {
+        let tool = create_sub_recipe_tool(&sub_recipe, recipe);
+        self.sub_recipes
+            .insert(tool.name.clone(), (sub_recipe, tool));
+    }
+
+    pub fn tools(&self) -> Vec<Tool> {
+        self.sub_recipes
+            .values()
+            .map(|(_, tool)| tool.clone())
+            .collect()
+    }
+
+    pub fn is_sub_recipe_tool(&self, name: &str) -> bool {
+        self.sub_recipes.contains_key(name)
+    }
+
+    /// Looks up what is needed to run a sub-recipe tool call, so the run does not hold the manager
+    pub fn prepare_call(&self, name: &str, arguments: &Value) -> Result<SubRecipeCall, ToolError> {
+        let (sub_recipe, _) = self
+            .sub_recipes
+            .get(name)
+            .ok_or_else(|| ToolError::NotFound(format!("Sub-recipe tool {} not found", name)))?;
+        let runner = self.runner.clone().ok_or_else(|| {
+            ToolError::ExecutionError("Sub-recipes are not supported here".to_string())
+        })?;
+        let params = sub_recipe_params(sub_recipe, arguments)?;
+        Ok(SubRecipeCall {
+            runner,
+            sub_recipe: sub_recipe.clone(),
+            params,
+        })
+    }
+}
+
+/// A sub-recipe tool call ready to run
+pub struct SubRecipeCall {
+    runner: Arc<dyn SubRecipeRunner>,
+    sub_recipe: SubRecipe,
+    params: Vec<(String, String)>,
+}
+
+impl SubRecipeCall {
+    /// Runs the sub-recipe and wraps its answer as tool content
+    pub async fn run(self) -> Result<Vec<Content>, ToolError> {
+        self.runner
+            .run(&self.sub_recipe, self.params)
+            .await
+            .map(|answer| vec![Content::text(answer)])
+            .map_err(|e| {
+                ToolError::ExecutionError(format!(
+                    "Sub-recipe {} failed: {}",
+                    self.sub_recipe.name, e
+                ))
+            })
+    }
+}
+
+fn create_sub_recipe_tool(sub_recipe: &SubRecipe, recipe: &Recipe) -> Tool {
+    let fixed = sub_recipe.values.clone().unwrap_or_default();
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+
+    for param in recipe.parameters.iter().flatten() {
+        if fixed.contains_key(&param.key) {
+            continue;
+        }
+        properties.insert(param.key.clone(), param.json_schema());
+        if param.is_required() {
+            required.push(param.key.clone());
+        }
+    }
+
+    let description = format!(
+        "{}\n\nRuns the '{}' recipe in a separate agent and returns its final answer. \
+         The recipe does not see this conversation, pass everything it needs as parameters.",
+        sub_recipe
+            .description
+            .as_deref()
+            .unwrap_or(&recipe.description),
+        recipe.title
+    );
+
+    Tool::new(
+        format!("{}{}", SUB_RECIPE_TOOL_NAME_PREFIX, sub_recipe.name),
+        description,
+        json!({
+            "type": "object",
+            "required": required,
+            "properties": properties,
+        }),
+        Some(ToolAnnotations {
+            title: Some(recipe.title.clone()),
+            read_only_hint: false,
+            destructive_hint: true,
+            idempotent_hint: false,
+            open_world_hint: false,
+        }),
+    )
+}
+
+/// Converts tool call arguments to recipe parameter values, adding the values fixed by the parent
+fn sub_recipe_params(
+    sub_recipe: &SubRecipe,
+    arguments: &Value,
+) -> Result<Vec<(String, String)>, ToolError> {
+    let mut params: Vec<(String, String)> = sub_recipe
+        .values
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    if let Some(arguments) = arguments.as_object() {
+        for (key, value) in arguments {
+            if params.iter().any(|(fixed, _)| fixed == key) {
+                continue;
+            }
+            let value = match value {
+                Value::Null => continue,
+                Value::String(s) => s.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                // List parameters take a JSON array, so items may contain commas
+                Value::Array(items)
+                    if items
+                        .iter()
+                        .all(|item| !item.is_array() && !item.is_object()) =>
+                {
+                    value.to_string()
+                }
+                _ => {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "Parameter '{}' must be a string, number, boolean or list of those",
+                        key
+                    )))
+                }
+            };
+            params.push((key.clone(), value));
+        }
+    } else if !arguments.is_null() {
+        return Err(ToolError::InvalidParameters(
+            "Arguments must be an object".to_string(),
+        ));
+    }
+
+    params.sort();
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::split_list_value;
+
+    fn recipe() -> Recipe {
+        serde_yaml::from_str(
+            r#"
+version: 1.0.0
+title: Summarize
+description: Summarizes a file
+instructions: Summarize the file
+parameters:
+  - key: file
+    input_type: file
+    requirement: required
+    description: File to summarize
+  - key: max_words
+    input_type: number
+    requirement: optional
+    description: Length limit
+    default: "100"
+  - key: tone
+    input_type: string
+    requirement: user_prompt
+    description: Tone of the summary
+"#,
+        )
+        .unwrap()
+    }
+
+    fn sub_recipe() -> SubRecipe {
+        SubRecipe {
+            name: "summarize".to_string(),
+            path: "summarize.yaml".to_string(),
+            description: None,
+            values: Some(HashMap::from([("tone".to_string(), "neutral".to_string())])),
+        }
+    }
+
+    #[test]
+    fn test_tool_schema_from_parameters() {
+        let tool = create_sub_recipe_tool(&sub_recipe(), &recipe());
+
+        assert_eq!(tool.name, "subrecipe__summarize");
+        assert!(tool.description.starts_with("Summarizes a file"));
+        assert_eq!(tool.input_schema["required"], json!(["file"]));
+        assert_eq!(
+            tool.input_schema["properties"]["max_words"]["type"],
+            "number"
+        );
+        // Values fixed by the parent recipe are not exposed to the model
+        assert!(tool.input_schema["properties"].get("tone").is_none());
+    }
+
+    #[test]
+    fn test_params_from_arguments() {
+        let params = sub_recipe_params(
+            &sub_recipe(),
+            &json!({"file": "notes.md", "max_words": 50, "tone": "excited"}),
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("file".to_string(), "notes.md".to_string()),
+                ("max_words".to_string(), "50".to_string()),
+                ("tone".to_string(), "neutral".to_string()),
+            ]
+        );
+
+        let params =
+            sub_recipe_params(&sub_recipe(), &json!({"file": ["a,b.md", "c.md", 3]})).unwrap();
+        assert_eq!(
+            params[0],
+            ("file".to_string(), r#"["a,b.md","c.md",3]"#.to_string())
+        );
+        assert_eq!(split_list_value(&params[0].1), vec!["a,b.md", "c.md", "3"]);
+        assert!(sub_recipe_params(&sub_recipe(), &json!({"file": {"path": "a.md"}})).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt};
+use mcp_core::role::Role;
+use mcp_core::{Content, ToolError};
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::agents::extension_manager::normalize;
+use crate::agents::{Agent, ExtensionConfig, SessionConfig};
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use crate::permission::permission_confirmation::PrincipalType;
+use crate::permission::{Permission, PermissionConfirmation};
+use crate::providers::base::Provider;
+use crate::session::{self, SessionMetadata};
+
+/// Model responses a subagent gets when the task doesn't set a budget
+pub const DEFAULT_SUBAGENT_MAX_TURNS: usize = 25;
+
+/// The largest budget a task can ask for
+const SUBAGENT_MAX_TURNS_LIMIT: usize = 100;
+
+const SUBAGENT_INSTRUCTIONS: &str = "You are a subagent, working on a task delegated to you by \
+another agent. You don't have access to its conversation, and you can't ask it or the user \
+questions, so work with what the task tells you. Tool calls that need the user's approval are \
+declined, so report what you couldn't do instead of retrying them. When you are done, reply \
+with a concise summary of what you did and found, including anything the other agent needs to \
+continue, such as file paths or names. That final reply is the only part of your work the other \
+agent sees.";
+
+#[derive(Deserialize)]
+struct DelegateTaskArgs {
+    task: String,
+    #[serde(default)]
+    extensions: Option<Vec<String>>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    max_turns: Option<usize>,
+}
+
+/// How the run of a child agent ended
+pub struct ChildRun {
+    /// The task and the messages of the run
+    pub messages: Vec<Message>,
+    /// Whether the turn budget ran out before the agent finished
+    pub exhausted: bool,
+}
+
+impl ChildRun {
+    /// The last text the agent replied with, its answer when it finished
+    pub fn last_text(&self) -> Option<String> {
+        self.messages
+            .iter()
+            .rev()
+            .filter(|message| message.role == Role::Assistant)
+            .map(|message| message.as_concat_text())
+            .find(|text| !text.trim().is_empty())
+    }
+}
+
+impl Agent {
+    /// Runs a task in a child agent and returns its final summary
+    ///
+    /// The child starts with a fresh history and the parent's extensions, or the requested
+    /// subset of them. It runs in the same GOOSE_MODE as the parent, but nobody can answer its
+    /// confirmations, so the tool calls that need approval are declined. When the parent runs in
+    /// a session, the child's transcript is saved next to it as a sub-session.
+    pub(super) async fn delegate_task(
+        &self,
+        arguments: Value,
+        session: Option<&SessionConfig>,
+    ) -> Result<Vec<Content>, ToolError> {
+        let args: DelegateTaskArgs = serde_json::from_value(arguments)
+            .map_err(|e| ToolError::InvalidParameters(e.to_string()))?;
+
+        let extensions = self.subagent_extensions(args.extensions.as_deref()).await?;
+        let provider = match &args.model {
+            Some(model) => subagent_provider(model),
+            None => self.provider().await,
+        }
+        .map_err(|e| ToolError::ExecutionError(format!("Failed to set up the subagent: {}", e)))?;
+        let max_turns = args
+            .max_turns
+            .unwrap_or(DEFAULT_SUBAGENT_MAX_TURNS)
+            .clamp(1, SUBAGENT_MAX_TURNS_LIMIT);
+
+        let child = Agent::new();
+        *child.subagents_enabled.lock().await = false;
+        child
+            .extend_system_prompt(SUBAGENT_INSTRUCTIONS.to_string())
+            .await;
+        child
+            .update_provider(provider)
+            .await
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+        for extension in extensions {
+            child.add_extension(extension).await.map_err(|e| {
+                ToolError::ExecutionError(format!("Failed to set up the subagent: {}", e))
+            })?;
+        }
+
+        let run = run_child_agent(&child, &args.task, Some(max_turns))
+            .await
+            .map_err(|e| ToolError::ExecutionError(format!("Subagent failed: {}", e)))?;
+
+        let mut content =
+            vec![Content::text(summarize(&run, max_turns)).with_audience(vec![Role::Assistant])];
+        if let Some(session) = session {
+            match save_transcript(session, &args.task, &run.messages) {
+                Ok(path) => content.push(
+                    Content::text(format!("Subagent transcript: {}", path.display()))
+                        .with_audience(vec![Role::User]),
+                ),
+                Err(e) => tracing::warn!("Failed to save the subagent transcript: {}", e),
+            }
+        }
+        Ok(content)
+    }
+
+    /// The configs of the extensions to start the subagent with
+    async fn subagent_extensions(
+        &self,
+        names: Option<&[String]>,
+    ) -> Result<Vec<ExtensionConfig>, ToolError> {
+        let extension_manager = self.extension_manager.lock().await;
+        let configs = extension_manager.get_extension_configs();
+        let Some(names) = names else {
+            return Ok(configs.values().cloned().collect());
+        };
+        names
+            .iter()
+            .map(|name| {
+                configs
+                    .get(&normalize(name.clone()))
+                    .cloned()
+                    .ok_or_else(|| {
+                        let mut enabled: Vec<&String> = configs.keys().collect();
+                        enabled.sort();
+                        ToolError::InvalidParameters(format!(
+                            "Extension '{}' is not enabled, the enabled extensions are: {}",
+                            name,
+                            enabled
+                                .iter()
+                                .map(|name| name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// A provider for another model of the configured provider
+fn subagent_provider(model: &str) -> Result<Arc<dyn Provider>> {
+    let provider_name: String = Config::global().get_param("GOOSE_PROVIDER")?;
+    crate::providers::create(&provider_name, ModelConfig::new(model.to_string()))
+}
+
+/// Runs a child agent on a task until it finishes, or uses up `max_turns` model responses
+///
+/// Nobody can answer the child's confirmations, so the tool calls that need approval are
+/// declined. The future is boxed because the child dispatches tool calls like its parent, which
+/// makes the future recursive.
+pub fn run_child_agent<'a>(
+    child: &'a Agent,
+    task: &'a str,
+    max_turns: Option<usize>,
+) -> BoxFuture<'a, Result<ChildRun>> {
+    async move {
+        let mut messages = vec![Message::user().with_text(task)];
+        let mut turns = 0;
+        let mut exhausted = false;
+
+        let mut stream = child.reply(&messages, None).await?;
+        while let Some(message) = stream.next().await {
+            let message = message?;
+            for content in &message.content {
+                // Approving the delegation doesn't approve what the child does with its tools
+                if let MessageContent::ToolConfirmationRequest(confirmation) = content {
+                    child
+                        .handle_confirmation(
+                            confirmation.id.clone(),
+                            PermissionConfirmation {
+                                principal_type: PrincipalType::Tool,
+                                permission: Permission::DenyOnce,
+                            },
+                        )
+                        .await;
+                }
+            }
+
+            let wants_tools = message
+                .content
+                .iter()
+                .any(|content| matches!(content, MessageContent::ToolRequest(_)));
+            let is_turn = message.role == Role::Assistant;
+            messages.push(message);
+            if is_turn {
+                turns += 1;
+                if max_turns.is_some_and(|max_turns| turns >= max_turns) && wants_tools {
+                    exhausted = true;
+                    break;
+                }
+            }
+        }
+        Ok(ChildRun {
+            messages,
+            exhausted,
+        })
+    }
+    .boxed()
+}
+
+/// The final reply of the subagent, which is all the parent sees of its work
+fn summarize(run: &ChildRun, max_turns: usize) -> String {
+    match (run.exhausted, run.last_text()) {
+        (false, Some(text)) => text,
+        (false, None) => "The subagent finished without a summary.".to_string(),
+        (true, text) => format!(
+            "The subagent used its budget of {} turns before finishing the task. Its last message was:\n{}",
+            max_turns,
+            text.unwrap_or_default()
+        ),
+    }
+}
+
+/// Saves the subagent's messages as a session linked to the parent session
+fn save_transcript(parent: &SessionConfig, task: &str, messages: &[Message]) -> Result<PathBuf> {
+    let parent_file = session::get_path(parent.id.clone());
+    let parent_id = parent_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let id = Uuid::new_v4().simple().to_string();
+    let path = parent_file.with_file_name(format!("{}-subagent-{}.jsonl", parent_id, &id[..8]));
+
+    let mut metadata = SessionMetadata::new(parent.working_dir.clone());
+    metadata.description = task
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(80)
+        .collect();
+    metadata.message_count = messages.len();
+    metadata.parent_session = Some(parent_id);
+    session::storage::save_messages_with_metadata(&path, &metadata, messages)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::replay::{Fixtures, ReplayProvider};
+    use mcp_core::tool::ToolCall;
+    use serde_json::json;
+
+    async fn agent_with_script(script: Vec<Message>) -> Agent {
+        let agent = Agent::new();
+        let provider = ReplayProvider::new(Fixtures::new()).with_script(script);
+        agent.update_provider(Arc::new(provider)).await.unwrap();
+        agent
+    }
+
+    fn text(content: &[Content]) -> String {
+        content
+            .iter()
+            .filter_map(|content| content.as_text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tokio::test]
+    async fn test_delegate_task() {
+        let agent = agent_with_script(vec![
+            Message::assistant().with_text("Found 3 TODO comments in src/lib.rs")
+        ])
+        .await;
+        let content = agent
+            .delegate_task(json!({"task": "Find the TODO comments"}), None)
+            .await
+            .unwrap();
+        assert_eq!(text(&content), "Found 3 TODO comments in src/lib.rs");
+
+        let error = agent
+            .delegate_task(json!({"task": "Search", "extensions": ["developer"]}), None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("'developer' is not enabled"));
+    }
+
+    #[tokio::test]
+    async fn test_turn_budget() {
+        let lookup = |id: &str| {
+            Message::assistant()
+                .with_text(format!("Looking at {}", id))
+                .with_tool_request(id, Ok(ToolCall::new("missing__tool", json!({}))))
+        };
+        let agent = agent_with_script(vec![lookup("a"), lookup("b"), lookup("c")]).await;
+        let content = agent
+            .delegate_task(json!({"task": "Explore", "max_turns": 2}), None)
+            .await
+            .unwrap();
+        let summary = text(&content);
+        assert!(summary.contains("budget of 2 turns"));
+        assert!(summary.ends_with("Looking at b"));
+    }
+}
@@ -0,0 +1,594 @@
+//! Picks the tools sent to the model on each turn
+//!
+//! With many extensions enabled the model gets hundreds of tools on every request, which costs
+//! tokens and makes it pick the wrong ones. The router sends the `top_k` tools most relevant to
+//! the recent conversation instead, ranked by embedding similarity or by asking the model. A pinned
+//! set is always sent: platform tools, tools named in GOOSE_TOOL_ROUTER_PINNED, tools already
+//! called in the conversation and tools the model found with the search tool in the session.
+//!
+//! Tools are ranked on the first turn of a reply and the same tools are sent on its later turns,
+//! so the tool definitions at the start of the prompt stay cached by providers that cache prompts.
+//! Only tools the model calls or finds during the reply are added. A new reply ranks them again,
+//! which changes the tools and costs one uncached prompt.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use mcp_core::tool::Tool;
+use serde_json::json;
+use tokio::sync::{Mutex, OnceCell};
+use tracing::warn;
+
+use crate::agents::final_output_tool::FINAL_OUTPUT_TOOL_NAME;
+use crate::agents::sub_recipe::SUB_RECIPE_TOOL_NAME_PREFIX;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::providers::base::{EmbeddingProvider, Provider, ProviderUsage};
+use crate::providers::embeddings::{cosine_similarity, create_embedding_provider};
+use crate::providers::errors::ProviderError;
+
+pub const DEFAULT_TOOL_ROUTER_TOP_K: usize = 20;
+/// How many of the latest messages describe what the model is working on
+const QUERY_MESSAGES: usize = 4;
+/// Long messages like pasted files would drown out the rest of the query
+const QUERY_MESSAGE_CHARS: usize = 1000;
+
+/// How the router ranks tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolSelection {
+    /// Similarity of the tool descriptions to the conversation, with GOOSE_EMBEDDING_PROVIDER
+    Embedding,
+    /// A short request asking the model which tools it needs
+    Llm,
+}
+
+pub struct ToolRouter {
+    selection: ToolSelection,
+    top_k: usize,
+    pinned: HashSet<String>,
+    /// Tools the model found with the search tool by session file, they stay available for the
+    /// session. The agent serves many sessions, replies without a session share `None`.
+    discovered: Mutex<HashMap<Option<PathBuf>, HashSet<String>>>,
+    embedder: OnceCell<Option<Arc<dyn EmbeddingProvider>>>,
+    /// Vectors of the tool descriptions by description, so they are only read once
+    tool_vectors: Mutex<HashMap<String, Vec<f32>>>,
+    /// Usage of the requests ranking tools with the model, until the agent records it
+    usage: Mutex<Vec<ProviderUsage>>,
+}
+
+impl ToolRouter {
+    pub fn new(selection: ToolSelection, top_k: usize, pinned: HashSet<String>) -> Self {
+        Self {
+            selection,
+            top_k,
+            pinned,
+            discovered: Mutex::new(HashMap::new()),
+            embedder: OnceCell::new(),
+            tool_vectors: Mutex::new(HashMap::new()),
+            usage: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The router configured with GOOSE_TOOL_ROUTER, if routing is on
+    pub fn from_config() -> Option<Self> {
+        let config = Config::global();
+        let selection = match config
+            .get_param::<String>("GOOSE_TOOL_ROUTER")
+            .ok()?
+            .to_lowercase()
+            .as_str()
+        {
+            "embedding" => ToolSelection::Embedding,
+            "llm" => ToolSelection::Llm,
+            "" | "off" | "false" => return None,
+            other => {
+                warn!(
+                    "Unknown GOOSE_TOOL_ROUTER '{}', expected embedding or llm",
+                    other
+                );
+                return None;
+            }
+        };
+        let top_k = config
+            .get_param("GOOSE_TOOL_ROUTER_TOP_K")
+            .unwrap_or(DEFAULT_TOOL_ROUTER_TOP_K);
+        let pinned = config
+            .get_param::<Vec<String>>("GOOSE_TOOL_ROUTER_PINNED")
+            .or_else(|_| {
+                config
+                    .get_param::<String>("GOOSE_TOOL_ROUTER_PINNED")
+                    .map(|names| names.split(',').map(|n| n.trim().to_string()).collect())
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .collect();
+        Some(Self::new(selection, top_k, pinned))
+    }
+
+    /// Use this embedding provider instead of the one from GOOSE_EMBEDDING_PROVIDER
+    pub fn with_embedder(self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self {
+            embedder: OnceCell::new_with(Some(Some(embedder))),
+            ..self
+        }
+    }
+
+    async fn embedder(&self) -> Option<Arc<dyn EmbeddingProvider>> {
+        self.embedder
+            .get_or_init(|| async {
+                match create_embedding_provider() {
+                    Ok(Some(embedder)) => Some(embedder),
+                    Ok(None) => {
+                        warn!("The embedding tool router needs GOOSE_EMBEDDING_PROVIDER, falling back to keywords");
+                        None
+                    }
+                    Err(e) => {
+                        warn!("Failed to create the embedding provider: {}", e);
+                        None
+                    }
+                }
+            })
+            .await
+            .clone()
+    }
+
+    fn is_pinned(&self, name: &str) -> bool {
+        name.starts_with("platform__")
+            || name.starts_with(SUB_RECIPE_TOOL_NAME_PREFIX)
+            || name == FINAL_OUTPUT_TOOL_NAME
+            || self.pinned.contains(name)
+    }
+
+    /// The tools to send for the next turn of the conversation, in their original order
+    ///
+    /// The tools are ranked when `ranked` is empty, on the first turn of a reply, and the ranked
+    /// tools are kept there for the later turns. `session` is the file of the conversation.
+    pub async fn select(
+        &self,
+        tools: &[Tool],
+        messages: &[Message],
+        session: Option<&Path>,
+        provider: Arc<dyn Provider>,
+        ranked: &mut Option<HashSet<String>>,
+    ) -> Vec<Tool> {
+        let used = used_tools(messages);
+        let discovered = self
+            .discovered
+            .lock()
+            .await
+            .get(&session.map(Path::to_path_buf))
+            .cloned()
+            .unwrap_or_default();
+        let candidates: Vec<&Tool> = tools
+            .iter()
+            .filter(|tool| {
+                !self.is_pinned(&tool.name)
+                    && !used.contains(&tool.name)
+                    && !discovered.contains(&tool.name)
+            })
+            .collect();
+        if candidates.len() <= self.top_k {
+            return tools.to_vec();
+        }
+
+        let selected = match ranked {
+            Some(selected) => selected,
+            None => {
+                let query = routing_query(messages);
+                let selected = if query.trim().is_empty() {
+                    HashSet::new()
+                } else {
+                    self.rank(&query, &candidates, self.top_k, provider)
+                        .await
+                        .into_iter()
+                        .collect()
+                };
+                ranked.insert(selected)
+            }
+        };
+
+        tools
+            .iter()
+            .filter(|tool| {
+                selected.contains(&tool.name)
+                    || self.is_pinned(&tool.name)
+                    || used.contains(&tool.name)
+                    || discovered.contains(&tool.name)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The tools matching a search of the model, which are then sent on every turn of the session
+    pub async fn search(
+        &self,
+        tools: &[Tool],
+        query: &str,
+        limit: usize,
+        session: Option<&Path>,
+        provider: Arc<dyn Provider>,
+    ) -> Vec<Tool> {
+        let candidates: Vec<&Tool> = tools
+            .iter()
+            .filter(|tool| !self.is_pinned(&tool.name))
+            .collect();
+        let names = self.rank(query, &candidates, limit, provider).await;
+        self.discovered
+            .lock()
+            .await
+            .entry(session.map(Path::to_path_buf))
+            .or_default()
+            .extend(names.iter().cloned());
+
+        let by_name: HashMap<&str, &Tool> = candidates
+            .iter()
+            .map(|tool| (tool.name.as_str(), *tool))
+            .collect();
+        names
+            .iter()
+            .filter_map(|name| by_name.get(name.as_str()).map(|tool| (*tool).clone()))
+            .collect()
+    }
+
+    /// Usage of the requests that ranked tools with the model since it was last taken
+    pub async fn take_usage(&self) -> Vec<ProviderUsage> {
+        std::mem::take(&mut *self.usage.lock().await)
+    }
+
+    /// The names of the `k` tools most relevant to the query, most relevant first
+    ///
+    /// Falls back to matching keywords when the configured ranking fails.
+    async fn rank(
+        &self,
+        query: &str,
+        candidates: &[&Tool],
+        k: usize,
+        provider: Arc<dyn Provider>,
+    ) -> Vec<String> {
+        let ranked = match self.selection {
+            ToolSelection::Embedding => match self.embedder().await {
+                Some(embedder) => {
+                    self.rank_by_embedding(&embedder, query, candidates, k)
+                        .await
+                }
+                None => return rank_by_keywords(query, candidates, k),
+            },
+            ToolSelection::Llm => match rank_by_llm(provider, query, candidates, k).await {
+                Ok((names, usage)) => {
+                    self.usage.lock().await.push(usage);
+                    Ok(names)
+                }
+                Err(e) => Err(e),
+            },
+        };
+        ranked.unwrap_or_else(|e| {
+            warn!("Failed to rank tools, falling back to keywords: {}", e);
+            rank_by_keywords(query, candidates, k)
+        })
+    }
+
+    async fn rank_by_embedding(
+        &self,
+        embedder: &Arc<dyn EmbeddingProvider>,
+        query: &str,
+        candidates: &[&Tool],
+        k: usize,
+    ) -> Result<Vec<String>, ProviderError> {
+        let descriptions: Vec<String> = candidates.iter().map(|tool| describe(tool)).collect();
+        let mut tool_vectors = self.tool_vectors.lock().await;
+        let missing: Vec<String> = descriptions
+            .iter()
+            .filter(|d| !tool_vectors.contains_key(*d))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            let vectors = embedder.embed(&missing).await?;
+            tool_vectors.extend(missing.into_iter().zip(vectors));
+        }
+
+        let query_vector = embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        let mut scored: Vec<(f32, &Tool)> = candidates
+            .iter()
+            .zip(&descriptions)
+            .map(|(tool, description)| {
+                (
+                    cosine_similarity(&query_vector, &tool_vectors[description]),
+                    *tool,
+                )
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(_, tool)| tool.name.clone())
+            .collect())
+    }
+}
+
+/// The text a tool is ranked by
+fn describe(tool: &Tool) -> String {
+    format!("{}: {}", tool.name, tool.description)
+}
+
+/// Names of the tools the model called in the conversation, so they stay available
+fn used_tools(messages: &[Message]) -> HashSet<String> {
+    messages
+        .iter()
+        .flat_map(|message| &message.content)
+        .filter_map(|content| match content {
+            MessageContent::ToolRequest(request) => request
+                .tool_call
+                .as_ref()
+                .ok()
+                .map(|call| call.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The text of the latest messages, which says what the model is working on
+fn routing_query(messages: &[Message]) -> String {
+    let texts: Vec<String> = messages
+        .iter()
+        .rev()
+        .take(QUERY_MESSAGES)
+        .map(|message| {
+            message
+                .as_concat_text()
+                .chars()
+                .take(QUERY_MESSAGE_CHARS)
+                .collect::<String>()
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect();
+    texts.into_iter().rev().collect::<Vec<_>>().join("\n")
+}
+
+/// Ranks tools by how many words of the query appear in their name and description
+fn rank_by_keywords(query: &str, candidates: &[&Tool], k: usize) -> Vec<String> {
+    let words: HashSet<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2)
+        .map(|word| word.to_lowercase())
+        .collect();
+    let mut scored: Vec<(usize, &Tool)> = candidates
+        .iter()
+        .map(|tool| {
+            let text = describe(tool).to_lowercase();
+            let score = words.iter().filter(|word| text.contains(*word)).count();
+            (score, *tool)
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    // Stable, so equally scored tools keep their order
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored
+        .into_iter()
+        .take(k)
+        .map(|(_, tool)| tool.name.clone())
+        .collect()
+}
+
+/// Asks the model to pick the tools it needs from their names and short descriptions
+async fn rank_by_llm(
+    provider: Arc<dyn Provider>,
+    query: &str,
+    candidates: &[&Tool],
+    k: usize,
+) -> Result<(Vec<String>, ProviderUsage), ProviderError> {
+    let names: Vec<&str> = candidates.iter().map(|tool| tool.name.as_str()).collect();
+    let tool_list: Vec<String> = candidates
+        .iter()
+        .map(|tool| {
+            let summary = tool.description.lines().next().unwrap_or_default();
+            format!("- {}: {}", tool.name, summary)
+        })
+        .collect();
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "tools": {"type": "array", "items": {"type": "string", "enum": names}}
+        },
+        "required": ["tools"]
+    });
+    let system = format!(
+        "You choose the tools an assistant needs for the next steps of a conversation. \
+        Reply with the names of at most {} tools from the list, the most useful first.",
+        k
+    );
+    let message = Message::user().with_text(format!(
+        "The conversation so far:\n{}\n\nThe tools:\n{}",
+        query,
+        tool_list.join("\n")
+    ));
+
+    let (value, usage) = provider
+        .complete_structured(&system, &[message], &schema)
+        .await?;
+    let known: HashSet<&str> = names.into_iter().collect();
+    let ranked = value["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str())
+        .filter(|name| known.contains(name))
+        .take(k)
+        .map(String::from)
+        .collect();
+    Ok((ranked, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use async_trait::async_trait;
+    use mcp_core::tool::ToolCall;
+
+    /// Embeds texts by the words they contain from a small vocabulary
+    struct WordEmbeddings;
+
+    #[async_trait]
+    impl EmbeddingProvider for WordEmbeddings {
+        fn model_id(&self) -> String {
+            "test/words".to_string()
+        }
+
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    ["file", "git", "calendar", "email"]
+                        .iter()
+                        .map(|word| if text.contains(word) { 1.0 } else { 0.0 })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    struct NoProvider;
+
+    #[async_trait]
+    impl Provider for NoProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant(),
+                ProviderUsage::new("test".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    fn tool(name: &str, description: &str) -> Tool {
+        Tool::new(name, description, json!({"type": "object"}), None)
+    }
+
+    fn tools() -> Vec<Tool> {
+        vec![
+            tool("developer__text_editor", "Edit a file"),
+            tool("git__commit", "Commit changes with git"),
+            tool("google__calendar", "List calendar events"),
+            tool("google__email", "Send an email"),
+            tool("platform__search_tools", "Search for tools"),
+        ]
+    }
+
+    fn names(tools: &[Tool]) -> Vec<&str> {
+        tools.iter().map(|tool| tool.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_select_tools() {
+        let router = ToolRouter::new(ToolSelection::Embedding, 1, HashSet::new())
+            .with_embedder(Arc::new(WordEmbeddings));
+        let messages = vec![Message::user().with_text("What is on my calendar today?")];
+
+        let mut ranked = None;
+        let selected = router
+            .select(&tools(), &messages, None, Arc::new(NoProvider), &mut ranked)
+            .await;
+        assert_eq!(
+            names(&selected),
+            vec!["google__calendar", "platform__search_tools"]
+        );
+
+        // Later turns of the reply keep the ranked tools, adding the ones called meanwhile
+        let messages = vec![
+            Message::assistant()
+                .with_tool_request("1", Ok(ToolCall::new("git__commit", json!({})))),
+            Message::user().with_text("Now send an email about it"),
+        ];
+        let selected = router
+            .select(&tools(), &messages, None, Arc::new(NoProvider), &mut ranked)
+            .await;
+        assert_eq!(
+            names(&selected),
+            vec!["git__commit", "google__calendar", "platform__search_tools"]
+        );
+
+        // A new reply ranks the tools again, keeping the ones called before
+        let messages = vec![
+            Message::assistant()
+                .with_tool_request("1", Ok(ToolCall::new("git__commit", json!({})))),
+            Message::user().with_text("Now send an email about it"),
+        ];
+        let selected = router
+            .select(&tools(), &messages, None, Arc::new(NoProvider), &mut None)
+            .await;
+        assert_eq!(
+            names(&selected),
+            vec!["git__commit", "google__email", "platform__search_tools"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_tools() {
+        let pinned = HashSet::from(["developer__text_editor".to_string()]);
+        let router = ToolRouter::new(ToolSelection::Llm, 1, pinned);
+
+        // The provider fails to rank, so keywords are used
+        let session = Path::new("/sessions/one.jsonl");
+        let found = router
+            .search(
+                &tools(),
+                "commit with git",
+                2,
+                Some(session),
+                Arc::new(NoProvider),
+            )
+            .await;
+        assert_eq!(names(&found), vec!["git__commit"]);
+
+        // Found tools are sent from then on, in that session only
+        let messages = vec![Message::user().with_text("hello")];
+        let selected = router
+            .select(
+                &tools(),
+                &messages,
+                Some(session),
+                Arc::new(NoProvider),
+                &mut None,
+            )
+            .await;
+        assert_eq!(
+            names(&selected),
+            vec![
+                "developer__text_editor",
+                "git__commit",
+                "platform__search_tools"
+            ]
+        );
+        let selected = router
+            .select(
+                &tools(),
+                &messages,
+                Some(Path::new("/sessions/two.jsonl")),
+                Arc::new(NoProvider),
+                &mut None,
+            )
+            .await;
+        assert!(!names(&selected).contains(&"git__commit"));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::session;
+use mcp_core::tool::ToolCall;
 use mcp_core::{Content, Tool, ToolResult};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -15,6 +16,12 @@ pub struct FrontendTool {
     pub tool: Tool,
 }
 
+/// Replaces the results of tool calls, used to test recipes without running their tools
+pub trait ToolMock: Send + Sync {
+    /// The result to use for a tool call, or None to run the tool
+    fn mock(&self, tool_call: &ToolCall) -> Option<ToolResult<Vec<Content>>>;
+}
+
 /// Session configuration for an agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -1,6 +1,11 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::providers::catalog::ModelCatalog;
 
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
 
@@ -33,6 +38,56 @@ static MODEL_SPECIFIC_LIMITS: Lazy<HashMap<&'static str, usize>> = Lazy::new(||
     map
 });
 
+// Extended thinking needs a budget of at least this many tokens
+pub const MIN_THINKING_BUDGET: i32 = 1024;
+
+/// How much a reasoning model should think before it answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// The thinking budget with the same effect, for models that take a budget instead
+    pub fn thinking_budget(&self) -> i32 {
+        match self {
+            ReasoningEffort::Low => 4_000,
+            ReasoningEffort::Medium => 16_000,
+            ReasoningEffort::High => 32_000,
+        }
+    }
+}
+
+impl fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let effort = match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        };
+        write!(f, "{}", effort)
+    }
+}
+
+impl FromStr for ReasoningEffort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(ReasoningEffort::Low),
+            "medium" => Ok(ReasoningEffort::Medium),
+            "high" => Ok(ReasoningEffort::High),
+            _ => Err(format!(
+                "Invalid reasoning effort '{}', expected low, medium or high",
+                s
+            )),
+        }
+    }
+}
+
 /// Configuration for model-specific settings and limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
@@ -52,6 +107,14 @@ pub struct ModelConfig {
     pub toolshim: bool,
     /// Model to use for toolshim (optional as a default exists)
     pub toolshim_model: Option<String>,
+    /// Optional reasoning effort for models that think before answering
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Optional number of tokens models with extended thinking may think for
+    pub thinking_budget: Option<i32>,
+    /// Optional nucleus sampling setting (0.0 - 1.0)
+    pub top_p: Option<f32>,
+    /// Optional sequences that stop generation
+    pub stop_sequences: Option<Vec<String>>,
 }
 
 /// Struct to represent model pattern matches and their limits
@@ -90,6 +153,59 @@ impl ModelConfig {
             max_tokens: None,
             toolshim,
             toolshim_model,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
+        }
+    }
+
+    /// Create a new ModelConfig, with the context limit of the model catalog taking precedence
+    /// over the model-specific default
+    pub fn new_with_catalog(model_name: String, catalog: &ModelCatalog) -> Self {
+        let context_limit = catalog.lookup(&model_name).map(|info| info.context_limit);
+        let config = Self::new(model_name);
+        Self {
+            context_limit: context_limit.or(config.context_limit),
+            ..config
+        }
+    }
+
+    /// Apply the model settings configured for the session's model: GOOSE_REASONING_EFFORT,
+    /// GOOSE_THINKING_BUDGET, GOOSE_TOP_P and GOOSE_STOP_SEQUENCES
+    ///
+    /// Only the session's model gets these, models goose uses on the side, like the tool call
+    /// interpreter, subagents or a lead model, keep their defaults.
+    pub fn with_configured_overrides(self) -> Self {
+        let config = Config::global();
+        let reasoning_effort = config
+            .get_param::<String>("GOOSE_REASONING_EFFORT")
+            .ok()
+            .and_then(|val| val.parse().ok());
+        // CLAUDE_THINKING_ENABLED and CLAUDE_THINKING_BUDGET predate GOOSE_THINKING_BUDGET
+        let thinking_budget = config
+            .get_param::<i32>("GOOSE_THINKING_BUDGET")
+            .ok()
+            .or_else(|| {
+                std::env::var("CLAUDE_THINKING_ENABLED").ok().map(|_| {
+                    std::env::var("CLAUDE_THINKING_BUDGET")
+                        .ok()
+                        .and_then(|val| val.parse().ok())
+                        .unwrap_or(16_000)
+                })
+            });
+        let top_p = config.get_param::<f32>("GOOSE_TOP_P").ok();
+        let stop_sequences = config
+            .get_param::<Vec<String>>("GOOSE_STOP_SEQUENCES")
+            .ok()
+            .filter(|sequences| !sequences.is_empty());
+
+        Self {
+            reasoning_effort: reasoning_effort.or(self.reasoning_effort),
+            thinking_budget: thinking_budget.or(self.thinking_budget),
+            top_p: top_p.or(self.top_p),
+            stop_sequences: stop_sequences.or(self.stop_sequences),
+            ..self
         }
     }
 
@@ -158,6 +274,30 @@ impl ModelConfig {
         self
     }
 
+    /// Set the reasoning effort
+    pub fn with_reasoning_effort(mut self, effort: Option<ReasoningEffort>) -> Self {
+        self.reasoning_effort = effort;
+        self
+    }
+
+    /// Set the thinking budget
+    pub fn with_thinking_budget(mut self, budget: Option<i32>) -> Self {
+        self.thinking_budget = budget;
+        self
+    }
+
+    /// Set the top_p
+    pub fn with_top_p(mut self, top_p: Option<f32>) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    /// Set the stop sequences
+    pub fn with_stop_sequences(mut self, sequences: Option<Vec<String>>) -> Self {
+        self.stop_sequences = sequences;
+        self
+    }
+
     /// Get the tokenizer name
     pub fn tokenizer_name(&self) -> &str {
         &self.tokenizer_name
@@ -168,6 +308,15 @@ impl ModelConfig {
     pub fn context_limit(&self) -> usize {
         self.context_limit.unwrap_or(DEFAULT_CONTEXT_LIMIT)
     }
+
+    /// The thinking budget for models that take one, if thinking was asked for
+    ///
+    /// An explicit budget wins over one derived from the reasoning effort.
+    pub fn effective_thinking_budget(&self) -> Option<i32> {
+        self.thinking_budget
+            .or_else(|| self.reasoning_effort.map(|effort| effort.thinking_budget()))
+            .map(|budget| budget.max(MIN_THINKING_BUDGET))
+    }
 }
 
 #[cfg(test)]
@@ -176,20 +325,23 @@ mod tests {
 
     #[test]
     fn test_model_config_context_limits() {
+        // Model lists cached on this machine must not change the limits
+        let catalog = ModelCatalog::in_memory();
+
         // Test explicit limit
-        let config =
-            ModelConfig::new("claude-3-opus".to_string()).with_context_limit(Some(150_000));
+        let config = ModelConfig::new_with_catalog("claude-3-opus".to_string(), &catalog)
+            .with_context_limit(Some(150_000));
         assert_eq!(config.context_limit(), 150_000);
 
         // Test model-specific defaults
-        let config = ModelConfig::new("claude-3-opus".to_string());
+        let config = ModelConfig::new_with_catalog("claude-3-opus".to_string(), &catalog);
         assert_eq!(config.context_limit(), 200_000);
 
-        let config = ModelConfig::new("gpt-4-turbo".to_string());
+        let config = ModelConfig::new_with_catalog("gpt-4-turbo".to_string(), &catalog);
         assert_eq!(config.context_limit(), 128_000);
 
         // Test fallback to default
-        let config = ModelConfig::new("unknown-model".to_string());
+        let config = ModelConfig::new_with_catalog("unknown-model".to_string(), &catalog);
         assert_eq!(config.context_limit(), DEFAULT_CONTEXT_LIMIT);
     }
 
@@ -244,6 +396,47 @@ mod tests {
         assert_eq!(config.temperature, None);
     }
 
+    #[test]
+    fn test_model_config_reasoning() {
+        let config = ModelConfig::new("test-model".to_string())
+            .with_reasoning_effort(None)
+            .with_thinking_budget(None);
+        assert_eq!(config.effective_thinking_budget(), None);
+
+        let config = config.with_reasoning_effort(Some("HIGH".parse().unwrap()));
+        assert_eq!(config.effective_thinking_budget(), Some(32_000));
+
+        let config = config.with_thinking_budget(Some(100));
+        assert_eq!(
+            config.effective_thinking_budget(),
+            Some(MIN_THINKING_BUDGET)
+        );
+
+        assert!("extreme".parse::<ReasoningEffort>().is_err());
+    }
+
+    #[test]
+    fn test_model_config_configured_overrides() {
+        use temp_env::with_vars;
+
+        with_vars(
+            [
+                ("GOOSE_TOP_P", Some("0.5")),
+                ("GOOSE_STOP_SEQUENCES", Some(r#"["END"]"#)),
+            ],
+            || {
+                // Models other than the session's keep their defaults
+                let config = ModelConfig::new("test-model".to_string());
+                assert_eq!(config.top_p, None);
+                assert_eq!(config.stop_sequences, None);
+
+                let config = config.with_configured_overrides();
+                assert_eq!(config.top_p, Some(0.5));
+                assert_eq!(config.stop_sequences, Some(vec!["END".to_string()]));
+            },
+        );
+    }
+
     #[test]
     fn test_get_all_model_limits() {
         let limits = ModelConfig::get_all_model_limits();
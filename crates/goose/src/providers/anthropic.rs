@@ -8,7 +8,7 @@ use std::time::Duration;
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
 use super::formats::anthropic::{create_request, get_usage, response_to_message};
-use super::utils::{emit_debug_trace, get_model};
+use super::utils::{emit_debug_trace, get_model, retry_after};
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
@@ -77,6 +77,7 @@ impl AnthropicProvider {
             .await?;
 
         let status = response.status();
+        let retry_delay = retry_after(response.headers());
         let payload: Option<Value> = response.json().await.ok();
 
         // https://docs.anthropic.com/en/api/errors
@@ -102,7 +103,7 @@ impl AnthropicProvider {
                 Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
             }
             StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+                Err(ProviderError::RateLimitExceeded { details: format!("{:?}", payload), retry_delay })
             }
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
                 Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -159,7 +160,7 @@ impl Provider for AnthropicProvider {
         headers.insert("x-api-key", self.api_key.parse().unwrap());
         headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
 
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
+        let is_thinking_enabled = self.model.effective_thinking_budget().is_some();
         if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
             // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
             headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
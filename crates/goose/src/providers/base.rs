@@ -1,10 +1,13 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use super::catalog::model_info;
 use super::errors::ProviderError;
+use super::utils::extract_json_values;
 use crate::message::Message;
 use crate::model::ModelConfig;
 use mcp_core::tool::Tool;
+use serde_json::Value;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
@@ -32,6 +35,40 @@ pub struct ModelInfo {
     pub name: String,
     /// The maximum context length this model supports
     pub context_limit: usize,
+    /// The maximum number of tokens the model generates in one reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<usize>,
+    /// Whether the model supports tool calling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    /// Whether the model accepts images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    /// Whether the model can think before it answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_thinking: Option<bool>,
+    /// Cost in USD per million input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_cost_per_million: Option<f64>,
+    /// Cost in USD per million output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_cost_per_million: Option<f64>,
+}
+
+impl ModelInfo {
+    /// A model of which only the context limit is known
+    pub fn new(name: impl Into<String>, context_limit: usize) -> Self {
+        Self {
+            name: name.into(),
+            context_limit,
+            max_output_tokens: None,
+            supports_tools: None,
+            supports_vision: None,
+            supports_thinking: None,
+            input_cost_per_million: None,
+            output_cost_per_million: None,
+        }
+    }
 }
 
 /// Metadata about a provider's configuration requirements and capabilities
@@ -46,7 +83,6 @@ pub struct ProviderMetadata {
     /// The default/recommended model for this provider
     pub default_model: String,
     /// A list of currently known models with their capabilities
-    /// TODO: eventually query the apis directly
     pub known_models: Vec<ModelInfo>,
     /// Link to the docs where models can be found
     pub model_doc_link: String,
@@ -69,13 +105,7 @@ impl ProviderMetadata {
             display_name: display_name.to_string(),
             description: description.to_string(),
             default_model: default_model.to_string(),
-            known_models: model_names
-                .iter()
-                .map(|&name| ModelInfo {
-                    name: name.to_string(),
-                    context_limit: ModelConfig::new(name.to_string()).context_limit(),
-                })
-                .collect(),
+            known_models: model_names.iter().map(|&name| model_info(name)).collect(),
             model_doc_link: model_doc_link.to_string(),
             config_keys,
         }
@@ -117,19 +147,37 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// Usage of other models that took part in the request, each under its own model name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_models: Vec<ProviderUsage>,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            other_models: Vec::new(),
+        }
+    }
+
+    pub fn with_other_model(mut self, usage: ProviderUsage) -> Self {
+        self.other_models.push(usage);
+        self
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct Usage {
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// Input tokens read from the provider's prompt cache, included in input_tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+    /// Input tokens written to the provider's prompt cache, included in input_tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<i32>,
 }
 
 impl Usage {
@@ -142,6 +190,17 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            cache_read_input_tokens: None,
+            cache_creation_input_tokens: None,
+        }
+    }
+
+    /// Adds the tokens that were read from and written to the prompt cache
+    pub fn with_cache_tokens(self, read: Option<i32>, creation: Option<i32>) -> Self {
+        Self {
+            cache_read_input_tokens: read,
+            cache_creation_input_tokens: creation,
+            ..self
         }
     }
 }
@@ -183,6 +242,82 @@ pub trait Provider: Send + Sync {
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
         Ok(None)
     }
+
+    /// Fetch the supported models with their capabilities
+    ///
+    /// Providers whose model list API reports capabilities should override this, by default
+    /// the capabilities of the supported models are looked up in the model catalog.
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        Ok(self
+            .fetch_supported_models_async()
+            .await?
+            .map(|names| names.iter().map(|name| model_info(name)).collect()))
+    }
+
+    /// Generate a JSON value that follows the given JSON schema
+    ///
+    /// Providers that can constrain their output to a schema should override this, by default
+    /// the schema is only described in the system prompt and the JSON is parsed from the reply.
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let system = format!(
+            "{}\n\nReply only with JSON that follows this JSON schema:\n{}",
+            system, schema
+        );
+        let (message, usage) = self.complete(&system, messages, &[]).await?;
+        let text = message.as_concat_text();
+        let value = extract_json_values(&text)
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ProviderError::ExecutionError(format!("Expected a JSON reply, got: {}", text))
+            })?;
+        Ok((value, usage))
+    }
+}
+
+/// Texts per request when the embedding provider does not say otherwise
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 64;
+
+/// Base trait for embedding providers, which turn texts into vectors that are close when the
+/// texts are similar
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifies the provider and model, vectors of different models cannot be compared
+    fn model_id(&self) -> String;
+
+    /// The most texts embedded in one request
+    fn max_batch_size(&self) -> usize {
+        DEFAULT_EMBEDDING_BATCH_SIZE
+    }
+
+    /// Embed texts in a single request, at most `max_batch_size` of them
+    ///
+    /// # Returns
+    /// One vector per text, in the order of the texts
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError>;
+
+    /// Embed any number of texts, in as many requests as needed
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.max_batch_size().max(1)) {
+            let batch_embeddings = self.embed_batch(batch).await?;
+            if batch_embeddings.len() != batch.len() {
+                return Err(ProviderError::ExecutionError(format!(
+                    "Expected {} embeddings from {}, got {}",
+                    batch.len(),
+                    self.model_id(),
+                    batch_embeddings.len()
+                )));
+            }
+            embeddings.extend(batch_embeddings);
+        }
+        Ok(embeddings)
+    }
 }
 
 #[cfg(test)]
@@ -272,24 +407,15 @@ mod tests {
     #[test]
     fn test_model_info_creation() {
         // Test direct ModelInfo creation
-        let info = ModelInfo {
-            name: "test-model".to_string(),
-            context_limit: 1000,
-        };
+        let info = ModelInfo::new("test-model", 1000);
         assert_eq!(info.context_limit, 1000);
 
         // Test equality
-        let info2 = ModelInfo {
-            name: "test-model".to_string(),
-            context_limit: 1000,
-        };
+        let info2 = ModelInfo::new("test-model", 1000);
         assert_eq!(info, info2);
 
         // Test inequality
-        let info3 = ModelInfo {
-            name: "test-model".to_string(),
-            context_limit: 2000,
-        };
+        let info3 = ModelInfo::new("test-model", 2000);
         assert_ne!(info, info3);
     }
 }
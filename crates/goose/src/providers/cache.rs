@@ -0,0 +1,117 @@
+//! Prompt caching
+//!
+//! Most of a request to a model is the same as the request before it: the system prompt, the
+//! tool definitions and the conversation up to the latest messages. Providers can cache that
+//! prefix, which makes requests cheaper and faster. Some providers cache automatically (OpenAI,
+//! Gemini), others need the cacheable parts to be marked (Anthropic, and Anthropic models behind
+//! other providers). The [`CachePlan`] says which parts to mark, and each format maps it to the
+//! provider's markers. Cached tokens are reported in [`super::base::Usage`].
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+
+/// How many messages mark the end of the cacheable conversation prefix
+///
+/// The latest user message is marked so the conversation so far gets cached, and the one before
+/// it so the request can read what the previous request cached. With the system prompt and the
+/// tools that makes four breakpoints, the most Anthropic allows in a request.
+const CONVERSATION_BREAKPOINTS: usize = 2;
+
+/// The parts of a request to mark as cacheable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePlan {
+    pub system: bool,
+    pub tools: bool,
+    pub conversation: bool,
+}
+
+impl CachePlan {
+    /// Caches everything, unless `GOOSE_PROMPT_CACHING` is false
+    pub fn from_config() -> Self {
+        let enabled = Config::global()
+            .get_param::<bool>("GOOSE_PROMPT_CACHING")
+            .unwrap_or(true);
+        if enabled {
+            Self::all()
+        } else {
+            Self::none()
+        }
+    }
+
+    pub fn all() -> Self {
+        Self {
+            system: true,
+            tools: true,
+            conversation: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            system: false,
+            tools: false,
+            conversation: false,
+        }
+    }
+
+    /// Indices of the messages that end the cacheable prefix of a conversation
+    pub fn conversation_breakpoints<T>(
+        &self,
+        messages: &[T],
+        is_user: impl Fn(&T) -> bool,
+    ) -> Vec<usize> {
+        if !self.conversation {
+            return Vec::new();
+        }
+        messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, message)| is_user(message))
+            .map(|(index, _)| index)
+            .take(CONVERSATION_BREAKPOINTS)
+            .collect()
+    }
+}
+
+/// Anthropic's marker for a cacheable block, everything up to and including the block is cached
+pub fn ephemeral_cache_control() -> Value {
+    json!({ "type": "ephemeral" })
+}
+
+/// Marks a content block with Anthropic's `cache_control`
+pub fn mark_cacheable(block: &mut Value) {
+    if let Some(block) = block.as_object_mut() {
+        block.insert("cache_control".to_string(), ephemeral_cache_control());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_breakpoints() {
+        let roles = [
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+        ];
+        let is_user = |role: &&str| *role == "user";
+        assert_eq!(
+            CachePlan::all().conversation_breakpoints(&roles, is_user),
+            vec![4, 2]
+        );
+        assert!(CachePlan::none()
+            .conversation_breakpoints(&roles, is_user)
+            .is_empty());
+        assert_eq!(
+            CachePlan::all().conversation_breakpoints(&roles[..2], is_user),
+            vec![0]
+        );
+    }
+}
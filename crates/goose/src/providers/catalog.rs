@@ -0,0 +1,374 @@
+//! Capabilities of the models providers serve
+//!
+//! Model capabilities come from the model list APIs of the providers that report them, and
+//! are cached on disk per provider. Models no provider reported yet fall back to a built-in
+//! table of well known models.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use super::base::{ModelInfo, Provider};
+use super::errors::ProviderError;
+use crate::model::ModelConfig;
+
+/// How long a fetched model list is used before it is fetched again
+pub const CATALOG_TTL_HOURS: i64 = 24;
+
+/// name, context limit, max output tokens, tools, vision, thinking, input and output cost per million tokens
+type BuiltinModel = (&'static str, usize, usize, bool, bool, bool, f64, f64);
+
+// Models whose provider API does not report capabilities, from the provider docs
+#[rustfmt::skip]
+const BUILTIN_MODELS: &[BuiltinModel] = &[
+    // OpenAI, https://platform.openai.com/docs/models
+    ("gpt-4o", 128_000, 16_384, true, true, false, 2.5, 10.0),
+    ("gpt-4o-mini", 128_000, 16_384, true, true, false, 0.15, 0.6),
+    ("gpt-4-turbo", 128_000, 4_096, true, true, false, 10.0, 30.0),
+    ("gpt-4.1", 1_047_576, 32_768, true, true, false, 2.0, 8.0),
+    ("gpt-4.1-mini", 1_047_576, 32_768, true, true, false, 0.4, 1.6),
+    ("gpt-4.1-nano", 1_047_576, 32_768, true, true, false, 0.1, 0.4),
+    ("gpt-3.5-turbo", 16_385, 4_096, true, false, false, 0.5, 1.5),
+    ("o1", 200_000, 100_000, true, true, true, 15.0, 60.0),
+    ("o3", 200_000, 100_000, true, true, true, 2.0, 8.0),
+    ("o3-mini", 200_000, 100_000, true, false, true, 1.1, 4.4),
+    ("o4-mini", 200_000, 100_000, true, true, true, 1.1, 4.4),
+    // Anthropic, https://docs.anthropic.com/en/docs/about-claude/models
+    ("claude-opus-4", 200_000, 32_000, true, true, true, 15.0, 75.0),
+    ("claude-sonnet-4", 200_000, 64_000, true, true, true, 3.0, 15.0),
+    ("claude-3-7-sonnet", 200_000, 64_000, true, true, true, 3.0, 15.0),
+    ("claude-3-5-sonnet", 200_000, 8_192, true, true, false, 3.0, 15.0),
+    ("claude-3-5-haiku", 200_000, 8_192, true, true, false, 0.8, 4.0),
+    ("claude-3-opus", 200_000, 4_096, true, true, false, 15.0, 75.0),
+    ("claude-3-haiku", 200_000, 4_096, true, true, false, 0.25, 1.25),
+    // Google, https://ai.google.dev/gemini-api/docs/models
+    ("gemini-2.5-pro", 1_048_576, 65_536, true, true, true, 1.25, 10.0),
+    ("gemini-2.5-flash", 1_048_576, 65_536, true, true, true, 0.3, 2.5),
+    ("gemini-2.0-flash", 1_048_576, 8_192, true, true, false, 0.1, 0.4),
+];
+
+/// Looks a model up in the built-in table
+///
+/// Dated and tagged versions like claude-3-5-sonnet-20241022 match the longest listed name
+/// they start with.
+fn builtin_model(model_name: &str) -> Option<ModelInfo> {
+    BUILTIN_MODELS
+        .iter()
+        .filter(|(name, ..)| {
+            model_name == *name
+                || (model_name.starts_with(name) && model_name[name.len()..].starts_with('-'))
+        })
+        .max_by_key(|(name, ..)| name.len())
+        .map(
+            |&(name, context, max_output, tools, vision, thinking, input, output)| ModelInfo {
+                max_output_tokens: Some(max_output),
+                supports_tools: Some(tools),
+                supports_vision: Some(vision),
+                supports_thinking: Some(thinking),
+                input_cost_per_million: Some(input),
+                output_cost_per_million: Some(output),
+                ..ModelInfo::new(name, context)
+            },
+        )
+}
+
+/// A provider's model list as it was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedModels {
+    fetched_at: DateTime<Utc>,
+    models: Vec<ModelInfo>,
+}
+
+impl CachedModels {
+    fn is_fresh(&self) -> bool {
+        Utc::now() - self.fetched_at < Duration::hours(CATALOG_TTL_HOURS)
+    }
+}
+
+/// Stores the fetched model lists as one json file per provider
+struct CatalogCache {
+    dir: PathBuf,
+}
+
+impl CatalogCache {
+    fn path(&self, provider_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", provider_name))
+    }
+
+    fn load(&self, provider_name: &str) -> Option<CachedModels> {
+        let contents = std::fs::read_to_string(self.path(provider_name)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn save(&self, provider_name: &str, models: &[ModelInfo]) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let cached = CachedModels {
+            fetched_at: Utc::now(),
+            models: models.to_vec(),
+        };
+        std::fs::write(self.path(provider_name), serde_json::to_string(&cached)?)?;
+        Ok(())
+    }
+
+    /// All cached models by name, stale ones included since they beat the built-in table
+    fn load_all(&self) -> HashMap<String, ModelInfo> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return HashMap::new();
+        };
+        let mut providers: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "json")
+                    .then(|| path.file_stem()?.to_str().map(String::from))?
+            })
+            .collect();
+        providers.sort();
+
+        let mut models = HashMap::new();
+        for provider in providers {
+            for model in self.load(&provider).map(|c| c.models).unwrap_or_default() {
+                models.entry(model.name.clone()).or_insert(model);
+            }
+        }
+        models
+    }
+}
+
+/// The model lists fetched from providers, indexed by model name
+///
+/// The global catalog is cached in Goose's cache directory. Code that should not depend on the
+/// cache, like tests, takes a catalog of its own such as [`ModelCatalog::in_memory`].
+pub struct ModelCatalog {
+    cache: Option<CatalogCache>,
+    models: RwLock<HashMap<String, ModelInfo>>,
+}
+
+static GLOBAL_CATALOG: OnceCell<ModelCatalog> = OnceCell::new();
+
+impl ModelCatalog {
+    /// A catalog cached in a directory, loading the model lists already there
+    pub fn new(dir: PathBuf) -> Self {
+        let cache = CatalogCache { dir };
+        let models = cache.load_all();
+        Self {
+            cache: Some(cache),
+            models: RwLock::new(models),
+        }
+    }
+
+    /// A catalog that is not cached on disk
+    pub fn in_memory() -> Self {
+        Self {
+            cache: None,
+            models: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn global() -> &'static ModelCatalog {
+        GLOBAL_CATALOG.get_or_init(|| {
+            Self::new(
+                choose_app_strategy(crate::config::APP_STRATEGY.clone())
+                    .expect("goose requires a home dir")
+                    .in_cache_dir("models"),
+            )
+        })
+    }
+
+    /// Looks up the capabilities of a model, first in the model lists fetched from providers,
+    /// then in the built-in table
+    pub fn lookup(&self, model_name: &str) -> Option<ModelInfo> {
+        let cached = self
+            .models
+            .read()
+            .ok()
+            .and_then(|models| models.get(model_name).cloned());
+        cached
+            .or_else(|| builtin_model(model_name))
+            .map(|info| ModelInfo {
+                name: model_name.to_string(),
+                ..info
+            })
+    }
+
+    fn cached(&self, provider_name: &str) -> Option<CachedModels> {
+        self.cache.as_ref()?.load(provider_name)
+    }
+
+    fn store(&self, provider_name: &str, models: &[ModelInfo]) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.save(provider_name, models) {
+                tracing::warn!("Failed to cache the models of {}: {}", provider_name, e);
+            }
+        }
+        if let Ok(mut cached) = self.models.write() {
+            for model in models {
+                cached.insert(model.name.clone(), model.clone());
+            }
+        }
+    }
+
+    /// Fetches the models of a provider with their capabilities and caches them
+    pub async fn refresh_models(
+        &self,
+        provider_name: &str,
+        provider: &dyn Provider,
+    ) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let models = provider.fetch_model_info_async().await?;
+        if let Some(models) = &models {
+            self.store(provider_name, models);
+        }
+        Ok(models)
+    }
+
+    /// The models of a provider, fetched again only when the cached list is stale
+    pub async fn fetch_models(
+        &self,
+        provider_name: &str,
+        provider: &dyn Provider,
+    ) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        match self.cached(provider_name) {
+            Some(cached) if cached.is_fresh() => Ok(Some(cached.models)),
+            _ => self.refresh_models(provider_name, provider).await,
+        }
+    }
+}
+
+/// Looks up the capabilities of a model in the global catalog
+pub fn lookup(model_name: &str) -> Option<ModelInfo> {
+    ModelCatalog::global().lookup(model_name)
+}
+
+/// The capabilities of a model, with the context limit guessed from its name when the
+/// catalog does not know it
+pub fn model_info(model_name: &str) -> ModelInfo {
+    lookup(model_name).unwrap_or_else(|| {
+        ModelInfo::new(
+            model_name,
+            ModelConfig::new(model_name.to_string()).context_limit(),
+        )
+    })
+}
+
+/// Fetches the models of a provider into the global catalog
+pub async fn refresh_models(
+    provider_name: &str,
+    provider: &dyn Provider,
+) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+    ModelCatalog::global()
+        .refresh_models(provider_name, provider)
+        .await
+}
+
+/// The models of a provider in the global catalog, fetched again when stale
+pub async fn fetch_models(
+    provider_name: &str,
+    provider: &dyn Provider,
+) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+    ModelCatalog::global()
+        .fetch_models(provider_name, provider)
+        .await
+}
+
+/// Refreshes a stale model list of a provider in the background, for the next lookups
+pub fn refresh_in_background(provider_name: &str, provider: Arc<dyn Provider>) {
+    let catalog = ModelCatalog::global();
+    if catalog
+        .cached(provider_name)
+        .is_some_and(|cached| cached.is_fresh())
+    {
+        return;
+    }
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let provider_name = provider_name.to_string();
+    runtime.spawn(async move {
+        if let Err(e) = catalog
+            .refresh_models(&provider_name, provider.as_ref())
+            .await
+        {
+            tracing::debug!("Failed to fetch the models of {}: {}", provider_name, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_model() {
+        let model = builtin_model("claude-3-5-sonnet-20241022").unwrap();
+        assert_eq!(model.name, "claude-3-5-sonnet");
+        assert_eq!(model.context_limit, 200_000);
+        assert_eq!(model.max_output_tokens, Some(8_192));
+
+        // The longest match wins
+        let model = builtin_model("gpt-4o-mini-2024-07-18").unwrap();
+        assert_eq!(model.name, "gpt-4o-mini");
+        assert_eq!(model.input_cost_per_million, Some(0.15));
+
+        assert_eq!(builtin_model("o3").unwrap().supports_thinking, Some(true));
+        assert!(builtin_model("o3x").is_none());
+        assert!(builtin_model("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_catalog_cache() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = CatalogCache {
+            dir: dir.path().join("models"),
+        };
+        assert!(cache.load("google").is_none());
+        assert!(cache.load_all().is_empty());
+
+        let models = vec![
+            ModelInfo::new("gemini-2.5-pro", 1_048_576),
+            ModelInfo {
+                supports_tools: Some(true),
+                ..ModelInfo::new("gemma-3", 32_768)
+            },
+        ];
+        cache.save("google", &models)?;
+        cache.save("openrouter", &[ModelInfo::new("gemma-3", 8_192)])?;
+
+        let cached = cache.load("google").unwrap();
+        assert!(cached.is_fresh());
+        assert_eq!(cached.models, models);
+
+        let all = cache.load_all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all["gemma-3"].context_limit, 32_768);
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_catalog() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = CatalogCache {
+            dir: dir.path().to_path_buf(),
+        };
+        cache.save("ollama", &[ModelInfo::new("qwen2.5", 32_768)])?;
+
+        let catalog = ModelCatalog::new(dir.path().to_path_buf());
+        assert_eq!(catalog.lookup("qwen2.5").unwrap().context_limit, 32_768);
+        assert_eq!(
+            ModelConfig::new_with_catalog("qwen2.5".to_string(), &catalog).context_limit(),
+            32_768
+        );
+
+        let catalog = ModelCatalog::in_memory();
+        assert!(catalog.lookup("qwen2.5").is_none());
+        catalog.store("ollama", &[ModelInfo::new("qwen2.5", 8_192)]);
+        assert_eq!(catalog.lookup("qwen2.5").unwrap().context_limit, 8_192);
+        // Built-in models are known without fetching them
+        assert_eq!(catalog.lookup("gpt-4o").unwrap().context_limit, 128_000);
+        Ok(())
+    }
+}
@@ -9,7 +9,7 @@ use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::formats::databricks::{create_request, get_usage, response_to_message};
 use super::oauth;
-use super::utils::{get_model, ImageFormat};
+use super::utils::{get_model, retry_after, ImageFormat};
 use crate::config::ConfigError;
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -181,6 +181,7 @@ impl DatabricksProvider {
             .await?;
 
         let status = response.status();
+        let retry_delay = retry_after(response.headers());
         let payload: Option<Value> = response.json().await.ok();
 
         match status {
@@ -225,7 +226,7 @@ impl DatabricksProvider {
                 Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
             }
             StatusCode::TOO_MANY_REQUESTS => {
-                Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+                Err(ProviderError::RateLimitExceeded { details: format!("{:?}", payload), retry_delay })
             }
             StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
                 Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -0,0 +1,198 @@
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::utils::{emit_debug_trace, get_model, retry_after, ImageFormat};
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::Tool;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::time::Duration;
+use url::Url;
+
+pub const DEEPSEEK_API_HOST: &str = "https://api.deepseek.com";
+pub const DEEPSEEK_DEFAULT_MODEL: &str = "deepseek-chat";
+pub const DEEPSEEK_KNOWN_MODELS: &[&str] = &["deepseek-chat", "deepseek-reasoner"];
+
+pub const DEEPSEEK_DOC_URL: &str = "https://api-docs.deepseek.com/quick_start/pricing";
+
+#[derive(serde::Serialize)]
+pub struct DeepSeekProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    model: ModelConfig,
+}
+
+impl Default for DeepSeekProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(DeepSeekProvider::metadata().default_model);
+        DeepSeekProvider::from_env(model).expect("Failed to initialize DeepSeek provider")
+    }
+}
+
+impl DeepSeekProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("DEEPSEEK_API_KEY")?;
+        let host: String = config
+            .get_param("DEEPSEEK_HOST")
+            .unwrap_or_else(|_| DEEPSEEK_API_HOST.to_string());
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let retry_delay = retry_after(response.headers());
+        let payload: Option<Value> = response.json().await.ok();
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["error"]["message"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        match status {
+            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                    Status: {}. Response: {:?}", status, payload)))
+            }
+            StatusCode::BAD_REQUEST if message.contains("maximum context length") => {
+                Err(ProviderError::ContextLengthExceeded(message))
+            }
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                Err(ProviderError::RequestFailed(format!("{} (status {})", message, status.as_u16())))
+            }
+            StatusCode::PAYMENT_REQUIRED => {
+                Err(ProviderError::RequestFailed(format!("{}. Please top up your DeepSeek account", message)))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded { details: message, retry_delay })
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(format!("{:?}", payload)))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            }
+        }
+    }
+}
+
+/// DeepSeek caches prompts automatically and reports cache hits in its own usage fields
+fn get_deepseek_usage(response: &Value) -> Result<Usage, ProviderError> {
+    let usage = get_usage(response)?;
+    let cache_hit_tokens = response["usage"]["prompt_cache_hit_tokens"]
+        .as_i64()
+        .map(|v| v as i32);
+    Ok(match cache_hit_tokens {
+        Some(tokens) => usage.with_cache_tokens(Some(tokens), None),
+        None => usage,
+    })
+}
+
+#[async_trait]
+impl Provider for DeepSeekProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "deepseek",
+            "DeepSeek",
+            "DeepSeek chat and reasoning models",
+            DEEPSEEK_DEFAULT_MODEL,
+            DEEPSEEK_KNOWN_MODELS.to_vec(),
+            DEEPSEEK_DOC_URL,
+            vec![
+                ConfigKey::new("DEEPSEEK_API_KEY", true, true, None),
+                ConfigKey::new("DEEPSEEK_HOST", false, false, Some(DEEPSEEK_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        // The reasoning of earlier turns is not sent back, deepseek-reasoner rejects it
+        let payload = create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+
+        let response = self.post(payload.clone()).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_deepseek_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_deepseek_usage() -> Result<()> {
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 20,
+                "total_tokens": 120,
+                "prompt_cache_hit_tokens": 64,
+                "prompt_cache_miss_tokens": 36,
+                "completion_tokens_details": {"reasoning_tokens": 12}
+            }
+        });
+        let usage = get_deepseek_usage(&response)?;
+        assert_eq!(usage.input_tokens, Some(100));
+        assert_eq!(usage.output_tokens, Some(20));
+        assert_eq!(usage.cache_read_input_tokens, Some(64));
+        Ok(())
+    }
+}
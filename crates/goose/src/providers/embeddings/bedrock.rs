@@ -0,0 +1,105 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::operation::invoke_model::InvokeModelError;
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::Client;
+use serde_json::{json, Value};
+
+use super::parse_vector;
+use crate::providers::base::EmbeddingProvider;
+use crate::providers::bedrock::bedrock_client;
+use crate::providers::errors::ProviderError;
+
+pub const BEDROCK_DEFAULT_EMBEDDING_MODEL: &str = "amazon.titan-embed-text-v2:0";
+
+/// Embeddings from the Titan and Cohere embedding models on Bedrock, with the AWS settings of
+/// the Bedrock provider
+pub struct BedrockEmbeddingProvider {
+    client: Client,
+    model: String,
+}
+
+impl BedrockEmbeddingProvider {
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        Ok(Self {
+            client: bedrock_client()?,
+            model: model.unwrap_or_else(|| BEDROCK_DEFAULT_EMBEDDING_MODEL.to_string()),
+        })
+    }
+
+    /// Cohere models embed many texts per request, Titan models one
+    fn is_cohere(&self) -> bool {
+        self.model.starts_with("cohere.") || self.model.contains(".cohere.")
+    }
+
+    fn request_body(&self, texts: &[String]) -> Value {
+        if self.is_cohere() {
+            json!({"texts": texts, "input_type": "search_document"})
+        } else {
+            json!({"inputText": texts.first().cloned().unwrap_or_default()})
+        }
+    }
+
+    fn parse_response(&self, response: &Value) -> Result<Vec<Vec<f32>>, ProviderError> {
+        if self.is_cohere() {
+            response["embeddings"]
+                .as_array()
+                .ok_or_else(|| {
+                    ProviderError::RequestFailed("No embeddings in the response".to_string())
+                })?
+                .iter()
+                .map(parse_vector)
+                .collect()
+        } else {
+            Ok(vec![parse_vector(&response["embedding"])?])
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for BedrockEmbeddingProvider {
+    fn model_id(&self) -> String {
+        format!("aws_bedrock/{}", self.model)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        if self.is_cohere() {
+            96
+        } else {
+            1
+        }
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let body = serde_json::to_vec(&self.request_body(texts))
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
+        let response = self
+            .client
+            .invoke_model()
+            .model_id(&self.model)
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(body))
+            .send()
+            .await
+            .map_err(|err| match err.into_service_error() {
+                InvokeModelError::ThrottlingException(err) => {
+                    ProviderError::rate_limit_exceeded(format!("Failed to call Bedrock: {:?}", err))
+                }
+                InvokeModelError::AccessDeniedException(err) => {
+                    ProviderError::Authentication(format!("Failed to call Bedrock: {:?}", err))
+                }
+                InvokeModelError::ValidationException(err) => {
+                    ProviderError::RequestFailed(format!("Failed to call Bedrock: {:?}", err))
+                }
+                InvokeModelError::ResourceNotFoundException(err) => {
+                    ProviderError::RequestFailed(format!("Failed to call Bedrock: {:?}", err))
+                }
+                err => ProviderError::ServerError(format!("Failed to call Bedrock: {:?}", err)),
+            })?;
+
+        let response: Value = serde_json::from_slice(response.body.as_ref())
+            .map_err(|e| ProviderError::RequestFailed(format!("Failed to parse response: {e}")))?;
+        self.parse_response(&response)
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+use super::parse_vector;
+use crate::providers::base::EmbeddingProvider;
+use crate::providers::errors::ProviderError;
+use crate::providers::formats::gcpvertexai::GcpLocation::Iowa;
+use crate::providers::gcpauth::GcpAuth;
+use crate::providers::utils::retry_after;
+
+pub const GCP_VERTEX_AI_DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-005";
+
+/// Embeddings from the Google text embedding models on Vertex AI, with the GCP_* settings of
+/// the Vertex AI provider
+pub struct VertexAIEmbeddingProvider {
+    client: Client,
+    auth: GcpAuth,
+    project_id: String,
+    location: String,
+    model: String,
+}
+
+impl VertexAIEmbeddingProvider {
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        futures::executor::block_on(Self::new_async(model))
+    }
+
+    async fn new_async(model: Option<String>) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let project_id = config.get_param("GCP_PROJECT_ID")?;
+        let location = config
+            .get_param("GCP_LOCATION")
+            .ok()
+            .filter(|location: &String| !location.trim().is_empty())
+            .unwrap_or_else(|| Iowa.to_string());
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            auth: GcpAuth::new().await?,
+            project_id,
+            location,
+            model: model.unwrap_or_else(|| GCP_VERTEX_AI_DEFAULT_EMBEDDING_MODEL.to_string()),
+        })
+    }
+
+    fn url(&self) -> String {
+        format!(
+            "https://{location}-aiplatform.googleapis.com/v1/projects/{}/locations/{location}/publishers/google/models/{}:predict",
+            self.project_id,
+            self.model,
+            location = self.location
+        )
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for VertexAIEmbeddingProvider {
+    fn model_id(&self) -> String {
+        format!("gcp_vertex_ai/{}", self.model)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let token = self
+            .auth
+            .get_token()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+        let instances: Vec<Value> = texts.iter().map(|text| json!({"content": text})).collect();
+        let response = self
+            .client
+            .post(self.url())
+            .header("Authorization", format!("Bearer {}", token.token_value))
+            .json(&json!({"instances": instances}))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ProviderError::RateLimitExceeded {
+                retry_delay: retry_after(response.headers()),
+                details: response.text().await.unwrap_or_default(),
+            });
+        }
+        let response_json = response
+            .json::<Value>()
+            .await
+            .map_err(|e| ProviderError::RequestFailed(format!("Failed to parse response: {e}")))?;
+
+        match status {
+            StatusCode::OK => response_json["predictions"]
+                .as_array()
+                .ok_or_else(|| {
+                    ProviderError::RequestFailed("No predictions in the response".to_string())
+                })?
+                .iter()
+                .map(|prediction| parse_vector(&prediction["embeddings"]["values"]))
+                .collect(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ProviderError::Authentication(
+                format!("Authentication failed: {response_json:?}"),
+            )),
+            _ => Err(ProviderError::RequestFailed(format!(
+                "Request failed with status {status}: {response_json:?}"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::config::Config;
+use crate::providers::base::EmbeddingProvider;
+use crate::providers::errors::ProviderError;
+use crate::providers::llamacpp::{default_models_dir, gguf_path, load_library};
+use crate::providers::llamacpp_ffi::{Context, Model};
+
+pub const LOCAL_DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text-v1.5.Q8_0.gguf";
+/// Longer texts are truncated, embedding models are trained on short passages
+pub const LOCAL_EMBEDDING_CONTEXT_SIZE: u32 = 2048;
+
+/// Embeddings from a GGUF embedding model run in-process with llama.cpp, so nothing leaves
+/// the machine
+///
+/// The model is found like the models of the llama.cpp provider, with LLAMACPP_MODELS_DIR
+/// and LLAMACPP_LIBRARY.
+pub struct LocalEmbeddingProvider {
+    model_path: PathBuf,
+    library_path: Option<PathBuf>,
+    threads: i32,
+    model: String,
+    loaded: tokio::sync::OnceCell<Arc<Model>>,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        let config = Config::global();
+        let models_dir = config
+            .get_param::<String>("LLAMACPP_MODELS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| default_models_dir());
+        let library_path = config
+            .get_param::<String>("LLAMACPP_LIBRARY")
+            .ok()
+            .map(PathBuf::from);
+        let threads = config.get_param("LLAMACPP_THREADS").unwrap_or_else(|_| {
+            std::thread::available_parallelism()
+                .map(|n| n.get() as i32)
+                .unwrap_or(4)
+        });
+        let model = model.unwrap_or_else(|| LOCAL_DEFAULT_EMBEDDING_MODEL.to_string());
+
+        Ok(Self {
+            model_path: gguf_path(&models_dir, &model),
+            library_path,
+            threads,
+            model,
+            loaded: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// Loads the library and the model on first use
+    async fn load(&self) -> Result<Arc<Model>, ProviderError> {
+        self.loaded
+            .get_or_try_init(|| async {
+                let library_path = self.library_path.clone();
+                let model_path = self.model_path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let library = load_library(library_path.as_deref())?;
+                    Ok(Arc::new(Model::load(library, &model_path)?))
+                })
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+                .map_err(|e: anyhow::Error| ProviderError::ExecutionError(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+}
+
+fn embed_texts(model: &Model, texts: &[String], threads: i32) -> Result<Vec<Vec<f32>>> {
+    let mut context = Context::new_embedding(model, LOCAL_EMBEDDING_CONTEXT_SIZE, threads)?;
+    let n_ctx = context.n_ctx();
+    texts
+        .iter()
+        .map(|text| {
+            let mut tokens = model.tokenize(text, true)?;
+            tokens.truncate(n_ctx);
+            context.embed(&mut tokens)
+        })
+        .collect()
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+    fn model_id(&self) -> String {
+        format!("local/{}", self.model)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let model = self.load().await?;
+        let texts = texts.to_vec();
+        let threads = self.threads;
+        tokio::task::spawn_blocking(move || embed_texts(&model, &texts, threads))
+            .await
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))
+    }
+}
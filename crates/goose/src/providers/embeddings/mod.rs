@@ -0,0 +1,279 @@
+//! Embedding providers
+//!
+//! An [`EmbeddingProvider`] turns texts into vectors, so texts can be compared by meaning with
+//! [`cosine_similarity`]. The provider is chosen with `GOOSE_EMBEDDING_PROVIDER` and the model
+//! with `GOOSE_EMBEDDING_MODEL`, see [`create_embedding_provider`]. Vectors are cached on disk
+//! by the hash of the model and the text, so each text is only embedded once.
+
+pub mod bedrock;
+pub mod gcpvertexai;
+pub mod local;
+pub mod ollama;
+pub mod openai;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use etcetera::{choose_app_strategy, AppStrategy};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::base::EmbeddingProvider;
+use super::errors::ProviderError;
+use crate::config::Config;
+use bedrock::BedrockEmbeddingProvider;
+use gcpvertexai::VertexAIEmbeddingProvider;
+use local::LocalEmbeddingProvider;
+use ollama::OllamaEmbeddingProvider;
+use openai::OpenAiEmbeddingProvider;
+
+/// Cosine similarity of two vectors, from -1 for opposite to 1 for the same direction
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Reads a vector from a json array of numbers
+fn parse_vector(value: &Value) -> Result<Vec<f32>, ProviderError> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|v| v.as_f64().map(|v| v as f32))
+                .collect::<Option<Vec<f32>>>()
+        })
+        .ok_or_else(|| {
+            ProviderError::RequestFailed("Embedding is not an array of numbers".to_string())
+        })
+}
+
+/// Stores vectors as little endian f32 files named by the hash of the model and the text
+struct EmbeddingCache {
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    fn key(model_id: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model_id.as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Spread the files over subdirectories, like git objects
+        self.dir.join(&key[..2]).join(&key[2..])
+    }
+
+    fn load(&self, key: &str) -> Option<Vec<f32>> {
+        let bytes = std::fs::read(self.path(key)).ok()?;
+        if bytes.len() % 4 != 0 {
+            return None;
+        }
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        )
+    }
+
+    fn save(&self, key: &str, embedding: &[f32]) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let bytes: Vec<u8> = embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+        // Write beside the entry and rename, so concurrent readers never see a partial file
+        static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+        let tmp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp, bytes)?;
+        if let Err(e) = std::fs::rename(&tmp, &path) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+/// Serves embeddings from the disk cache, and only sends the texts it has not seen before
+/// to the provider
+pub struct CachedEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    cache: EmbeddingCache,
+}
+
+impl CachedEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>) -> Self {
+        let dir = choose_app_strategy(crate::config::APP_STRATEGY.clone())
+            .expect("goose requires a home dir")
+            .in_cache_dir("embeddings");
+        Self {
+            inner,
+            cache: EmbeddingCache { dir },
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CachedEmbeddingProvider {
+    fn model_id(&self) -> String {
+        self.inner.model_id()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.embed(texts).await
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let model_id = self.inner.model_id();
+        let keys: Vec<String> = texts
+            .iter()
+            .map(|text| EmbeddingCache::key(&model_id, text))
+            .collect();
+
+        let mut found: HashMap<&str, Vec<f32>> = HashMap::new();
+        let mut missing = Vec::new();
+        for (key, text) in keys.iter().zip(texts) {
+            if found.contains_key(key.as_str()) {
+                continue;
+            }
+            match self.cache.load(key) {
+                Some(embedding) => {
+                    found.insert(key, embedding);
+                }
+                None => {
+                    // Duplicates are only embedded once
+                    found.insert(key, Vec::new());
+                    missing.push((key.as_str(), text.clone()));
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let missing_texts: Vec<String> = missing.iter().map(|(_, text)| text.clone()).collect();
+            let embeddings = self.inner.embed(&missing_texts).await?;
+            for ((key, _), embedding) in missing.into_iter().zip(embeddings) {
+                if let Err(e) = self.cache.save(key, &embedding) {
+                    tracing::warn!("Failed to cache an embedding: {}", e);
+                }
+                found.insert(key, embedding);
+            }
+        }
+
+        Ok(keys.iter().map(|key| found[key.as_str()].clone()).collect())
+    }
+}
+
+/// Creates the embedding provider configured with GOOSE_EMBEDDING_PROVIDER, if there is one
+///
+/// GOOSE_EMBEDDING_MODEL picks the model, each provider has a default. The provider is wrapped
+/// in a [`CachedEmbeddingProvider`].
+pub fn create_embedding_provider() -> Result<Option<Arc<dyn EmbeddingProvider>>> {
+    let config = Config::global();
+    let Ok(name) = config.get_param::<String>("GOOSE_EMBEDDING_PROVIDER") else {
+        return Ok(None);
+    };
+    let model: Option<String> = config.get_param("GOOSE_EMBEDDING_MODEL").ok();
+
+    let provider: Arc<dyn EmbeddingProvider> = match name.as_str() {
+        "openai" => Arc::new(OpenAiEmbeddingProvider::from_env(model)?),
+        "ollama" => Arc::new(OllamaEmbeddingProvider::from_env(model)?),
+        "aws_bedrock" => Arc::new(BedrockEmbeddingProvider::from_env(model)?),
+        "gcp_vertex_ai" => Arc::new(VertexAIEmbeddingProvider::from_env(model)?),
+        "local" => Arc::new(LocalEmbeddingProvider::from_env(model)?),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown embedding provider: {}, expected one of openai, ollama, aws_bedrock, gcp_vertex_ai or local",
+                name
+            ))
+        }
+    };
+    Ok(Some(Arc::new(CachedEmbeddingProvider::new(provider))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Embeds texts as their length and counts the texts it was asked for
+    struct LengthEmbeddings {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for LengthEmbeddings {
+        fn model_id(&self) -> String {
+            "test/length".to_string()
+        }
+
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+            assert!(texts.len() <= 2);
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_cached_embeddings() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let inner = Arc::new(LengthEmbeddings {
+            embedded: AtomicUsize::new(0),
+        });
+        let cached = CachedEmbeddingProvider {
+            inner: inner.clone(),
+            cache: EmbeddingCache {
+                dir: dir.path().to_path_buf(),
+            },
+        };
+
+        let texts: Vec<String> = ["a", "bb", "a", "ccc", "dddd"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let embeddings = cached.embed(&texts).await?;
+        assert_eq!(embeddings.len(), 5);
+        assert_eq!(embeddings[2], vec![1.0, 1.0]);
+        assert_eq!(embeddings[4], vec![4.0, 1.0]);
+        // The duplicate was embedded once, in batches of two
+        assert_eq!(inner.embedded.load(Ordering::SeqCst), 4);
+
+        let embeddings = cached.embed(&["ccc".to_string(), "e".to_string()]).await?;
+        assert_eq!(embeddings, vec![vec![3.0, 1.0], vec![1.0, 1.0]]);
+        assert_eq!(inner.embedded.load(Ordering::SeqCst), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+
+use super::parse_vector;
+use crate::providers::base::EmbeddingProvider;
+use crate::providers::errors::ProviderError;
+use crate::providers::ollama::{ollama_base_url, OLLAMA_HOST};
+use crate::providers::utils::handle_response_openai_compat;
+
+pub const OLLAMA_DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+/// Embeddings from an Ollama server, found with OLLAMA_HOST like the Ollama provider
+pub struct OllamaEmbeddingProvider {
+    client: Client,
+    host: String,
+    model: String,
+}
+
+impl OllamaEmbeddingProvider {
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let host: String = config
+            .get_param("OLLAMA_HOST")
+            .unwrap_or_else(|_| OLLAMA_HOST.to_string());
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            host,
+            model: model.unwrap_or_else(|| OLLAMA_DEFAULT_EMBEDDING_MODEL.to_string()),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    fn model_id(&self) -> String {
+        format!("ollama/{}", self.model)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let url = ollama_base_url(&self.host)?
+            .join("api/embed")
+            .map_err(|e| ProviderError::RequestFailed(format!("Failed to construct URL: {e}")))?;
+        let response = self
+            .client
+            .post(url)
+            .json(&json!({"model": self.model, "input": texts}))
+            .send()
+            .await?;
+        let response = handle_response_openai_compat(response).await?;
+        response["embeddings"]
+            .as_array()
+            .ok_or_else(|| {
+                ProviderError::RequestFailed("No embeddings in the response".to_string())
+            })?
+            .iter()
+            .map(parse_vector)
+            .collect()
+    }
+}
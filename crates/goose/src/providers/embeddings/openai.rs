@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use super::parse_vector;
+use crate::providers::base::EmbeddingProvider;
+use crate::providers::errors::ProviderError;
+use crate::providers::utils::handle_response_openai_compat;
+
+pub const OPENAI_DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Embeddings from the OpenAI embeddings API, with the OPENAI_* settings of the OpenAI provider
+pub struct OpenAiEmbeddingProvider {
+    client: Client,
+    host: String,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiEmbeddingProvider {
+    pub fn from_env(model: Option<String>) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("OPENAI_API_KEY")?;
+        let host: String = config
+            .get_param("OPENAI_HOST")
+            .unwrap_or_else(|_| "https://api.openai.com".to_string());
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model: model.unwrap_or_else(|| OPENAI_DEFAULT_EMBEDDING_MODEL.to_string()),
+        })
+    }
+}
+
+/// The vectors of an embeddings response, in the order of the inputs
+fn parse_embeddings(response: &Value) -> Result<Vec<Vec<f32>>, ProviderError> {
+    let mut data: Vec<&Value> = response["data"]
+        .as_array()
+        .ok_or_else(|| ProviderError::RequestFailed("No data in the response".to_string()))?
+        .iter()
+        .collect();
+    data.sort_by_key(|item| item["index"].as_u64());
+    data.iter()
+        .map(|item| parse_vector(&item["embedding"]))
+        .collect()
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbeddingProvider {
+    fn model_id(&self) -> String {
+        format!("openai/{}", self.model)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        512
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let url = url::Url::parse(&self.host)
+            .and_then(|base| base.join("v1/embeddings"))
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&json!({"model": self.model, "input": texts}))
+            .send()
+            .await?;
+        parse_embeddings(&handle_response_openai_compat(response).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_embeddings() {
+        let response = json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.5, -1.0]},
+                {"object": "embedding", "index": 0, "embedding": [0.25, 2.0]}
+            ],
+            "model": "text-embedding-3-small"
+        });
+        assert_eq!(
+            parse_embeddings(&response).unwrap(),
+            vec![vec![0.25, 2.0], vec![0.5, -1.0]]
+        );
+        assert!(parse_embeddings(&json!({"error": "bad"})).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use reqwest::StatusCode;
 use thiserror::Error;
 
@@ -9,8 +11,12 @@ pub enum ProviderError {
     #[error("Context length exceeded: {0}")]
     ContextLengthExceeded(String),
 
-    #[error("Rate limit exceeded: {0}")]
-    RateLimitExceeded(String),
+    #[error("Rate limit exceeded: {details}")]
+    RateLimitExceeded {
+        details: String,
+        /// How long the provider asked to wait before retrying
+        retry_delay: Option<Duration>,
+    },
 
     #[error("Server error: {0}")]
     ServerError(String),
@@ -18,6 +24,9 @@ pub enum ProviderError {
     #[error("Request failed: {0}")]
     RequestFailed(String),
 
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
     #[error("Execution error: {0}")]
     ExecutionError(String),
 
@@ -25,6 +34,33 @@ pub enum ProviderError {
     UsageError(String),
 }
 
+impl ProviderError {
+    pub fn rate_limit_exceeded(details: impl Into<String>) -> Self {
+        ProviderError::RateLimitExceeded {
+            details: details.into(),
+            retry_delay: None,
+        }
+    }
+
+    /// Whether the same request may succeed when it is sent again
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ProviderError::RateLimitExceeded { .. }
+                | ProviderError::ServerError(_)
+                | ProviderError::NetworkError(_)
+        )
+    }
+
+    /// How long the provider asked to wait before retrying, if it said
+    pub fn retry_delay(&self) -> Option<Duration> {
+        match self {
+            ProviderError::RateLimitExceeded { retry_delay, .. } => *retry_delay,
+            _ => None,
+        }
+    }
+}
+
 impl From<anyhow::Error> for ProviderError {
     fn from(error: anyhow::Error) -> Self {
         ProviderError::ExecutionError(error.to_string())
@@ -33,7 +69,11 @@ impl From<anyhow::Error> for ProviderError {
 
 impl From<reqwest::Error> for ProviderError {
     fn from(error: reqwest::Error) -> Self {
-        ProviderError::ExecutionError(error.to_string())
+        if error.is_timeout() || error.is_connect() {
+            ProviderError::NetworkError(error.to_string())
+        } else {
+            ProviderError::ExecutionError(error.to_string())
+        }
     }
 }
 
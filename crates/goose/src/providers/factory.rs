@@ -5,37 +5,80 @@ use super::{
     azure::AzureProvider,
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
+    catalog,
     databricks::DatabricksProvider,
+    deepseek::DeepSeekProvider,
     gcpvertexai::GcpVertexAIProvider,
     githubcopilot::GithubCopilotProvider,
     google::GoogleProvider,
     groq::GroqProvider,
+    lead_worker::LeadWorkerProvider,
+    llamacpp::LlamaCppProvider,
+    mistral::MistralProvider,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
+    openai_compatible::{CustomProviderConfig, OpenAiCompatibleProvider},
     openrouter::OpenRouterProvider,
+    retry::{RetryConfig, RetryProvider},
     venice::VeniceProvider,
+    xai::XaiProvider,
 };
 use crate::model::ModelConfig;
 use anyhow::Result;
 
+/// Metadata of the built-in providers followed by the ones declared in the config file
 pub fn providers() -> Vec<ProviderMetadata> {
-    vec![
+    let mut providers = vec![
         AnthropicProvider::metadata(),
         AzureProvider::metadata(),
         BedrockProvider::metadata(),
         DatabricksProvider::metadata(),
+        DeepSeekProvider::metadata(),
         GcpVertexAIProvider::metadata(),
         GithubCopilotProvider::metadata(),
         GoogleProvider::metadata(),
         GroqProvider::metadata(),
+        LlamaCppProvider::metadata(),
+        MistralProvider::metadata(),
         OllamaProvider::metadata(),
         OpenAiProvider::metadata(),
         OpenRouterProvider::metadata(),
         VeniceProvider::metadata(),
-    ]
+        XaiProvider::metadata(),
+    ];
+    match CustomProviderConfig::load_all() {
+        Ok(custom) => {
+            for provider in custom {
+                if providers.iter().any(|p| p.name == provider.name) {
+                    tracing::warn!(
+                        "Custom provider {} has the name of a built-in provider, skipping it",
+                        provider.name
+                    );
+                    continue;
+                }
+                providers.push(provider.metadata());
+            }
+        }
+        Err(e) => tracing::warn!("Failed to load custom providers: {}", e),
+    }
+    providers
 }
 
+/// Creates a provider, failed requests are retried as configured in `GOOSE_PROVIDER_MAX_RETRIES`
+/// and the related settings, see [`RetryConfig::from_config`]
 pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let retry_config = match name {
+        "aws_bedrock" => BedrockProvider::retry_config(),
+        "gcp_vertex_ai" => GcpVertexAIProvider::retry_config(),
+        _ => RetryConfig::from_config("GOOSE_PROVIDER", RetryConfig::default()),
+    };
+    Ok(Arc::new(RetryProvider::new(
+        create_without_retry(name, model)?,
+        retry_config,
+    )))
+}
+
+fn create_without_retry(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     // We use Arc instead of Box to be able to clone for multiple async tasks
     match name {
         "openai" => Ok(Arc::new(OpenAiProvider::from_env(model)?)),
@@ -45,11 +88,32 @@ pub fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
         "databricks" => Ok(Arc::new(DatabricksProvider::from_env(model)?)),
         "groq" => Ok(Arc::new(GroqProvider::from_env(model)?)),
         "ollama" => Ok(Arc::new(OllamaProvider::from_env(model)?)),
+        "llamacpp" => Ok(Arc::new(LlamaCppProvider::from_env(model)?)),
         "openrouter" => Ok(Arc::new(OpenRouterProvider::from_env(model)?)),
         "gcp_vertex_ai" => Ok(Arc::new(GcpVertexAIProvider::from_env(model)?)),
         "google" => Ok(Arc::new(GoogleProvider::from_env(model)?)),
         "venice" => Ok(Arc::new(VeniceProvider::from_env(model)?)),
         "github_copilot" => Ok(Arc::new(GithubCopilotProvider::from_env(model)?)),
-        _ => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        "mistral" => Ok(Arc::new(MistralProvider::from_env(model)?)),
+        "deepseek" => Ok(Arc::new(DeepSeekProvider::from_env(model)?)),
+        "xai" => Ok(Arc::new(XaiProvider::from_env(model)?)),
+        _ => match CustomProviderConfig::find(name)? {
+            Some(provider) => Ok(Arc::new(OpenAiCompatibleProvider::new(provider, model)?)),
+            None => Err(anyhow::anyhow!("Unknown provider: {}", name)),
+        },
+    }
+}
+
+/// Creates the provider an agent runs with
+///
+/// When a lead model is configured, the model is paired with it as the worker model, see
+/// [`LeadWorkerProvider`].
+pub fn create_with_lead(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
+    let worker = create(name, model)?;
+    // Keeps the model catalog current for the context limits of later sessions
+    catalog::refresh_in_background(name, worker.clone());
+    match LeadWorkerProvider::from_config(name, worker.clone())? {
+        Some(lead_worker) => Ok(Arc::new(lead_worker)),
+        None => Ok(worker),
     }
 }
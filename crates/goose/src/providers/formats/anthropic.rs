@@ -1,6 +1,7 @@
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::base::Usage;
+use crate::providers::cache::{mark_cacheable, CachePlan};
 use crate::providers::errors::ProviderError;
 use anyhow::{anyhow, Result};
 use mcp_core::content::Content;
@@ -66,6 +67,8 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                // Thinking of other providers has no signature and would be rejected
+                MessageContent::Thinking(thinking) if thinking.signature.is_empty() => continue,
                 MessageContent::Thinking(thinking) => {
                     content.push(json!({
                         "type": "thinking",
@@ -113,30 +116,6 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
         }));
     }
 
-    // Add "cache_control" to the last and second-to-last "user" messages.
-    // During each turn, we mark the final message with cache_control so the conversation can be
-    // incrementally cached. The second-to-last user message is also marked for caching with the
-    // cache_control parameter, so that this checkpoint can read from the previous cache.
-    let mut user_count = 0;
-    for message in anthropic_messages.iter_mut().rev() {
-        if message.get("role") == Some(&json!("user")) {
-            if let Some(content) = message.get_mut("content") {
-                if let Some(content_array) = content.as_array_mut() {
-                    if let Some(last_content) = content_array.last_mut() {
-                        last_content
-                            .as_object_mut()
-                            .unwrap()
-                            .insert("cache_control".to_string(), json!({ "type": "ephemeral" }));
-                    }
-                }
-            }
-            user_count += 1;
-            if user_count >= 2 {
-                break;
-            }
-        }
-    }
-
     anthropic_messages
 }
 
@@ -155,15 +134,6 @@ pub fn format_tools(tools: &[Tool]) -> Vec<Value> {
         }
     }
 
-    // Add "cache_control" to the last tool spec, if any. This means that all tool definitions,
-    // will be cached as a single prefix.
-    if let Some(last_tool) = tool_specs.last_mut() {
-        last_tool
-            .as_object_mut()
-            .unwrap()
-            .insert("cache_control".to_string(), json!({ "type": "ephemeral" }));
-    }
-
     tool_specs
 }
 
@@ -171,8 +141,7 @@ pub fn format_tools(tools: &[Tool]) -> Vec<Value> {
 pub fn format_system(system: &str) -> Value {
     json!([{
         "type": "text",
-        "text": system,
-        "cache_control": { "type": "ephemeral" }
+        "text": system
     }])
 }
 
@@ -263,7 +232,13 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
 
         let total_tokens = output_tokens.map(|o| total_input_tokens as i32 + o);
 
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        let cache_tokens = |key: &str| usage.get(key).and_then(|v| v.as_u64()).map(|v| v as i32);
+        Ok(
+            Usage::new(input_tokens, output_tokens, total_tokens).with_cache_tokens(
+                cache_tokens("cache_read_input_tokens"),
+                cache_tokens("cache_creation_input_tokens"),
+            ),
+        )
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -274,6 +249,13 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// Whether a Claude model can think before it answers, the name may have a provider prefix
+pub fn supports_extended_thinking(model_name: &str) -> bool {
+    ["claude-3-7-sonnet", "claude-sonnet-4", "claude-opus-4"]
+        .iter()
+        .any(|family| model_name.contains(family))
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -315,26 +297,35 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tool_specs));
     }
 
-    // Add temperature if specified and not using extended thinking model
-    if let Some(temp) = model_config.temperature {
-        // Claude 3.7 models with thinking enabled don't support temperature
-        if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+    let thinking_budget = model_config
+        .effective_thinking_budget()
+        .filter(|_| supports_extended_thinking(&model_config.model_name));
+
+    // Extended thinking doesn't support temperature or top_p
+    if thinking_budget.is_none() {
+        if let Some(temp) = model_config.temperature {
             payload
                 .as_object_mut()
                 .unwrap()
                 .insert("temperature".to_string(), json!(temp));
         }
+        if let Some(top_p) = model_config.top_p {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("top_p".to_string(), json!(top_p));
+        }
     }
 
-    // Add thinking parameters for claude-3-7-sonnet model
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
+    if let Some(sequences) = &model_config.stop_sequences {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stop_sequences".to_string(), json!(sequences));
+    }
 
+    // The thinking budget is part of max_tokens, add it so the answer keeps its own budget
+    if let Some(budget_tokens) = thinking_budget {
         payload
             .as_object_mut()
             .unwrap()
@@ -349,12 +340,58 @@ pub fn create_request(
         );
     }
 
+    apply_cache_plan(&mut payload, CachePlan::from_config());
     Ok(payload)
 }
 
+/// Marks the cacheable parts of a request with `cache_control`
+///
+/// The marker goes on the system prompt, the last tool, which caches all tool definitions, and
+/// the last block of the latest user messages.
+pub fn apply_cache_plan(payload: &mut Value, plan: CachePlan) {
+    if plan.system {
+        if let Some(system) = payload
+            .get_mut("system")
+            .and_then(|system| system.as_array_mut())
+            .and_then(|blocks| blocks.last_mut())
+        {
+            mark_cacheable(system);
+        }
+    }
+
+    if plan.tools {
+        if let Some(last_tool) = payload
+            .get_mut("tools")
+            .and_then(|tools| tools.as_array_mut())
+            .and_then(|tools| tools.last_mut())
+        {
+            mark_cacheable(last_tool);
+        }
+    }
+
+    if let Some(messages) = payload
+        .get_mut("messages")
+        .and_then(|messages| messages.as_array_mut())
+    {
+        let breakpoints = plan.conversation_breakpoints(messages, |message| {
+            message.get("role") == Some(&json!("user"))
+        });
+        for index in breakpoints {
+            if let Some(block) = messages[index]
+                .get_mut("content")
+                .and_then(|content| content.as_array_mut())
+                .and_then(|content| content.last_mut())
+            {
+                mark_cacheable(block);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::ReasoningEffort;
     use serde_json::json;
 
     #[test]
@@ -571,9 +608,6 @@ mod tests {
         assert_eq!(spec[0]["description"], "Calculate mathematical expressions");
         assert_eq!(spec[1]["name"], "weather");
         assert_eq!(spec[1]["description"], "Get weather information");
-
-        // Verify cache control is added to last tool
-        assert!(spec[1].get("cache_control").is_some());
     }
 
     #[test]
@@ -586,7 +620,57 @@ mod tests {
         assert_eq!(spec_array.len(), 1);
         assert_eq!(spec_array[0]["type"], "text");
         assert_eq!(spec_array[0]["text"], system);
-        assert!(spec_array[0].get("cache_control").is_some());
+    }
+
+    #[test]
+    fn test_apply_cache_plan() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("reply"),
+            Message::user().with_text("second"),
+            Message::assistant().with_text("reply"),
+            Message::user().with_text("third"),
+        ];
+        let tools = vec![
+            Tool::new("a", "first tool", json!({"type": "object"}), None),
+            Tool::new("b", "second tool", json!({"type": "object"}), None),
+        ];
+        let model_config = ModelConfig::new("claude-3-5-sonnet-latest".to_string());
+        let mut payload = create_request(&model_config, "system", &messages, &tools)?;
+
+        apply_cache_plan(&mut payload, CachePlan::all());
+        let cached = |block: &Value| block.get("cache_control").is_some();
+        assert!(cached(&payload["system"][0]));
+        assert!(!cached(&payload["tools"][0]));
+        assert!(cached(&payload["tools"][1]));
+        let cached_messages: Vec<bool> = payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| cached(&message["content"][0]))
+            .collect();
+        assert_eq!(cached_messages, vec![false, false, true, false, true]);
+
+        let mut payload = json!({"system": format_system("system"), "tools": format_tools(&tools), "messages": format_messages(&messages)});
+        apply_cache_plan(&mut payload, CachePlan::none());
+        assert!(!payload.to_string().contains("cache_control"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_usage_with_cache() -> Result<()> {
+        let data = json!({"usage": {
+            "input_tokens": 10,
+            "cache_creation_input_tokens": 100,
+            "cache_read_input_tokens": 1000,
+            "output_tokens": 5
+        }});
+        let usage = get_usage(&data)?;
+        assert_eq!(usage.input_tokens, Some(1110));
+        assert_eq!(usage.total_tokens, Some(1115));
+        assert_eq!(usage.cache_read_input_tokens, Some(1000));
+        assert_eq!(usage.cache_creation_input_tokens, Some(100));
+        Ok(())
     }
 
     #[test]
@@ -599,7 +683,8 @@ mod tests {
 
         // Execute the test
         let result = (|| {
-            let model_config = ModelConfig::new("claude-3-7-sonnet-20250219".to_string());
+            let model_config = ModelConfig::new("claude-3-7-sonnet-20250219".to_string())
+                .with_configured_overrides();
             let system = "You are a helpful assistant.";
             let messages = vec![Message::user().with_text("Hello")];
             let tools = vec![];
@@ -631,4 +716,38 @@ mod tests {
         // Return the test result
         result
     }
+
+    #[test]
+    fn test_create_request_with_model_settings() -> Result<()> {
+        let messages = vec![Message::user().with_text("Hello")];
+        let model_config = ModelConfig::new("claude-sonnet-4-20250514".to_string())
+            .with_temperature(Some(0.5))
+            .with_top_p(Some(0.9))
+            .with_stop_sequences(Some(vec!["END".to_string()]))
+            .with_max_tokens(Some(1000))
+            .with_thinking_budget(None)
+            .with_reasoning_effort(None);
+
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        assert_eq!(payload["temperature"], json!(0.5));
+        assert_eq!(payload["top_p"], json!(0.9f32));
+        assert_eq!(payload["stop_sequences"], json!(["END"]));
+        assert!(payload.get("thinking").is_none());
+
+        let model_config = model_config.with_reasoning_effort(Some(ReasoningEffort::Low));
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        assert_eq!(payload["thinking"]["budget_tokens"], json!(4_000));
+        assert_eq!(payload["max_tokens"], json!(5_000));
+        assert!(payload.get("temperature").is_none());
+        assert!(payload.get("top_p").is_none());
+
+        // Models without extended thinking ignore the setting
+        let model_config = ModelConfig {
+            model_name: "claude-3-5-haiku-latest".to_string(),
+            ..model_config
+        };
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        assert!(payload.get("thinking").is_none());
+        Ok(())
+    }
 }
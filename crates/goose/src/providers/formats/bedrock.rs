@@ -6,10 +6,32 @@ use aws_sdk_bedrockruntime::types as bedrock;
 use aws_smithy_types::{Document, Number};
 use chrono::Utc;
 use mcp_core::{Content, ResourceContents, Role, Tool, ToolCall, ToolError, ToolResult};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use super::super::base::Usage;
+use super::super::cache::CachePlan;
+use super::anthropic::supports_extended_thinking;
 use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// The messages of a request, with a cache point closing the latest user messages when the plan
+/// caches the conversation
+pub fn to_bedrock_messages(messages: &[Message], plan: CachePlan) -> Result<Vec<bedrock::Message>> {
+    let breakpoints = plan.conversation_breakpoints(messages, |message| message.role == Role::User);
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let mut message = to_bedrock_message(message)?;
+            if breakpoints.contains(&index) {
+                message
+                    .content
+                    .push(bedrock::ContentBlock::CachePoint(cache_point()?));
+            }
+            Ok(message)
+        })
+        .collect()
+}
 
 pub fn to_bedrock_message(message: &Message) -> Result<bedrock::Message> {
     bedrock::Message::builder()
@@ -136,11 +158,44 @@ pub fn to_bedrock_role(role: &Role) -> bedrock::ConversationRole {
     }
 }
 
-pub fn to_bedrock_tool_config(tools: &[Tool]) -> Result<bedrock::ToolConfiguration> {
+/// The system prompt, followed by a cache point when the plan caches it
+pub fn to_bedrock_system(
+    system: &str,
+    plan: CachePlan,
+) -> Result<Vec<bedrock::SystemContentBlock>> {
+    let mut blocks = vec![bedrock::SystemContentBlock::Text(system.to_string())];
+    if plan.system {
+        blocks.push(bedrock::SystemContentBlock::CachePoint(cache_point()?));
+    }
+    Ok(blocks)
+}
+
+/// The tool definitions, followed by a cache point when the plan caches them
+pub fn to_bedrock_tool_config(
+    tools: &[Tool],
+    plan: CachePlan,
+) -> Result<bedrock::ToolConfiguration> {
+    let mut tools = tools
+        .iter()
+        .map(to_bedrock_tool)
+        .collect::<Result<Vec<_>>>()?;
+    if plan.tools {
+        tools.push(bedrock::Tool::CachePoint(cache_point()?));
+    }
     Ok(bedrock::ToolConfiguration::builder()
-        .set_tools(Some(
-            tools.iter().map(to_bedrock_tool).collect::<Result<_>>()?,
-        ))
+        .set_tools(Some(tools))
+        .build()?)
+}
+
+/// Bedrock only caches prompts of Claude and Nova models, and rejects cache points for the others
+pub fn supports_cache_points(model_name: &str) -> bool {
+    model_name.contains("anthropic.claude") || model_name.contains("amazon.nova")
+}
+
+/// Marks everything before it in the request as cacheable
+fn cache_point() -> Result<bedrock::CachePointBlock> {
+    Ok(bedrock::CachePointBlock::builder()
+        .r#type(bedrock::CachePointType::Default)
         .build()?)
 }
 
@@ -156,6 +211,37 @@ pub fn to_bedrock_tool(tool: &Tool) -> Result<bedrock::Tool> {
     ))
 }
 
+/// The thinking budget, for Claude models that can think before they answer
+fn thinking_budget(model_config: &ModelConfig) -> Option<i32> {
+    model_config
+        .effective_thinking_budget()
+        .filter(|_| supports_extended_thinking(&model_config.model_name))
+}
+
+pub fn to_bedrock_inference_config(model_config: &ModelConfig) -> bedrock::InferenceConfiguration {
+    let config = bedrock::InferenceConfiguration::builder()
+        .set_stop_sequences(model_config.stop_sequences.clone());
+    match thinking_budget(model_config) {
+        // Extended thinking doesn't support temperature or top_p, and the budget is part of
+        // max_tokens, so it is added to keep the answer's own budget
+        Some(budget) => config.max_tokens(model_config.max_tokens.unwrap_or(8192) + budget),
+        None => config
+            .set_max_tokens(model_config.max_tokens)
+            .set_temperature(model_config.temperature)
+            .set_top_p(model_config.top_p),
+    }
+    .build()
+}
+
+/// Model-specific request fields, such as the thinking budget of Claude models
+pub fn to_bedrock_additional_fields(model_config: &ModelConfig) -> Option<Document> {
+    thinking_budget(model_config).map(|budget| {
+        to_bedrock_json(&json!({
+            "thinking": {"type": "enabled", "budget_tokens": budget}
+        }))
+    })
+}
+
 pub fn to_bedrock_json(value: &Value) -> Document {
     match value {
         Value::Null => Document::Null,
@@ -285,12 +371,16 @@ pub fn from_bedrock_role(role: &bedrock::ConversationRole) -> Result<Role> {
     })
 }
 
+/// Bedrock counts the tokens read from and written to the cache apart from the input tokens
 pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
-    Usage {
-        input_tokens: Some(usage.input_tokens),
-        output_tokens: Some(usage.output_tokens),
-        total_tokens: Some(usage.total_tokens),
-    }
+    let cache_read = usage.cache_read_input_tokens;
+    let cache_write = usage.cache_write_input_tokens;
+    Usage::new(
+        Some(usage.input_tokens + cache_read.unwrap_or(0) + cache_write.unwrap_or(0)),
+        Some(usage.output_tokens),
+        Some(usage.total_tokens),
+    )
+    .with_cache_tokens(cache_read, cache_write)
 }
 
 pub fn from_bedrock_json(document: &Document) -> Result<Value> {
@@ -315,3 +405,42 @@ pub fn from_bedrock_json(document: &Document) -> Result<Value> {
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_points() -> Result<()> {
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("reply"),
+            Message::user().with_text("second"),
+        ];
+        let tools = vec![Tool::new(
+            "read",
+            "Reads a file",
+            json!({"type": "object"}),
+            None,
+        )];
+
+        let converted = to_bedrock_messages(&messages, CachePlan::all())?;
+        for index in [0, 2] {
+            assert!(converted[index].content.last().unwrap().is_cache_point());
+        }
+        assert_eq!(converted[1].content.len(), 1);
+        assert!(to_bedrock_system("system", CachePlan::all())?[1].is_cache_point());
+        assert!(to_bedrock_tool_config(&tools, CachePlan::all())?.tools[1].is_cache_point());
+
+        let converted = to_bedrock_messages(&messages, CachePlan::none())?;
+        assert!(converted.iter().all(|message| message.content.len() == 1));
+        assert_eq!(to_bedrock_system("system", CachePlan::none())?.len(), 1);
+        assert_eq!(
+            to_bedrock_tool_config(&tools, CachePlan::none())?
+                .tools
+                .len(),
+            1
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 use crate::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::model::{ModelConfig, ReasoningEffort};
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
+use crate::providers::formats::anthropic::supports_extended_thinking;
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file,
     sanitize_function_name, ImageFormat,
@@ -389,7 +390,17 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    // Prompts are cached automatically, the usage reports how much was read from the cache
+    let cached_tokens = usage
+        .get("prompt_tokens_details")
+        .and_then(|details| details.get("cached_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    Ok(
+        Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cached_tokens, None),
+    )
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -454,7 +465,10 @@ pub fn create_request(
     let model_name = model_config.model_name.to_string();
     let is_o1 = model_name.starts_with("o1") || model_name.starts_with("goose-o1");
     let is_o3 = model_name.starts_with("o3") || model_name.starts_with("goose-o3");
-    let is_claude_3_7_sonnet = model_name.contains("claude-3-7-sonnet"); // can be goose- or databricks-
+    // Claude model names can have a goose- or databricks- prefix
+    let thinking_budget = model_config
+        .effective_thinking_budget()
+        .filter(|_| supports_extended_thinking(&model_name));
 
     // Only extract reasoning effort for O1/O3 models
     let (model_name, reasoning_effort) = if is_o1 || is_o3 {
@@ -468,7 +482,12 @@ pub fn create_request(
             }
             _ => (
                 model_config.model_name.to_string(),
-                Some("medium".to_string()),
+                Some(
+                    model_config
+                        .reasoning_effort
+                        .unwrap_or(ReasoningEffort::Medium)
+                        .to_string(),
+                ),
             ),
         }
     } else {
@@ -513,15 +532,20 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tools_spec));
     }
 
-    // Add thinking parameters for Claude 3.7 Sonnet model when requested
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if is_claude_3_7_sonnet && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
+    // o1, o3 models currently don't support stop sequences
+    if let Some(sequences) = model_config
+        .stop_sequences
+        .as_ref()
+        .filter(|_| !is_o1 && !is_o3)
+    {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stop".to_string(), json!(sequences));
+    }
 
+    // Add thinking parameters for Claude models when requested
+    if let Some(budget_tokens) = thinking_budget {
         // For Claude models with thinking enabled, we need to add max_tokens + budget_tokens
         // Default to 8192 (Claude max output) + budget if not specified
         let max_completion_tokens = model_config.max_tokens.unwrap_or(8192);
@@ -544,7 +568,7 @@ pub fn create_request(
             .unwrap()
             .insert("temperature".to_string(), json!(2));
     } else {
-        // o1, o3 models currently don't support temperature
+        // o1, o3 models currently don't support temperature or top_p
         if !is_o1 && !is_o3 {
             if let Some(temp) = model_config.temperature {
                 payload
@@ -552,6 +576,12 @@ pub fn create_request(
                     .unwrap()
                     .insert("temperature".to_string(), json!(temp));
             }
+            if let Some(top_p) = model_config.top_p {
+                payload
+                    .as_object_mut()
+                    .unwrap()
+                    .insert("top_p".to_string(), json!(top_p));
+            }
         }
 
         // o1 models use max_completion_tokens instead of max_tokens
@@ -980,6 +1010,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1012,6 +1046,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1045,6 +1083,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
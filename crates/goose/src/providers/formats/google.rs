@@ -274,7 +274,13 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             .get("totalTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as i32);
-        Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+        // Gemini caches prompts implicitly and reports the tokens it read from the cache
+        let cached_tokens = usage_meta_data
+            .get("cachedContentTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as i32);
+        Ok(Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cached_tokens, None))
     } else {
         tracing::debug!(
             "Failed to get usage data: {}",
@@ -311,6 +317,25 @@ pub fn create_request(
     if let Some(tokens) = model_config.max_tokens {
         generation_config.insert("maxOutputTokens".to_string(), json!(tokens));
     }
+    if let Some(top_p) = model_config.top_p {
+        generation_config.insert("topP".to_string(), json!(top_p));
+    }
+    if let Some(sequences) = &model_config.stop_sequences {
+        generation_config.insert("stopSequences".to_string(), json!(sequences));
+    }
+    // Only Gemini 2.5 models think, and take a budget rather than an effort
+    let is_thinking_model = ["gemini-2.5", "gemini-2-5"]
+        .iter()
+        .any(|family| model_config.model_name.contains(family));
+    if let Some(budget) = model_config
+        .effective_thinking_budget()
+        .filter(|_| is_thinking_model)
+    {
+        generation_config.insert(
+            "thinkingConfig".to_string(),
+            json!({"thinkingBudget": budget}),
+        );
+    }
     if !generation_config.is_empty() {
         payload.insert("generationConfig".to_string(), json!(generation_config));
     }
@@ -321,6 +346,7 @@ pub fn create_request(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::ReasoningEffort;
     use serde_json::json;
 
     fn set_up_text_message(text: &str, role: Role) -> Message {
@@ -628,4 +654,30 @@ mod tests {
 
         assert_eq!(payload, expected_payload);
     }
+
+    #[test]
+    fn test_create_request_model_settings() -> Result<()> {
+        let messages = vec![set_up_text_message("Hello", Role::User)];
+        let model_config = ModelConfig::new("gemini-2.5-flash".to_string())
+            .with_top_p(Some(0.5))
+            .with_stop_sequences(Some(vec!["END".to_string()]))
+            .with_thinking_budget(None)
+            .with_reasoning_effort(Some(ReasoningEffort::High));
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+        let generation_config = &payload["generationConfig"];
+        assert_eq!(generation_config["topP"], json!(0.5));
+        assert_eq!(generation_config["stopSequences"], json!(["END"]));
+        assert_eq!(
+            generation_config["thinkingConfig"]["thinkingBudget"],
+            json!(32_000)
+        );
+
+        let model_config = ModelConfig {
+            model_name: "gemini-2.0-flash".to_string(),
+            ..model_config
+        };
+        let payload = create_request(&model_config, "system", &messages, &[])?;
+        assert!(payload["generationConfig"].get("thinkingConfig").is_none());
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 use crate::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::model::{ModelConfig, ReasoningEffort};
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{
@@ -224,12 +224,42 @@ pub fn response_to_message(response: Value) -> anyhow::Result<Message> {
     let original = response["choices"][0]["message"].clone();
     let mut content = Vec::new();
 
-    if let Some(text) = original.get("content") {
-        if let Some(text_str) = text.as_str() {
-            content.push(MessageContent::text(text_str));
+    // DeepSeek and xAI return the reasoning of their reasoning models next to the content
+    if let Some(reasoning) = original.get("reasoning_content").and_then(|r| r.as_str()) {
+        if !reasoning.is_empty() {
+            content.push(MessageContent::thinking(reasoning, ""));
         }
     }
 
+    match original.get("content") {
+        Some(Value::String(text)) => content.push(MessageContent::text(text)),
+        // Mistral returns a list of chunks, with thinking chunks for its reasoning models
+        Some(Value::Array(chunks)) => {
+            for chunk in chunks {
+                match chunk["type"].as_str() {
+                    Some("text") => {
+                        content.push(MessageContent::text(
+                            chunk["text"].as_str().unwrap_or_default(),
+                        ));
+                    }
+                    Some("thinking") => {
+                        let thinking = match &chunk["thinking"] {
+                            Value::String(text) => text.clone(),
+                            Value::Array(parts) => parts
+                                .iter()
+                                .filter_map(|part| part["text"].as_str())
+                                .collect(),
+                            _ => String::new(),
+                        };
+                        content.push(MessageContent::thinking(thinking, ""));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
             for tool_call in tool_calls_array {
@@ -305,7 +335,17 @@ pub fn get_usage(data: &Value) -> Result<Usage, ProviderError> {
             _ => None,
         });
 
-    Ok(Usage::new(input_tokens, output_tokens, total_tokens))
+    // Prompts are cached automatically, the usage reports how much was read from the cache
+    let cached_tokens = usage
+        .get("prompt_tokens_details")
+        .and_then(|details| details.get("cached_tokens"))
+        .and_then(|v| v.as_i64())
+        .map(|v| v as i32);
+
+    Ok(
+        Usage::new(input_tokens, output_tokens, total_tokens)
+            .with_cache_tokens(cached_tokens, None),
+    )
 }
 
 /// Validates and fixes tool schemas to ensure they have proper parameter structure.
@@ -354,6 +394,27 @@ fn ensure_valid_json_schema(schema: &mut Value) {
     }
 }
 
+/// Constrains the reply of a chat request to JSON that follows the schema
+pub fn set_response_schema(payload: &mut Value, schema: &Value) {
+    payload["response_format"] = json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "response",
+            "schema": schema
+        }
+    });
+}
+
+/// Parses the reply of a chat request that was constrained with a response schema
+pub fn response_to_json(response: &Value) -> Result<Value, ProviderError> {
+    let content = response["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or_default();
+    serde_json::from_str(content).map_err(|e| {
+        ProviderError::ExecutionError(format!("Structured reply is not valid JSON: {}", e))
+    })
+}
+
 pub fn create_request(
     model_config: &ModelConfig,
     system: &str,
@@ -381,7 +442,12 @@ pub fn create_request(
             }
             _ => (
                 model_config.model_name.to_string(),
-                Some("medium".to_string()),
+                Some(
+                    model_config
+                        .reasoning_effort
+                        .unwrap_or(ReasoningEffort::Medium)
+                        .to_string(),
+                ),
             ),
         }
     } else {
@@ -425,7 +491,7 @@ pub fn create_request(
             .unwrap()
             .insert("tools".to_string(), json!(tools_spec));
     }
-    // o1, o3 models currently don't support temperature
+    // o1, o3 models currently don't support temperature, top_p or stop sequences
     if !is_ox_model {
         if let Some(temp) = model_config.temperature {
             payload
@@ -433,6 +499,18 @@ pub fn create_request(
                 .unwrap()
                 .insert("temperature".to_string(), json!(temp));
         }
+        if let Some(top_p) = model_config.top_p {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("top_p".to_string(), json!(top_p));
+        }
+        if let Some(sequences) = &model_config.stop_sequences {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("stop".to_string(), json!(sequences));
+        }
     }
 
     // o1 models use max_completion_tokens instead of max_tokens
@@ -456,6 +534,21 @@ mod tests {
     use mcp_core::content::Content;
     use serde_json::json;
 
+    #[test]
+    fn test_get_usage_with_cached_tokens() -> anyhow::Result<()> {
+        let data = json!({"usage": {
+            "prompt_tokens": 2000,
+            "completion_tokens": 50,
+            "total_tokens": 2050,
+            "prompt_tokens_details": {"cached_tokens": 1920}
+        }});
+        let usage = get_usage(&data)?;
+        assert_eq!(usage.input_tokens, Some(2000));
+        assert_eq!(usage.cache_read_input_tokens, Some(1920));
+        assert_eq!(usage.cache_creation_input_tokens, None);
+        Ok(())
+    }
+
     #[test]
     fn test_validate_tool_schemas() {
         // Test case 1: Empty parameters object
@@ -768,6 +861,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_response_to_message_reasoning() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "The user greets me.",
+                    "content": "Hello!"
+                }
+            }]
+        });
+        let message = response_to_message(response)?;
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(
+            message.content[0].as_thinking().unwrap().thinking,
+            "The user greets me."
+        );
+        assert_eq!(message.content[1].as_text(), Some("Hello!"));
+
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": [
+                        {"type": "thinking", "thinking": [{"type": "text", "text": "Greeting."}]},
+                        {"type": "text", "text": "Hello!"}
+                    ]
+                }
+            }]
+        });
+        let message = response_to_message(response)?;
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(
+            message.content[0].as_thinking().unwrap().thinking,
+            "Greeting."
+        );
+        assert_eq!(message.content[1].as_text(), Some("Hello!"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_valid_toolrequest() -> anyhow::Result<()> {
         let response: Value = serde_json::from_str(OPENAI_TOOL_USE_RESPONSE)?;
@@ -859,6 +993,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -891,6 +1029,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -924,6 +1066,10 @@ mod tests {
             max_tokens: Some(1024),
             toolshim: false,
             toolshim_model: None,
+            reasoning_effort: None,
+            thinking_budget: None,
+            top_p: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -945,4 +1091,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_create_request_model_settings() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new("gpt-4o".to_string())
+            .with_top_p(Some(0.5))
+            .with_stop_sequences(Some(vec!["END".to_string()]));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["top_p"], json!(0.5));
+        assert_eq!(request["stop"], json!(["END"]));
+
+        // o-series models take the reasoning effort from the config unless the name has one
+        let model_config = ModelConfig::new("o3".to_string())
+            .with_top_p(Some(0.5))
+            .with_reasoning_effort(Some(ReasoningEffort::Low));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["reasoning_effort"], "low");
+        assert!(request.get("top_p").is_none());
+
+        let model_config = ModelConfig {
+            model_name: "o3-high".to_string(),
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["reasoning_effort"], "high");
+        Ok(())
+    }
 }
@@ -4,7 +4,6 @@ use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode};
 use serde_json::Value;
-use tokio::time::sleep;
 use url::Url;
 
 use crate::message::Message;
@@ -19,7 +18,8 @@ use crate::providers::formats::gcpvertexai::{
 
 use crate::providers::formats::gcpvertexai::GcpLocation::Iowa;
 use crate::providers::gcpauth::GcpAuth;
-use crate::providers::utils::emit_debug_trace;
+use crate::providers::retry::RetryConfig;
+use crate::providers::utils::{emit_debug_trace, retry_after};
 use mcp_core::tool::Tool;
 
 /// Base URL for GCP Vertex AI documentation
@@ -47,53 +47,6 @@ enum GcpVertexAIError {
     AuthError(String),
 }
 
-/// Retry configuration for handling rate limit errors
-#[derive(Debug, Clone)]
-struct RetryConfig {
-    /// Maximum number of retry attempts
-    max_retries: usize,
-    /// Initial interval between retries in milliseconds
-    initial_interval_ms: u64,
-    /// Multiplier for backoff (exponential)
-    backoff_multiplier: f64,
-    /// Maximum interval between retries in milliseconds
-    max_interval_ms: u64,
-}
-
-impl Default for RetryConfig {
-    fn default() -> Self {
-        Self {
-            max_retries: DEFAULT_MAX_RETRIES,
-            initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
-            backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
-            max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
-        }
-    }
-}
-
-impl RetryConfig {
-    /// Calculate the delay for a specific retry attempt (with jitter)
-    fn delay_for_attempt(&self, attempt: usize) -> Duration {
-        if attempt == 0 {
-            return Duration::from_millis(0);
-        }
-
-        // Calculate exponential backoff
-        let exponent = (attempt - 1) as u32;
-        let base_delay_ms = (self.initial_interval_ms as f64
-            * self.backoff_multiplier.powi(exponent as i32)) as u64;
-
-        // Apply max limit
-        let capped_delay_ms = std::cmp::min(base_delay_ms, self.max_interval_ms);
-
-        // Add jitter (+/-20% randomness) to avoid thundering herd problem
-        let jitter_factor = 0.8 + (rand::random::<f64>() * 0.4); // Between 0.8 and 1.2
-        let jittered_delay_ms = (capped_delay_ms as f64 * jitter_factor) as u64;
-
-        Duration::from_millis(jittered_delay_ms)
-    }
-}
-
 /// Provider implementation for Google Cloud Platform's Vertex AI service.
 ///
 /// This provider enables interaction with various AI models hosted on GCP Vertex AI,
@@ -115,9 +68,6 @@ pub struct GcpVertexAIProvider {
     location: String,
     /// Configuration for the specific model being used
     model: ModelConfig,
-    /// Retry configuration for handling rate limit errors
-    #[serde(skip)]
-    retry_config: RetryConfig,
 }
 
 impl GcpVertexAIProvider {
@@ -156,9 +106,6 @@ impl GcpVertexAIProvider {
 
         let auth = GcpAuth::new().await?;
 
-        // Load optional retry configuration from environment
-        let retry_config = Self::load_retry_config(config);
-
         Ok(Self {
             client,
             auth,
@@ -166,42 +113,20 @@ impl GcpVertexAIProvider {
             project_id,
             location,
             model,
-            retry_config,
         })
     }
 
-    /// Loads retry configuration from environment variables or uses defaults.
-    fn load_retry_config(config: &crate::config::Config) -> RetryConfig {
-        let max_retries = config
-            .get_param("GCP_MAX_RETRIES")
-            .ok()
-            .and_then(|v: String| v.parse::<usize>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRIES);
-
-        let initial_interval_ms = config
-            .get_param("GCP_INITIAL_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_INITIAL_RETRY_INTERVAL_MS);
-
-        let backoff_multiplier = config
-            .get_param("GCP_BACKOFF_MULTIPLIER")
-            .ok()
-            .and_then(|v: String| v.parse::<f64>().ok())
-            .unwrap_or(DEFAULT_BACKOFF_MULTIPLIER);
-
-        let max_interval_ms = config
-            .get_param("GCP_MAX_RETRY_INTERVAL_MS")
-            .ok()
-            .and_then(|v: String| v.parse::<u64>().ok())
-            .unwrap_or(DEFAULT_MAX_RETRY_INTERVAL_MS);
-
-        RetryConfig {
-            max_retries,
-            initial_interval_ms,
-            backoff_multiplier,
-            max_interval_ms,
-        }
+    /// Retry configuration for handling rate limit errors, from the GCP_* retry settings
+    pub fn retry_config() -> RetryConfig {
+        RetryConfig::from_config(
+            "GCP",
+            RetryConfig {
+                max_retries: DEFAULT_MAX_RETRIES,
+                initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
+                backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+                max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
+            },
+        )
     }
 
     /// Determines the appropriate GCP location for model deployment.
@@ -269,7 +194,6 @@ impl GcpVertexAIProvider {
     }
 
     /// Makes an authenticated POST request to the Vertex AI API at a specific location.
-    /// Includes retry logic for 429 Too Many Requests errors.
     ///
     /// # Arguments
     /// * `payload` - The request payload to send
@@ -285,93 +209,55 @@ impl GcpVertexAIProvider {
             .build_request_url(context.provider(), location)
             .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
 
-        // Initialize retry counter
-        let mut attempts = 0;
-        let mut last_error = None;
-
-        loop {
-            // Check if we've exceeded max retries
-            if attempts > 0 && attempts > self.retry_config.max_retries {
-                let error_msg = format!(
-                    "Exceeded maximum retry attempts ({}) for rate limiting (429)",
-                    self.retry_config.max_retries
-                );
-                tracing::error!("{}", error_msg);
-                return Err(last_error.unwrap_or(ProviderError::RateLimitExceeded(error_msg)));
-            }
-
-            // Get a fresh auth token for each attempt
-            let auth_header = self
-                .get_auth_header()
-                .await
-                .map_err(|e| ProviderError::Authentication(e.to_string()))?;
-
-            // Make the request
-            let response = self
-                .client
-                .post(url.clone())
-                .json(payload)
-                .header("Authorization", auth_header)
-                .send()
-                .await
-                .map_err(|e| ProviderError::RequestFailed(e.to_string()))?;
-
-            let status = response.status();
-
-            // If not a 429, process normally
-            if status != StatusCode::TOO_MANY_REQUESTS {
-                let response_json = response.json::<Value>().await.map_err(|e| {
-                    ProviderError::RequestFailed(format!("Failed to parse response: {e}"))
-                })?;
-
-                return match status {
-                    StatusCode::OK => Ok(response_json),
-                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                        tracing::debug!(
-                            "Authentication failed. Status: {status}, Payload: {payload:?}"
-                        );
-                        Err(ProviderError::Authentication(format!(
-                            "Authentication failed: {response_json:?}"
-                        )))
-                    }
-                    _ => {
-                        tracing::debug!(
-                            "Request failed. Status: {status}, Response: {response_json:?}"
-                        );
-                        Err(ProviderError::RequestFailed(format!(
-                            "Request failed with status {status}: {response_json:?}"
-                        )))
-                    }
-                };
-            }
-
-            // Handle 429 Too Many Requests
-            attempts += 1;
-
-            // Try to parse response for more detailed error info
+        let auth_header = self
+            .get_auth_header()
+            .await
+            .map_err(|e| ProviderError::Authentication(e.to_string()))?;
+
+        let response = self
+            .client
+            .post(url)
+            .json(payload)
+            .header("Authorization", auth_header)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_delay = retry_after(response.headers());
             let cite_gcp_vertex_429 =
                 "See https://cloud.google.com/vertex-ai/generative-ai/docs/error-code-429";
             let response_text = response.text().await.unwrap_or_default();
-            let quota_error = if response_text.contains("Exceeded the Provisioned Throughput") {
+            let details = if response_text.contains("Exceeded the Provisioned Throughput") {
                 format!("Exceeded the Provisioned Throughput: {cite_gcp_vertex_429}.")
             } else {
                 format!("Pay-as-you-go resource exhausted: {cite_gcp_vertex_429}.")
             };
+            return Err(ProviderError::RateLimitExceeded {
+                details,
+                retry_delay,
+            });
+        }
 
-            tracing::warn!(
-                "Rate limit exceeded (attempt {}/{}): {}. Retrying after backoff...",
-                attempts,
-                self.retry_config.max_retries,
-                quota_error
-            );
-
-            // Store the error in case we need to return it after max retries
-            last_error = Some(ProviderError::RateLimitExceeded(quota_error));
-
-            // Calculate and apply the backoff delay
-            let delay = self.retry_config.delay_for_attempt(attempts);
-            tracing::info!("Backing off for {:?} before retry", delay);
-            sleep(delay).await;
+        let response_json = response
+            .json::<Value>()
+            .await
+            .map_err(|e| ProviderError::RequestFailed(format!("Failed to parse response: {e}")))?;
+
+        match status {
+            StatusCode::OK => Ok(response_json),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                tracing::debug!("Authentication failed. Status: {status}, Payload: {payload:?}");
+                Err(ProviderError::Authentication(format!(
+                    "Authentication failed: {response_json:?}"
+                )))
+            }
+            _ => {
+                tracing::debug!("Request failed. Status: {status}, Response: {response_json:?}");
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status {status}: {response_json:?}"
+                )))
+            }
         }
     }
 
@@ -522,32 +408,6 @@ impl Provider for GcpVertexAIProvider {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_retry_config_delay_calculation() {
-        let config = RetryConfig {
-            max_retries: 5,
-            initial_interval_ms: 1000,
-            backoff_multiplier: 2.0,
-            max_interval_ms: 32000,
-        };
-
-        // First attempt has no delay
-        let delay0 = config.delay_for_attempt(0);
-        assert_eq!(delay0.as_millis(), 0);
-
-        // First retry should be around initial_interval with jitter
-        let delay1 = config.delay_for_attempt(1);
-        assert!(delay1.as_millis() >= 800 && delay1.as_millis() <= 1200);
-
-        // Second retry should be around initial_interval * multiplier^1 with jitter
-        let delay2 = config.delay_for_attempt(2);
-        assert!(delay2.as_millis() >= 1600 && delay2.as_millis() <= 2400);
-
-        // Check that max interval is respected
-        let delay10 = config.delay_for_attempt(10);
-        assert!(delay10.as_millis() <= 38400); // max_interval_ms * 1.2 (max jitter)
-    }
-
     #[test]
     fn test_model_provider_conversion() {
         assert_eq!(ModelProvider::Anthropic.as_str(), "anthropic");
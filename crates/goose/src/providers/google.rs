@@ -1,7 +1,7 @@
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
 use crate::providers::formats::google::{create_request, get_usage, response_to_message};
 use crate::providers::utils::{
     emit_debug_trace, handle_response_google_compat, unescape_json_values,
@@ -76,49 +76,15 @@ impl GoogleProvider {
                 ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
             })?;
 
-        let max_retries = 3;
-        let mut retries = 0;
-        let base_delay = Duration::from_secs(2);
-
-        loop {
-            let response = self
-                .client
-                .post(url.clone()) // Clone the URL for each retry
-                .header("CONTENT_TYPE", "application/json")
-                .json(&payload)
-                .send()
-                .await;
-
-            match response {
-                Ok(res) => {
-                    match handle_response_google_compat(res).await {
-                        Ok(result) => return Ok(result),
-                        Err(ProviderError::RateLimitExceeded(_)) => {
-                            retries += 1;
-                            if retries > max_retries {
-                                return Err(ProviderError::RateLimitExceeded(
-                                    "Max retries exceeded for rate limit error".to_string(),
-                                ));
-                            }
-
-                            let delay = 2u64.pow(retries);
-                            let total_delay = Duration::from_secs(delay) + base_delay;
-
-                            println!("Rate limit hit. Retrying in {:?}", total_delay);
-                            tokio::time::sleep(total_delay).await;
-                            continue;
-                        }
-                        Err(err) => return Err(err), // Other errors
-                    }
-                }
-                Err(err) => {
-                    return Err(ProviderError::RequestFailed(format!(
-                        "Request failed: {}",
-                        err
-                    )));
-                }
-            }
-        }
+        let response = self
+            .client
+            .post(url)
+            .header("CONTENT_TYPE", "application/json")
+            .json(&payload)
+            .send()
+            .await?;
+
+        handle_response_google_compat(response).await
     }
 }
 
@@ -172,6 +138,14 @@ impl Provider for GoogleProvider {
 
     /// Fetch supported models from Google Generative Language API; returns Err on failure, Ok(None) if not present
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        Ok(self
+            .fetch_model_info_async()
+            .await?
+            .map(|models| models.into_iter().map(|m| m.name).collect()))
+    }
+
+    /// The models list reports the token limits and whether a model thinks
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
         // List models via the v1beta/models endpoint
         let url = format!("{}/v1beta/models?key={}", self.host, self.api_key);
         let response = self.client.get(&url).send().await?;
@@ -181,12 +155,26 @@ impl Provider for GoogleProvider {
             Some(arr) => arr,
             None => return Ok(None),
         };
-        let mut models: Vec<String> = arr
-            .iter()
-            .filter_map(|m| m.get("name").and_then(|v| v.as_str()))
-            .map(|name| name.split('/').next_back().unwrap_or(name).to_string())
-            .collect();
-        models.sort();
+        let mut models: Vec<ModelInfo> = arr.iter().filter_map(parse_google_model).collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
         Ok(Some(models))
     }
 }
+
+/// Reads a model of the models list, skipping models that cannot chat like embedding models
+fn parse_google_model(model: &Value) -> Option<ModelInfo> {
+    let name = model["name"].as_str()?;
+    let name = name.split('/').next_back().unwrap_or(name);
+    let generates = model["supportedGenerationMethods"]
+        .as_array()?
+        .iter()
+        .any(|method| method == "generateContent");
+    if !generates {
+        return None;
+    }
+    Some(ModelInfo {
+        max_output_tokens: model["outputTokenLimit"].as_u64().map(|v| v as usize),
+        supports_thinking: model["thinking"].as_bool(),
+        ..ModelInfo::new(name, model["inputTokenLimit"].as_u64()? as usize)
+    })
+}
@@ -0,0 +1,333 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::tool::{Tool, ToolAnnotations};
+use serde_json::{json, Value};
+
+use super::base::{Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use mcp_core::role::Role;
+
+/// Model responses at the start of the conversation that go to the lead model
+pub const DEFAULT_LEAD_TURNS: usize = 3;
+
+/// Failed tool calls in a row after which the lead model takes over
+pub const DEFAULT_LEAD_FAILURE_THRESHOLD: usize = 2;
+
+/// Tool the worker model calls to hand the current step to the lead model
+pub const ESCALATE_TOOL_NAME: &str = "escalate_to_lead_model";
+
+/// Pairs a strong lead model with a cheaper worker model
+///
+/// The lead model answers the first responses of the conversation, where the work gets planned,
+/// and takes over when tool calls keep failing. The worker model handles the routine
+/// tool calls in between, and can escalate a step it can't handle to the lead model. Which model
+/// answers is decided from the conversation alone, so the choice is the same after resuming.
+pub struct LeadWorkerProvider {
+    lead: Arc<dyn Provider>,
+    worker: Arc<dyn Provider>,
+    lead_turns: usize,
+    failure_threshold: usize,
+}
+
+impl LeadWorkerProvider {
+    pub fn new(lead: Arc<dyn Provider>, worker: Arc<dyn Provider>) -> Self {
+        Self {
+            lead,
+            worker,
+            lead_turns: DEFAULT_LEAD_TURNS,
+            failure_threshold: DEFAULT_LEAD_FAILURE_THRESHOLD,
+        }
+    }
+
+    pub fn with_lead_turns(self, lead_turns: usize) -> Self {
+        Self { lead_turns, ..self }
+    }
+
+    pub fn with_failure_threshold(self, failure_threshold: usize) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            ..self
+        }
+    }
+
+    /// Pairs the worker with the lead model configured in `GOOSE_LEAD_MODEL`, if there is one
+    ///
+    /// The lead model comes from `GOOSE_LEAD_PROVIDER`, or the worker's provider.
+    /// `GOOSE_LEAD_TURNS` and `GOOSE_LEAD_FAILURE_THRESHOLD` override when the lead answers.
+    pub fn from_config(worker_provider: &str, worker: Arc<dyn Provider>) -> Result<Option<Self>> {
+        let config = Config::global();
+        let Ok(lead_model) = config.get_param::<String>("GOOSE_LEAD_MODEL") else {
+            return Ok(None);
+        };
+        let lead_provider = config
+            .get_param::<String>("GOOSE_LEAD_PROVIDER")
+            .unwrap_or_else(|_| worker_provider.to_string());
+        let lead = super::create(&lead_provider, ModelConfig::new(lead_model))?;
+
+        Ok(Some(
+            Self::new(lead, worker)
+                .with_lead_turns(
+                    config
+                        .get_param("GOOSE_LEAD_TURNS")
+                        .unwrap_or(DEFAULT_LEAD_TURNS),
+                )
+                .with_failure_threshold(
+                    config
+                        .get_param("GOOSE_LEAD_FAILURE_THRESHOLD")
+                        .unwrap_or(DEFAULT_LEAD_FAILURE_THRESHOLD),
+                ),
+        ))
+    }
+
+    /// Whether the lead model should answer the conversation
+    fn lead_answers(&self, messages: &[Message]) -> bool {
+        let turns = messages
+            .iter()
+            .filter(|message| message.role == Role::Assistant)
+            .count();
+        turns < self.lead_turns || failed_tool_calls(messages) >= self.failure_threshold
+    }
+}
+
+fn is_user_text(message: &Message) -> bool {
+    message.role == Role::User
+        && message
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::Text(_)))
+}
+
+/// Tool calls that failed in a row at the end of the conversation
+fn failed_tool_calls(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .rev()
+        .take_while(|message| !is_user_text(message))
+        .flat_map(|message| message.content.iter().rev())
+        .filter_map(|content| match content {
+            MessageContent::ToolResponse(response) => Some(response.tool_result.is_err()),
+            _ => None,
+        })
+        .take_while(|failed| *failed)
+        .count()
+}
+
+fn escalate_tool() -> Tool {
+    Tool::new(
+        ESCALATE_TOOL_NAME.to_string(),
+        "Hand the next step to a more capable model. Call this instead of guessing when the next step needs careful reasoning, such as planning changes, debugging a failure you don't understand, or making a decision with lasting consequences.".to_string(),
+        json!({
+            "type": "object",
+            "properties": {
+                "reason": {"type": "string", "description": "Why the step needs a more capable model"}
+            }
+        }),
+        Some(ToolAnnotations {
+            title: Some("Escalate to the lead model".to_string()),
+            read_only_hint: true,
+            destructive_hint: false,
+            idempotent_hint: true,
+            open_world_hint: false,
+        }),
+    )
+}
+
+fn is_escalation(message: &Message) -> bool {
+    message.content.iter().any(|content| match content {
+        MessageContent::ToolRequest(request) => request
+            .tool_call
+            .as_ref()
+            .is_ok_and(|call| call.name == ESCALATE_TOOL_NAME),
+        _ => false,
+    })
+}
+
+#[async_trait]
+impl Provider for LeadWorkerProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    /// The worker's config, limited to the context both models can handle
+    fn get_model_config(&self) -> ModelConfig {
+        let lead = self.lead.get_model_config();
+        let mut worker = self.worker.get_model_config();
+        worker.context_limit = Some(worker.context_limit().min(lead.context_limit()));
+        worker
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        // Requests without tools are chores like summaries and descriptions, the worker does those
+        if tools.is_empty() {
+            return self.worker.complete(system, messages, tools).await;
+        }
+        if self.lead_answers(messages) {
+            return self.lead.complete(system, messages, tools).await;
+        }
+
+        let mut worker_tools = tools.to_vec();
+        if !tools.iter().any(|tool| tool.name == ESCALATE_TOOL_NAME) {
+            worker_tools.push(escalate_tool());
+        }
+        let (message, usage) = self
+            .worker
+            .complete(system, messages, &worker_tools)
+            .await?;
+        if !is_escalation(&message) {
+            return Ok((message, usage));
+        }
+
+        tracing::debug!(
+            "{} escalated to the lead model after using {:?} tokens",
+            usage.model,
+            usage.usage.total_tokens
+        );
+        // The worker's attempt is paid for too, it is reported under the worker's model
+        let (message, lead_usage) = self.lead.complete(system, messages, tools).await?;
+        Ok((message, lead_usage.with_other_model(usage)))
+    }
+
+    /// Structured output is a chore as well
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        self.worker
+            .complete_structured(system, messages, schema)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use mcp_core::{tool::ToolCall, Content, ToolError};
+
+    struct FixedProvider {
+        model: &'static str,
+        response: Message,
+    }
+
+    #[async_trait]
+    impl Provider for FixedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new(self.model.to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                self.response.clone(),
+                ProviderUsage::new(
+                    self.model.to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                ),
+            ))
+        }
+    }
+
+    fn provider(worker_response: Message) -> LeadWorkerProvider {
+        let lead = FixedProvider {
+            model: "lead",
+            response: Message::assistant().with_text("lead"),
+        };
+        let worker = FixedProvider {
+            model: "worker",
+            response: worker_response,
+        };
+        LeadWorkerProvider::new(Arc::new(lead), Arc::new(worker))
+            .with_lead_turns(1)
+            .with_failure_threshold(2)
+    }
+
+    fn tool_call(id: &str) -> Message {
+        Message::assistant().with_tool_request(id, Ok(ToolCall::new("developer__shell", json!({}))))
+    }
+
+    fn tool_result(id: &str, ok: bool) -> Message {
+        let result = if ok {
+            Ok(vec![Content::text("done")])
+        } else {
+            Err(ToolError::ExecutionError("failed".to_string()))
+        };
+        Message::user().with_tool_response(id, result)
+    }
+
+    async fn answering_model(provider: &LeadWorkerProvider, messages: &[Message]) -> String {
+        let tools = vec![escalate_tool()];
+        let (_, usage) = provider.complete("", messages, &tools).await.unwrap();
+        usage.model
+    }
+
+    #[tokio::test]
+    async fn test_switching_rules() {
+        let provider = provider(Message::assistant().with_text("worker"));
+        let mut messages = vec![Message::user().with_text("Fix the build")];
+        assert_eq!(answering_model(&provider, &messages).await, "lead");
+
+        messages.extend([tool_call("1"), tool_result("1", true)]);
+        assert_eq!(answering_model(&provider, &messages).await, "worker");
+
+        messages.extend([tool_call("2"), tool_result("2", false)]);
+        assert_eq!(answering_model(&provider, &messages).await, "worker");
+        messages.extend([tool_call("3"), tool_result("3", false)]);
+        assert_eq!(answering_model(&provider, &messages).await, "lead");
+
+        // Failures before the user's last message don't count, and neither does the message
+        messages.push(Message::user().with_text("Now run the tests"));
+        assert_eq!(answering_model(&provider, &messages).await, "worker");
+
+        let (_, usage) = provider.complete("", &messages, &[]).await.unwrap();
+        assert_eq!(usage.model, "worker");
+    }
+
+    #[tokio::test]
+    async fn test_escalation() {
+        let escalation = Message::assistant().with_tool_request(
+            "e",
+            Ok(ToolCall::new(
+                ESCALATE_TOOL_NAME,
+                json!({"reason": "tricky"}),
+            )),
+        );
+        let provider = provider(escalation);
+        let messages = vec![
+            Message::user().with_text("Fix the build"),
+            tool_call("1"),
+            tool_result("1", true),
+        ];
+        assert_eq!(answering_model(&provider, &messages).await, "lead");
+
+        // The worker's tokens are reported apart from the lead's
+        let (_, usage) = provider
+            .complete("", &messages, &[escalate_tool()])
+            .await
+            .unwrap();
+        assert_eq!(usage.model, "lead");
+        assert_eq!(usage.usage.total_tokens, Some(15));
+        assert_eq!(usage.other_models.len(), 1);
+        assert_eq!(usage.other_models[0].model, "worker");
+        assert_eq!(usage.other_models[0].usage.total_tokens, Some(15));
+    }
+}
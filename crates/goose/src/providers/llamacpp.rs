@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use etcetera::{choose_app_strategy, AppStrategy};
+use mcp_core::content::Content;
+use mcp_core::role::Role;
+use mcp_core::tool::{Tool, ToolCall};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::llamacpp_ffi::{Context, LlamaLibrary, Model, Sampler};
+use super::utils::emit_debug_trace;
+use crate::config::{Config, APP_STRATEGY};
+use crate::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+pub const LLAMACPP_DEFAULT_MODEL: &str = "qwen2.5-7b-instruct-q4_k_m.gguf";
+pub const LLAMACPP_KNOWN_MODELS: &[&str] = &[LLAMACPP_DEFAULT_MODEL];
+pub const LLAMACPP_DOC_URL: &str = "https://huggingface.co/models?library=gguf";
+pub const LLAMACPP_DEFAULT_CONTEXT_SIZE: u32 = 8192;
+pub const LLAMACPP_DEFAULT_MAX_TOKENS: usize = 2048;
+pub const LLAMACPP_DEFAULT_TEMPERATURE: f32 = 0.2;
+
+/// The library is loaded once per process
+static LIBRARY: OnceCell<Arc<LlamaLibrary>> = OnceCell::new();
+
+/// Runs GGUF models in-process with llama.cpp
+///
+/// Tool calls are generated as JSON constrained by a grammar, so the reply always has a valid
+/// shape and only names the tools that exist, even with small models.
+#[derive(serde::Serialize)]
+pub struct LlamaCppProvider {
+    models_dir: PathBuf,
+    library_path: Option<PathBuf>,
+    context_size: u32,
+    threads: i32,
+    model: ModelConfig,
+    #[serde(skip)]
+    loaded: tokio::sync::OnceCell<Arc<Model>>,
+}
+
+impl Default for LlamaCppProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(LlamaCppProvider::metadata().default_model);
+        LlamaCppProvider::from_env(model).expect("Failed to initialize llama.cpp provider")
+    }
+}
+
+pub(crate) fn default_models_dir() -> PathBuf {
+    choose_app_strategy(APP_STRATEGY.clone())
+        .expect("goose requires a home dir")
+        .data_dir()
+        .join("models")
+}
+
+/// The GGUF file of a model, the model name is a path or a file in the models directory
+pub(crate) fn gguf_path(models_dir: &Path, model_name: &str) -> PathBuf {
+    let name = Path::new(model_name);
+    if name.is_absolute() {
+        return name.to_path_buf();
+    }
+    let path = models_dir.join(name);
+    if path.exists() || path.extension().is_some_and(|ext| ext == "gguf") {
+        path
+    } else {
+        path.with_extension("gguf")
+    }
+}
+
+/// Loads the library on first use, the first caller decides where it is loaded from
+pub(crate) fn load_library(path: Option<&Path>) -> Result<Arc<LlamaLibrary>> {
+    LIBRARY
+        .get_or_try_init(|| LlamaLibrary::load(path))
+        .cloned()
+}
+
+impl LlamaCppProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let models_dir = config
+            .get_param::<String>("LLAMACPP_MODELS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| default_models_dir());
+        let library_path = config
+            .get_param::<String>("LLAMACPP_LIBRARY")
+            .ok()
+            .map(PathBuf::from);
+        let context_size = config
+            .get_param("LLAMACPP_CONTEXT_SIZE")
+            .unwrap_or(LLAMACPP_DEFAULT_CONTEXT_SIZE);
+        let threads = config.get_param("LLAMACPP_THREADS").unwrap_or_else(|_| {
+            std::thread::available_parallelism()
+                .map(|n| n.get() as i32)
+                .unwrap_or(4)
+        });
+
+        Ok(Self {
+            models_dir,
+            library_path,
+            context_size,
+            threads,
+            model,
+            loaded: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    fn model_path(&self) -> PathBuf {
+        gguf_path(&self.models_dir, &self.model.model_name)
+    }
+
+    /// Loads the library and the model on first use, loading a model takes a while
+    async fn load(&self) -> Result<Arc<Model>, ProviderError> {
+        self.loaded
+            .get_or_try_init(|| async {
+                let library_path = self.library_path.clone();
+                let model_path = self.model_path();
+                tokio::task::spawn_blocking(move || {
+                    let library = load_library(library_path.as_deref())?;
+                    Ok(Arc::new(Model::load(library, &model_path)?))
+                })
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))?
+                .map_err(|e: anyhow::Error| ProviderError::ExecutionError(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+}
+
+/// Settings of a single generation
+struct Generation {
+    context_size: u32,
+    threads: i32,
+    max_tokens: usize,
+    temperature: f32,
+    top_p: Option<f32>,
+    grammar: Option<String>,
+}
+
+/// A reply generated by [`generate`]
+struct Generated {
+    text: String,
+    prompt_tokens: usize,
+    reply_tokens: usize,
+    /// The reply stopped at the token limit before the model ended it
+    truncated: bool,
+}
+
+/// Generates a reply to the prompt
+fn generate(
+    model: &Model,
+    prompt: &str,
+    generation: &Generation,
+) -> Result<Generated, ProviderError> {
+    let execution_error = |e: anyhow::Error| ProviderError::ExecutionError(e.to_string());
+
+    let mut tokens = model.tokenize(prompt, false).map_err(execution_error)?;
+    let mut context = Context::new(model, generation.context_size, generation.threads)
+        .map_err(execution_error)?;
+    let n_ctx = context.n_ctx();
+    if tokens.len() >= n_ctx {
+        return Err(ProviderError::ContextLengthExceeded(format!(
+            "The prompt has {} tokens, the context holds {}",
+            tokens.len(),
+            n_ctx
+        )));
+    }
+    let n_batch = context.n_batch();
+    for chunk in tokens.chunks_mut(n_batch) {
+        context.decode(chunk).map_err(execution_error)?;
+    }
+
+    let sampler = Sampler::new(
+        model,
+        generation.grammar.as_deref(),
+        generation.temperature,
+        generation.top_p,
+    )
+    .map_err(execution_error)?;
+    let max_tokens = generation.max_tokens.min(n_ctx - tokens.len());
+    let mut output = Vec::new();
+    let mut generated = 0;
+    let mut truncated = true;
+    while generated < max_tokens {
+        let mut token = context.sample(&sampler);
+        if model.is_end_of_generation(token) {
+            truncated = false;
+            break;
+        }
+        output.extend(model.token_to_piece(token));
+        generated += 1;
+        context
+            .decode(std::slice::from_mut(&mut token))
+            .map_err(execution_error)?;
+    }
+
+    Ok(Generated {
+        text: String::from_utf8_lossy(&output).into_owned(),
+        prompt_tokens: tokens.len(),
+        reply_tokens: generated,
+        truncated,
+    })
+}
+
+/// Describes the tools and the reply format in the system prompt
+fn tools_prompt(tools: &[Tool]) -> String {
+    let tools: Vec<String> = tools
+        .iter()
+        .map(|tool| {
+            format!(
+                "- {}: {}\n  Parameters: {}",
+                tool.name, tool.description, tool.input_schema
+            )
+        })
+        .collect();
+    format!(
+        "You can use the tools below. Always reply with a JSON object, either\n\
+        {{\"message\": \"<your reply to the user>\"}}\n\
+        or, to use one or more tools,\n\
+        {{\"tool_calls\": [{{\"name\": \"<tool name>\", \"arguments\": {{<arguments matching the parameters of the tool>}}}}]}}\n\n\
+        Tools:\n{}",
+        tools.join("\n")
+    )
+}
+
+/// GBNF grammar of the replies described in [`tools_prompt`]
+fn tool_call_grammar(tools: &[Tool]) -> String {
+    let names: Vec<String> = tools
+        .iter()
+        .map(|tool| {
+            let name = json!(tool.name).to_string();
+            format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+        })
+        .collect();
+    format!(
+        r#"root ::= "{{" ws "\"message\"" ws ":" ws string ws "}}" | "{{" ws "\"tool_calls\"" ws ":" ws "[" ws call ( ws "," ws call )* ws "]" ws "}}"
+call ::= "{{" ws "\"name\"" ws ":" ws name ws "," ws "\"arguments\"" ws ":" ws object ws "}}"
+name ::= {}
+value ::= object | array | string | number | "true" | "false" | "null"
+object ::= "{{" ws ( string ws ":" ws value ( ws "," ws string ws ":" ws value )* )? ws "}}"
+array ::= "[" ws ( value ( ws "," ws value )* )? ws "]"
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\""
+number ::= "-"? ( "0" | [1-9] [0-9]* ) ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+ws ::= [ \t\n]? [ \t\n]? [ \t\n]?
+"#,
+        names.join(" | ")
+    )
+}
+
+/// Converts the conversation to chat messages, tool calls are written in the reply format
+fn to_chat(system: &str, messages: &[Message], tools: &[Tool]) -> Vec<(String, String)> {
+    let system = if tools.is_empty() {
+        system.to_string()
+    } else {
+        format!("{}\n\n{}", system, tools_prompt(tools))
+    };
+    let mut chat = vec![("system".to_string(), system)];
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+
+    for message in messages {
+        let mut parts = Vec::new();
+        let mut tool_calls = Vec::new();
+        for content in &message.content {
+            match content {
+                MessageContent::Text(text) if !text.text.is_empty() => {
+                    if message.role == Role::Assistant && !tools.is_empty() {
+                        parts.push(json!({"message": text.text}).to_string());
+                    } else {
+                        parts.push(text.text.clone());
+                    }
+                }
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(call) = &request.tool_call {
+                        tool_names.insert(request.id.clone(), call.name.clone());
+                        tool_calls.push(json!({"name": call.name, "arguments": call.arguments}));
+                    }
+                }
+                MessageContent::ToolResponse(response) => {
+                    let name = tool_names
+                        .get(&response.id)
+                        .map(String::as_str)
+                        .unwrap_or("the tool");
+                    let output = match &response.tool_result {
+                        Ok(contents) => contents
+                            .iter()
+                            .filter(|c| c.audience().is_none_or(|a| a.contains(&Role::Assistant)))
+                            .map(|c| match c {
+                                Content::Text(text) => text.text.clone(),
+                                Content::Resource(resource) => resource.get_text(),
+                                Content::Image(_) => "[image]".to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        Err(e) => format!("Error: {}", e),
+                    };
+                    parts.push(format!("Result of {}:\n{}", name, output));
+                }
+                MessageContent::Image(_) => parts.push("[image]".to_string()),
+                _ => {}
+            }
+        }
+        if !tool_calls.is_empty() {
+            parts.push(json!({"tool_calls": tool_calls}).to_string());
+        }
+        if parts.is_empty() {
+            continue;
+        }
+
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        let content = parts.join("\n\n");
+        match chat.last_mut() {
+            Some((last_role, last)) if last_role == role => {
+                last.push_str("\n\n");
+                last.push_str(&content);
+            }
+            _ => chat.push((role.to_string(), content)),
+        }
+    }
+    chat
+}
+
+/// Formats the chat in the ChatML format, for models without a template llama.cpp knows
+fn chatml(chat: &[(String, String)]) -> String {
+    let mut prompt: String = chat
+        .iter()
+        .map(|(role, content)| format!("<|im_start|>{}\n{}<|im_end|>\n", role, content))
+        .collect();
+    prompt.push_str("<|im_start|>assistant\n");
+    prompt
+}
+
+/// Reads a reply in the format of [`tools_prompt`]
+fn parse_reply(reply: &str) -> Result<Message, ProviderError> {
+    let value: Value = serde_json::from_str(reply.trim()).map_err(|e| {
+        ProviderError::ExecutionError(format!(
+            "The model reply is incomplete, it may have reached the token limit: {}",
+            e
+        ))
+    })?;
+
+    let mut message = Message::assistant();
+    if let Some(text) = value.get("message").and_then(Value::as_str) {
+        message = message.with_text(text);
+    }
+    for call in value
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let name = call["name"].as_str().unwrap_or_default();
+        message = message.with_tool_request(
+            format!("call_{}", uuid::Uuid::new_v4().simple()),
+            Ok(ToolCall::new(name, call["arguments"].clone())),
+        );
+    }
+    Ok(message)
+}
+
+#[async_trait]
+impl Provider for LlamaCppProvider {
+    fn metadata() -> ProviderMetadata {
+        let models_dir = default_models_dir().to_string_lossy().into_owned();
+        ProviderMetadata::new(
+            "llamacpp",
+            "llama.cpp",
+            "Run GGUF models locally and in-process with llama.cpp, without a server",
+            LLAMACPP_DEFAULT_MODEL,
+            LLAMACPP_KNOWN_MODELS.to_vec(),
+            LLAMACPP_DOC_URL,
+            vec![
+                ConfigKey::new("LLAMACPP_MODELS_DIR", true, false, Some(&models_dir)),
+                ConfigKey::new("LLAMACPP_LIBRARY", false, false, None),
+                ConfigKey::new("LLAMACPP_CONTEXT_SIZE", false, false, Some("8192")),
+                ConfigKey::new("LLAMACPP_THREADS", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let model = self.load().await?;
+
+        let chat = to_chat(system, messages, tools);
+        let prompt = model
+            .apply_chat_template(&chat)
+            .unwrap_or_else(|| chatml(&chat));
+        let generation = Generation {
+            context_size: self.context_size,
+            threads: self.threads,
+            max_tokens: self
+                .model
+                .max_tokens
+                .map(|n| n as usize)
+                .unwrap_or(LLAMACPP_DEFAULT_MAX_TOKENS),
+            temperature: self
+                .model
+                .temperature
+                .unwrap_or(LLAMACPP_DEFAULT_TEMPERATURE),
+            top_p: self.model.top_p,
+            grammar: (!tools.is_empty()).then(|| tool_call_grammar(tools)),
+        };
+
+        let generation_prompt = prompt.clone();
+        let generated =
+            tokio::task::spawn_blocking(move || generate(&model, &generation_prompt, &generation))
+                .await
+                .map_err(|e| ProviderError::ExecutionError(e.to_string()))??;
+        let (reply, input_tokens, output_tokens) = (
+            generated.text,
+            generated.prompt_tokens,
+            generated.reply_tokens,
+        );
+
+        let message = if tools.is_empty() {
+            Message::assistant().with_text(reply.trim())
+        } else if generated.truncated {
+            // The grammar only ends the reply once the JSON is complete
+            return Err(ProviderError::ContextLengthExceeded(format!(
+                "The reply reached the limit of {} tokens before its JSON was complete",
+                output_tokens
+            )));
+        } else {
+            parse_reply(&reply)?
+        };
+        let usage = Usage::new(
+            Some(input_tokens as i32),
+            Some(output_tokens as i32),
+            Some((input_tokens + output_tokens) as i32),
+        );
+        emit_debug_trace(
+            &self.model,
+            &json!({"prompt": prompt}),
+            &json!({"reply": reply}),
+            &usage,
+        );
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+
+    /// Lists the GGUF files in the models directory
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        let Ok(entries) = std::fs::read_dir(&self.models_dir) else {
+            return Ok(None);
+        };
+        let mut models: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".gguf"))
+            .collect();
+        models.sort();
+        Ok((!models.is_empty()).then_some(models))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell_tool() -> Tool {
+        Tool::new(
+            "developer__shell",
+            "Run a shell command",
+            json!({"type": "object", "properties": {"command": {"type": "string"}}}),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_to_chat() {
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant().with_tool_request(
+                "call_1",
+                Ok(ToolCall::new("developer__shell", json!({"command": "ls"}))),
+            ),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("README.md")])),
+        ];
+        let chat = to_chat("You are goose.", &messages, &[shell_tool()]);
+
+        assert_eq!(chat.len(), 4);
+        assert_eq!(chat[0].0, "system");
+        assert!(chat[0]
+            .1
+            .contains("- developer__shell: Run a shell command"));
+        assert_eq!(
+            chat[2],
+            (
+                "assistant".to_string(),
+                r#"{"tool_calls":[{"arguments":{"command":"ls"},"name":"developer__shell"}]}"#
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            chat[3],
+            (
+                "user".to_string(),
+                "Result of developer__shell:\nREADME.md".to_string()
+            )
+        );
+        assert!(chatml(&chat).ends_with("<|im_start|>assistant\n"));
+    }
+
+    #[test]
+    fn test_tool_call_grammar() {
+        let grammar = tool_call_grammar(&[shell_tool()]);
+        assert!(grammar.contains(r#"name ::= "\"developer__shell\"""#));
+        assert!(grammar.starts_with("root ::= "));
+    }
+
+    #[test]
+    fn test_parse_reply() -> Result<()> {
+        let message = parse_reply(r#"{"message": "Done!"}"#)?;
+        assert_eq!(message.as_concat_text(), "Done!");
+
+        let message = parse_reply(
+            r#"{"tool_calls": [{"name": "developer__shell", "arguments": {"command": "ls"}}]}"#,
+        )?;
+        let request = message.content[0].as_tool_request().unwrap();
+        let call = request.tool_call.as_ref().unwrap();
+        assert_eq!(call.name, "developer__shell");
+        assert_eq!(call.arguments, json!({"command": "ls"}));
+
+        assert!(matches!(
+            parse_reply(r#"{"message": "cut o"#),
+            Err(ProviderError::ExecutionError(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,646 @@
+//! Bindings to the C API of llama.cpp, loaded at runtime from the `libllama` shared library
+//!
+//! Loading the library at runtime keeps llama.cpp out of the build, users bring the build that
+//! fits their hardware. Only the functions the llama.cpp providers need are bound.
+//!
+//! The functions follow `llama.h` of [`LLAMACPP_RELEASE`], and libraries of other releases are
+//! refused when they are loaded. The model and context parameters are passed by value, and their
+//! layout changes between releases that share a ggml version, so goose doesn't bind them: it
+//! takes the library's defaults into a buffer larger than the structs and passes the buffer
+//! back, only writing fields whose layout is checked, see [`ContextSizes`].
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use libloading::Library;
+
+/// The llama.cpp release whose C API is bound
+pub const LLAMACPP_RELEASE: &str = "b6700";
+
+/// The version of ggml that release ships with, as reported by `ggml_version`
+const GGML_VERSION: &str = "0.9.4";
+
+pub type LlamaToken = i32;
+
+/// Bytes reserved for the parameter structs, well above their size in any release
+///
+/// Structs this large are returned through a pointer to the caller's buffer and passed as a copy
+/// in memory on every supported platform, so the library reads and writes only the prefix it
+/// knows.
+const PARAMS_SIZE: usize = 512;
+
+/// `struct llama_model_params`, passed back to the library as it returned it
+#[repr(C, align(8))]
+#[derive(Clone, Copy)]
+pub struct ModelParams([u8; PARAMS_SIZE]);
+
+/// `struct llama_context_params`, of which goose only sets the leading [`ContextSizes`]
+#[repr(C, align(8))]
+#[derive(Clone, Copy)]
+pub struct ContextParams([u8; PARAMS_SIZE]);
+
+/// The leading fields of `struct llama_context_params`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContextSizes {
+    pub n_ctx: u32,
+    pub n_batch: u32,
+    pub n_ubatch: u32,
+    pub n_seq_max: u32,
+    pub n_threads: i32,
+    pub n_threads_batch: i32,
+}
+
+impl ContextParams {
+    pub fn sizes(&self) -> ContextSizes {
+        unsafe { std::ptr::read_unaligned(self.0.as_ptr() as *const ContextSizes) }
+    }
+
+    pub fn set_sizes(&mut self, sizes: ContextSizes) {
+        unsafe { std::ptr::write_unaligned(self.0.as_mut_ptr() as *mut ContextSizes, sizes) }
+    }
+}
+
+impl ContextSizes {
+    /// Whether these are the defaults of a library whose context parameters start with these
+    /// fields. Releases that start with other fields, like the `seed` of older ones, put values
+    /// here that don't pass.
+    fn are_defaults(&self) -> bool {
+        self.n_ctx > 0
+            && self.n_ubatch > 0
+            && self.n_ubatch <= self.n_batch
+            && self.n_seq_max == 1
+            && self.n_threads > 0
+            && self.n_threads == self.n_threads_batch
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Batch {
+    pub n_tokens: i32,
+    pub token: *mut LlamaToken,
+    pub embd: *mut f32,
+    pub pos: *mut i32,
+    pub n_seq_id: *mut i32,
+    pub seq_id: *mut *mut i32,
+    pub logits: *mut i8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SamplerChainParams {
+    pub no_perf: bool,
+}
+
+#[repr(C)]
+pub struct ChatMessage {
+    pub role: *const c_char,
+    pub content: *const c_char,
+}
+
+/// Seed that makes llama.cpp pick a random seed
+pub const DEFAULT_SEED: u32 = 0xFFFFFFFF;
+
+/// The functions of a loaded `libllama`
+pub struct LlamaLibrary {
+    pub backend_init: unsafe extern "C" fn(),
+    pub model_default_params: unsafe extern "C" fn() -> ModelParams,
+    pub context_default_params: unsafe extern "C" fn() -> ContextParams,
+    pub model_load_from_file:
+        unsafe extern "C" fn(path: *const c_char, params: ModelParams) -> *mut c_void,
+    pub model_free: unsafe extern "C" fn(model: *mut c_void),
+    pub model_get_vocab: unsafe extern "C" fn(model: *const c_void) -> *const c_void,
+    pub model_chat_template:
+        unsafe extern "C" fn(model: *const c_void, name: *const c_char) -> *const c_char,
+    pub init_from_model:
+        unsafe extern "C" fn(model: *mut c_void, params: ContextParams) -> *mut c_void,
+    pub free: unsafe extern "C" fn(ctx: *mut c_void),
+    pub n_ctx: unsafe extern "C" fn(ctx: *const c_void) -> u32,
+    pub tokenize: unsafe extern "C" fn(
+        vocab: *const c_void,
+        text: *const c_char,
+        text_len: i32,
+        tokens: *mut LlamaToken,
+        n_tokens_max: i32,
+        add_special: bool,
+        parse_special: bool,
+    ) -> i32,
+    pub token_to_piece: unsafe extern "C" fn(
+        vocab: *const c_void,
+        token: LlamaToken,
+        buf: *mut c_char,
+        length: i32,
+        lstrip: i32,
+        special: bool,
+    ) -> i32,
+    pub vocab_is_eog: unsafe extern "C" fn(vocab: *const c_void, token: LlamaToken) -> bool,
+    pub chat_apply_template: unsafe extern "C" fn(
+        tmpl: *const c_char,
+        chat: *const ChatMessage,
+        n_msg: usize,
+        add_ass: bool,
+        buf: *mut c_char,
+        length: i32,
+    ) -> i32,
+    pub batch_get_one: unsafe extern "C" fn(tokens: *mut LlamaToken, n_tokens: i32) -> Batch,
+    pub decode: unsafe extern "C" fn(ctx: *mut c_void, batch: Batch) -> i32,
+    pub sampler_chain_default_params: unsafe extern "C" fn() -> SamplerChainParams,
+    pub sampler_chain_init: unsafe extern "C" fn(params: SamplerChainParams) -> *mut c_void,
+    pub sampler_chain_add: unsafe extern "C" fn(chain: *mut c_void, sampler: *mut c_void),
+    pub sampler_init_greedy: unsafe extern "C" fn() -> *mut c_void,
+    pub sampler_init_dist: unsafe extern "C" fn(seed: u32) -> *mut c_void,
+    pub sampler_init_temp: unsafe extern "C" fn(t: f32) -> *mut c_void,
+    pub sampler_init_top_p: unsafe extern "C" fn(p: f32, min_keep: usize) -> *mut c_void,
+    pub sampler_init_grammar: unsafe extern "C" fn(
+        vocab: *const c_void,
+        grammar: *const c_char,
+        root: *const c_char,
+    ) -> *mut c_void,
+    pub sampler_sample:
+        unsafe extern "C" fn(sampler: *mut c_void, ctx: *mut c_void, idx: i32) -> LlamaToken,
+    pub sampler_free: unsafe extern "C" fn(sampler: *mut c_void),
+    pub model_n_embd: unsafe extern "C" fn(model: *const c_void) -> i32,
+    pub set_embeddings: unsafe extern "C" fn(ctx: *mut c_void, embeddings: bool),
+    // Only needed for embeddings, and missing from some builds, so chat works without them
+    pub get_embeddings_seq: Option<unsafe extern "C" fn(ctx: *mut c_void, seq_id: i32) -> *mut f32>,
+    pub get_memory: Option<unsafe extern "C" fn(ctx: *const c_void) -> *mut c_void>,
+    pub memory_clear: Option<unsafe extern "C" fn(memory: *mut c_void, data: bool)>,
+    // Keeps the functions above valid
+    _library: Library,
+}
+
+impl LlamaLibrary {
+    /// Loads `libllama` from `path`, or from the library search path when no path is given
+    pub fn load(path: Option<&Path>) -> Result<Arc<Self>> {
+        let library = unsafe {
+            match path {
+                Some(path) => Library::new(path),
+                None => Library::new(libloading::library_filename("llama")),
+            }
+        }
+        .map_err(|e| {
+            anyhow!(
+                "Failed to load the llama.cpp library, install llama.cpp or set LLAMACPP_LIBRARY to the path of libllama: {}",
+                e
+            )
+        })?;
+
+        check_version(&library)?;
+        check_context_layout(&library)?;
+
+        macro_rules! symbol {
+            ($name:literal) => {{
+                let symbol: libloading::Symbol<_> =
+                    unsafe { library.get(concat!($name, "\0").as_bytes()) }.map_err(|e| {
+                        anyhow!(
+                            "The llama.cpp library is missing {}, it may be too old: {}",
+                            $name,
+                            e
+                        )
+                    })?;
+                *symbol
+            }};
+        }
+        macro_rules! optional_symbol {
+            ($name:literal) => {{
+                unsafe { library.get(concat!($name, "\0").as_bytes()) }
+                    .ok()
+                    .map(|symbol: libloading::Symbol<_>| *symbol)
+            }};
+        }
+
+        let llama = Self {
+            backend_init: symbol!("llama_backend_init"),
+            model_default_params: symbol!("llama_model_default_params"),
+            context_default_params: symbol!("llama_context_default_params"),
+            model_load_from_file: symbol!("llama_model_load_from_file"),
+            model_free: symbol!("llama_model_free"),
+            model_get_vocab: symbol!("llama_model_get_vocab"),
+            model_chat_template: symbol!("llama_model_chat_template"),
+            init_from_model: symbol!("llama_init_from_model"),
+            free: symbol!("llama_free"),
+            n_ctx: symbol!("llama_n_ctx"),
+            tokenize: symbol!("llama_tokenize"),
+            token_to_piece: symbol!("llama_token_to_piece"),
+            vocab_is_eog: symbol!("llama_vocab_is_eog"),
+            chat_apply_template: symbol!("llama_chat_apply_template"),
+            batch_get_one: symbol!("llama_batch_get_one"),
+            decode: symbol!("llama_decode"),
+            sampler_chain_default_params: symbol!("llama_sampler_chain_default_params"),
+            sampler_chain_init: symbol!("llama_sampler_chain_init"),
+            sampler_chain_add: symbol!("llama_sampler_chain_add"),
+            sampler_init_greedy: symbol!("llama_sampler_init_greedy"),
+            sampler_init_dist: symbol!("llama_sampler_init_dist"),
+            sampler_init_temp: symbol!("llama_sampler_init_temp"),
+            sampler_init_top_p: symbol!("llama_sampler_init_top_p"),
+            sampler_init_grammar: symbol!("llama_sampler_init_grammar"),
+            sampler_sample: symbol!("llama_sampler_sample"),
+            sampler_free: symbol!("llama_sampler_free"),
+            model_n_embd: symbol!("llama_model_n_embd"),
+            set_embeddings: symbol!("llama_set_embeddings"),
+            get_embeddings_seq: optional_symbol!("llama_get_embeddings_seq"),
+            get_memory: optional_symbol!("llama_get_memory"),
+            memory_clear: optional_symbol!("llama_memory_clear"),
+            _library: library,
+        };
+        unsafe { (llama.backend_init)() };
+        Ok(Arc::new(llama))
+    }
+}
+
+/// Refuses libraries of other releases than the one bound, whose structs may not match
+///
+/// libllama doesn't report its release, the version of the ggml it is linked with is checked
+/// instead.
+fn check_version(library: &Library) -> Result<()> {
+    let version = unsafe {
+        library
+            .get::<unsafe extern "C" fn() -> *const c_char>(b"ggml_version\0")
+            .ok()
+            .map(|ggml_version| ggml_version())
+            .filter(|version| !version.is_null())
+            .map(|version| CStr::from_ptr(version).to_string_lossy().into_owned())
+    };
+    match version {
+        Some(version) if version == GGML_VERSION => Ok(()),
+        version => Err(anyhow!(
+            "The llama.cpp library is not supported, goose needs llama.cpp {} (ggml {}) but found ggml {}",
+            LLAMACPP_RELEASE,
+            GGML_VERSION,
+            version.as_deref().unwrap_or("of an unknown version")
+        )),
+    }
+}
+
+/// Refuses libraries whose context parameters don't start with [`ContextSizes`]
+fn check_context_layout(library: &Library) -> Result<()> {
+    let sizes = unsafe {
+        library
+            .get::<unsafe extern "C" fn() -> ContextParams>(b"llama_context_default_params\0")
+            .map(|default_params| default_params().sizes())
+    }
+    .map_err(|e| anyhow!("Failed to load llama_context_default_params: {}", e))?;
+    if !sizes.are_defaults() {
+        return Err(anyhow!(
+            "The llama.cpp library is not supported, its context parameters don't match llama.cpp {}",
+            LLAMACPP_RELEASE
+        ));
+    }
+    Ok(())
+}
+
+/// A model loaded from a GGUF file
+pub struct Model {
+    llama: Arc<LlamaLibrary>,
+    model: *mut c_void,
+    vocab: *const c_void,
+}
+
+// The model is only read after loading, llama.cpp allows using it from several threads
+unsafe impl Send for Model {}
+unsafe impl Sync for Model {}
+
+impl Model {
+    pub fn load(llama: Arc<LlamaLibrary>, path: &Path) -> Result<Self> {
+        let c_path = CString::new(path.to_string_lossy().as_bytes())?;
+        let model = unsafe {
+            (llama.model_load_from_file)(c_path.as_ptr(), (llama.model_default_params)())
+        };
+        if model.is_null() {
+            return Err(anyhow!("Failed to load model from {}", path.display()));
+        }
+        let vocab = unsafe { (llama.model_get_vocab)(model) };
+        Ok(Self {
+            llama,
+            model,
+            vocab,
+        })
+    }
+
+    /// Formats messages with the chat template of the model
+    ///
+    /// Returns None when the model has no template or llama.cpp does not know it.
+    pub fn apply_chat_template(&self, messages: &[(String, String)]) -> Option<String> {
+        let template = unsafe { (self.llama.model_chat_template)(self.model, std::ptr::null()) };
+        if template.is_null() {
+            return None;
+        }
+        let strings: Vec<(CString, CString)> = messages
+            .iter()
+            .map(|(role, content)| {
+                Some((
+                    CString::new(role.as_str()).ok()?,
+                    CString::new(content.as_str()).ok()?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        let chat: Vec<ChatMessage> = strings
+            .iter()
+            .map(|(role, content)| ChatMessage {
+                role: role.as_ptr(),
+                content: content.as_ptr(),
+            })
+            .collect();
+
+        let apply = |buf: &mut Vec<u8>| unsafe {
+            (self.llama.chat_apply_template)(
+                template,
+                chat.as_ptr(),
+                chat.len(),
+                true,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as i32,
+            )
+        };
+        let mut buf = vec![0u8; 4096];
+        let mut length = apply(&mut buf);
+        // A negative length means llama.cpp does not know the template
+        if length < 0 {
+            return None;
+        }
+        if length as usize > buf.len() {
+            buf.resize(length as usize, 0);
+            length = apply(&mut buf);
+            if length < 0 {
+                return None;
+            }
+        }
+        buf.truncate(length as usize);
+        String::from_utf8(buf).ok()
+    }
+
+    /// Tokenizes the text, `add_special` adds the BOS/EOS tokens the model expects around raw
+    /// text, chat prompts already carry them from the chat template
+    pub fn tokenize(&self, text: &str, add_special: bool) -> Result<Vec<LlamaToken>> {
+        let tokenize = |tokens: &mut Vec<LlamaToken>| unsafe {
+            (self.llama.tokenize)(
+                self.vocab,
+                text.as_ptr() as *const c_char,
+                text.len() as i32,
+                tokens.as_mut_ptr(),
+                tokens.len() as i32,
+                add_special,
+                true,
+            )
+        };
+        let mut tokens = vec![0; text.len() + 2];
+        let mut count = tokenize(&mut tokens);
+        if count < 0 {
+            tokens.resize(count.unsigned_abs() as usize, 0);
+            count = tokenize(&mut tokens);
+        }
+        if count < 0 {
+            return Err(anyhow!("Failed to tokenize the prompt"));
+        }
+        tokens.truncate(count as usize);
+        Ok(tokens)
+    }
+
+    pub fn token_to_piece(&self, token: LlamaToken) -> Vec<u8> {
+        let mut buf = vec![0u8; 64];
+        let mut length = unsafe {
+            (self.llama.token_to_piece)(
+                self.vocab,
+                token,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as i32,
+                0,
+                false,
+            )
+        };
+        if length < 0 {
+            buf.resize(length.unsigned_abs() as usize, 0);
+            length = unsafe {
+                (self.llama.token_to_piece)(
+                    self.vocab,
+                    token,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len() as i32,
+                    0,
+                    false,
+                )
+            };
+        }
+        buf.truncate(length.max(0) as usize);
+        buf
+    }
+
+    pub fn is_end_of_generation(&self, token: LlamaToken) -> bool {
+        unsafe { (self.llama.vocab_is_eog)(self.vocab, token) }
+    }
+
+    /// The size of the embeddings of the model
+    pub fn n_embd(&self) -> usize {
+        unsafe { (self.llama.model_n_embd)(self.model) as usize }
+    }
+}
+
+impl Drop for Model {
+    fn drop(&mut self) {
+        unsafe { (self.llama.model_free)(self.model) };
+    }
+}
+
+/// The functions of the library only embeddings use
+struct EmbeddingFunctions {
+    get_embeddings_seq: unsafe extern "C" fn(ctx: *mut c_void, seq_id: i32) -> *mut f32,
+    get_memory: unsafe extern "C" fn(ctx: *const c_void) -> *mut c_void,
+    memory_clear: unsafe extern "C" fn(memory: *mut c_void, data: bool),
+}
+
+impl EmbeddingFunctions {
+    fn of(llama: &LlamaLibrary) -> Result<Self> {
+        let missing = |name: &str| {
+            anyhow!(
+                "The llama.cpp library is missing {}, which embeddings need, it may be too old",
+                name
+            )
+        };
+        Ok(Self {
+            get_embeddings_seq: llama
+                .get_embeddings_seq
+                .ok_or_else(|| missing("llama_get_embeddings_seq"))?,
+            get_memory: llama
+                .get_memory
+                .ok_or_else(|| missing("llama_get_memory"))?,
+            memory_clear: llama
+                .memory_clear
+                .ok_or_else(|| missing("llama_memory_clear"))?,
+        })
+    }
+}
+
+/// An inference context of a model, holds the KV cache of one generation
+pub struct Context<'a> {
+    model: &'a Model,
+    ctx: *mut c_void,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(model: &'a Model, n_ctx: u32, n_threads: i32) -> Result<Self> {
+        let mut params = unsafe { (model.llama.context_default_params)() };
+        params.set_sizes(ContextSizes {
+            n_ctx,
+            n_batch: n_ctx.min(2048),
+            n_threads,
+            n_threads_batch: n_threads,
+            ..params.sizes()
+        });
+        let ctx = unsafe { (model.llama.init_from_model)(model.model, params) };
+        if ctx.is_null() {
+            return Err(anyhow!("Failed to create a llama.cpp context"));
+        }
+        Ok(Self { model, ctx })
+    }
+
+    /// A context that outputs embeddings, each text is evaluated in a single batch
+    ///
+    /// Fails when the library lacks the functions embeddings need.
+    pub fn new_embedding(model: &'a Model, n_ctx: u32, n_threads: i32) -> Result<Self> {
+        EmbeddingFunctions::of(&model.llama)?;
+        let mut params = unsafe { (model.llama.context_default_params)() };
+        params.set_sizes(ContextSizes {
+            n_ctx,
+            n_batch: n_ctx,
+            n_ubatch: n_ctx,
+            n_threads,
+            n_threads_batch: n_threads,
+            ..params.sizes()
+        });
+        let ctx = unsafe { (model.llama.init_from_model)(model.model, params) };
+        if ctx.is_null() {
+            return Err(anyhow!("Failed to create a llama.cpp context"));
+        }
+        unsafe { (model.llama.set_embeddings)(ctx, true) };
+        Ok(Self { model, ctx })
+    }
+
+    pub fn n_ctx(&self) -> usize {
+        unsafe { (self.model.llama.n_ctx)(self.ctx) as usize }
+    }
+
+    /// The pooled embedding of the tokens, evaluated on their own
+    pub fn embed(&mut self, tokens: &mut [LlamaToken]) -> Result<Vec<f32>> {
+        let functions = EmbeddingFunctions::of(&self.model.llama)?;
+        unsafe { (functions.memory_clear)((functions.get_memory)(self.ctx), true) };
+        self.decode(tokens)?;
+        let embedding = unsafe { (functions.get_embeddings_seq)(self.ctx, 0) };
+        if embedding.is_null() {
+            return Err(anyhow!(
+                "The model does not pool its embeddings, it is not an embedding model"
+            ));
+        }
+        Ok(unsafe { std::slice::from_raw_parts(embedding, self.model.n_embd()) }.to_vec())
+    }
+
+    pub fn n_batch(&self) -> usize {
+        self.n_ctx().min(2048)
+    }
+
+    /// Evaluates tokens following the ones evaluated before
+    pub fn decode(&mut self, tokens: &mut [LlamaToken]) -> Result<()> {
+        let batch =
+            unsafe { (self.model.llama.batch_get_one)(tokens.as_mut_ptr(), tokens.len() as i32) };
+        match unsafe { (self.model.llama.decode)(self.ctx, batch) } {
+            0 => Ok(()),
+            code => Err(anyhow!("llama.cpp failed to decode tokens (code {})", code)),
+        }
+    }
+
+    pub fn sample(&mut self, sampler: &Sampler) -> LlamaToken {
+        unsafe { (self.model.llama.sampler_sample)(sampler.sampler, self.ctx, -1) }
+    }
+}
+
+impl Drop for Context<'_> {
+    fn drop(&mut self) {
+        unsafe { (self.model.llama.free)(self.ctx) };
+    }
+}
+
+/// A chain of samplers that picks the next token
+pub struct Sampler {
+    llama: Arc<LlamaLibrary>,
+    sampler: *mut c_void,
+}
+
+impl Sampler {
+    /// Samples greedily when the temperature is zero, optionally constrained by a GBNF grammar
+    pub fn new(
+        model: &Model,
+        grammar: Option<&str>,
+        temperature: f32,
+        top_p: Option<f32>,
+    ) -> Result<Self> {
+        let llama = model.llama.clone();
+        let sampler = unsafe { (llama.sampler_chain_init)((llama.sampler_chain_default_params)()) };
+        let chain = Self { llama, sampler };
+        let add = |sampler: *mut c_void| unsafe {
+            (chain.llama.sampler_chain_add)(chain.sampler, sampler)
+        };
+
+        if let Some(grammar) = grammar {
+            let grammar = CString::new(grammar)?;
+            let sampler = unsafe {
+                (chain.llama.sampler_init_grammar)(model.vocab, grammar.as_ptr(), c"root".as_ptr())
+            };
+            if sampler.is_null() {
+                return Err(anyhow!("llama.cpp could not parse the tool call grammar"));
+            }
+            add(sampler);
+        }
+        if temperature <= 0.0 {
+            add(unsafe { (chain.llama.sampler_init_greedy)() });
+        } else {
+            if let Some(top_p) = top_p {
+                add(unsafe { (chain.llama.sampler_init_top_p)(top_p, 1) });
+            }
+            add(unsafe { (chain.llama.sampler_init_temp)(temperature) });
+            add(unsafe { (chain.llama.sampler_init_dist)(DEFAULT_SEED) });
+        }
+        Ok(chain)
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        // Frees the samplers added to the chain as well
+        unsafe { (self.llama.sampler_free)(self.sampler) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_sizes() {
+        let mut params = ContextParams([0; PARAMS_SIZE]);
+        let defaults = ContextSizes {
+            n_ctx: 512,
+            n_batch: 2048,
+            n_ubatch: 512,
+            n_seq_max: 1,
+            n_threads: 4,
+            n_threads_batch: 4,
+        };
+        params.set_sizes(defaults);
+        assert_eq!(params.sizes(), defaults);
+        assert_eq!(&params.0[..4], &512u32.to_ne_bytes());
+        assert!(defaults.are_defaults());
+
+        // Releases that started with the seed
+        let shifted = ContextSizes {
+            n_ctx: DEFAULT_SEED,
+            n_batch: 512,
+            n_ubatch: 2048,
+            n_seq_max: 512,
+            n_threads: 1,
+            n_threads_batch: 4,
+        };
+        assert!(!shifted.are_defaults());
+    }
+}
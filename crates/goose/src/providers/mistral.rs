@@ -0,0 +1,346 @@
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::{
+    ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::utils::{
+    emit_debug_trace, get_model, handle_response_openai_compat, retry_after, ImageFormat,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::Tool;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use url::Url;
+
+pub const MISTRAL_API_HOST: &str = "https://api.mistral.ai";
+pub const MISTRAL_DEFAULT_MODEL: &str = "mistral-medium-latest";
+pub const MISTRAL_KNOWN_MODELS: &[&str] = &[
+    "mistral-large-latest",
+    "mistral-medium-latest",
+    "mistral-small-latest",
+    "codestral-latest",
+    "devstral-medium-latest",
+    "magistral-medium-latest",
+];
+
+pub const MISTRAL_DOC_URL: &str = "https://docs.mistral.ai/getting-started/models/";
+
+/// Mistral only accepts tool call ids of exactly this many letters and digits
+const TOOL_CALL_ID_LENGTH: usize = 9;
+
+#[derive(serde::Serialize)]
+pub struct MistralProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    model: ModelConfig,
+}
+
+impl Default for MistralProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(MistralProvider::metadata().default_model);
+        MistralProvider::from_env(model).expect("Failed to initialize Mistral provider")
+    }
+}
+
+impl MistralProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("MISTRAL_API_KEY")?;
+        let host: String = config
+            .get_param("MISTRAL_HOST")
+            .unwrap_or_else(|_| MISTRAL_API_HOST.to_string());
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            model,
+        })
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let retry_delay = retry_after(response.headers());
+        let payload: Option<Value> = response.json().await.ok();
+
+        match status {
+            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                    Status: {}. Response: {:?}", status, payload)))
+            }
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                let message = error_message(payload.as_ref());
+                if message.contains("too large for model") {
+                    return Err(ProviderError::ContextLengthExceeded(message));
+                }
+                Err(ProviderError::RequestFailed(format!("{} (status {})", message, status.as_u16())))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded { details: error_message(payload.as_ref()), retry_delay })
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(format!("{:?}", payload)))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            }
+        }
+    }
+}
+
+/// Mistral reports errors at the top level as `{"object": "error", "message": ...}`, and
+/// validation errors as `{"detail": [{"msg": ...}]}`
+fn error_message(payload: Option<&Value>) -> String {
+    let Some(payload) = payload else {
+        return "Unknown error".to_string();
+    };
+    if let Some(message) = payload.get("message").and_then(|m| m.as_str()) {
+        return message.to_string();
+    }
+    if let Some(details) = payload.get("detail").and_then(|d| d.as_array()) {
+        return details
+            .iter()
+            .filter_map(|d| d.get("msg").and_then(|m| m.as_str()))
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+    format!("{:?}", payload)
+}
+
+/// Rewrites tool call ids that do not match Mistral's format, e.g. ids from another provider
+///
+/// The same id always maps to the same replacement, so tool calls and their results still match.
+fn normalize_tool_call_ids(payload: &mut Value) {
+    let Some(messages) = payload.get_mut("messages").and_then(|m| m.as_array_mut()) else {
+        return;
+    };
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let mut normalize = |id: &mut Value| {
+        if let Some(original) = id.as_str() {
+            let replacement = ids
+                .entry(original.to_string())
+                .or_insert_with(|| mistral_tool_call_id(original))
+                .clone();
+            *id = Value::String(replacement);
+        }
+    };
+
+    for message in messages {
+        if let Some(tool_calls) = message.get_mut("tool_calls").and_then(|t| t.as_array_mut()) {
+            for tool_call in tool_calls {
+                if let Some(id) = tool_call.get_mut("id") {
+                    normalize(id);
+                }
+            }
+        }
+        if let Some(id) = message.get_mut("tool_call_id") {
+            normalize(id);
+        }
+    }
+}
+
+fn mistral_tool_call_id(id: &str) -> String {
+    if id.len() == TOOL_CALL_ID_LENGTH && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return id.to_string();
+    }
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let mut hash = hasher.finish();
+    (0..TOOL_CALL_ID_LENGTH)
+        .map(|_| {
+            let c = ALPHABET[(hash % ALPHABET.len() as u64) as usize] as char;
+            hash /= ALPHABET.len() as u64;
+            c
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Provider for MistralProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "mistral",
+            "Mistral AI",
+            "Mistral, Codestral and Magistral models by Mistral AI",
+            MISTRAL_DEFAULT_MODEL,
+            MISTRAL_KNOWN_MODELS.to_vec(),
+            MISTRAL_DOC_URL,
+            vec![
+                ConfigKey::new("MISTRAL_API_KEY", true, true, None),
+                ConfigKey::new("MISTRAL_HOST", false, false, Some(MISTRAL_API_HOST)),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        normalize_tool_call_ids(&mut payload);
+
+        let response = self.post(payload.clone()).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        Ok(self
+            .fetch_model_info_async()
+            .await?
+            .map(|models| models.into_iter().map(|m| m.name).collect()))
+    }
+
+    /// The models list reports the context length and capabilities of every model
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/models").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.api_key)
+            .send()
+            .await?;
+        let json = handle_response_openai_compat(response).await?;
+        Ok(json["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(parse_mistral_model).collect()))
+    }
+}
+
+/// Reads a model of the models list, skipping models that cannot chat like embedding models
+fn parse_mistral_model(model: &Value) -> Option<ModelInfo> {
+    let capabilities = &model["capabilities"];
+    if capabilities["completion_chat"].as_bool() != Some(true) {
+        return None;
+    }
+    Some(ModelInfo {
+        supports_tools: capabilities["function_calling"].as_bool(),
+        supports_vision: capabilities["vision"].as_bool(),
+        ..ModelInfo::new(
+            model["id"].as_str()?,
+            model["max_context_length"].as_u64()? as usize,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_tool_call_ids() {
+        let mut payload = json!({
+            "messages": [
+                {"role": "assistant", "tool_calls": [
+                    {"id": "toolu_01A09q90qw90lq917835lq9", "type": "function"},
+                    {"id": "D681PevKs", "type": "function"}
+                ]},
+                {"role": "tool", "tool_call_id": "toolu_01A09q90qw90lq917835lq9", "content": "ok"},
+                {"role": "tool", "tool_call_id": "D681PevKs", "content": "ok"}
+            ]
+        });
+        normalize_tool_call_ids(&mut payload);
+
+        let rewritten = payload["messages"][0]["tool_calls"][0]["id"]
+            .as_str()
+            .unwrap();
+        assert_eq!(rewritten.len(), TOOL_CALL_ID_LENGTH);
+        assert!(rewritten.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(payload["messages"][1]["tool_call_id"], rewritten);
+        assert_eq!(payload["messages"][0]["tool_calls"][1]["id"], "D681PevKs");
+        assert_eq!(payload["messages"][2]["tool_call_id"], "D681PevKs");
+    }
+
+    #[test]
+    fn test_error_message() {
+        let payload = json!({
+            "object": "error",
+            "message": "Prompt contains 140000 tokens, too large for model with 131072 maximum context length",
+            "type": "invalid_request_error"
+        });
+        assert!(error_message(Some(&payload)).contains("too large for model"));
+
+        let payload = json!({"detail": [{"loc": ["body", "messages"], "msg": "Field required"}]});
+        assert_eq!(error_message(Some(&payload)), "Field required");
+    }
+
+    #[test]
+    fn test_parse_mistral_model() {
+        let model = json!({
+            "id": "mistral-medium-2505",
+            "max_context_length": 131072,
+            "capabilities": {"completion_chat": true, "function_calling": true, "vision": true}
+        });
+        let info = parse_mistral_model(&model).unwrap();
+        assert_eq!(info.name, "mistral-medium-2505");
+        assert_eq!(info.context_limit, 131_072);
+        assert_eq!(info.supports_tools, Some(true));
+        assert_eq!(info.supports_vision, Some(true));
+
+        let embed = json!({
+            "id": "mistral-embed",
+            "max_context_length": 8192,
+            "capabilities": {"completion_chat": false}
+        });
+        assert!(parse_mistral_model(&embed).is_none());
+    }
+}
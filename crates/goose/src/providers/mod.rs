@@ -3,7 +3,11 @@ pub mod azure;
 pub mod azureauth;
 pub mod base;
 pub mod bedrock;
+pub mod cache;
+pub mod catalog;
 pub mod databricks;
+pub mod deepseek;
+pub mod embeddings;
 pub mod errors;
 mod factory;
 pub mod formats;
@@ -12,13 +16,21 @@ pub mod gcpvertexai;
 pub mod githubcopilot;
 pub mod google;
 pub mod groq;
+pub mod lead_worker;
+pub mod llamacpp;
+mod llamacpp_ffi;
+pub mod mistral;
 pub mod oauth;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
 pub mod openrouter;
+pub mod replay;
+pub mod retry;
 pub mod toolshim;
 pub mod utils;
 pub mod utils_universal_openai_stream;
 pub mod venice;
+pub mod xai;
 
-pub use factory::{create, providers};
+pub use factory::{create, create_with_lead, providers};
@@ -1,14 +1,16 @@
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::utils::{get_model, handle_response_openai_compat};
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::formats::openai::{
+    create_request, get_usage, response_to_json, response_to_message, set_response_schema,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use mcp_core::tool::Tool;
 use reqwest::Client;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::time::Duration;
 use url::Url;
 
@@ -19,6 +21,34 @@ pub const OLLAMA_DEFAULT_MODEL: &str = "qwen2.5";
 pub const OLLAMA_KNOWN_MODELS: &[&str] = &[OLLAMA_DEFAULT_MODEL];
 pub const OLLAMA_DOC_URL: &str = "https://ollama.com/library";
 
+/// The base URL of an Ollama server from OLLAMA_HOST
+pub(crate) fn ollama_base_url(host: &str) -> Result<Url, ProviderError> {
+    // OLLAMA_HOST is sometimes just the 'host' or 'host:port' without a scheme
+    let base = if host.starts_with("http://") || host.starts_with("https://") {
+        host.to_string()
+    } else {
+        format!("http://{}", host)
+    };
+
+    let mut base_url = Url::parse(&base)
+        .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+
+    // Set the default port if missing
+    // Don't add default port if:
+    // 1. URL explicitly ends with standard ports (:80 or :443)
+    // 2. URL uses HTTPS (which implicitly uses port 443)
+    let explicit_default_port = host.ends_with(":80") || host.ends_with(":443");
+    let is_https = base_url.scheme() == "https";
+
+    if base_url.port().is_none() && !explicit_default_port && !is_https {
+        base_url
+            .set_port(Some(OLLAMA_DEFAULT_PORT))
+            .map_err(|_| ProviderError::RequestFailed("Failed to set default port".to_string()))?;
+    }
+
+    Ok(base_url)
+}
+
 #[derive(serde::Serialize)]
 pub struct OllamaProvider {
     #[serde(skip)]
@@ -54,30 +84,7 @@ impl OllamaProvider {
 
     /// Get the base URL for Ollama API calls
     fn get_base_url(&self) -> Result<Url, ProviderError> {
-        // OLLAMA_HOST is sometimes just the 'host' or 'host:port' without a scheme
-        let base = if self.host.starts_with("http://") || self.host.starts_with("https://") {
-            self.host.clone()
-        } else {
-            format!("http://{}", self.host)
-        };
-
-        let mut base_url = Url::parse(&base)
-            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
-
-        // Set the default port if missing
-        // Don't add default port if:
-        // 1. URL explicitly ends with standard ports (:80 or :443)
-        // 2. URL uses HTTPS (which implicitly uses port 443)
-        let explicit_default_port = self.host.ends_with(":80") || self.host.ends_with(":443");
-        let is_https = base_url.scheme() == "https";
-
-        if base_url.port().is_none() && !explicit_default_port && !is_https {
-            base_url.set_port(Some(OLLAMA_DEFAULT_PORT)).map_err(|_| {
-                ProviderError::RequestFailed("Failed to set default port".to_string())
-            })?;
-        }
-
-        Ok(base_url)
+        ollama_base_url(&self.host)
     }
 
     async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
@@ -150,4 +157,148 @@ impl Provider for OllamaProvider {
         super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let mut payload = create_request(
+            &self.model,
+            system,
+            messages,
+            &[],
+            &super::utils::ImageFormat::OpenAi,
+        )?;
+        set_response_schema(&mut payload, schema);
+
+        let response = self.post(payload.clone()).await?;
+        let value = response_to_json(&response)?;
+        let usage = get_usage(&response).unwrap_or_default();
+        let model = get_model(&response);
+        super::utils::emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((value, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        let base_url = self.get_base_url()?;
+        let url = base_url.join("api/tags").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        let json: Value = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut models: Vec<String> = json["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["name"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        models.sort();
+        Ok(Some(models))
+    }
+
+    /// Every pulled model is shown, which reports its context length and capabilities
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let Some(names) = self.fetch_supported_models_async().await? else {
+            return Ok(None);
+        };
+        let base_url = self.get_base_url()?;
+        let url = base_url.join("api/show").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let mut models = Vec::new();
+        for name in names {
+            let json: Value = self
+                .client
+                .post(url.clone())
+                .json(&json!({"model": name}))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            models.push(parse_ollama_model(&name, &json));
+        }
+        Ok(Some(models))
+    }
+}
+
+/// The context window Ollama runs models with when their Modelfile sets no num_ctx
+pub const OLLAMA_DEFAULT_NUM_CTX: usize = 4096;
+
+/// Reads the details of a model
+///
+/// Ollama truncates prompts to the num_ctx of the model rather than the context length its
+/// architecture supports, so the context limit is num_ctx capped at that length.
+fn parse_ollama_model(name: &str, details: &Value) -> ModelInfo {
+    let context_length = details["model_info"].as_object().and_then(|info| {
+        info.iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|v| v as usize)
+    });
+    let num_ctx = details["parameters"]
+        .as_str()
+        .and_then(|parameters| {
+            parameters.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next() == Some("num_ctx"))
+                    .then(|| parts.next()?.parse::<usize>().ok())
+                    .flatten()
+            })
+        })
+        .unwrap_or(OLLAMA_DEFAULT_NUM_CTX);
+    let context_limit = context_length.map_or(num_ctx, |length| length.min(num_ctx));
+    let has = |capability: &str| {
+        details["capabilities"]
+            .as_array()
+            .map(|c| c.iter().any(|v| v == capability))
+    };
+    ModelInfo {
+        supports_tools: has("tools"),
+        supports_vision: has("vision"),
+        supports_thinking: has("thinking"),
+        ..ModelInfo::new(name, context_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ollama_model() {
+        let details = json!({
+            "parameters": "num_ctx 32768\nstop \"<|im_end|>\"",
+            "model_info": {"qwen2.context_length": 131072},
+            "capabilities": ["completion", "tools"]
+        });
+        let model = parse_ollama_model("qwen2.5", &details);
+        assert_eq!(model.context_limit, 32_768);
+        assert_eq!(model.supports_tools, Some(true));
+        assert_eq!(model.supports_vision, Some(false));
+
+        // Without num_ctx, Ollama's default window is used
+        let details = json!({"model_info": {"llama.context_length": 131072}});
+        let model = parse_ollama_model("llama3.2", &details);
+        assert_eq!(model.context_limit, OLLAMA_DEFAULT_NUM_CTX);
+
+        // num_ctx beyond the architecture's context length is capped
+        let details = json!({
+            "parameters": "num_ctx 65536",
+            "model_info": {"gemma.context_length": 8192}
+        });
+        assert_eq!(parse_ollama_model("gemma", &details).context_limit, 8192);
+    }
 }
@@ -7,7 +7,9 @@ use std::time::Duration;
 
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
-use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::formats::openai::{
+    create_request, get_usage, response_to_json, response_to_message, set_response_schema,
+};
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
 use crate::message::Message;
 use crate::model::ModelConfig;
@@ -170,6 +172,23 @@ impl Provider for OpenAiProvider {
         Ok((message, ProviderUsage::new(model, usage)))
     }
 
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, &[], &ImageFormat::OpenAi)?;
+        set_response_schema(&mut payload, schema);
+
+        let response = self.post(payload.clone()).await?;
+        let value = response_to_json(&response)?;
+        let usage = get_usage(&response).unwrap_or_default();
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((value, ProviderUsage::new(model, usage)))
+    }
+
     /// Fetch supported models from OpenAI; returns Err on any failure, Ok(None) if no data
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
         // List available models via OpenAI API
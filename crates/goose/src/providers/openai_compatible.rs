@@ -0,0 +1,429 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::formats::openai::{create_request, get_usage, response_to_message};
+use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
+use super::utils_universal_openai_stream::{OAIStreamChunk, OAIStreamCollector};
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+use mcp_core::tool::Tool;
+
+/// Config key holding the user-declared providers, keyed by provider name
+pub const CUSTOM_PROVIDERS_CONFIG_KEY: &str = "custom_providers";
+pub const DEFAULT_BASE_PATH: &str = "v1/chat/completions";
+pub const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// How requests to a custom provider are authenticated
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// The key is sent as-is in the named header, e.g. `api-key`
+    Header { name: String },
+    /// No authentication, e.g. for local servers
+    None,
+}
+
+/// Deviations from the OpenAI chat API that some compatible servers have
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProviderQuirks {
+    /// The server does not support tool calling, tools are left out of requests
+    pub no_tools: bool,
+    /// The server does not accept the system role, the system prompt is sent in the first user message
+    pub no_system_role: bool,
+    /// The server only answers with server-sent events
+    pub streaming_only: bool,
+}
+
+/// A provider declared in the config file that speaks the OpenAI chat API
+///
+/// ```yaml
+/// custom_providers:
+///   vllm:
+///     display_name: vLLM
+///     base_url: http://localhost:8000
+///     auth:
+///       type: none
+///     models:
+///       - qwen2.5-coder
+///     quirks:
+///       no_system_role: true
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CustomProviderConfig {
+    /// The name the provider is selected by, taken from its key in the config file
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Scheme and host of the server, e.g. `https://api.together.xyz`
+    pub base_url: String,
+    /// Path of the chat completions endpoint relative to `base_url`
+    #[serde(default)]
+    pub base_path: Option<String>,
+    #[serde(default)]
+    pub auth: AuthScheme,
+    /// Name of the secret holding the API key, defaults to `<NAME>_API_KEY`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Models offered by the provider, the first one is the default
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub quirks: ProviderQuirks,
+}
+
+impl CustomProviderConfig {
+    /// Loads all providers declared in the config file
+    pub fn load_all() -> Result<Vec<CustomProviderConfig>> {
+        let providers: HashMap<String, CustomProviderConfig> =
+            match Config::global().get_param(CUSTOM_PROVIDERS_CONFIG_KEY) {
+                Ok(providers) => providers,
+                Err(crate::config::ConfigError::NotFound(_)) => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            };
+        let mut providers: Vec<CustomProviderConfig> = providers
+            .into_iter()
+            .map(|(name, provider)| CustomProviderConfig { name, ..provider })
+            .collect();
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(providers)
+    }
+
+    /// Finds a declared provider by name
+    pub fn find(name: &str) -> Result<Option<CustomProviderConfig>> {
+        Ok(Self::load_all()?.into_iter().find(|p| p.name == name))
+    }
+
+    /// The name of the secret holding the API key, if the provider needs one
+    pub fn api_key_name(&self) -> Option<String> {
+        match self.auth {
+            AuthScheme::None => None,
+            _ => Some(self.api_key.clone().unwrap_or_else(|| {
+                format!("{}_API_KEY", self.name.to_uppercase().replace('-', "_"))
+            })),
+        }
+    }
+
+    pub fn metadata(&self) -> ProviderMetadata {
+        let display_name = self.display_name.clone().unwrap_or(self.name.clone());
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("OpenAI compatible models at {}", self.base_url));
+        ProviderMetadata::new(
+            &self.name,
+            &display_name,
+            &description,
+            self.models.first().map(String::as_str).unwrap_or_default(),
+            self.models.iter().map(String::as_str).collect(),
+            &self.base_url,
+            self.api_key_name()
+                .map(|key| vec![ConfigKey::new(&key, true, true, None)])
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Runs models of a [`CustomProviderConfig`] using the OpenAI chat format
+#[derive(Debug, serde::Serialize)]
+pub struct OpenAiCompatibleProvider {
+    #[serde(skip)]
+    client: Client,
+    #[serde(skip)]
+    api_key: Option<String>,
+    provider: CustomProviderConfig,
+    model: ModelConfig,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(provider: CustomProviderConfig, model: ModelConfig) -> Result<Self> {
+        let api_key = match provider.api_key_name() {
+            Some(key) => Some(Config::global().get_secret(&key)?),
+            None => None,
+        };
+        let client = Client::builder()
+            .timeout(Duration::from_secs(
+                provider.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ))
+            .build()?;
+
+        Ok(Self {
+            client,
+            api_key,
+            provider,
+            model,
+        })
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match (&self.provider.auth, &self.api_key) {
+            (AuthScheme::Bearer, Some(key)) => request.bearer_auth(key),
+            (AuthScheme::Header { name }, Some(key)) => request.header(name, key),
+            _ => request,
+        }
+    }
+
+    async fn post(&self, mut payload: Value) -> Result<Value, ProviderError> {
+        let base_url = url::Url::parse(&self.provider.base_url)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url
+            .join(
+                self.provider
+                    .base_path
+                    .as_deref()
+                    .unwrap_or(DEFAULT_BASE_PATH),
+            )
+            .map_err(|e| {
+                ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+            })?;
+
+        if self.provider.quirks.streaming_only {
+            payload["stream"] = json!(true);
+        }
+
+        let response = self
+            .authenticate(self.client.post(url))
+            .json(&payload)
+            .send()
+            .await?;
+
+        if self.provider.quirks.streaming_only && response.status().is_success() {
+            collect_stream(&response.text().await?)
+        } else {
+            handle_response_openai_compat(response).await
+        }
+    }
+}
+
+/// Moves the system prompt into the first user message for servers without a system role
+fn merge_system_message(payload: &mut Value) {
+    let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let Some(index) = messages
+        .iter()
+        .position(|m| m["role"] == "system" || m["role"] == "developer")
+    else {
+        return;
+    };
+    let system = messages.remove(index)["content"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    if system.is_empty() {
+        return;
+    }
+
+    match messages.iter_mut().find(|m| m["role"] == "user") {
+        Some(user) => match &mut user["content"] {
+            Value::String(text) => *text = format!("{}\n\n{}", system, text),
+            Value::Array(parts) => parts.insert(0, json!({"type": "text", "text": system})),
+            content => *content = json!(system),
+        },
+        None => messages.insert(0, json!({"role": "user", "content": system})),
+    }
+}
+
+/// Collects server-sent chat completion chunks into a single chat completion response
+fn collect_stream(body: &str) -> Result<Value, ProviderError> {
+    let mut collector = OAIStreamCollector::new();
+    for line in body.lines() {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            continue;
+        };
+        let data = data.trim();
+        if data == "[DONE]" {
+            break;
+        }
+        match serde_json::from_str::<OAIStreamChunk>(data) {
+            Ok(chunk) => collector.add_chunk(&chunk),
+            Err(e) => tracing::debug!("Skipping unparseable stream chunk: {}", e),
+        }
+    }
+    serde_json::to_value(collector.build_response())
+        .map_err(|e| ProviderError::RequestFailed(e.to_string()))
+}
+
+#[async_trait]
+impl Provider for OpenAiCompatibleProvider {
+    /// Custom providers describe themselves through [`CustomProviderConfig::metadata`]
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let tools = if self.provider.quirks.no_tools {
+            &[]
+        } else {
+            tools
+        };
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        if self.provider.quirks.no_system_role {
+            merge_system_message(&mut payload);
+        }
+
+        let response = self.post(payload.clone()).await?;
+
+        let message = response_to_message(response.clone())?;
+        let usage = match get_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = match get_model(&response).as_str() {
+            "" | "Unknown" => self.model.model_name.clone(),
+            model => model.to_string(),
+        };
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        if self.provider.models.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.provider.models.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_provider_config() -> Result<()> {
+        let providers: HashMap<String, CustomProviderConfig> = serde_yaml::from_str(
+            r#"
+together:
+  base_url: https://api.together.xyz
+  models: [meta-llama/Llama-3.3-70B-Instruct-Turbo]
+azure-proxy:
+  base_url: https://proxy.internal
+  auth:
+    type: header
+    name: api-key
+  api_key: PROXY_KEY
+lmstudio:
+  display_name: LM Studio
+  base_url: http://localhost:1234
+  auth:
+    type: none
+  quirks:
+    no_tools: true
+    streaming_only: true
+"#,
+        )?;
+
+        let together = CustomProviderConfig {
+            name: "together".to_string(),
+            ..providers["together"].clone()
+        };
+        assert_eq!(together.auth, AuthScheme::Bearer);
+        assert_eq!(together.quirks, ProviderQuirks::default());
+        assert_eq!(together.api_key_name().as_deref(), Some("TOGETHER_API_KEY"));
+        let metadata = together.metadata();
+        assert_eq!(metadata.display_name, "together");
+        assert_eq!(
+            metadata.default_model,
+            "meta-llama/Llama-3.3-70B-Instruct-Turbo"
+        );
+        assert_eq!(metadata.config_keys[0].name, "TOGETHER_API_KEY");
+
+        let proxy = &providers["azure-proxy"];
+        assert_eq!(
+            proxy.auth,
+            AuthScheme::Header {
+                name: "api-key".to_string()
+            }
+        );
+        assert_eq!(proxy.api_key_name().as_deref(), Some("PROXY_KEY"));
+
+        let lmstudio = &providers["lmstudio"];
+        assert_eq!(lmstudio.api_key_name(), None);
+        assert!(lmstudio.metadata().config_keys.is_empty());
+        assert!(lmstudio.quirks.no_tools && lmstudio.quirks.streaming_only);
+        assert!(!lmstudio.quirks.no_system_role);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_system_message() {
+        let mut payload = json!({
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hello"}
+            ]
+        });
+        merge_system_message(&mut payload);
+        assert_eq!(
+            payload["messages"],
+            json!([{"role": "user", "content": "Be brief.\n\nHello"}])
+        );
+
+        let mut payload = json!({
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": [{"type": "image_url", "image_url": {"url": "x"}}]}
+            ]
+        });
+        merge_system_message(&mut payload);
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            payload["messages"][0]["content"][0],
+            json!({"type": "text", "text": "Be brief."})
+        );
+    }
+
+    #[test]
+    fn test_collect_stream() -> Result<()> {
+        let body = concat!(
+            "data: {\"id\":\"1\",\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"1\",\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}],",
+            "\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"total_tokens\":5}}\n\n",
+            "data: [DONE]\n\n"
+        );
+        let response = collect_stream(body)?;
+        assert_eq!(response["choices"][0]["message"]["content"], "Hello");
+        assert_eq!(response["model"], "m");
+
+        let message = response_to_message(response.clone())?;
+        assert_eq!(message.as_concat_text(), "Hello");
+        let usage = get_usage(&response)?;
+        assert_eq!(usage.total_tokens, Some(5));
+        Ok(())
+    }
+}
@@ -4,7 +4,8 @@ use reqwest::Client;
 use serde_json::{json, Value};
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::cache::{ephemeral_cache_control, mark_cacheable, CachePlan};
 use super::errors::ProviderError;
 use super::utils::{
     emit_debug_trace, get_model, handle_response_google_compat, handle_response_openai_compat,
@@ -113,7 +114,7 @@ impl OpenRouterProvider {
             // Return appropriate error based on the OpenRouter error code
             match error_code {
                 401 | 403 => return Err(ProviderError::Authentication(error_message.to_string())),
-                429 => return Err(ProviderError::RateLimitExceeded(error_message.to_string())),
+                429 => return Err(ProviderError::rate_limit_exceeded(error_message)),
                 500 | 503 => return Err(ProviderError::ServerError(error_message.to_string())),
                 _ => return Err(ProviderError::RequestFailed(error_message.to_string())),
             }
@@ -127,7 +128,7 @@ impl OpenRouterProvider {
 /// Update the request when using anthropic model.
 /// For anthropic model, we can enable prompt caching to save cost. Since openrouter is the OpenAI compatible
 /// endpoint, we need to modify the open ai request to have anthropic cache control field.
-fn update_request_for_anthropic(original_payload: &Value) -> Value {
+fn update_request_for_anthropic(original_payload: &Value, plan: CachePlan) -> Value {
     let mut payload = original_payload.clone();
 
     if let Some(messages_spec) = payload
@@ -135,25 +136,18 @@ fn update_request_for_anthropic(original_payload: &Value) -> Value {
         .and_then(|obj| obj.get_mut("messages"))
         .and_then(|messages| messages.as_array_mut())
     {
-        // Add "cache_control" to the last and second-to-last "user" messages.
-        // During each turn, we mark the final message with cache_control so the conversation can be
-        // incrementally cached. The second-to-last user message is also marked for caching with the
-        // cache_control parameter, so that this checkpoint can read from the previous cache.
-        let mut user_count = 0;
-        for message in messages_spec.iter_mut().rev() {
-            if message.get("role") == Some(&json!("user")) {
-                if let Some(content) = message.get_mut("content") {
-                    if let Some(content_str) = content.as_str() {
-                        *content = json!([{
-                            "type": "text",
-                            "text": content_str,
-                            "cache_control": { "type": "ephemeral" }
-                        }]);
-                    }
-                }
-                user_count += 1;
-                if user_count >= 2 {
-                    break;
+        // Mark the latest user messages, so the conversation so far is cached
+        let breakpoints = plan.conversation_breakpoints(messages_spec, |message| {
+            message.get("role") == Some(&json!("user"))
+        });
+        for index in breakpoints {
+            if let Some(content) = messages_spec[index].get_mut("content") {
+                if let Some(content_str) = content.as_str() {
+                    *content = json!([{
+                        "type": "text",
+                        "text": content_str,
+                        "cache_control": ephemeral_cache_control()
+                    }]);
                 }
             }
         }
@@ -162,6 +156,7 @@ fn update_request_for_anthropic(original_payload: &Value) -> Value {
         if let Some(system_message) = messages_spec
             .iter_mut()
             .find(|msg| msg.get("role") == Some(&json!("system")))
+            .filter(|_| plan.system)
         {
             if let Some(content) = system_message.get_mut("content") {
                 if let Some(content_str) = content.as_str() {
@@ -170,7 +165,7 @@ fn update_request_for_anthropic(original_payload: &Value) -> Value {
                         "content": [{
                             "type": "text",
                             "text": content_str,
-                            "cache_control": { "type": "ephemeral" }
+                            "cache_control": ephemeral_cache_control()
                         }]
                     });
                 }
@@ -182,16 +177,15 @@ fn update_request_for_anthropic(original_payload: &Value) -> Value {
         .as_object_mut()
         .and_then(|obj| obj.get_mut("tools"))
         .and_then(|tools| tools.as_array_mut())
+        .filter(|_| plan.tools)
     {
         // Add "cache_control" to the last tool spec, if any. This means that all tool definitions,
         // will be cached as a single prefix.
-        if let Some(last_tool) = tools_spec.last_mut() {
-            if let Some(function) = last_tool.get_mut("function") {
-                function
-                    .as_object_mut()
-                    .unwrap()
-                    .insert("cache_control".to_string(), json!({ "type": "ephemeral" }));
-            }
+        if let Some(function) = tools_spec
+            .last_mut()
+            .and_then(|last_tool| last_tool.get_mut("function"))
+        {
+            mark_cacheable(function);
         }
     }
     payload
@@ -215,7 +209,7 @@ fn create_request_based_on_model(
         .model_name
         .starts_with(OPENROUTER_MODEL_PREFIX_ANTHROPIC)
     {
-        payload = update_request_for_anthropic(&payload);
+        payload = update_request_for_anthropic(&payload, CachePlan::from_config());
     }
 
     Ok(payload)
@@ -277,4 +271,84 @@ impl Provider for OpenRouterProvider {
         emit_debug_trace(&self.model, &payload, &response, &usage);
         Ok((message, ProviderUsage::new(model, usage)))
     }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        Ok(self
+            .fetch_model_info_async()
+            .await?
+            .map(|models| models.into_iter().map(|m| m.name).collect()))
+    }
+
+    /// The models list reports limits, capabilities and prices of every model
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("api/v1/models").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+        let json = handle_response_openai_compat(response).await?;
+        Ok(json["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(parse_openrouter_model).collect()))
+    }
+}
+
+/// Reads a model of the models list, where prices are in USD per token
+fn parse_openrouter_model(model: &Value) -> Option<ModelInfo> {
+    let per_million = |price: &Value| {
+        price
+            .as_str()
+            .and_then(|p| p.parse::<f64>().ok())
+            .map(|p| p * 1_000_000.0)
+    };
+    let has = |list: &Value, item: &str| list.as_array().map(|l| l.iter().any(|v| v == item));
+
+    Some(ModelInfo {
+        max_output_tokens: model["top_provider"]["max_completion_tokens"]
+            .as_u64()
+            .map(|v| v as usize),
+        supports_tools: has(&model["supported_parameters"], "tools"),
+        supports_vision: has(&model["architecture"]["input_modalities"], "image"),
+        supports_thinking: has(&model["supported_parameters"], "reasoning"),
+        input_cost_per_million: per_million(&model["pricing"]["prompt"]),
+        output_cost_per_million: per_million(&model["pricing"]["completion"]),
+        ..ModelInfo::new(
+            model["id"].as_str()?,
+            model["context_length"].as_u64()? as usize,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openrouter_model() {
+        let model = json!({
+            "id": "anthropic/claude-sonnet-4",
+            "context_length": 200000,
+            "architecture": {"input_modalities": ["image", "text", "file"]},
+            "pricing": {"prompt": "0.000003", "completion": "0.000015"},
+            "top_provider": {"max_completion_tokens": 64000},
+            "supported_parameters": ["max_tokens", "reasoning", "tools"]
+        });
+        let info = parse_openrouter_model(&model).unwrap();
+        assert_eq!(info.name, "anthropic/claude-sonnet-4");
+        assert_eq!(info.context_limit, 200_000);
+        assert_eq!(info.max_output_tokens, Some(64_000));
+        assert_eq!(info.supports_tools, Some(true));
+        assert_eq!(info.supports_vision, Some(true));
+        assert_eq!(info.supports_thinking, Some(true));
+        assert!((info.input_cost_per_million.unwrap() - 3.0).abs() < 1e-9);
+        assert!((info.output_cost_per_million.unwrap() - 15.0).abs() < 1e-9);
+
+        assert!(parse_openrouter_model(&json!({"id": "no/context"})).is_none());
+    }
 }
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use super::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+/// Recorded responses, keyed by the hash of the request that produced them
+pub type Fixtures = BTreeMap<String, Message>;
+
+/// Placeholder for the working directory in hashed requests
+const WORKDIR_PLACEHOLDER: &str = "{{ workdir }}";
+
+/// Placeholder for the current date and time in hashed system prompts
+const DATE_TIME_PLACEHOLDER: &str = "{{ current_date_time }}";
+
+static DATE_TIME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}").expect("valid regex"));
+
+/// Hashes the system prompt and conversation sent to a provider, so a recorded response can be
+/// found again, and changing a recipe's instructions calls for new recordings
+///
+/// The tools depend on the local configuration, so they are left out. The current date in the
+/// system prompt and message timestamps are ignored, and `workdir` is replaced by a placeholder.
+pub fn request_hash(system: &str, messages: &[Message], workdir: Option<&str>) -> String {
+    let messages: Vec<Message> = messages
+        .iter()
+        .cloned()
+        .map(|mut message| {
+            message.created = 0;
+            message
+        })
+        .collect();
+    let system = DATE_TIME.replace_all(system, DATE_TIME_PLACEHOLDER);
+    let mut serialized = serde_json::to_string(&(system, messages))
+        .expect("system prompt and messages serialize to JSON");
+    if let Some(workdir) = workdir.filter(|workdir| !workdir.is_empty()) {
+        serialized = serialized.replace(workdir, WORKDIR_PLACEHOLDER);
+    }
+    format!("{:x}", Sha256::digest(serialized.as_bytes()))
+}
+
+/// A provider that answers from recorded fixtures and scripted responses, without any network calls
+///
+/// A request whose hash is in the fixtures gets the recorded response. Otherwise the next scripted
+/// response is returned, and once those run out the request fails.
+pub struct ReplayProvider {
+    fixtures: Fixtures,
+    script: Mutex<VecDeque<Message>>,
+    misses: Mutex<Vec<String>>,
+    workdir: Option<String>,
+    model: ModelConfig,
+}
+
+impl ReplayProvider {
+    pub fn new(fixtures: Fixtures) -> Self {
+        Self {
+            fixtures,
+            script: Mutex::new(VecDeque::new()),
+            misses: Mutex::new(Vec::new()),
+            workdir: None,
+            model: ModelConfig::new("replay".to_string()),
+        }
+    }
+
+    /// Responses returned in order for requests that have no recorded response
+    pub fn with_script(self, script: Vec<Message>) -> Self {
+        Self {
+            script: Mutex::new(script.into()),
+            ..self
+        }
+    }
+
+    /// The directory the run works in, which is left out of the request hashes
+    pub fn with_workdir(self, workdir: impl Into<String>) -> Self {
+        Self {
+            workdir: Some(workdir.into()),
+            ..self
+        }
+    }
+
+    /// Hashes of the requests that had no response to replay
+    pub fn misses(&self) -> Vec<String> {
+        self.misses.lock().expect("misses lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let hash = request_hash(system, messages, self.workdir.as_deref());
+        let scripted = || {
+            self.script
+                .lock()
+                .expect("script lock poisoned")
+                .pop_front()
+        };
+        let Some(response) = self.fixtures.get(&hash).cloned().or_else(scripted) else {
+            self.misses
+                .lock()
+                .expect("misses lock poisoned")
+                .push(hash.clone());
+            return Err(ProviderError::ExecutionError(format!(
+                "No recorded response for request {}, record the fixtures again",
+                hash
+            )));
+        };
+        Ok((
+            response,
+            ProviderUsage::new(self.model.model_name.clone(), Usage::default()),
+        ))
+    }
+}
+
+/// A provider that passes requests to another provider and records the responses as fixtures
+pub struct RecordingProvider {
+    inner: Arc<dyn Provider>,
+    recorded: Mutex<Fixtures>,
+    workdir: Option<String>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn Provider>) -> Self {
+        Self {
+            inner,
+            recorded: Mutex::new(Fixtures::new()),
+            workdir: None,
+        }
+    }
+
+    /// The directory the run works in, which is left out of the request hashes
+    pub fn with_workdir(self, workdir: impl Into<String>) -> Self {
+        Self {
+            workdir: Some(workdir.into()),
+            ..self
+        }
+    }
+
+    /// The responses recorded so far
+    pub fn fixtures(&self) -> Fixtures {
+        self.recorded
+            .lock()
+            .expect("fixtures lock poisoned")
+            .clone()
+    }
+}
+
+#[async_trait]
+impl Provider for RecordingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (response, usage) = self.inner.complete(system, messages, tools).await?;
+        let hash = request_hash(system, messages, self.workdir.as_deref());
+        self.recorded
+            .lock()
+            .expect("fixtures lock poisoned")
+            .insert(hash, response.clone());
+        Ok((response, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let script = vec![
+            Message::assistant().with_text("first"),
+            Message::assistant().with_text("second"),
+        ];
+        let scripted: Arc<dyn Provider> =
+            Arc::new(ReplayProvider::new(Fixtures::new()).with_script(script));
+        let recorder = RecordingProvider::new(scripted).with_workdir("/tmp/case-1");
+
+        let request = vec![Message::user().with_text("Look at /tmp/case-1/notes.md")];
+        let (response, _) = recorder
+            .complete("The date is 2025-01-01 09:00:00.", &request, &[])
+            .await
+            .unwrap();
+        assert_eq!(response.as_concat_text(), "first");
+
+        // The same request in another directory and at another time gets the recorded response
+        let replay = ReplayProvider::new(recorder.fixtures()).with_workdir("/tmp/case-2");
+        let mut request = vec![Message::user().with_text("Look at /tmp/case-2/notes.md")];
+        request[0].created += 60;
+        let (response, _) = replay
+            .complete("The date is 2025-03-04 17:30:12.", &request, &[])
+            .await
+            .unwrap();
+        assert_eq!(response.as_concat_text(), "first");
+
+        let request = vec![Message::user().with_text("Something else")];
+        let err = replay.complete("system", &request, &[]).await.unwrap_err();
+        assert!(err.to_string().contains("No recorded response"));
+
+        // Other instructions need new recordings
+        let request = vec![Message::user().with_text("Look at /tmp/case-2/notes.md")];
+        assert!(replay
+            .complete("Other instructions", &request, &[])
+            .await
+            .is_err());
+        assert_eq!(replay.misses().len(), 2);
+    }
+}
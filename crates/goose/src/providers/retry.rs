@@ -0,0 +1,291 @@
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use mcp_core::tool::Tool;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::time::sleep;
+
+use super::base::{ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::config::Config;
+use crate::message::Message;
+use crate::model::ModelConfig;
+
+/// Default maximum number of retries
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+/// Default initial interval for retry (in milliseconds)
+pub const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 1000;
+/// Default retry backoff multiplier
+pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+/// Default maximum interval for retry (in milliseconds)
+pub const DEFAULT_MAX_RETRY_INTERVAL_MS: u64 = 30_000;
+
+/// Retry configuration for requests that fail with a retryable error
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Maximum number of retry attempts
+    pub max_retries: usize,
+    /// Initial interval between retries in milliseconds
+    pub initial_interval_ms: u64,
+    /// Multiplier for backoff (exponential)
+    pub backoff_multiplier: f64,
+    /// Maximum interval between retries in milliseconds
+    pub max_interval_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
+            backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+            max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Loads the retry configuration from `<prefix>_MAX_RETRIES`,
+    /// `<prefix>_INITIAL_RETRY_INTERVAL_MS`, `<prefix>_BACKOFF_MULTIPLIER` and
+    /// `<prefix>_MAX_RETRY_INTERVAL_MS`, using `defaults` for the ones that are not set
+    pub fn from_config(prefix: &str, defaults: RetryConfig) -> Self {
+        let config = Config::global();
+        let param = |name: &str| format!("{}_{}", prefix, name);
+        Self {
+            max_retries: get_number(config, &param("MAX_RETRIES")).unwrap_or(defaults.max_retries),
+            initial_interval_ms: get_number(config, &param("INITIAL_RETRY_INTERVAL_MS"))
+                .unwrap_or(defaults.initial_interval_ms),
+            backoff_multiplier: get_number(config, &param("BACKOFF_MULTIPLIER"))
+                .unwrap_or(defaults.backoff_multiplier),
+            max_interval_ms: get_number(config, &param("MAX_RETRY_INTERVAL_MS"))
+                .unwrap_or(defaults.max_interval_ms),
+        }
+    }
+
+    /// Calculate the delay for a specific retry attempt (with jitter)
+    pub fn delay_for_attempt(&self, attempt: usize) -> Duration {
+        if attempt == 0 {
+            return Duration::from_millis(0);
+        }
+
+        // Calculate exponential backoff
+        let exponent = (attempt - 1) as u32;
+        let base_delay_ms = (self.initial_interval_ms as f64
+            * self.backoff_multiplier.powi(exponent as i32)) as u64;
+
+        // Apply max limit
+        let capped_delay_ms = std::cmp::min(base_delay_ms, self.max_interval_ms);
+
+        // Add jitter (+/-20% randomness) to avoid thundering herd problem
+        let jitter_factor = 0.8 + (rand::random::<f64>() * 0.4); // Between 0.8 and 1.2
+        let jittered_delay_ms = (capped_delay_ms as f64 * jitter_factor) as u64;
+
+        Duration::from_millis(jittered_delay_ms)
+    }
+}
+
+/// Reads a number that may be stored as a number or as a string
+fn get_number<T: FromStr + DeserializeOwned>(config: &Config, key: &str) -> Option<T> {
+    config.get_param::<T>(key).ok().or_else(|| {
+        config
+            .get_param::<String>(key)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+    })
+}
+
+/// Retries requests of a provider that fail with a retryable error
+///
+/// Rate limits, server errors and network errors are retried with jittered exponential
+/// backoff, or after the delay the provider asked for. Other errors, and requests the provider
+/// asks to retry only after more than the maximum interval, are returned right away.
+pub struct RetryProvider {
+    inner: Arc<dyn Provider>,
+    config: RetryConfig,
+}
+
+impl RetryProvider {
+    pub fn new(inner: Arc<dyn Provider>, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+
+    async fn with_retries<T, F, Fut>(&self, request: F) -> Result<T, ProviderError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let max_delay = Duration::from_millis(self.config.max_interval_ms);
+        let mut attempts = 0;
+        loop {
+            match request().await {
+                Err(error)
+                    if error.is_retryable()
+                        && attempts < self.config.max_retries
+                        && error.retry_delay().is_none_or(|delay| delay <= max_delay) =>
+                {
+                    attempts += 1;
+                    let delay = error
+                        .retry_delay()
+                        .unwrap_or_else(|| self.config.delay_for_attempt(attempts));
+                    tracing::warn!(
+                        attempt = attempts,
+                        max_retries = self.config.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        "Retrying provider request after error: {}",
+                        error
+                    );
+                    sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RetryProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.with_retries(|| self.inner.complete(system, messages, tools))
+            .await
+    }
+
+    async fn complete_structured(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Value, ProviderUsage), ProviderError> {
+        self.with_retries(|| self.inner.complete_structured(system, messages, schema))
+            .await
+    }
+
+    async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models_async().await
+    }
+
+    async fn fetch_model_info_async(&self) -> Result<Option<Vec<ModelInfo>>, ProviderError> {
+        self.inner.fetch_model_info_async().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FlakyProvider {
+        calls: AtomicUsize,
+        failures: usize,
+        error: fn() -> ProviderError,
+    }
+
+    #[async_trait]
+    impl Provider for FlakyProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("flaky".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok((
+                Message::assistant().with_text("done"),
+                ProviderUsage::new("flaky".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    async fn calls_until_done(failures: usize, error: fn() -> ProviderError) -> (usize, bool) {
+        let inner = Arc::new(FlakyProvider {
+            calls: AtomicUsize::new(0),
+            failures,
+            error,
+        });
+        let config = RetryConfig {
+            max_retries: 2,
+            initial_interval_ms: 1,
+            backoff_multiplier: 2.0,
+            max_interval_ms: 5,
+        };
+        let provider = RetryProvider::new(inner.clone(), config);
+        let result = provider.complete("", &[], &[]).await;
+        (inner.calls.load(Ordering::SeqCst), result.is_ok())
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_errors() {
+        let rate_limited = || ProviderError::RateLimitExceeded {
+            details: "slow down".to_string(),
+            retry_delay: Some(Duration::from_millis(1)),
+        };
+        assert_eq!(calls_until_done(2, rate_limited).await, (3, true));
+        assert_eq!(calls_until_done(3, rate_limited).await, (3, false));
+
+        // Waiting longer than the maximum interval is not worth it
+        let rate_limited_for_long = || ProviderError::RateLimitExceeded {
+            details: "come back tomorrow".to_string(),
+            retry_delay: Some(Duration::from_secs(86_400)),
+        };
+        assert_eq!(calls_until_done(1, rate_limited_for_long).await, (1, false));
+
+        let unavailable = || ProviderError::ServerError("unavailable".to_string());
+        assert_eq!(calls_until_done(1, unavailable).await, (2, true));
+
+        let unauthorized = || ProviderError::Authentication("bad key".to_string());
+        assert_eq!(calls_until_done(1, unauthorized).await, (1, false));
+    }
+
+    #[test]
+    fn test_retry_config_delay_calculation() {
+        let config = RetryConfig {
+            max_retries: 5,
+            initial_interval_ms: 1000,
+            backoff_multiplier: 2.0,
+            max_interval_ms: 32000,
+        };
+
+        // First attempt has no delay
+        let delay0 = config.delay_for_attempt(0);
+        assert_eq!(delay0.as_millis(), 0);
+
+        // First retry should be around initial_interval with jitter
+        let delay1 = config.delay_for_attempt(1);
+        assert!(delay1.as_millis() >= 800 && delay1.as_millis() <= 1200);
+
+        // Second retry should be around initial_interval * multiplier^1 with jitter
+        let delay2 = config.delay_for_attempt(2);
+        assert!(delay2.as_millis() >= 1600 && delay2.as_millis() <= 2400);
+
+        // Check that max interval is respected
+        let delay10 = config.delay_for_attempt(10);
+        assert!(delay10.as_millis() <= 38400); // max_interval_ms * 1.2 (max jitter)
+    }
+}
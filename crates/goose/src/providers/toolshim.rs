@@ -7,39 +7,48 @@
 //!
 //! ToolShim addresses the challenge of working with models that don't natively support tools by:
 //!
-//! 1. Taking the text output from any LLM
-//! 2. Sending it to a separate "interpreter" model (which can be the same or different model)
-//! 3. Using a model to extract tool call intentions into the appropriate format
-//! 4. Converting the outputs of the interpreter model into proper tool call structs
+//! 1. Describing the tools and how to call them in the system prompt
+//! 2. Taking the text output from any LLM
+//! 3. Using an "interpreter" to extract tool call intentions from the text, either by parsing it or by asking a model
+//! 4. Converting the outputs of the interpreter into proper tool call structs
 //! 5. Augmenting the original message with the extracted tool calls
 //!
 //! ## Key Components
 //!
 //! ### ToolInterpreter Trait
 //!
-//! The core of ToolShim is the `ToolInterpreter` trait, which defines the interface for any model that can interpret text and extract tool calls.
+//! The core of ToolShim is the `ToolInterpreter` trait, which defines the interface for anything that can interpret text and extract tool calls,
+//! along with the tool instructions it expects in the system prompt.
 //!
 //! ### Implementations
 //!
-//! The module provides an implementation for Ollama:
+//! The interpreter is selected with `GOOSE_TOOLSHIM_INTERPRETER`:
 //!
-//! - `OllamaInterpreter`: Uses Ollama's structured output API to interpret tool calls
+//! - `ollama` (default), `OllamaInterpreter`: Uses Ollama's structured output API to interpret tool calls with a separate model
+//! - `json`, `JsonBlockInterpreter`: Parses the JSON tool calls, preferably in ```json blocks, out of the text
+//! - `hermes`, `HermesInterpreter`: Prompts for and parses Hermes style `<tool_call>` tags
+//! - `provider`, `ProviderInterpreter`: Asks the provider that wrote the text again, constrained to the tool call schema
 //!
 //! ### Helper Functions
 //!
+//! - `create_interpreter`: Creates the configured interpreter for a provider
 //! - `augment_message_with_tool_calls`: A utility function that takes any message, extracts text content, sends it to an interpreter, and adds any detected tool calls back to the message.
 //!
 
+use super::base::Provider;
 use super::errors::ProviderError;
 use super::ollama::OLLAMA_DEFAULT_PORT;
 use super::ollama::OLLAMA_HOST;
+use super::utils::extract_json_values;
 use crate::message::{Message, MessageContent};
 use crate::model::ModelConfig;
 use crate::providers::formats::openai::create_request;
 use anyhow::Result;
 use mcp_core::tool::{Tool, ToolCall};
+use regex::Regex;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
@@ -48,16 +57,219 @@ pub const DEFAULT_INTERPRETER_MODEL_OLLAMA: &str = "mistral-nemo";
 
 /// Environment variables that affect behavior:
 /// - GOOSE_TOOLSHIM: When set to "true" or "1", enables using the tool shim in the standard OllamaProvider (default: false)
+/// - GOOSE_TOOLSHIM_INTERPRETER: The interpreter to use, "ollama", "json", "hermes" or "provider" (default: "ollama")
 /// - GOOSE_TOOLSHIM_OLLAMA_MODEL: Ollama model to use as the tool interpreter (default: DEFAULT_INTERPRETER_MODEL)
 /// A trait for models that can interpret text into structured tool call JSON format
 #[async_trait::async_trait]
-pub trait ToolInterpreter {
+pub trait ToolInterpreter: Send + Sync {
     /// Interpret potential tool calls from text and convert them to proper tool call JSON format
     async fn interpret_to_tool_calls(
         &self,
         content: &str,
         tools: &[Tool],
     ) -> Result<Vec<ToolCall>, ProviderError>;
+
+    /// Adds the instructions for calling tools that this interpreter understands to the system prompt
+    fn modify_system_prompt(&self, system_prompt: &str, tools: &[Tool]) -> String {
+        modify_system_prompt_for_tool_json(system_prompt, tools)
+    }
+}
+
+/// Creates the interpreter configured with GOOSE_TOOLSHIM_INTERPRETER
+///
+/// The provider is the one whose replies are interpreted, it is only used by the "provider" interpreter.
+pub fn create_interpreter(
+    provider: Arc<dyn Provider>,
+) -> Result<Box<dyn ToolInterpreter>, ProviderError> {
+    let config = crate::config::Config::global();
+    let name: String = config
+        .get_param("GOOSE_TOOLSHIM_INTERPRETER")
+        .unwrap_or_else(|_| "ollama".to_string());
+
+    match name.to_lowercase().as_str() {
+        "ollama" => Ok(Box::new(OllamaInterpreter::new()?)),
+        "json" => Ok(Box::new(JsonBlockInterpreter)),
+        "hermes" => Ok(Box::new(HermesInterpreter)),
+        "provider" => Ok(Box::new(ProviderInterpreter::new(provider))),
+        _ => Err(ProviderError::ExecutionError(format!(
+            "Unknown tool shim interpreter '{}', expected one of ollama, json, hermes or provider",
+            name
+        ))),
+    }
+}
+
+/// Converts parsed JSON into tool calls for the given tools
+///
+/// Accepts a single `{"name": ..., "arguments": ...}` call, `parameters` in place of `arguments`,
+/// OpenAI style `{"function": {...}}` calls, `{"tool_calls": [...]}` and arrays of any of these.
+/// Calls to tools that do not exist, like the "noop" tool, are dropped.
+pub fn parse_tool_calls(value: &Value, tools: &[Tool]) -> Vec<ToolCall> {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .flat_map(|item| parse_tool_calls(item, tools))
+            .collect(),
+        Value::Object(object) => {
+            if let Some(calls) = object.get("tool_calls") {
+                return parse_tool_calls(calls, tools);
+            }
+            if let Some(function) = object.get("function").filter(|f| f.is_object()) {
+                return parse_tool_calls(function, tools);
+            }
+            let Some(name) = object.get("name").and_then(|n| n.as_str()) else {
+                return vec![];
+            };
+            if !tools.iter().any(|tool| tool.name == name) {
+                return vec![];
+            }
+            let arguments = match object.get("arguments").or_else(|| object.get("parameters")) {
+                // Some models write the arguments as a JSON string, like the OpenAI API does
+                Some(Value::String(text)) => serde_json::from_str(text).unwrap_or(json!({})),
+                Some(arguments @ Value::Object(_)) => arguments.clone(),
+                _ => json!({}),
+            };
+            vec![ToolCall::new(name, arguments)]
+        }
+        _ => vec![],
+    }
+}
+
+/// Parses the JSON tool calls that the model wrote into its reply
+///
+/// Works with models that follow the JSON tool instructions, with or without ```json blocks,
+/// and needs no second model.
+pub struct JsonBlockInterpreter;
+
+#[async_trait::async_trait]
+impl ToolInterpreter for JsonBlockInterpreter {
+    async fn interpret_to_tool_calls(
+        &self,
+        content: &str,
+        tools: &[Tool],
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        Ok(extract_json_values(content)
+            .iter()
+            .flat_map(|value| parse_tool_calls(value, tools))
+            .collect())
+    }
+}
+
+/// Prompts for and parses tool calls in the Hermes format, used by Hermes, Qwen and many
+/// fine tunes, which write each call as `<tool_call>{"name": ..., "arguments": ...}</tool_call>`
+pub struct HermesInterpreter;
+
+#[async_trait::async_trait]
+impl ToolInterpreter for HermesInterpreter {
+    async fn interpret_to_tool_calls(
+        &self,
+        content: &str,
+        tools: &[Tool],
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        // The closing tag is often missing when the model stops right after the call
+        let re = Regex::new(r"(?s)<tool_call>(.*?)(?:</tool_call>|$)").unwrap();
+        Ok(re
+            .captures_iter(content)
+            .flat_map(|c| {
+                let body = c[1].trim();
+                let values = match serde_json::from_str::<Value>(body) {
+                    Ok(value) => vec![value],
+                    Err(_) => extract_json_values(body),
+                };
+                values
+                    .iter()
+                    .flat_map(|value| parse_tool_calls(value, tools))
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+
+    fn modify_system_prompt(&self, system_prompt: &str, tools: &[Tool]) -> String {
+        let signatures: Vec<String> = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema
+                    }
+                })
+                .to_string()
+            })
+            .collect();
+
+        format!(
+            "{}\n\nYou may call functions to assist with the user query. You are provided with function signatures within <tools></tools> XML tags:\n<tools>\n{}\n</tools>\n\nFor each function call, return a json object with the function name and arguments within <tool_call></tool_call> XML tags:\n<tool_call>\n{{\"name\": <function-name>, \"arguments\": <args-json-object>}}\n</tool_call>\nDo one step and function call at a time, then wait for the result before deciding on the next step.",
+            system_prompt,
+            signatures.join("\n")
+        )
+    }
+}
+
+/// Asks the provider that wrote the reply to restate its tool calls as JSON following the
+/// tool call schema, which providers with structured output enforce while generating
+pub struct ProviderInterpreter {
+    provider: Arc<dyn Provider>,
+}
+
+impl ProviderInterpreter {
+    pub fn new(provider: Arc<dyn Provider>) -> Self {
+        Self { provider }
+    }
+
+    fn tool_calls_schema(tools: &[Tool]) -> Value {
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        json!({
+            "type": "object",
+            "properties": {
+                "tool_calls": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": {
+                                "type": "string",
+                                "enum": names,
+                                "description": "The name of the tool to call"
+                            },
+                            "arguments": {
+                                "type": "object",
+                                "description": "The arguments to pass to the tool"
+                            }
+                        },
+                        "required": ["name", "arguments"]
+                    }
+                }
+            },
+            "required": ["tool_calls"]
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ToolInterpreter for ProviderInterpreter {
+    async fn interpret_to_tool_calls(
+        &self,
+        content: &str,
+        tools: &[Tool],
+    ) -> Result<Vec<ToolCall>, ProviderError> {
+        if tools.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let system_prompt = format!(
+            "You extract the tool calls that an assistant asked for in its reply. These are the available tools:\n\n{}List every tool call in the reply with its arguments. If the reply does not ask for a tool call, return an empty list of tool calls.",
+            format_tool_info(tools)
+        );
+        let messages = vec![Message::user().with_text(format!("Reply: {}", content))];
+        let (value, _) = self
+            .provider
+            .complete_structured(&system_prompt, &messages, &Self::tool_calls_schema(tools))
+            .await?;
+
+        Ok(parse_tool_calls(&value, tools))
+    }
 }
 
 /// Ollama-specific implementation of the ToolInterpreter trait
@@ -310,7 +522,7 @@ pub fn modify_system_prompt_for_tool_json(system_prompt: &str, tools: &[Tool]) -
 }
 
 /// Helper function to augment a message with tool calls if any are detected
-pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
+pub async fn augment_message_with_tool_calls<T: ToolInterpreter + ?Sized>(
     interpreter: &T,
     message: Message,
     tools: &[Tool],
@@ -364,3 +576,112 @@ pub async fn augment_message_with_tool_calls<T: ToolInterpreter>(
 
     Ok(final_message)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+
+    fn tools() -> Vec<Tool> {
+        vec![Tool::new(
+            "developer__shell",
+            "Run a shell command",
+            json!({"type": "object", "properties": {"command": {"type": "string"}}}),
+            None,
+        )]
+    }
+
+    #[tokio::test]
+    async fn test_json_block_interpreter() -> Result<()> {
+        let content = "I'll list the files.\n```json\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}\n```";
+        let calls = JsonBlockInterpreter
+            .interpret_to_tool_calls(content, &tools())
+            .await?;
+        assert_eq!(
+            calls,
+            vec![ToolCall::new("developer__shell", json!({"command": "ls"}))]
+        );
+
+        // Bare JSON, string arguments and unknown tools
+        let content = r#"{"tool_calls": [{"function": {"name": "developer__shell", "arguments": "{\"command\": \"pwd\"}"}}, {"name": "noop", "arguments": {}}]}"#;
+        let calls = JsonBlockInterpreter
+            .interpret_to_tool_calls(content, &tools())
+            .await?;
+        assert_eq!(
+            calls,
+            vec![ToolCall::new("developer__shell", json!({"command": "pwd"}))]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hermes_interpreter() -> Result<()> {
+        let content = "Checking.\n<tool_call>\n{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}\n</tool_call>\n<tool_call>{\"name\": \"developer__shell\", \"parameters\": {\"command\": \"pwd\"}}";
+        let calls = HermesInterpreter
+            .interpret_to_tool_calls(content, &tools())
+            .await?;
+        assert_eq!(
+            calls,
+            vec![
+                ToolCall::new("developer__shell", json!({"command": "ls"})),
+                ToolCall::new("developer__shell", json!({"command": "pwd"})),
+            ]
+        );
+
+        let prompt = HermesInterpreter.modify_system_prompt("You are goose.", &tools());
+        assert!(prompt.starts_with("You are goose."));
+        assert!(prompt.contains("\"name\":\"developer__shell\""));
+        assert!(prompt.contains("<tool_call>"));
+        Ok(())
+    }
+
+    struct FixedProvider {
+        reply: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for FixedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("fixed".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(self.reply),
+                ProviderUsage::new("fixed".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_interpreter() -> Result<()> {
+        let interpreter = ProviderInterpreter::new(Arc::new(FixedProvider {
+            reply: "```json\n{\"tool_calls\": [{\"name\": \"developer__shell\", \"arguments\": {\"command\": \"ls\"}}]}\n```",
+        }));
+        let calls = interpreter
+            .interpret_to_tool_calls("Let me run ls", &tools())
+            .await?;
+        assert_eq!(
+            calls,
+            vec![ToolCall::new("developer__shell", json!({"command": "ls"}))]
+        );
+
+        let interpreter = ProviderInterpreter::new(Arc::new(FixedProvider {
+            reply: "{\"tool_calls\": []}",
+        }));
+        let calls = interpreter
+            .interpret_to_tool_calls("All done", &tools())
+            .await?;
+        assert!(calls.is_empty());
+        Ok(())
+    }
+}
@@ -3,12 +3,15 @@ use super::errors::GoogleErrorCode;
 use crate::model::ModelConfig;
 use anyhow::Result;
 use base64::Engine;
+use chrono::{DateTime, Utc};
 use regex::Regex;
+use reqwest::header::HeaderMap;
 use reqwest::{Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, Map, Value};
 use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 use crate::providers::errors::{OpenAIError, ProviderError};
 use mcp_core::content::ImageContent;
@@ -44,11 +47,34 @@ pub fn convert_image(image: &ImageContent, image_format: &ImageFormat) -> Value
     }
 }
 
+/// How long the server asked to wait before retrying
+///
+/// Reads `retry-after-ms`, which OpenAI compatible endpoints send, and the standard `Retry-After`
+/// in seconds or as a date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+    let seconds =
+        |value: f64| (value.is_finite() && value >= 0.0).then(|| Duration::from_secs_f64(value));
+
+    if let Some(millis) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return seconds(millis / 1000.0);
+    }
+    let value = header("retry-after")?;
+    match value.parse::<f64>() {
+        Ok(secs) => seconds(secs),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+        }
+    }
+}
+
 /// Handle response from OpenAI compatible endpoints
 /// Error codes: https://platform.openai.com/docs/guides/error-codes
 /// Context window exceeded: https://community.openai.com/t/help-needed-tackling-context-length-limits-in-openai-models/617543
 pub async fn handle_response_openai_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_delay = retry_after(response.headers());
     // Try to parse the response body as JSON (if applicable)
     let payload = match response.json::<Value>().await {
         Ok(json) => json,
@@ -75,7 +101,7 @@ pub async fn handle_response_openai_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Unknown error (status {})", status)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded { details: format!("{:?}", payload), retry_delay })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -140,6 +166,7 @@ fn get_google_final_status(status: StatusCode, payload: Option<&Value>) -> Statu
 /// - `Err(ProviderError)`: Describes the failure reason.
 pub async fn handle_response_google_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let retry_delay = retry_after(response.headers());
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
 
@@ -166,7 +193,7 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", final_status, error_msg)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
+            Err(ProviderError::RateLimitExceeded { details: format!("{:?}", payload), retry_delay })
         }
         StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
             Err(ProviderError::ServerError(format!("{:?}", payload)))
@@ -317,6 +344,36 @@ pub fn unescape_json_values(value: &Value) -> Value {
     }
 }
 
+/// Finds the JSON values in a model reply
+///
+/// Fenced code blocks are preferred when there are any, otherwise every top level JSON
+/// object or array in the text is returned in order.
+pub fn extract_json_values(text: &str) -> Vec<Value> {
+    let fence = Regex::new(r"(?s)```[a-zA-Z]*\s*\n(.*?)```").unwrap();
+    let fenced: Vec<Value> = fence
+        .captures_iter(text)
+        .filter_map(|c| serde_json::from_str(c[1].trim()).ok())
+        .collect();
+    if !fenced.is_empty() {
+        return fenced;
+    }
+
+    let mut values = Vec::new();
+    let mut start = 0;
+    while let Some(offset) = text[start..].find(['{', '[']) {
+        let position = start + offset;
+        let mut stream = serde_json::Deserializer::from_str(&text[position..]).into_iter();
+        match stream.next() {
+            Some(Ok(value @ (Value::Object(_) | Value::Array(_)))) => {
+                values.push(value);
+                start = position + stream.byte_offset();
+            }
+            _ => start = position + 1,
+        }
+    }
+    values
+}
+
 pub fn emit_debug_trace(
     model_config: &ModelConfig,
     payload: &Value,
@@ -338,6 +395,39 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_retry_after() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        assert_eq!(retry_after(&headers(&[])), None);
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "2")])),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "2"), ("retry-after-ms", "150")])),
+            Some(Duration::from_millis(150))
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "-1")])), None);
+
+        let date = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = retry_after(&headers(&[("retry-after", &date)])).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+        assert_eq!(
+            retry_after(&headers(&[(
+                "retry-after",
+                "Wed, 21 Oct 2015 07:28:00 GMT"
+            )])),
+            None
+        );
+    }
+
     #[test]
     fn test_detect_image_path() {
         // Create a temporary PNG file with valid PNG magic numbers
@@ -543,4 +633,17 @@ mod tests {
             assert_eq!(result, expected_status);
         }
     }
+
+    #[test]
+    fn test_extract_json_values() {
+        let fenced = "Let me look.\n```json\n{\"name\": \"shell\"}\n```\nand {\"ignored\": true}";
+        assert_eq!(extract_json_values(fenced), vec![json!({"name": "shell"})]);
+
+        let bare = "First {\"a\": 1} then [1, 2] and {broken";
+        assert_eq!(
+            extract_json_values(bare),
+            vec![json!({"a": 1}), json!([1, 2])]
+        );
+        assert!(extract_json_values("no json here").is_empty());
+    }
 }
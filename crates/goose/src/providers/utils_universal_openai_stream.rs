@@ -136,6 +136,19 @@ impl OAIStreamCollector {
     }
 
     pub fn add_chunk(&mut self, chunk: &OAIStreamChunk) {
+        // Keep the response level fields of the first chunk that has them, usage usually only comes with the last one
+        self.id = self.id.take().or_else(|| chunk.id.clone());
+        self.object = self.object.take().or_else(|| chunk.object.clone());
+        self.created = self.created.or(chunk.created);
+        self.model = self.model.take().or_else(|| chunk.model.clone());
+        self.system_fingerprint = self
+            .system_fingerprint
+            .take()
+            .or_else(|| chunk.system_fingerprint.clone());
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage.clone();
+        }
+
         for ch in chunk.choices.iter() {
             // Always ensure choice exists, even if all fields are absent!
             let idx = ch.index;
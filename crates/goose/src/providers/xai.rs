@@ -0,0 +1,321 @@
+use super::errors::ProviderError;
+use crate::message::{Message, MessageContent};
+use crate::model::{ModelConfig, ReasoningEffort};
+use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use crate::providers::formats::openai::{create_request, get_usage, response_to_message};
+use crate::providers::utils::{emit_debug_trace, get_model, retry_after, ImageFormat};
+use anyhow::Result;
+use async_trait::async_trait;
+use mcp_core::Tool;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::time::Duration;
+use url::Url;
+
+pub const XAI_API_HOST: &str = "https://api.x.ai";
+pub const XAI_DEFAULT_MODEL: &str = "grok-3";
+pub const XAI_KNOWN_MODELS: &[&str] = &["grok-4", "grok-3", "grok-3-fast", "grok-3-mini"];
+
+pub const XAI_DOC_URL: &str = "https://docs.x.ai/docs/models";
+
+#[derive(serde::Serialize)]
+pub struct XaiProvider {
+    #[serde(skip)]
+    client: Client,
+    host: String,
+    api_key: String,
+    /// Live search mode, "off", "auto" or "on", live search is not requested when unset
+    search_mode: Option<String>,
+    max_search_results: Option<u32>,
+    model: ModelConfig,
+}
+
+impl Default for XaiProvider {
+    fn default() -> Self {
+        let model = ModelConfig::new(XaiProvider::metadata().default_model);
+        XaiProvider::from_env(model).expect("Failed to initialize xAI provider")
+    }
+}
+
+impl XaiProvider {
+    pub fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = crate::config::Config::global();
+        let api_key: String = config.get_secret("XAI_API_KEY")?;
+        let host: String = config
+            .get_param("XAI_HOST")
+            .unwrap_or_else(|_| XAI_API_HOST.to_string());
+        let search_mode: Option<String> = config.get_param("XAI_SEARCH_MODE").ok();
+        let max_search_results: Option<u32> = config.get_param("XAI_MAX_SEARCH_RESULTS").ok();
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(600))
+            .build()?;
+
+        Ok(Self {
+            client,
+            host,
+            api_key,
+            search_mode,
+            max_search_results,
+            model,
+        })
+    }
+
+    /// Adds the xAI specific request fields to an OpenAI chat request
+    fn customize_request(&self, payload: &mut Value) {
+        let request = payload.as_object_mut().unwrap();
+        let model_name = self.model.model_name.as_str();
+
+        // Reasoning models do not accept stop sequences
+        if is_reasoning_model(model_name) {
+            request.remove("stop");
+        }
+        // grok-3-mini is the only model with adjustable reasoning, and only knows low and high
+        if model_name.starts_with("grok-3-mini") {
+            if let Some(effort) = self.model.reasoning_effort {
+                let effort = match effort {
+                    ReasoningEffort::Low => "low",
+                    ReasoningEffort::Medium | ReasoningEffort::High => "high",
+                };
+                request.insert("reasoning_effort".to_string(), json!(effort));
+            }
+        }
+
+        if let Some(mode) = &self.search_mode {
+            let mut search_parameters = json!({
+                "mode": mode,
+                "return_citations": true
+            });
+            if let Some(max) = self.max_search_results {
+                search_parameters["max_search_results"] = json!(max);
+            }
+            request.insert("search_parameters".to_string(), search_parameters);
+        }
+    }
+
+    async fn post(&self, payload: Value) -> Result<Value, ProviderError> {
+        let base_url = Url::parse(&self.host)
+            .map_err(|e| ProviderError::RequestFailed(format!("Invalid base URL: {e}")))?;
+        let url = base_url.join("v1/chat/completions").map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
+        })?;
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let retry_delay = retry_after(response.headers());
+        let payload: Option<Value> = response.json().await.ok();
+        // xAI reports errors as `{"code": ..., "error": "message"}`
+        let message = payload
+            .as_ref()
+            .and_then(|p| p["error"].as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        match status {
+            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
+                    Status: {}. Response: {:?}", status, payload)))
+            }
+            StatusCode::BAD_REQUEST if message.contains("maximum prompt length") => {
+                Err(ProviderError::ContextLengthExceeded(message))
+            }
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND | StatusCode::UNPROCESSABLE_ENTITY => {
+                Err(ProviderError::RequestFailed(format!("{} (status {})", message, status.as_u16())))
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(ProviderError::RateLimitExceeded { details: message, retry_delay })
+            }
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+                Err(ProviderError::ServerError(format!("{:?}", payload)))
+            }
+            _ => {
+                tracing::debug!(
+                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                );
+                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+            }
+        }
+    }
+}
+
+fn is_reasoning_model(model_name: &str) -> bool {
+    model_name.starts_with("grok-4") || model_name.starts_with("grok-3-mini")
+}
+
+/// xAI does not count reasoning tokens in the completion tokens, unlike OpenAI
+///
+/// The total is recomputed from the parts, so it includes them whether or not xAI's does.
+fn get_xai_usage(response: &Value) -> Result<Usage, ProviderError> {
+    let usage = get_usage(response)?;
+    let reasoning_tokens = response["usage"]["completion_tokens_details"]["reasoning_tokens"]
+        .as_i64()
+        .map(|v| v as i32);
+    Ok(match (usage.output_tokens, reasoning_tokens) {
+        (Some(output), Some(reasoning)) => {
+            let output_tokens = output + reasoning;
+            Usage {
+                output_tokens: Some(output_tokens),
+                total_tokens: usage
+                    .input_tokens
+                    .map(|input| input + output_tokens)
+                    .or(usage.total_tokens),
+                ..usage
+            }
+        }
+        _ => usage,
+    })
+}
+
+/// Live search returns the sources it used next to the message
+fn citations(response: &Value) -> Option<String> {
+    let urls: Vec<&str> = response
+        .get("citations")?
+        .as_array()?
+        .iter()
+        .filter_map(|c| c.as_str())
+        .collect();
+    if urls.is_empty() {
+        return None;
+    }
+    let sources: Vec<String> = urls.iter().map(|url| format!("- {}", url)).collect();
+    Some(format!("Sources:\n{}", sources.join("\n")))
+}
+
+#[async_trait]
+impl Provider for XaiProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "xai",
+            "xAI",
+            "Grok models by xAI, with optional live search",
+            XAI_DEFAULT_MODEL,
+            XAI_KNOWN_MODELS.to_vec(),
+            XAI_DOC_URL,
+            vec![
+                ConfigKey::new("XAI_API_KEY", true, true, None),
+                ConfigKey::new("XAI_HOST", false, false, Some(XAI_API_HOST)),
+                ConfigKey::new("XAI_SEARCH_MODE", false, false, None),
+                ConfigKey::new("XAI_MAX_SEARCH_RESULTS", false, false, None),
+            ],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, system, messages, tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload =
+            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+        self.customize_request(&mut payload);
+
+        let response = self.post(payload.clone()).await?;
+
+        let mut message = response_to_message(response.clone())?;
+        if let Some(sources) = citations(&response) {
+            message = message.with_content(MessageContent::text(sources));
+        }
+        let usage = match get_xai_usage(&response) {
+            Ok(usage) => usage,
+            Err(ProviderError::UsageError(e)) => {
+                tracing::debug!("Failed to get usage data: {}", e);
+                Usage::default()
+            }
+            Err(e) => return Err(e),
+        };
+        let model = get_model(&response);
+        emit_debug_trace(&self.model, &payload, &response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(model: &str, search_mode: Option<&str>) -> XaiProvider {
+        XaiProvider {
+            client: Client::new(),
+            host: XAI_API_HOST.to_string(),
+            api_key: "key".to_string(),
+            search_mode: search_mode.map(str::to_string),
+            max_search_results: Some(5),
+            model: ModelConfig::new(model.to_string())
+                .with_reasoning_effort(Some(ReasoningEffort::Medium))
+                .with_stop_sequences(Some(vec!["END".to_string()])),
+        }
+    }
+
+    #[test]
+    fn test_customize_request() -> Result<()> {
+        let xai = provider("grok-3-mini", Some("auto"));
+        let mut payload = create_request(&xai.model, "system", &[], &[], &ImageFormat::OpenAi)?;
+        xai.customize_request(&mut payload);
+        assert!(payload.get("stop").is_none());
+        assert_eq!(payload["reasoning_effort"], "high");
+        assert_eq!(
+            payload["search_parameters"],
+            json!({"mode": "auto", "return_citations": true, "max_search_results": 5})
+        );
+
+        let xai = provider("grok-3", None);
+        let mut payload = create_request(&xai.model, "system", &[], &[], &ImageFormat::OpenAi)?;
+        xai.customize_request(&mut payload);
+        assert_eq!(payload["stop"], json!(["END"]));
+        assert!(payload.get("reasoning_effort").is_none());
+        assert!(payload.get("search_parameters").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_and_citations() -> Result<()> {
+        let response = json!({
+            "citations": ["https://x.ai/news", "https://docs.x.ai"],
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 20,
+                "total_tokens": 150,
+                "completion_tokens_details": {"reasoning_tokens": 30}
+            }
+        });
+        let usage = get_xai_usage(&response)?;
+        assert_eq!(usage.output_tokens, Some(50));
+        assert_eq!(usage.total_tokens, Some(150));
+        assert_eq!(
+            citations(&response).as_deref(),
+            Some("Sources:\n- https://x.ai/news\n- https://docs.x.ai")
+        );
+        assert_eq!(citations(&json!({})), None);
+
+        // A total that leaves out the reasoning tokens is corrected
+        let response = json!({
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 20,
+                "total_tokens": 120,
+                "completion_tokens_details": {"reasoning_tokens": 30}
+            }
+        });
+        let usage = get_xai_usage(&response)?;
+        assert_eq!(usage.output_tokens, Some(50));
+        assert_eq!(usage.total_tokens, Some(150));
+        Ok(())
+    }
+}
@@ -1,8 +1,14 @@
+use std::collections::HashMap;
 use std::fmt;
 
 use crate::agents::extension::ExtensionConfig;
+use crate::model::{ModelConfig, ReasoningEffort};
 use serde::{Deserialize, Serialize};
 
+mod parameters;
+
+pub use parameters::split_list_value;
+
 fn default_version() -> String {
     "1.0.0".to_string()
 }
@@ -25,6 +31,13 @@ fn default_version() -> String {
 /// * `activities` - Activity labels that appear when loading the Recipe
 /// * `author` - Information about the Recipe's creator and metadata
 /// * `parameters` - Additional parameters for the Recipe
+/// * `extends` - A recipe this one inherits from, its own fields take precedence
+/// * `include` - Recipe fragments merged into this one, e.g. shared extensions or instructions
+/// * `sub_recipes` - Other recipes the agent can run as tools
+/// * `response` - The format of the final answer, for runs that are consumed by other programs
+/// * `settings` - Model settings the recipe runs with, such as temperature or reasoning effort
+///
+/// `extends` and `include` are resolved when the recipe file is loaded.
 ///
 /// # Example
 ///
@@ -51,9 +64,14 @@ fn default_version() -> String {
 ///     activities: None,
 ///     author: None,
 ///     parameters: None,
+///     extends: None,
+///     include: None,
+///     sub_recipes: None,
+///     response: None,
+///     settings: None,
 /// };
 /// ```
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Recipe {
     // Required fields
     #[serde(default = "default_version")]
@@ -85,9 +103,84 @@ pub struct Recipe {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<Vec<RecipeParameter>>, // any additional parameters for the recipe
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>, // a recipe name or path to inherit from
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<String>>, // recipe names or paths of fragments to merge in
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_recipes: Option<Vec<SubRecipe>>, // recipes the agent can run as tools
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Response>, // format of the final answer
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Settings>, // model settings, overriding the configured ones
+}
+
+/// Model settings a recipe runs with, unset ones keep the configured value
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>, // low, medium or high
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<i32>, // tokens models with extended thinking may think for
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+impl Settings {
+    /// Overrides the model config with the settings that are set
+    pub fn apply(&self, config: ModelConfig) -> ModelConfig {
+        let temperature = self.temperature.or(config.temperature);
+        let reasoning_effort = self.reasoning_effort.or(config.reasoning_effort);
+        let thinking_budget = self.thinking_budget.or(config.thinking_budget);
+        let top_p = self.top_p.or(config.top_p);
+        let stop_sequences = self
+            .stop_sequences
+            .clone()
+            .or_else(|| config.stop_sequences.clone());
+        config
+            .with_temperature(temperature)
+            .with_reasoning_effort(reasoning_effort)
+            .with_thinking_budget(thinking_budget)
+            .with_top_p(top_p)
+            .with_stop_sequences(stop_sequences)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The format of a recipe's final answer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>, // schema of an object the agent must end with
+}
+
+/// A recipe the agent can run as a tool in a child agent, returning its final answer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubRecipe {
+    pub name: String, // name of the tool, letters, digits, '_' and '-' only
+
+    pub path: String, // recipe name or path to the recipe file
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>, // when to use it, defaults to the recipe's description
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<HashMap<String, String>>, // parameter values fixed by the parent recipe
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Author {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contact: Option<String>, // creator/contact information of the recipe
@@ -96,7 +189,7 @@ pub struct Author {
     pub metadata: Option<String>, // any additional metadata for the author
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum RecipeParameterRequirement {
     Required,
@@ -114,7 +207,7 @@ impl fmt::Display for RecipeParameterRequirement {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum RecipeParameterInputType {
     String,
@@ -122,6 +215,7 @@ pub enum RecipeParameterInputType {
     Boolean,
     Date,
     File,
+    List,
 }
 
 impl fmt::Display for RecipeParameterInputType {
@@ -134,7 +228,7 @@ impl fmt::Display for RecipeParameterInputType {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RecipeParameter {
     pub key: String,
     pub input_type: RecipeParameterInputType,
@@ -142,6 +236,14 @@ pub struct RecipeParameter {
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>, // allowed values of strings and list items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>, // regex the whole of strings and list items must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>, // smallest number, or fewest list items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>, // largest number, or most list items
 }
 
 /// Builder for creating Recipe instances
@@ -159,6 +261,9 @@ pub struct RecipeBuilder {
     activities: Option<Vec<String>>,
     author: Option<Author>,
     parameters: Option<Vec<RecipeParameter>>,
+    sub_recipes: Option<Vec<SubRecipe>>,
+    response: Option<Response>,
+    settings: Option<Settings>,
 }
 
 impl Recipe {
@@ -188,6 +293,9 @@ impl Recipe {
             activities: None,
             author: None,
             parameters: None,
+            sub_recipes: None,
+            response: None,
+            settings: None,
         }
     }
 }
@@ -252,6 +360,24 @@ impl RecipeBuilder {
         self
     }
 
+    /// Sets the recipes the agent can run as tools
+    pub fn sub_recipes(mut self, sub_recipes: Vec<SubRecipe>) -> Self {
+        self.sub_recipes = Some(sub_recipes);
+        self
+    }
+
+    /// Sets the format of the final answer
+    pub fn response(mut self, response: Response) -> Self {
+        self.response = Some(response);
+        self
+    }
+
+    /// Sets the model settings
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
     /// Builds the Recipe instance
     ///
     /// Returns an error if any required fields are missing
@@ -274,6 +400,11 @@ impl RecipeBuilder {
             activities: self.activities,
             author: self.author,
             parameters: self.parameters,
+            extends: None,
+            include: None,
+            sub_recipes: self.sub_recipes,
+            response: self.response,
+            settings: self.settings,
         })
     }
 }
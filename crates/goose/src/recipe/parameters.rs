@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use super::{Recipe, RecipeParameter, RecipeParameterInputType, RecipeParameterRequirement};
+
+/// Splits the value of a list parameter into its items
+///
+/// Items are separated by commas, or given as a JSON array of strings, numbers and booleans when
+/// they contain commas. Surrounding whitespace and empty items are dropped.
+pub fn split_list_value(value: &str) -> Vec<String> {
+    let json_items = serde_json::from_str::<Vec<Value>>(value.trim())
+        .ok()
+        .and_then(|items| {
+            items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(s) => Some(s),
+                    Value::Number(_) | Value::Bool(_) => Some(item.to_string()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        });
+    let items = match json_items {
+        Some(items) => items,
+        None => value.split(',').map(str::to_string).collect(),
+    };
+    items
+        .iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl RecipeParameter {
+    /// Checks that the constraints fit the input type, and that the default value meets them
+    pub fn validate_definition(&self) -> Result<()> {
+        let is_number = matches!(self.input_type, RecipeParameterInputType::Number);
+        let is_list = matches!(self.input_type, RecipeParameterInputType::List);
+        let is_text = matches!(self.input_type, RecipeParameterInputType::String) || is_list;
+
+        if (self.min.is_some() || self.max.is_some()) && !is_number && !is_list {
+            return Err(anyhow!(
+                "min and max only apply to number and list parameters"
+            ));
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(anyhow!("min ({}) is larger than max ({})", min, max));
+            }
+        }
+        if (self.options.is_some() || self.pattern.is_some()) && !is_text {
+            return Err(anyhow!(
+                "options and pattern only apply to string and list parameters"
+            ));
+        }
+        if self
+            .options
+            .as_ref()
+            .is_some_and(|options| options.is_empty())
+        {
+            return Err(anyhow!("options must not be empty"));
+        }
+        if let Some(pattern) = &self.pattern {
+            Regex::new(pattern).map_err(|e| anyhow!("invalid pattern: {}", e))?;
+        }
+
+        // The default of a file parameter is a path, which is only read when the recipe is used
+        if let Some(default) = &self.default {
+            if !matches!(self.input_type, RecipeParameterInputType::File) {
+                self.parse_value(default)
+                    .map_err(|e| anyhow!("invalid default value: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a value given for the parameter and converts it to the value used in the template
+    ///
+    /// List values are split with [`split_list_value`] and become a sequence of strings. File
+    /// parameters take a path and become the contents of the file. Other values are kept as text.
+    pub fn parse_value(&self, value: &str) -> Result<Value> {
+        self.parse(value, true)
+    }
+
+    fn parse(&self, value: &str, read_files: bool) -> Result<Value> {
+        match self.input_type {
+            RecipeParameterInputType::String => {
+                self.check_text(value)?;
+                Ok(Value::String(value.to_string()))
+            }
+            RecipeParameterInputType::Number => {
+                let number: f64 = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|number: &f64| number.is_finite())
+                    .ok_or_else(|| anyhow!("'{}' is not a number", value))?;
+                self.check_bounds(number)?;
+                Ok(Value::String(value.trim().to_string()))
+            }
+            RecipeParameterInputType::Boolean => match value.trim().to_lowercase().as_str() {
+                boolean @ ("true" | "false") => Ok(Value::String(boolean.to_string())),
+                _ => Err(anyhow!("'{}' is not true or false", value)),
+            },
+            RecipeParameterInputType::Date => {
+                NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                    .map_err(|_| anyhow!("'{}' is not a date in the YYYY-MM-DD format", value))?;
+                Ok(Value::String(value.trim().to_string()))
+            }
+            RecipeParameterInputType::File if !read_files => {
+                if value.trim().is_empty() {
+                    return Err(anyhow!("a path is required"));
+                }
+                Ok(Value::String(value.trim().to_string()))
+            }
+            RecipeParameterInputType::File => std::fs::read_to_string(value.trim())
+                .map(Value::String)
+                .map_err(|e| anyhow!("failed to read file '{}': {}", value, e)),
+            RecipeParameterInputType::List => {
+                let items = split_list_value(value);
+                self.check_bounds(items.len() as f64)?;
+                for item in &items {
+                    self.check_text(item)?;
+                }
+                Ok(json!(items))
+            }
+        }
+    }
+
+    fn check_bounds(&self, number: f64) -> Result<()> {
+        let is_list = matches!(self.input_type, RecipeParameterInputType::List);
+        if let Some(min) = self.min.filter(|min| number < *min) {
+            return Err(if is_list {
+                anyhow!("has {} items, at least {} are required", number, min)
+            } else {
+                anyhow!("{} is less than the minimum of {}", number, min)
+            });
+        }
+        if let Some(max) = self.max.filter(|max| number > *max) {
+            return Err(if is_list {
+                anyhow!("has {} items, at most {} are allowed", number, max)
+            } else {
+                anyhow!("{} is more than the maximum of {}", number, max)
+            });
+        }
+        Ok(())
+    }
+
+    fn check_text(&self, value: &str) -> Result<()> {
+        if let Some(options) = &self.options {
+            if !options.iter().any(|option| option == value) {
+                return Err(anyhow!("'{}' is not one of: {}", value, options.join(", ")));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            let regex = Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| anyhow!("invalid pattern: {}", e))?;
+            if !regex.is_match(value) {
+                return Err(anyhow!(
+                    "'{}' does not match the pattern {}",
+                    value,
+                    pattern
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a value has to be given for the parameter
+    pub fn is_required(&self) -> bool {
+        !matches!(self.requirement, RecipeParameterRequirement::Optional) && self.default.is_none()
+    }
+
+    /// JSON schema of the values the parameter accepts
+    pub fn json_schema(&self) -> Value {
+        let mut text = json!({"type": "string"});
+        if let Some(options) = &self.options {
+            text["enum"] = json!(options);
+        }
+        if let Some(pattern) = &self.pattern {
+            text["pattern"] = json!(format!("^(?:{})$", pattern));
+        }
+
+        let mut description = self.description.clone();
+        let mut schema = match self.input_type {
+            RecipeParameterInputType::String => text,
+            RecipeParameterInputType::Number => {
+                let mut number = json!({"type": "number"});
+                if let Some(min) = self.min {
+                    number["minimum"] = json!(min);
+                }
+                if let Some(max) = self.max {
+                    number["maximum"] = json!(max);
+                }
+                number
+            }
+            RecipeParameterInputType::Boolean => json!({"type": "boolean"}),
+            RecipeParameterInputType::Date => json!({"type": "string", "format": "date"}),
+            RecipeParameterInputType::File => {
+                description.push_str(" (path to a file, its contents are used)");
+                json!({"type": "string"})
+            }
+            RecipeParameterInputType::List => {
+                let mut list = json!({"type": "array", "items": text});
+                if let Some(min) = self.min {
+                    list["minItems"] = json!(min as u64);
+                }
+                if let Some(max) = self.max {
+                    list["maxItems"] = json!(max as u64);
+                }
+                list
+            }
+        };
+        if let Some(default) = &self.default {
+            description.push_str(&format!(" (default: {})", default));
+        }
+        schema["description"] = json!(description);
+        schema
+    }
+}
+
+impl Recipe {
+    /// JSON schema of an object with a value for each parameter of the recipe
+    pub fn parameters_schema(&self) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for param in self.parameters.iter().flatten() {
+            properties.insert(param.key.clone(), param.json_schema());
+            if param.is_required() {
+                required.push(param.key.clone());
+            }
+        }
+        json!({
+            "type": "object",
+            "required": required,
+            "properties": properties,
+        })
+    }
+
+    /// Checks the values given for the parameters and converts them to the values used in the
+    /// template, using the defaults for parameters without a value
+    ///
+    /// Values for keys that aren't parameters are kept as text. Returns the problem with each
+    /// parameter that is missing or has an invalid value.
+    pub fn resolve_parameters(
+        &self,
+        values: &HashMap<String, String>,
+    ) -> Result<HashMap<String, Value>, BTreeMap<String, String>> {
+        self.resolve(values, true)
+    }
+
+    /// Checks the values given for the parameters like [`Recipe::resolve_parameters`], without
+    /// reading the files given for file parameters
+    pub fn check_parameters(
+        &self,
+        values: &HashMap<String, String>,
+    ) -> Result<(), BTreeMap<String, String>> {
+        self.resolve(values, false).map(|_| ())
+    }
+
+    fn resolve(
+        &self,
+        values: &HashMap<String, String>,
+        read_files: bool,
+    ) -> Result<HashMap<String, Value>, BTreeMap<String, String>> {
+        let mut resolved: HashMap<String, Value> = values
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        let mut errors = BTreeMap::new();
+
+        for param in self.parameters.iter().flatten() {
+            let Some(value) = values.get(&param.key).or(param.default.as_ref()) else {
+                errors.insert(param.key.clone(), "a value is required".to_string());
+                continue;
+            };
+            match param.parse(value, read_files) {
+                Ok(value) => {
+                    resolved.insert(param.key.clone(), value);
+                }
+                Err(e) => {
+                    errors.insert(param.key.clone(), e.to_string());
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(resolved)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(yaml: &str) -> RecipeParameter {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let count = parameter(
+            "{key: count, input_type: number, requirement: required, description: d, min: 1, max: 10}",
+        );
+        assert_eq!(count.parse_value(" 3 ").unwrap(), json!("3"));
+        assert!(count.parse_value("three").is_err());
+        assert!(count.parse_value("NaN").is_err());
+        assert!(count.parse_value("inf").is_err());
+        assert_eq!(
+            count.parse_value("11").unwrap_err().to_string(),
+            "11 is more than the maximum of 10"
+        );
+
+        let tags = parameter(
+            "{key: tags, input_type: list, requirement: required, description: d, options: [bug, docs], max: 2}",
+        );
+        assert_eq!(
+            tags.parse_value("bug, docs").unwrap(),
+            json!(["bug", "docs"])
+        );
+        assert!(tags.parse_value("bug,feature").is_err());
+        assert!(tags.parse_value("bug,docs,bug").is_err());
+        assert_eq!(
+            split_list_value(r#"["a, b", " c ", ""]"#),
+            vec!["a, b".to_string(), "c".to_string()]
+        );
+        assert_eq!(split_list_value("[1, true]"), vec!["1", "true"]);
+
+        let branch = parameter(
+            "{key: branch, input_type: string, requirement: required, description: d, pattern: 'release/[0-9]+'}",
+        );
+        assert!(branch.parse_value("release/12").is_ok());
+        assert!(branch.parse_value("release/12-hotfix").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "# Notes").unwrap();
+        let file =
+            parameter("{key: notes, input_type: file, requirement: required, description: d}");
+        assert_eq!(
+            file.parse_value(path.to_str().unwrap()).unwrap(),
+            json!("# Notes")
+        );
+        assert!(file.parse_value("missing.md").is_err());
+        assert_eq!(
+            file.parse("missing.md", false).unwrap(),
+            json!("missing.md")
+        );
+    }
+
+    #[test]
+    fn test_validate_definition() {
+        assert!(parameter(
+            "{key: a, input_type: boolean, requirement: required, description: d, min: 1}"
+        )
+        .validate_definition()
+        .is_err());
+        assert!(parameter(
+            "{key: a, input_type: string, requirement: optional, description: d, options: [x], default: y}"
+        )
+        .validate_definition()
+        .is_err());
+        assert!(parameter(
+            "{key: a, input_type: string, requirement: required, description: d, pattern: '['}"
+        )
+        .validate_definition()
+        .is_err());
+        assert!(parameter(
+            "{key: a, input_type: date, requirement: optional, description: d, default: 2025-01-31}"
+        )
+        .validate_definition()
+        .is_ok());
+    }
+
+    #[test]
+    fn test_resolve_parameters() {
+        let recipe: Recipe = serde_yaml::from_str(
+            r#"
+title: Release
+description: Prepares a release
+instructions: Release {{ version }}
+parameters:
+  - key: version
+    input_type: string
+    requirement: required
+    description: Version to release
+  - key: dry_run
+    input_type: boolean
+    requirement: optional
+    description: Only print what would happen
+    default: "true"
+  - key: count
+    input_type: number
+    requirement: required
+    description: How many
+"#,
+        )
+        .unwrap();
+
+        let values = HashMap::from([
+            ("version".to_string(), "1.2.0".to_string()),
+            ("count".to_string(), "many".to_string()),
+            ("recipe_dir".to_string(), "/recipes".to_string()),
+        ]);
+        let errors = recipe.resolve_parameters(&values).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors["count"], "'many' is not a number");
+
+        let values = HashMap::from([
+            ("version".to_string(), "1.2.0".to_string()),
+            ("count".to_string(), "2".to_string()),
+        ]);
+        let resolved = recipe.resolve_parameters(&values).unwrap();
+        assert_eq!(resolved["dry_run"], json!("true"));
+
+        let schema = recipe.parameters_schema();
+        assert_eq!(schema["required"], json!(["version", "count"]));
+        assert_eq!(schema["properties"]["dry_run"]["type"], "boolean");
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+
+/// How far ahead to look for the next run, schedules like "30 February" never run
+const MAX_DAYS_AHEAD: i64 = 5 * 366;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A standard five-field cron expression: minute, hour, day of month, month and day of week
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and lists (`1,15`).
+/// Months and days of the week can also be given by their three-letter English names. Sunday is
+/// 0 or 7. Like cron, a run is due when either the day of month or the day of week matches, if
+/// both are restricted. Times are local.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(anyhow!(
+                "cron expression '{}' must have 5 fields: minute hour day-of-month month day-of-week",
+                expression
+            ));
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0, 7, DAY_NAMES, "day of week")?;
+        // 7 is another way to write Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], "minute")?,
+            hours: parse_field(hour, 0, 23, &[], "hour")?,
+            days_of_month: parse_field(day_of_month, 1, 31, &[], "day of month")?,
+            months: parse_field(month, 1, 12, MONTH_NAMES, "month")?,
+            days_of_week,
+            days_of_month_restricted: !day_of_month.starts_with('*'),
+            days_of_week_restricted: !day_of_week.starts_with('*'),
+        })
+    }
+
+    /// The first time after `after` the schedule is due, if any
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = (after.naive_local() + Duration::minutes(1))
+            .with_second(0)?
+            .with_nanosecond(0)?;
+        let start_day = start.date();
+
+        for day_offset in 0..MAX_DAYS_AHEAD {
+            let day = start_day + Duration::days(day_offset);
+            if !self.matches_day(day) {
+                continue;
+            }
+            for hour in 0..24u32 {
+                if !self.hours[hour as usize] {
+                    continue;
+                }
+                for minute in 0..60u32 {
+                    if !self.minutes[minute as usize] {
+                        continue;
+                    }
+                    let time = day.and_hms_opt(hour, minute, 0)?;
+                    if time < start {
+                        continue;
+                    }
+                    // Times skipped by a daylight saving change don't exist, so they never run
+                    if let Some(time) = Local.from_local_datetime(&time).earliest() {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        if !self.months[day.month() as usize] {
+            return false;
+        }
+        let day_of_month = self.days_of_month[day.day() as usize];
+        let day_of_week = self.days_of_week[day.weekday().num_days_from_sunday() as usize];
+        match (self.days_of_month_restricted, self.days_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+/// Parses a field into a table of which values match, indexed by value
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], label: &str) -> Result<Vec<bool>> {
+    let mut matches = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let invalid = || anyhow!("invalid {} '{}' in cron expression", label, part);
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let value = |text: &str| -> Result<u32> {
+            let value = match names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(text))
+            {
+                // Names of months start at 1, names of days at 0
+                Some(index) => index as u32 + min,
+                None => text.parse().map_err(|_| invalid())?,
+            };
+            if value < min || value > max {
+                return Err(anyhow!(
+                    "{} {} is out of range, expected {} to {}",
+                    label,
+                    value,
+                    min,
+                    max
+                ));
+            }
+            Ok(value)
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // A single value with a step runs from that value to the end, like `5/15`
+                None if part.contains('/') => (value(range)?, max),
+                None => {
+                    let value = value(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            matches[value as usize] = true;
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(text: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> String {
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(local(after))
+            .unwrap()
+            .format("%Y-%m-%d %H:%M %a")
+            .to_string()
+    }
+
+    #[test]
+    fn test_next_after() {
+        // Weekdays at 9, from a Friday afternoon
+        assert_eq!(
+            next("0 9 * * 1-5", "2025-01-03 14:00"),
+            "2025-01-06 09:00 Mon"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2025-01-03 14:07"),
+            "2025-01-03 14:15 Fri"
+        );
+        // A time that is due now runs at the next occurrence
+        assert_eq!(
+            next("30 8 1 * *", "2025-01-01 08:30"),
+            "2025-02-01 08:30 Sat"
+        );
+        assert_eq!(
+            next("0 0 * feb sun", "2025-01-01 00:00"),
+            "2025-02-02 00:00 Sun"
+        );
+        // Day of month or day of week, when both are given
+        assert_eq!(
+            next("0 12 13 * fri", "2025-06-01 00:00"),
+            "2025-06-06 12:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2025-03-01 00:00"),
+            "2028-02-29 00:00 Tue"
+        );
+        assert!(CronSchedule::parse("0 0 30 2 *")
+            .unwrap()
+            .next_after(local("2025-01-01 00:00"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expression in [
+            "0 9 * *",
+            "60 * * * *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(
+                CronSchedule::parse(expression).is_err(),
+                "{} should be invalid",
+                expression
+            );
+        }
+        assert_eq!(
+            CronSchedule::parse("0 9 * * 7").unwrap(),
+            CronSchedule::parse("0 9 * * 0").unwrap()
+        );
+    }
+}
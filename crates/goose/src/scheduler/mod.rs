@@ -0,0 +1,654 @@
+//! Runs recipes on cron schedules
+//!
+//! Scheduled jobs and the history of their runs are kept in `schedules.json` in the config
+//! directory. Any number of processes can run a [`Scheduler`], only the one holding the scheduler
+//! lock starts jobs. Each run executes `goose run` with the job's recipe, and saves its session
+//! to a file recorded in the run history. The process running a job holds a lock for the run
+//! until it is recorded as finished, which tells the runs still going on from the interrupted ones.
+
+mod cron;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Timelike, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+pub use cron::CronSchedule;
+
+use crate::config::APP_STRATEGY;
+use crate::session;
+
+const SCHEDULES_FILE: &str = "schedules.json";
+
+/// Directory next to the schedules file with the locks of the runs going on
+const RUN_LOCKS_DIR: &str = "schedule-runs";
+
+/// How many runs of each job are kept in the history
+const MAX_RUNS_PER_JOB: usize = 50;
+
+/// Errors of changes to the scheduled jobs that callers tell apart
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("A job with id '{0}' already exists")]
+    DuplicateJob(String),
+    #[error("No scheduled job with id '{0}'")]
+    JobNotFound(String),
+}
+
+/// A recipe that runs on a cron schedule
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduledJob {
+    pub id: String,
+    /// Recipe name or path, paths are absolute
+    pub recipe: String,
+    /// Five-field cron expression, in local time
+    pub cron: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// Directory the recipe runs in
+    pub working_dir: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ScheduledJob {
+    pub fn new(
+        id: String,
+        recipe: String,
+        cron: String,
+        params: BTreeMap<String, String>,
+        working_dir: PathBuf,
+    ) -> Result<Self> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow!(
+                "Invalid job id '{}', use only letters, digits, '_' and '-'",
+                id
+            ));
+        }
+        CronSchedule::parse(&cron)?;
+        Ok(Self {
+            id,
+            recipe,
+            cron,
+            params,
+            working_dir,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// When the job runs next after `after`
+    pub fn next_run(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        CronSchedule::parse(&self.cron).ok()?.next_after(after)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A run of a scheduled job
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleRun {
+    pub id: String,
+    pub job_id: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: RunStatus,
+    /// The session of the run, which can be resumed to see what happened
+    pub session_file: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct ScheduleData {
+    #[serde(default)]
+    jobs: Vec<ScheduledJob>,
+    #[serde(default)]
+    runs: Vec<ScheduleRun>,
+}
+
+/// The scheduled jobs and their run history, shared by all goose processes through a file
+#[derive(Debug, Clone)]
+pub struct ScheduleStore {
+    path: PathBuf,
+}
+
+impl ScheduleStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The store in the goose config directory
+    pub fn from_config_dir() -> Result<Self> {
+        let config_dir = choose_app_strategy(APP_STRATEGY.clone())
+            .context("goose requires a home dir")?
+            .config_dir();
+        Ok(Self::new(config_dir.join(SCHEDULES_FILE)))
+    }
+
+    pub fn jobs(&self) -> Result<Vec<ScheduledJob>> {
+        Ok(self.read()?.jobs)
+    }
+
+    pub fn job(&self, id: &str) -> Result<ScheduledJob> {
+        self.jobs()?
+            .into_iter()
+            .find(|job| job.id == id)
+            .ok_or_else(|| ScheduleError::JobNotFound(id.to_string()).into())
+    }
+
+    pub fn add_job(&self, job: ScheduledJob) -> Result<()> {
+        self.update(|data| {
+            if data.jobs.iter().any(|existing| existing.id == job.id) {
+                return Err(ScheduleError::DuplicateJob(job.id.clone()).into());
+            }
+            data.jobs.push(job);
+            Ok(())
+        })
+    }
+
+    /// Removes a job and its run history, the session files of its runs are kept
+    pub fn remove_job(&self, id: &str) -> Result<()> {
+        self.update(|data| {
+            let count = data.jobs.len();
+            data.jobs.retain(|job| job.id != id);
+            if data.jobs.len() == count {
+                return Err(ScheduleError::JobNotFound(id.to_string()).into());
+            }
+            data.runs.retain(|run| run.job_id != id);
+            Ok(())
+        })
+    }
+
+    /// Runs of a job, or of all jobs, newest first
+    pub fn runs(&self, job_id: Option<&str>) -> Result<Vec<ScheduleRun>> {
+        let mut runs: Vec<ScheduleRun> = self
+            .read()?
+            .runs
+            .into_iter()
+            .filter(|run| job_id.is_none_or(|job_id| run.job_id == job_id))
+            .collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+        Ok(runs)
+    }
+
+    /// Records a run as started, the run counts as interrupted once the returned lock is dropped
+    fn start_run(&self, job: &ScheduledJob) -> Result<(ScheduleRun, RunLock)> {
+        let started_at = Utc::now();
+        let id = format!(
+            "{}-{}",
+            job.id,
+            started_at.with_timezone(&Local).format("%Y%m%d_%H%M%S")
+        );
+        let lock = RunLock::acquire(self.run_lock_path(&id))?;
+        let run = ScheduleRun {
+            session_file: session::storage::get_path(session::Identifier::Name(format!(
+                "schedule-{}",
+                id
+            ))),
+            id,
+            job_id: job.id.clone(),
+            started_at,
+            finished_at: None,
+            status: RunStatus::Running,
+            error: None,
+        };
+
+        self.update(|data| {
+            data.runs.push(run.clone());
+            let job_runs = data.runs.iter().filter(|r| r.job_id == job.id).count();
+            let mut excess = job_runs.saturating_sub(MAX_RUNS_PER_JOB);
+            // Runs are appended, so the oldest come first
+            data.runs.retain(|r| {
+                let drop = excess > 0 && r.job_id == job.id;
+                if drop {
+                    excess -= 1;
+                }
+                !drop
+            });
+            Ok(())
+        })?;
+        Ok((run, lock))
+    }
+
+    fn finish_run(&self, run_id: &str, result: &Result<()>) -> Result<ScheduleRun> {
+        self.update(|data| {
+            let run = data
+                .runs
+                .iter_mut()
+                .find(|run| run.id == run_id)
+                .ok_or_else(|| anyhow!("Run {} is no longer in the history", run_id))?;
+            run.finished_at = Some(Utc::now());
+            match result {
+                Ok(()) => run.status = RunStatus::Succeeded,
+                Err(e) => {
+                    run.status = RunStatus::Failed;
+                    run.error = Some(e.to_string());
+                }
+            }
+            Ok(run.clone())
+        })
+    }
+
+    /// Marks the runs left running by processes that stopped as failed, returning how many
+    ///
+    /// Runs going on in other processes, like runs started by hand, are left alone.
+    pub fn fail_interrupted_runs(&self) -> Result<usize> {
+        self.update(|data| {
+            let mut count = 0;
+            for run in data
+                .runs
+                .iter_mut()
+                .filter(|run| run.status == RunStatus::Running)
+            {
+                // The lock is free once the process running the job is gone
+                match RunLock::try_acquire(self.run_lock_path(&run.id))? {
+                    Some(lock) => drop(lock),
+                    None => continue,
+                }
+                run.status = RunStatus::Failed;
+                run.finished_at = Some(Utc::now());
+                run.error = Some("Goose stopped before the run finished".to_string());
+                count += 1;
+            }
+            Ok(count)
+        })
+    }
+
+    fn read(&self) -> Result<ScheduleData> {
+        if !self.path.exists() {
+            return Ok(ScheduleData::default());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))
+    }
+
+    /// Changes the stored data while holding a lock, so processes don't overwrite each other
+    fn update<T>(&self, change: impl FnOnce(&mut ScheduleData) -> Result<T>) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = self.lock_file("lock")?;
+        lock.lock_exclusive()?;
+
+        let mut data = self.read()?;
+        let result = change(&mut data)?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&data)?)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        lock.unlock()?;
+        Ok(result)
+    }
+
+    fn run_lock_path(&self, run_id: &str) -> PathBuf {
+        self.path
+            .with_file_name(RUN_LOCKS_DIR)
+            .join(format!("{}.lock", run_id))
+    }
+
+    fn lock_file(&self, extension: &str) -> Result<File> {
+        Ok(OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension(extension))?)
+    }
+}
+
+/// Held by the process running a job until the run is recorded as finished
+struct RunLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl RunLock {
+    fn open(path: PathBuf) -> Result<(File, PathBuf)> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        Ok((file, path))
+    }
+
+    fn acquire(path: PathBuf) -> Result<Self> {
+        let (file, path) = Self::open(path)?;
+        file.lock_exclusive()?;
+        Ok(Self { file, path })
+    }
+
+    /// The lock, unless another process holds it
+    fn try_acquire(path: PathBuf) -> Result<Option<Self>> {
+        let (file, path) = Self::open(path)?;
+        Ok(file
+            .try_lock_exclusive()
+            .is_ok()
+            .then_some(Self { file, path }))
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Runs the recipe of a job
+#[async_trait]
+pub trait JobExecutor: Send + Sync {
+    /// Runs the job to completion, saving its session to `session_file`
+    async fn execute(&self, job: &ScheduledJob, session_file: &Path) -> Result<()>;
+}
+
+/// Runs jobs with `goose run`, so scheduled runs behave like runs from the command line
+pub struct CommandExecutor {
+    program: PathBuf,
+}
+
+impl CommandExecutor {
+    /// `program` is the goose CLI executable
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl JobExecutor for CommandExecutor {
+    async fn execute(&self, job: &ScheduledJob, session_file: &Path) -> Result<()> {
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .arg("run")
+            .arg("--recipe")
+            .arg(&job.recipe)
+            .arg("--path")
+            .arg(session_file)
+            .args(["--output-format", "json"]);
+        for (key, value) in &job.params {
+            command.arg("--params").arg(format!("{}={}", key, value));
+        }
+        let output = command
+            .current_dir(&job.working_dir)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .with_context(|| format!("Failed to start {}", self.program.display()))?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        // The result line has the error of the run, errors before it starts go to stderr
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let result = stdout
+            .lines()
+            .last()
+            .and_then(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .and_then(|event| event.get("result").cloned())
+            .map(|result| match result {
+                serde_json::Value::String(text) => text,
+                other => other.to_string(),
+            });
+        // Backtraces are left out of the run history
+        let stderr = String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .take_while(|line| !line.to_lowercase().starts_with("stack backtrace"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+        Err(anyhow!(
+            "{}",
+            result
+                .filter(|result| !result.is_empty())
+                .or_else(|| (!stderr.is_empty()).then_some(stderr))
+                .unwrap_or_else(|| format!("goose run exited with {}", output.status))
+        ))
+    }
+}
+
+/// Starts scheduled jobs when they are due
+pub struct Scheduler {
+    store: ScheduleStore,
+    executor: Arc<dyn JobExecutor>,
+    running: Mutex<HashSet<String>>,
+}
+
+impl Scheduler {
+    pub fn new(store: ScheduleStore, executor: Arc<dyn JobExecutor>) -> Self {
+        Self {
+            store,
+            executor,
+            running: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn store(&self) -> &ScheduleStore {
+        &self.store
+    }
+
+    /// Checks for due jobs every minute, forever
+    ///
+    /// Only one scheduler starts jobs at a time, the others take over when it stops. Runs that
+    /// were due while no scheduler was running are skipped, and the runs the stopped scheduler
+    /// left running are marked as failed.
+    pub async fn run(self: Arc<Self>) {
+        let mut lock: Option<File> = None;
+        let mut checked_until = Local::now();
+        loop {
+            if lock.is_none() {
+                lock = self
+                    .store
+                    .lock_file("scheduler.lock")
+                    .ok()
+                    .filter(|file| file.try_lock_exclusive().is_ok());
+                if lock.is_some() {
+                    info!("Running scheduled jobs from {}", self.store.path.display());
+                    let store = self.store.clone();
+                    match tokio::task::spawn_blocking(move || store.fail_interrupted_runs()).await {
+                        Ok(Ok(0)) => {}
+                        Ok(Ok(count)) => info!("Marked {} interrupted runs as failed", count),
+                        Ok(Err(e)) => error!("Failed to update interrupted runs: {}", e),
+                        Err(e) => error!("Failed to update interrupted runs: {}", e),
+                    }
+                }
+            }
+
+            let now = Local::now();
+            let wait = 60 - u64::from(now.second());
+            tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+            let now = Local::now();
+
+            if lock.is_some() {
+                self.start_due_jobs(checked_until, now);
+            }
+            checked_until = now;
+        }
+    }
+
+    /// Starts the jobs due after `from` and up to `to`
+    fn start_due_jobs(self: &Arc<Self>, from: DateTime<Local>, to: DateTime<Local>) {
+        let jobs = match self.store.jobs() {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("Failed to read scheduled jobs: {}", e);
+                return;
+            }
+        };
+        for job in jobs {
+            if job.next_run(from).is_some_and(|next| next <= to) {
+                let scheduler = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = scheduler.run_job(job).await {
+                        error!("Failed to run scheduled job: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Runs a job now and records the run in the history
+    ///
+    /// A job doesn't run again while it is still running.
+    pub async fn run_job(&self, job: ScheduledJob) -> Result<ScheduleRun> {
+        if !self
+            .running
+            .lock()
+            .expect("running lock poisoned")
+            .insert(job.id.clone())
+        {
+            return Err(anyhow!("Job '{}' is already running", job.id));
+        }
+
+        let result = async {
+            // The history is changed under a file lock, which blocks
+            let (store, started) = (self.store.clone(), job.clone());
+            let (run, lock) =
+                tokio::task::spawn_blocking(move || store.start_run(&started)).await??;
+            info!("Running scheduled job {} as {}", job.id, run.id);
+            let result = self.executor.execute(&job, &run.session_file).await;
+            let (store, run_id) = (self.store.clone(), run.id.clone());
+            tokio::task::spawn_blocking(move || {
+                let finished = store.finish_run(&run_id, &result);
+                drop(lock);
+                finished
+            })
+            .await?
+        }
+        .await;
+
+        self.running
+            .lock()
+            .expect("running lock poisoned")
+            .remove(&job.id);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FailingExecutor;
+
+    #[async_trait]
+    impl JobExecutor for FailingExecutor {
+        async fn execute(&self, job: &ScheduledJob, _session_file: &Path) -> Result<()> {
+            Err(anyhow!("{} needs a prompt", job.recipe))
+        }
+    }
+
+    fn job(id: &str) -> ScheduledJob {
+        ScheduledJob::new(
+            id.to_string(),
+            "/recipes/audit.yaml".to_string(),
+            "0 9 * * 1-5".to_string(),
+            BTreeMap::from([("severity".to_string(), "high".to_string())]),
+            PathBuf::from("/projects/app"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ScheduleStore::new(dir.path().join(SCHEDULES_FILE));
+
+        store.add_job(job("audit")).unwrap();
+        store.add_job(job("notes")).unwrap();
+        let error = store.add_job(job("audit")).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(ScheduleError::DuplicateJob(_))
+        ));
+        assert_eq!(store.job("audit").unwrap().params["severity"], "high");
+
+        store.remove_job("audit").unwrap();
+        let ids: Vec<String> = store.jobs().unwrap().into_iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec!["notes"]);
+        assert!(store.remove_job("audit").is_err());
+
+        assert!(ScheduledJob::new(
+            "bad id".to_string(),
+            "r.yaml".to_string(),
+            "0 9 * * *".to_string(),
+            BTreeMap::new(),
+            PathBuf::from("/")
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ScheduleStore::new(dir.path().join(SCHEDULES_FILE));
+        store.add_job(job("audit")).unwrap();
+        let scheduler = Scheduler::new(store.clone(), Arc::new(FailingExecutor));
+
+        let run = scheduler
+            .run_job(store.job("audit").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(run.status, RunStatus::Failed);
+        assert_eq!(
+            run.error.as_deref(),
+            Some("/recipes/audit.yaml needs a prompt")
+        );
+        assert!(run
+            .session_file
+            .to_string_lossy()
+            .contains("schedule-audit-"));
+
+        let runs = store.runs(Some("audit")).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].id, run.id);
+        assert!(store.runs(Some("notes")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fail_interrupted_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ScheduleStore::new(dir.path().join(SCHEDULES_FILE));
+        let audit = job("audit");
+        store.add_job(audit.clone()).unwrap();
+        let (finished, _) = store.start_run(&audit).unwrap();
+        store.finish_run(&finished.id, &Ok(())).unwrap();
+        let (interrupted, lock) = store.start_run(&job("notes")).unwrap();
+
+        // The run is still going on while its lock is held
+        assert_eq!(store.fail_interrupted_runs().unwrap(), 0);
+        drop(lock);
+        assert_eq!(store.fail_interrupted_runs().unwrap(), 1);
+        let runs = store.runs(None).unwrap();
+        let status = |id: &str| runs.iter().find(|run| run.id == id).unwrap().status;
+        assert_eq!(status(&finished.id), RunStatus::Succeeded);
+        assert_eq!(status(&interrupted.id), RunStatus::Failed);
+        assert_eq!(store.fail_interrupted_runs().unwrap(), 0);
+        assert!(!store.run_lock_path(&interrupted.id).exists());
+    }
+}
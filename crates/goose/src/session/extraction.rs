@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use mcp_core::role::Role;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::providers::base::Provider;
+
+/// The transcript sent for extraction is cut to its last characters, where the outcome is
+const MAX_TRANSCRIPT_CHARS: usize = 60_000;
+/// Tool calls and results are only context, so they are shortened more than the conversation
+const MAX_TOOL_TEXT_CHARS: usize = 500;
+
+const EXTRACTION_PROMPT: &str = "You review a finished conversation between a user and an AI \
+assistant and pick the facts worth remembering in future conversations: preferences the user \
+stated, conventions of the project, and commands or approaches that worked. Only keep facts that \
+will stay true and be useful later, not the details of this one task, and never secrets such as \
+passwords or keys. Each fact is one short self-contained sentence. Facts about the user are \
+global, facts about the project in the working directory are not. Reply with an empty list when \
+nothing is worth remembering.";
+
+/// A fact worth remembering found in a session transcript
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExtractedMemory {
+    pub category: String,
+    pub data: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub is_global: bool,
+}
+
+/// Whether memories are extracted from sessions when they end, off unless
+/// `GOOSE_MEMORY_EXTRACTION` is true
+pub fn memory_extraction_enabled() -> bool {
+    Config::global()
+        .get_param::<bool>("GOOSE_MEMORY_EXTRACTION")
+        .unwrap_or(false)
+}
+
+/// Asks the provider for the durable facts of a session, to store as memories
+pub async fn extract_memories(
+    messages: &[Message],
+    provider: Arc<dyn Provider>,
+) -> Result<Vec<ExtractedMemory>> {
+    if !messages.iter().any(|m| m.role == Role::User) {
+        return Ok(Vec::new());
+    }
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "memories": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "category": {"type": "string", "description": "A short lowercase category such as preferences, conventions or commands"},
+                        "data": {"type": "string"},
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "is_global": {"type": "boolean"}
+                    },
+                    "required": ["category", "data", "is_global"]
+                }
+            }
+        },
+        "required": ["memories"]
+    });
+    let message = Message::user().with_text(format!(
+        "The conversation:\n{}",
+        render_transcript(messages)
+    ));
+
+    let (value, _usage) = provider
+        .complete_structured(EXTRACTION_PROMPT, &[message], &schema)
+        .await?;
+    let memories: Vec<ExtractedMemory> =
+        serde_json::from_value(value["memories"].clone()).unwrap_or_default();
+    Ok(memories
+        .into_iter()
+        .filter(|memory| !memory.category.trim().is_empty() && !memory.data.trim().is_empty())
+        .collect())
+}
+
+/// The conversation as plain text, with tool calls and their results shortened
+fn render_transcript(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+    for message in messages {
+        let speaker = match message.role {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        };
+        for content in &message.content {
+            match content {
+                MessageContent::Text(text) => lines.push(format!("{}: {}", speaker, text.text)),
+                MessageContent::ToolRequest(request) => {
+                    if let Ok(call) = &request.tool_call {
+                        lines.push(format!(
+                            "{} called {}: {}",
+                            speaker,
+                            call.name,
+                            shorten(&call.arguments.to_string())
+                        ));
+                    }
+                }
+                MessageContent::ToolResponse(response) => match &response.tool_result {
+                    Ok(contents) => {
+                        let text: Vec<&str> = contents.iter().filter_map(|c| c.as_text()).collect();
+                        lines.push(format!("Tool result: {}", shorten(&text.join("\n"))));
+                    }
+                    Err(e) => lines.push(format!("Tool error: {}", shorten(&e.to_string()))),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    let transcript = lines.join("\n");
+    match transcript
+        .char_indices()
+        .rev()
+        .nth(MAX_TRANSCRIPT_CHARS - 1)
+    {
+        Some((start, _)) => transcript[start..].to_string(),
+        None => transcript,
+    }
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_TOOL_TEXT_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use async_trait::async_trait;
+    use mcp_core::content::Content;
+    use mcp_core::tool::{Tool, ToolCall};
+
+    /// Replies with the same text whatever it is asked
+    struct FixedProvider(String);
+
+    #[async_trait]
+    impl Provider for FixedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test".to_string())
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(&self.0),
+                ProviderUsage::new("test".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_memories() {
+        let reply = r#"{"memories": [
+            {"category": "commands", "data": "Tests run with cargo nextest", "tags": ["rust"], "is_global": false},
+            {"category": "preferences", "data": "", "is_global": true}
+        ]}"#;
+        let messages = vec![
+            Message::user().with_text("Run the tests, I use cargo nextest"),
+            Message::assistant().with_tool_request(
+                "1",
+                Ok(ToolCall::new(
+                    "developer__shell",
+                    json!({"command": "cargo nextest run"}),
+                )),
+            ),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("all passed")])),
+        ];
+
+        let memories = extract_memories(&messages, Arc::new(FixedProvider(reply.to_string())))
+            .await
+            .unwrap();
+        assert_eq!(
+            memories,
+            vec![ExtractedMemory {
+                category: "commands".to_string(),
+                data: "Tests run with cargo nextest".to_string(),
+                tags: vec!["rust".to_string()],
+                is_global: false,
+            }]
+        );
+
+        // Nothing is asked when the user never spoke
+        let memories = extract_memories(&[], Arc::new(FixedProvider("not json".to_string())))
+            .await
+            .unwrap();
+        assert!(memories.is_empty());
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let messages = vec![
+            Message::user().with_text("hello"),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("x".repeat(1000))])),
+        ];
+        let transcript = render_transcript(&messages);
+        assert!(transcript.starts_with("User: hello\nTool result: x"));
+        assert!(transcript.ends_with("..."));
+        assert!(transcript.len() < 600);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod extraction;
 pub mod info;
 pub mod storage;
 
@@ -8,4 +9,5 @@ pub use storage::{
     Identifier, SessionMetadata,
 };
 
+pub use extraction::{extract_memories, memory_extraction_enabled, ExtractedMemory};
 pub use info::{get_session_info, SessionInfo};
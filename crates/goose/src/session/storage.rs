@@ -1,9 +1,11 @@
+use crate::agents::plan::Plan;
 use crate::message::Message;
-use crate::providers::base::Provider;
+use crate::providers::base::{Provider, Usage};
 use anyhow::Result;
 use chrono::Local;
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
@@ -39,6 +41,15 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// The tokens used by each model in the session. Accumulated across all messages.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_usage: BTreeMap<String, Usage>,
+    /// The session that started this one, for sessions of subagents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session: Option<String>,
+    /// The plan the agent is working through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -58,6 +69,12 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            model_usage: BTreeMap<String, Usage>,
+            #[serde(default)]
+            parent_session: Option<String>,
+            #[serde(default)]
+            plan: Option<Plan>,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -78,6 +95,9 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
             working_dir,
+            model_usage: helper.model_usage,
+            parent_session: helper.parent_session,
+            plan: helper.plan,
         })
     }
 }
@@ -101,6 +121,9 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            model_usage: BTreeMap::new(),
+            parent_session: None,
+            plan: None,
         }
     }
 }
@@ -3,9 +3,11 @@ import { getApiUrl, getSecretKey } from '../config';
 interface initializeAgentProps {
   model: string;
   provider: string;
+  // Model settings of the recipe the session runs
+  settings?: Record<string, unknown>;
 }
 
-export async function initializeAgent({ model, provider }: initializeAgentProps) {
+export async function initializeAgent({ model, provider, settings }: initializeAgentProps) {
   const response = await fetch(getApiUrl('/agent/update_provider'), {
     method: 'POST',
     headers: {
@@ -15,6 +17,7 @@ export async function initializeAgent({ model, provider }: initializeAgentProps)
     body: JSON.stringify({
       provider: provider.toLowerCase().replace(/ /g, '_'),
       model: model,
+      settings: settings,
     }),
   });
   return response;
@@ -208,10 +208,34 @@ export type ModelInfo = {
      * The maximum context length this model supports
      */
     context_limit: number;
+    /**
+     * Cost in USD per million input tokens
+     */
+    input_cost_per_million?: number | null;
+    /**
+     * The maximum number of tokens the model generates in one reply
+     */
+    max_output_tokens?: number | null;
     /**
      * The name of the model
      */
     name: string;
+    /**
+     * Cost in USD per million output tokens
+     */
+    output_cost_per_million?: number | null;
+    /**
+     * Whether the model can think before it answers
+     */
+    supports_thinking?: boolean | null;
+    /**
+     * Whether the model supports tool calling
+     */
+    supports_tools?: boolean | null;
+    /**
+     * Whether the model accepts images
+     */
+    supports_vision?: boolean | null;
 };
 
 export type PermissionConfirmationRequest = {
@@ -225,6 +249,22 @@ export type PermissionConfirmationRequest = {
  */
 export type PermissionLevel = 'always_allow' | 'ask_before' | 'never_allow';
 
+/**
+ * A checklist of steps the agent works through
+ *
+ * Plans are kept in the session metadata, so they survive resuming the session, and shown to the
+ * model in the system prompt. The model updates the status of the steps with the
+ * `platform__update_plan` tool.
+ */
+export type Plan = {
+    steps: Array<PlanStep>;
+};
+
+export type PlanStep = {
+    description: string;
+    status: StepStatus;
+};
+
 export type PrincipalType = 'Extension' | 'Tool';
 
 export type ProviderDetails = {
@@ -261,7 +301,6 @@ export type ProviderMetadata = {
     display_name: string;
     /**
      * A list of currently known models with their capabilities
-     * TODO: eventually query the apis directly
      */
     known_models: Array<ModelInfo>;
     /**
@@ -348,10 +387,21 @@ export type SessionMetadata = {
      * Number of messages in the session
      */
     message_count: number;
+    /**
+     * The tokens used by each model in the session. Accumulated across all messages.
+     */
+    model_usage?: {
+        [key: string]: Usage;
+    };
     /**
      * The number of output tokens used in the session. Retrieved from the provider's last usage.
      */
     output_tokens?: number | null;
+    /**
+     * The session that started this one, for sessions of subagents
+     */
+    parent_session?: string | null;
+    plan?: Plan | null;
     /**
      * The total number of tokens used in the session. Retrieved from the provider's last usage.
      */
@@ -362,6 +412,8 @@ export type SessionMetadata = {
     working_dir: string;
 };
 
+export type StepStatus = 'pending' | 'in_progress' | 'completed' | 'skipped';
+
 export type SummarizationRequested = {
     msg: string;
 };
@@ -502,6 +554,20 @@ export type UpsertPermissionsQuery = {
     tool_permissions: Array<ToolPermission>;
 };
 
+export type Usage = {
+    /**
+     * Input tokens written to the provider's prompt cache, included in input_tokens
+     */
+    cache_creation_input_tokens?: number | null;
+    /**
+     * Input tokens read from the provider's prompt cache, included in input_tokens
+     */
+    cache_read_input_tokens?: number | null;
+    input_tokens?: number | null;
+    output_tokens?: number | null;
+    total_tokens?: number | null;
+};
+
 export type GetToolsData = {
     body?: never;
     path?: never;
@@ -134,11 +134,12 @@ export const initializeSystem = async (
   }
 ) => {
   try {
-    console.log('initializing agent with provider', provider, 'model', model);
-    await initializeAgent({ provider, model });
-
     // Get recipeConfig directly here
     const recipeConfig = window.appConfig?.get?.('recipeConfig');
+
+    console.log('initializing agent with provider', provider, 'model', model);
+    await initializeAgent({ provider, model, settings: recipeConfig?.settings });
+
     const botPrompt = recipeConfig?.instructions;
 
     // Extend the system prompt with desktop-specific information